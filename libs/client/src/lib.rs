@@ -596,6 +596,13 @@ fn find_reqwest_error_source<E: std::error::Error + 'static>(
     None
 }
 
+/// A write that the server warned about still succeeded, so the warnings are only shown.
+fn log_write_warnings(warnings: &[String]) {
+    for warning in warnings {
+        warn!(%warning, "The server warned about this write");
+    }
+}
+
 impl KanidmClient {
     pub fn get_origin(&self) -> &Url {
         &self.origin
@@ -1548,19 +1555,26 @@ impl KanidmClient {
         r.map(|v| v.entries)
     }
 
-    pub async fn create(&self, entries: Vec<Entry>) -> Result<(), ClientError> {
+    /// Create entries, returning the uuids of the created entries in the order they were given.
+    pub async fn create(&self, entries: Vec<Entry>) -> Result<Vec<Uuid>, ClientError> {
         let c = CreateRequest { entries };
-        self.perform_post_request("/v1/raw/create", c).await
+        let r: Result<CreateResponse, _> = self.perform_post_request("/v1/raw/create", c).await;
+        r.map(|cr| {
+            log_write_warnings(&cr.warnings);
+            cr.uuids
+        })
     }
 
     pub async fn modify(&self, filter: Filter, modlist: ModifyList) -> Result<(), ClientError> {
         let mr = ModifyRequest { filter, modlist };
-        self.perform_post_request("/v1/raw/modify", mr).await
+        let r: Result<WriteResponse, _> = self.perform_post_request("/v1/raw/modify", mr).await;
+        r.map(|wr| log_write_warnings(&wr.warnings))
     }
 
     pub async fn delete(&self, filter: Filter) -> Result<(), ClientError> {
         let dr = DeleteRequest { filter };
-        self.perform_post_request("/v1/raw/delete", dr).await
+        let r: Result<WriteResponse, _> = self.perform_post_request("/v1/raw/delete", dr).await;
+        r.map(|wr| log_write_warnings(&wr.warnings))
     }
 
     // === idm actions here ==
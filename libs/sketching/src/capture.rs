@@ -0,0 +1,181 @@
+//! Targeted debug capture. While a capture is set, operations from one account or client address
+//! are logged at every level, regardless of the log level of the server, so that one user's
+//! problem can be investigated on a busy server without enabling verbose logging for everyone.
+//!
+//! An operation is captured once the account or address it is from is known, from that point
+//! on. This marks the outermost span of the operation, and every event within it is then logged.
+
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Metadata, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Filter};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Registry;
+
+/// Who to capture the operations of.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum DebugCapture {
+    /// An account, by name, spn or uuid.
+    #[serde(rename = "account")]
+    Account(String),
+    /// A client address.
+    #[serde(rename = "client_ip")]
+    ClientIp(IpAddr),
+}
+
+impl Display for DebugCapture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DebugCapture::Account(account) => write!(f, "account {}", account),
+            DebugCapture::ClientIp(ip) => write!(f, "client address {}", ip),
+        }
+    }
+}
+
+/// Marks the outermost span of a captured operation.
+struct Captured;
+
+/// Checked before taking the lock, so that operations are not slowed while nothing is captured.
+static CAPTURE_ACTIVE: AtomicBool = AtomicBool::new(false);
+static CAPTURE: RwLock<Option<DebugCapture>> = RwLock::new(None);
+
+/// Set who to capture the operations of, or stop capturing with `None`.
+pub fn set_debug_capture(capture: Option<DebugCapture>) -> Result<(), String> {
+    let mut current = CAPTURE
+        .write()
+        .map_err(|_| "The debug capture lock is poisoned".to_string())?;
+    CAPTURE_ACTIVE.store(capture.is_some(), Ordering::Release);
+    *current = capture;
+    // Callsites that were disabled by the log level must be checked again.
+    tracing::callsite::rebuild_interest_cache();
+    Ok(())
+}
+
+/// Who is currently being captured, if anyone.
+pub fn debug_capture() -> Option<DebugCapture> {
+    CAPTURE.read().ok().and_then(|current| current.clone())
+}
+
+/// If anyone is being captured. Callers can check this to avoid preparing names to capture.
+pub fn is_capturing() -> bool {
+    CAPTURE_ACTIVE.load(Ordering::Acquire)
+}
+
+fn current_matches(f: impl FnOnce(&DebugCapture) -> bool) -> bool {
+    CAPTURE
+        .read()
+        .map(|current| current.as_ref().map(f).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+/// Capture the current operation if it is from the account being captured. Any of the names the
+/// account is known by can be given.
+pub fn capture_account(names: &[&str]) {
+    if is_capturing()
+        && current_matches(|capture| match capture {
+            DebugCapture::Account(account) => names.iter().any(|name| name == account),
+            DebugCapture::ClientIp(_) => false,
+        })
+    {
+        mark_current_operation();
+    }
+}
+
+/// Capture the current operation if it is from the client address being captured.
+pub fn capture_client_ip(ip: IpAddr) {
+    if is_capturing() && current_matches(|capture| capture == &DebugCapture::ClientIp(ip)) {
+        mark_current_operation();
+    }
+}
+
+fn mark_current_operation() {
+    tracing::dispatcher::get_default(|dispatch| {
+        let Some(registry) = dispatch.downcast_ref::<Registry>() else {
+            return;
+        };
+        let Some(id) = dispatch.current_span().id().cloned() else {
+            return;
+        };
+        if let Some(root) = registry
+            .span(&id)
+            .and_then(|span| span.scope().from_root().next())
+        {
+            root.extensions_mut().insert(Captured);
+        }
+    });
+}
+
+/// Enables every event within a captured operation, and otherwise defers to the wrapped filter.
+pub struct CaptureFilter<F> {
+    inner: F,
+}
+
+impl<F> CaptureFilter<F> {
+    pub fn new(inner: F) -> Self {
+        CaptureFilter { inner }
+    }
+}
+
+impl<S, F> Filter<S> for CaptureFilter<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    F: Filter<S>,
+{
+    fn enabled(&self, meta: &Metadata<'_>, cx: &Context<'_, S>) -> bool {
+        if self.inner.enabled(meta, cx) {
+            return true;
+        }
+        meta.is_event()
+            && is_capturing()
+            && cx
+                .lookup_current()
+                .and_then(|span| span.scope().from_root().next())
+                .map(|root| root.extensions().get::<Captured>().is_some())
+                .unwrap_or(false)
+    }
+
+    fn callsite_enabled(&self, meta: &'static Metadata<'static>) -> Interest {
+        // Only events are captured. A span that is created but then not enabled here has no
+        // tracing-forest state, which breaks the operation id of everything within it.
+        if is_capturing() && meta.is_event() {
+            Interest::sometimes()
+        } else {
+            self.inner.callsite_enabled(meta)
+        }
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        if is_capturing() {
+            Some(LevelFilter::TRACE)
+        } else {
+            self.inner.max_level_hint()
+        }
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_new_span(attrs, id, ctx)
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        self.inner.on_record(id, values, ctx)
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx)
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx)
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx)
+    }
+}
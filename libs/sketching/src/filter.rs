@@ -0,0 +1,161 @@
+//! The log filter of a running server, which can be changed at runtime so that verbose
+//! diagnostics can be enabled without a restart.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::{reload, EnvFilter};
+
+use crate::LogLevel;
+
+/// Areas of the server that can have their own log level.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogSubsystem {
+    #[serde(rename = "be")]
+    Backend,
+    #[serde(rename = "schema")]
+    Schema,
+    #[serde(rename = "server")]
+    Server,
+    #[serde(rename = "http")]
+    Http,
+}
+
+impl LogSubsystem {
+    /// The module that events of this subsystem are emitted from.
+    fn target(self) -> &'static str {
+        match self {
+            LogSubsystem::Backend => "kanidmd_lib::be",
+            LogSubsystem::Schema => "kanidmd_lib::schema",
+            LogSubsystem::Server => "kanidmd_lib::server",
+            LogSubsystem::Http => "kanidmd_core::https",
+        }
+    }
+}
+
+impl FromStr for LogSubsystem {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "be" => Ok(LogSubsystem::Backend),
+            "schema" => Ok(LogSubsystem::Schema),
+            "server" => Ok(LogSubsystem::Server),
+            "http" => Ok(LogSubsystem::Http),
+            _ => Err("Must be one of be, schema, server, http"),
+        }
+    }
+}
+
+impl Display for LogSubsystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogSubsystem::Backend => "be",
+            LogSubsystem::Schema => "schema",
+            LogSubsystem::Server => "server",
+            LogSubsystem::Http => "http",
+        })
+    }
+}
+
+/// The current log levels, as reported to an admin.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct LogLevels {
+    pub level: LogLevel,
+    pub subsystems: BTreeMap<LogSubsystem, LogLevel>,
+}
+
+/// Rebuilds the filter of one layer from the current levels.
+type ReloadFn = Box<dyn Fn(&LogLevels) -> Result<(), String> + Send>;
+
+struct LogFilter {
+    levels: LogLevels,
+    reloads: Vec<ReloadFn>,
+}
+
+static LOG_FILTER: OnceLock<Mutex<LogFilter>> = OnceLock::new();
+
+pub(crate) fn build_filter(
+    levels: &LogLevels,
+    fixed_directives: &[&'static str],
+) -> Result<EnvFilter, String> {
+    let mut filter = EnvFilter::builder()
+        .with_default_directive(levels.level.into())
+        .from_env_lossy();
+    for directive in fixed_directives {
+        filter = filter.add_directive(
+            directive
+                .parse()
+                .map_err(|e| format!("Invalid log directive {}: {:?}", directive, e))?,
+        );
+    }
+    for (subsystem, level) in levels.subsystems.iter() {
+        let directive = format!("{}={}", subsystem.target(), level);
+        filter = filter.add_directive(
+            directive
+                .parse()
+                .map_err(|e| format!("Invalid log directive {}: {:?}", directive, e))?,
+        );
+    }
+    Ok(filter)
+}
+
+/// A filter for one layer of the pipeline that follows the levels set with [set_log_level].
+/// The returned reload function must be passed to [register].
+pub(crate) fn reloadable_filter<S: 'static>(
+    levels: &LogLevels,
+    fixed_directives: Vec<&'static str>,
+) -> Result<(reload::Layer<EnvFilter, S>, ReloadFn), String> {
+    let (filter, handle) = reload::Layer::new(build_filter(levels, &fixed_directives)?);
+    let reload_fn: ReloadFn = Box::new(move |levels| {
+        handle
+            .reload(build_filter(levels, &fixed_directives)?)
+            .map_err(|e| format!("Failed to reload log filter: {:?}", e))
+    });
+    Ok((filter, reload_fn))
+}
+
+/// Make the filters of a pipeline adjustable with [set_log_level]. Only the first registered
+/// pipeline is used.
+pub(crate) fn register(levels: LogLevels, reloads: Vec<ReloadFn>) {
+    let _ = LOG_FILTER.set(Mutex::new(LogFilter { levels, reloads }));
+}
+
+/// Change the log level of the running server. With a subsystem, only that subsystem is changed,
+/// otherwise the default level for everything without its own level is changed.
+pub fn set_log_level(
+    subsystem: Option<LogSubsystem>,
+    level: LogLevel,
+) -> Result<LogLevels, String> {
+    let mut log_filter = LOG_FILTER
+        .get()
+        .ok_or_else(|| "The log filter can not be changed at runtime".to_string())?
+        .lock()
+        .map_err(|_| "The log filter lock is poisoned".to_string())?;
+
+    let mut levels = log_filter.levels.clone();
+    match subsystem {
+        Some(subsystem) => {
+            levels.subsystems.insert(subsystem, level);
+        }
+        None => levels.level = level,
+    }
+
+    // Every layer is reloaded, so that the console and the exported traces stay in step.
+    for reload_fn in log_filter.reloads.iter() {
+        reload_fn(&levels)?;
+    }
+    log_filter.levels = levels.clone();
+    Ok(levels)
+}
+
+/// The log levels of the running server, if they can be changed at runtime.
+pub fn log_levels() -> Option<LogLevels> {
+    LOG_FILTER
+        .get()
+        .and_then(|log_filter| log_filter.lock().ok())
+        .map(|log_filter| log_filter.levels.clone())
+}
@@ -5,7 +5,7 @@ use std::fmt::Display;
 use std::str::FromStr;
 
 use num_enum::{IntoPrimitive, TryFromPrimitive};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tracing_forest::printer::TestCapturePrinter;
 use tracing_forest::tag::NoTag;
 use tracing_forest::util::*;
@@ -13,8 +13,12 @@ use tracing_forest::Tag;
 use tracing_subscriber::filter::Directive;
 use tracing_subscriber::prelude::*;
 
+pub mod capture;
+pub mod filter;
 pub mod macros;
 pub mod otel;
+pub mod security;
+pub mod slow;
 
 pub use {tracing, tracing_forest, tracing_subscriber};
 
@@ -105,7 +109,7 @@ impl EventTag {
     }
 }
 
-#[derive(Clone, Copy, Deserialize, Debug, Default)]
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
 pub enum LogLevel {
     #[default]
     #[serde(rename = "info")]
@@ -139,6 +143,49 @@ impl Display for LogLevel {
     }
 }
 
+/// How server logs are written to stdout.
+#[derive(Clone, Copy, Deserialize, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Events are grouped into a tree under the operation that produced them.
+    #[default]
+    #[serde(rename = "forest")]
+    Forest,
+    /// One json object per event, for log collectors that process each line separately.
+    #[serde(rename = "json")]
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forest" => Ok(LogFormat::Forest),
+            "json" => Ok(LogFormat::Json),
+            _ => Err("Must be one of forest, json"),
+        }
+    }
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LogFormat::Forest => "forest",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
 impl From<LogLevel> for Directive {
     fn from(value: LogLevel) -> Self {
         match value {
@@ -3,10 +3,18 @@ use opentelemetry::KeyValue;
 use opentelemetry_otlp::{Protocol, WithExportConfig};
 use opentelemetry_sdk::trace::{self, Sampler};
 use opentelemetry_sdk::Resource;
+use std::collections::BTreeMap;
 use std::time::Duration;
 use tracing::Subscriber;
+use tracing_subscriber::filter::{filter_fn, LevelFilter};
+use tracing_subscriber::prelude::*;
 use tracing_subscriber::Registry;
-use tracing_subscriber::{prelude::*, EnvFilter};
+
+use crate::capture::CaptureFilter;
+use crate::filter::{self, reloadable_filter, LogLevels};
+use crate::security::{SecurityLogConfig, SecurityLogLayer};
+use crate::slow::SlowOperationLayer;
+use crate::LogFormat;
 
 pub const MAX_EVENTS_PER_SPAN: u32 = 64 * 1024;
 pub const MAX_ATTRIBUTES_PER_SPAN: u32 = 128;
@@ -31,12 +39,66 @@ pub const MAX_ATTRIBUTES_PER_SPAN: u32 = 128;
 /// This does all the startup things for the logging pipeline
 pub fn start_logging_pipeline(
     otlp_endpoint: Option<String>,
+    sample_ratio: Option<f64>,
     log_filter: crate::LogLevel,
+    log_format: crate::LogFormat,
+    security_log: Option<SecurityLogConfig>,
+    slow_operation_threshold: Option<Duration>,
     service_name: String,
 ) -> Result<Box<dyn Subscriber + Send + Sync>, String> {
-    let forest_filter: EnvFilter = EnvFilter::builder()
-        .with_default_directive(log_filter.into())
-        .from_env_lossy();
+    // adding these filters because when you close out the process the OTLP comms layer is NOISY
+    let fixed_directives = match otlp_endpoint {
+        Some(_) => vec!["tonic=info", "h2=info", "hyper=info"],
+        None => Vec::new(),
+    };
+    let levels = LogLevels {
+        level: log_filter,
+        subsystems: BTreeMap::new(),
+    };
+    // The filters can be reloaded, so that log levels can be changed at runtime.
+    let (forest_filter, forest_reload) = reloadable_filter(&levels, fixed_directives.clone())?;
+    let mut reloads = vec![forest_reload];
+
+    // These layers are always present, and filter everything out when they are not configured,
+    // since an absent layer causes spans no layer wants to be created. They only see the
+    // operation spans at info, as a span that the console layer has not seen breaks the
+    // tracing-forest operation id of everything within it, and the console layer may be set to a
+    // lower level at runtime.
+    let security_level = security_log
+        .as_ref()
+        .map(|cfg| LevelFilter::from(cfg.level))
+        .unwrap_or(LevelFilter::OFF);
+    let security_layer = security_log
+        .map(|cfg| SecurityLogLayer::open(&cfg))
+        .transpose()?
+        .with_filter(
+            filter_fn(move |meta| match meta.is_span() {
+                true => security_level != LevelFilter::OFF && LevelFilter::INFO >= *meta.level(),
+                false => security_level >= *meta.level(),
+            })
+            .with_max_level_hint(security_level),
+        );
+
+    let slow_layer = match slow_operation_threshold {
+        Some(threshold) => Some(SlowOperationLayer::new(threshold)).with_filter(LevelFilter::INFO),
+        None => None.with_filter(LevelFilter::OFF),
+    };
+
+    // Operations that are being captured are logged regardless of the log level.
+    let forest_filter = CaptureFilter::new(forest_filter);
+
+    let console_layer = match log_format {
+        LogFormat::Forest => tracing_forest::ForestLayer::default()
+            .with_filter(forest_filter)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_writer(std::io::stdout)
+            .with_filter(forest_filter)
+            .boxed(),
+    };
 
     // TODO: work out how to do metrics things
     // let meter_provider = init_metrics()
@@ -44,23 +106,8 @@ pub fn start_logging_pipeline(
 
     match otlp_endpoint {
         Some(endpoint) => {
-            // adding these filters because when you close out the process the OTLP comms layer is NOISY
-            let forest_filter = forest_filter
-                .add_directive(
-                    "tonic=info"
-                        .parse()
-                        .expect("Failed to set tonic logging to info"),
-                )
-                .add_directive("h2=info".parse().expect("Failed to set h2 logging to info"))
-                .add_directive(
-                    "hyper=info"
-                        .parse()
-                        .expect("Failed to set hyper logging to info"),
-                );
-            let forest_layer = tracing_forest::ForestLayer::default().with_filter(forest_filter);
-            let t_filter: EnvFilter = EnvFilter::builder()
-                .with_default_directive(log_filter.into())
-                .from_env_lossy();
+            let (t_filter, t_reload) = reloadable_filter(&levels, fixed_directives)?;
+            reloads.push(t_reload);
 
             let tracer = opentelemetry_otlp::new_pipeline().tracing().with_exporter(
                 opentelemetry_otlp::new_exporter()
@@ -81,11 +128,18 @@ pub fn start_logging_pipeline(
             let hostname = hostname.to_string_lossy();
             let hostname = hostname.to_lowercase();
 
+            // By default we want *everything!*, but busy servers can sample a ratio of root
+            // traces to bound the export overhead. Child spans follow their parent's decision
+            // so that sampled traces are always complete.
+            let sampler = match sample_ratio {
+                Some(ratio) => Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(ratio))),
+                None => Sampler::AlwaysOn,
+            };
+
             let tracer = tracer
                 .with_trace_config(
                     trace::config()
-                        // we want *everything!*
-                        .with_sampler(Sampler::AlwaysOn)
+                        .with_sampler(sampler)
                         .with_max_events_per_span(MAX_EVENTS_PER_SPAN)
                         .with_max_attributes_per_span(MAX_ATTRIBUTES_PER_SPAN)
                         .with_resource(Resource::new(vec![
@@ -107,13 +161,23 @@ pub fn start_logging_pipeline(
                 .with_threads(true)
                 .with_filter(t_filter);
 
+            filter::register(levels, reloads);
             Ok(Box::new(
-                Registry::default().with(forest_layer).with(telemetry),
+                Registry::default()
+                    .with(console_layer)
+                    .with(security_layer)
+                    .with(slow_layer)
+                    .with(telemetry),
             ))
         }
         None => {
-            let forest_layer = tracing_forest::ForestLayer::default().with_filter(forest_filter);
-            Ok(Box::new(Registry::default().with(forest_layer)))
+            filter::register(levels, reloads);
+            Ok(Box::new(
+                Registry::default()
+                    .with(console_layer)
+                    .with(security_layer)
+                    .with(slow_layer),
+            ))
         }
     }
 }
@@ -0,0 +1,182 @@
+//! A dedicated log of security relevant events, such as authentication failures and access
+//! control decisions. These are the events tagged by the `security_*` macros, written as one
+//! json object per line to their own file, so that they can be retained and shipped separately
+//! to operational logs.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::{EventTag, LogLevel};
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SecurityLogConfig {
+    /// The file that security events are appended to, one json object per line.
+    pub path: PathBuf,
+    /// The minimum level of security events to record, one of info, debug, trace. Defaults to
+    /// "info" if not set. This is independent of the server log level.
+    #[serde(default)]
+    pub level: LogLevel,
+}
+
+/// Finds the tag of an event, without the cost of formatting the other fields.
+#[derive(Default)]
+struct EventTagVisitor {
+    tag: Option<EventTag>,
+}
+
+impl Visit for EventTagVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() == "event_tag_id" {
+            self.tag = EventTag::try_from(value).ok();
+        }
+    }
+
+    fn record_debug(&mut self, _field: &Field, _value: &dyn fmt::Debug) {}
+}
+
+#[derive(Default)]
+struct SecurityEventVisitor {
+    fields: Map<String, Value>,
+}
+
+impl SecurityEventVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        self.fields.insert(field.name().to_string(), value);
+    }
+}
+
+impl Visit for SecurityEventVisitor {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if field.name() != "event_tag_id" {
+            self.insert(field, value.into());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, format!("{:?}", value).into());
+    }
+}
+
+fn is_security_tag(tag: EventTag) -> bool {
+    matches!(
+        tag,
+        EventTag::SecurityCritical
+            | EventTag::SecurityDebug
+            | EventTag::SecurityInfo
+            | EventTag::SecurityAccess
+            | EventTag::SecurityError
+    )
+}
+
+pub struct SecurityLogLayer {
+    file: Mutex<File>,
+    /// Security events that could not be written.
+    write_errors: AtomicU64,
+}
+
+impl SecurityLogLayer {
+    pub fn open(cfg: &SecurityLogConfig) -> Result<Self, String> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&cfg.path)
+            .map(|file| SecurityLogLayer {
+                file: Mutex::new(file),
+                write_errors: AtomicU64::new(0),
+            })
+            .map_err(|e| {
+                format!(
+                    "Failed to open security log {}: {:?}",
+                    cfg.path.display(),
+                    e
+                )
+            })
+    }
+
+    /// Count a security event that could not be written. This can't be logged through tracing
+    /// from within the layer, and a failing disk would fail every event, so only the first
+    /// failure and then each doubling of the count is reported.
+    fn write_failed(&self, reason: &dyn fmt::Debug) {
+        let total = self.write_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if total.is_power_of_two() {
+            eprintln!(
+                "Failed to write to security log, {} security events lost: {:?}",
+                total, reason
+            );
+        }
+    }
+}
+
+impl<S> Layer<S> for SecurityLogLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().fields().field("event_tag_id").is_none() {
+            return;
+        }
+        let mut tag_visitor = EventTagVisitor::default();
+        event.record(&mut tag_visitor);
+        let tag = match tag_visitor.tag {
+            Some(tag) if is_security_tag(tag) => tag,
+            _ => return,
+        };
+
+        let mut visitor = SecurityEventVisitor::default();
+        event.record(&mut visitor);
+
+        let mut timestamp = String::new();
+        let _ = SystemTime.format_time(&mut Writer::new(&mut timestamp));
+        // The operations this event occurred within, outermost first.
+        let spans: Vec<Value> = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|span| span.name().into()).collect())
+            .unwrap_or_default();
+
+        let record = serde_json::json!({
+            "timestamp": timestamp,
+            "level": event.metadata().level().as_str(),
+            "tag": tag.pretty(),
+            "target": event.metadata().target(),
+            "spans": spans,
+            "fields": visitor.fields,
+        });
+
+        let mut line = record.to_string();
+        line.push('\n');
+        match self.file.lock() {
+            Ok(mut file) => {
+                if let Err(e) = file.write_all(line.as_bytes()) {
+                    self.write_failed(&e);
+                }
+            }
+            Err(_) => self.write_failed(&"lock is poisoned"),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! Surfaces operations that take longer than a threshold. Each top level span (an operation such
+//! as a search or a write) is timed, and when it exceeds the threshold a warning is emitted with
+//! the timings of every span within it, so the slow part can be found without enabling verbose
+//! logging.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::EventTag;
+
+/// The timing of an open span, and the rendered timings of the spans within it that have closed.
+struct SpanTiming {
+    start: Instant,
+    scope: Vec<String>,
+}
+
+pub struct SlowOperationLayer {
+    threshold: Duration,
+}
+
+impl SlowOperationLayer {
+    pub fn new(threshold: Duration) -> Self {
+        SlowOperationLayer { threshold }
+    }
+}
+
+impl<S> Layer<S> for SlowOperationLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanTiming {
+                start: Instant::now(),
+                scope: Vec::new(),
+            });
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        let elapsed = timing.start.elapsed();
+
+        // Render this span above the spans within it, which have all closed before it.
+        let mut scope = Vec::with_capacity(timing.scope.len() + 1);
+        scope.push(format!("{} [{:?}]", span.name(), elapsed));
+        scope.extend(timing.scope.into_iter().map(|line| format!("  {}", line)));
+
+        match span.parent() {
+            Some(parent) => {
+                if let Some(parent_timing) = parent.extensions_mut().get_mut::<SpanTiming>() {
+                    parent_timing.scope.extend(scope);
+                }
+            }
+            None if elapsed >= self.threshold => {
+                let mut rendered = String::new();
+                for line in scope.iter() {
+                    let _ = writeln!(rendered, "{}", line);
+                }
+                crate::tagged_event!(
+                    WARN,
+                    EventTag::RequestWarn,
+                    operation = span.name(),
+                    duration_ms = elapsed.as_millis() as u64,
+                    threshold_ms = self.threshold.as_millis() as u64,
+                    scope = rendered.trim_end(),
+                    "Slow operation"
+                );
+            }
+            None => {}
+        }
+    }
+}
@@ -41,6 +41,9 @@ pub const DEFAULT_LDAP_LOCALHOST: &str = "localhost:636";
 pub const DEFAULT_REPLICATION_ADDRESS: &str = "127.0.0.1:8444";
 pub const DEFAULT_REPLICATION_ORIGIN: &str = "repl://localhost:8444";
 
+/// The maximum amount of recursion allowed in a filter.
+pub const DEFAULT_LIMIT_FILTER_DEPTH_MAX: u64 = 12;
+
 // IF YOU CHANGE THESE VALUES YOU BREAK EVERYTHING
 pub const ATTR_ACCOUNT_EXPIRE: &str = "account_expire";
 pub const ATTR_ACCOUNT_VALID_FROM: &str = "account_valid_from";
@@ -69,9 +72,11 @@ pub const ATTR_CLASS: &str = "class";
 pub const ATTR_CLASSNAME: &str = "classname";
 pub const ATTR_CN: &str = "cn";
 pub const ATTR_COOKIE_PRIVATE_KEY: &str = "cookie_private_key";
+pub const ATTR_CREATED_AT_CID: &str = "created_at_cid";
 pub const ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN: &str = "credential_update_intent_token";
 pub const ATTR_CREDENTIAL_TYPE_MINIMUM: &str = "credential_type_minimum";
 pub const ATTR_DENIED_NAME: &str = "denied_name";
+pub const ATTR_DEPRECATED: &str = "deprecated";
 pub const ATTR_DESCRIPTION: &str = "description";
 pub const ATTR_DIRECTMEMBEROF: &str = "directmemberof";
 pub const ATTR_DISPLAYNAME: &str = "displayname";
@@ -236,6 +241,10 @@ pub const KSESSIONID: &str = "X-KANIDM-AUTH-SESSION-ID";
 pub const KOPID: &str = "X-KANIDM-OPID";
 /// HTTP Header containing the Kanidm server version
 pub const KVERSION: &str = "X-KANIDM-VERSION";
+/// HTTP Header containing the code of the error that caused a request to fail
+pub const KERRORCODE: &str = "X-KANIDM-ERROR-CODE";
+/// HTTP Header containing a warning about a write, such as the use of deprecated schema
+pub const KWARNING: &str = "X-KANIDM-WARNING";
 
 /// X-Forwarded-For header
 pub const X_FORWARDED_FOR: &str = "x-forwarded-for";
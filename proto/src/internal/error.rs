@@ -22,12 +22,18 @@ pub enum SchemaError {
     EmptyFilter,
     Corrupted,
     PhantomAttribute(String),
+    TooManyAttributes(usize),
+    TooManyValues(String),
+    ValueTooLarge(String),
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum PluginError {
     AttrUnique(String),
+    /// The value of this attribute is already held by another entry. Which entry is only
+    /// logged, as the client may not be able to read it.
+    AttrUniqueConflict(String),
     Base(String),
     ReferentialIntegrity(String),
     CredImport(String),
@@ -61,6 +67,9 @@ pub enum ConsistencyError {
     KeyProviderUuidMissing { key_object: Uuid },
     KeyProviderNoKeys { key_object: Uuid },
     KeyProviderNotFound { key_object: Uuid, provider: Uuid },
+    BackendEntryCorrupt(u64),
+    BackendIndexMissingId { index: String, key: String, id: u64 },
+    BackendIndexStaleId { index: String, key: String, id: u64 },
 }
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
@@ -142,6 +151,14 @@ pub enum OperationError {
     // DB low level errors.
     DB0001MismatchedRestoreVersion,
     DB0002MismatchedRestoreVersion,
+    DB0003SearchCandidateLimitExceeded,
+    DB0004BackupChecksumMismatch,
+    DB0005BackupEncryptionKeyRequired,
+    DB0006EntryQuotaExceeded,
+    DB0007SizeQuotaExceeded,
+    DB0008InvalidTombstoneMaxAge,
+    DB0009BackupUnverified,
+    DB0010InvalidRecycleBinMaxAge,
 
     // SCIM
     SC0001IncomingSshPublicKey,
@@ -227,6 +244,19 @@ impl Display for OperationError {
 }
 
 impl OperationError {
+    /// A machine readable code for the error, which is the name of its variant. This is sent
+    /// to clients with the error and is included when the error is logged, so a failure seen by
+    /// a client can be found in the server logs. Codes are part of the API, so variants must not
+    /// be renamed once released.
+    pub fn code(&self) -> String {
+        // The derived Debug output starts with the variant name, followed by any fields.
+        let debug = format!("{:?}", self);
+        match debug.find(|c: char| !c.is_ascii_alphanumeric()) {
+            Some(end) => debug[..end].to_string(),
+            None => debug,
+        }
+    }
+
     /// Return the message associated with the error if there is one.
     fn message(&self) -> Option<&'static str> {
         match self {
@@ -300,6 +330,14 @@ impl OperationError {
             Self::MG0003ServerPhaseInvalidForMigration => None,
             Self::DB0001MismatchedRestoreVersion => None,
             Self::DB0002MismatchedRestoreVersion => None,
+            Self::DB0003SearchCandidateLimitExceeded => Some("The search could match more entries than the server allows. Refine the filter with an indexed attribute."),
+            Self::DB0004BackupChecksumMismatch => Some("The backup does not match its manifest or the backup encryption key, it may be damaged or altered."),
+            Self::DB0005BackupEncryptionKeyRequired => Some("The backup is encrypted, but no backup encryption key is configured."),
+            Self::DB0006EntryQuotaExceeded => Some("The database holds the most entries it is allowed, so no more can be created."),
+            Self::DB0007SizeQuotaExceeded => Some("The database has reached the most space it is allowed to use, so no more entries can be created."),
+            Self::DB0008InvalidTombstoneMaxAge => Some("The tombstone max age must be at least the minimum, and less than the time since the unix epoch."),
+            Self::DB0009BackupUnverified => Some("The backup can not be verified with the backup encryption key, as it is not encrypted. Restore it with --allow-unverified if it is trusted."),
+            Self::DB0010InvalidRecycleBinMaxAge => Some("The recycle bin max age must be at least the minimum, and less than the time since the unix epoch."),
             Self::MG0004DomainLevelInDevelopment => None,
             Self::MG0005GidConstraintsNotMet => None,
             Self::KP0001KeyProviderNotLoaded => None,
@@ -371,3 +409,20 @@ fn test_operationerror_as_nice_string() {
         "CorruptedEntry(12345)".to_string()
     );
 }
+
+#[test]
+fn test_operationerror_code() {
+    assert_eq!(OperationError::NotAuthenticated.code(), "NotAuthenticated");
+    assert_eq!(
+        OperationError::CorruptedEntry(12345).code(),
+        "CorruptedEntry"
+    );
+    assert_eq!(
+        OperationError::KP0012KeyObjectMissingProvider.code(),
+        "KP0012KeyObjectMissingProvider"
+    );
+    assert_eq!(
+        OperationError::SchemaViolation(SchemaError::NoClassFound).code(),
+        "SchemaViolation"
+    );
+}
@@ -210,6 +210,80 @@ impl TryFrom<&str> for FsType {
     }
 }
 
+#[derive(Debug, Copy, Clone, Deserialize, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+/// How durable database writes are, used to trade safety for write speed.
+pub enum DbDurability {
+    #[default]
+    /// The default setting, each commit is synced to disk before it completes.
+    Full,
+    /// Commits are synced to disk when the write ahead log is checkpointed. An operating system
+    /// crash or power loss may lose the most recent commits, but the database remains consistent.
+    Normal,
+    /// Nothing is synced to disk. An operating system crash or power loss may corrupt the
+    /// database, so this is only suitable for tests, or bulk loads that can be repeated.
+    Off,
+}
+
+impl DbDurability {
+    /// The value of the sqlite `synchronous` pragma for this mode.
+    pub fn synchronous(&self) -> &'static str {
+        match self {
+            DbDurability::Full => "FULL",
+            DbDurability::Normal => "NORMAL",
+            DbDurability::Off => "OFF",
+        }
+    }
+}
+
+impl TryFrom<&str> for DbDurability {
+    type Error = ();
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "full" => Ok(DbDurability::Full),
+            "normal" => Ok(DbDurability::Normal),
+            "off" => Ok(DbDurability::Off),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+/// How the database journals writes before they are applied.
+pub enum DbJournalMode {
+    #[default]
+    /// The default setting, writes are appended to a write ahead log, so that readers and the
+    /// writer do not block each other.
+    Wal,
+    /// Writes use a rollback journal that is deleted on commit. Readers and the writer block each
+    /// other, but no write ahead log needs to be checkpointed, and the database is a single file.
+    Delete,
+}
+
+impl DbJournalMode {
+    /// The value of the sqlite `journal_mode` pragma for this mode.
+    pub fn journal_mode(&self) -> &'static str {
+        match self {
+            DbJournalMode::Wal => "WAL",
+            DbJournalMode::Delete => "DELETE",
+        }
+    }
+}
+
+impl TryFrom<&str> for DbJournalMode {
+    type Error = ();
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "wal" => Ok(DbJournalMode::Wal),
+            "delete" => Ok(DbJournalMode::Delete),
+            _ => Err(()),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, ToSchema)]
 pub enum Oauth2ClaimMapJoin {
     #[serde(rename = "csv")]
@@ -251,6 +325,65 @@ pub struct DomainUpgradeCheckItem {
     pub affected_entries: Vec<String>,
 }
 
+/// Statistics about the content and storage of the database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackendStats {
+    pub entries: u64,
+    /// The size in bytes of the database file, excluding the write ahead log.
+    pub db_size: u64,
+    /// The bytes of the database file that are unused, and would be released by a vacuum.
+    pub db_free: u64,
+    /// When the database was last vacuumed by scheduled maintenance, as a unix timestamp.
+    pub last_vacuum: Option<i64>,
+    pub indexes: Vec<IndexStats>,
+}
+
+/// How the server would execute a search filter, reported without loading any entries.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FilterExplain {
+    /// How each term of the filter was resolved against the indexes.
+    pub plan: FilterExplainTerm,
+    /// The number of entries the indexes could not exclude, and that would be loaded.
+    pub candidates: u64,
+    /// No term of the filter could be resolved by an index, so every entry is loaded.
+    pub full_scan: bool,
+    /// The loaded candidates must still be tested against the filter.
+    pub filter_test: bool,
+}
+
+/// How a single term of a filter, and the terms within it, were resolved against the indexes.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FilterExplainTerm {
+    /// One of eq, cnt, stw, enw, pres, lt, gt, or, and, inclusion or andnot.
+    pub term: String,
+    /// The attribute the term tests, if it is not a set of other terms.
+    pub attr: Option<String>,
+    pub index: FilterExplainIndex,
+    /// The number of entries this term alone would load. For andnot, the number it excludes.
+    pub candidates: u64,
+    pub terms: Vec<FilterExplainTerm>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterExplainIndex {
+    /// The indexes give exactly the entries that match.
+    Indexed,
+    /// The indexes narrow the candidates, but they must still be tested.
+    Partial,
+    /// The indexes can't narrow the candidates, so every entry is loaded.
+    Unindexed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct IndexStats {
+    pub attr: String,
+    /// One of eq, pres, sub or ord.
+    pub itype: String,
+    /// The number of distinct keys in the index.
+    pub keys: u64,
+}
+
 #[test]
 fn test_fstype_deser() {
     assert_eq!(FsType::try_from("zfs"), Ok(FsType::Zfs));
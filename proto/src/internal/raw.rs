@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
+use crate::constants::DEFAULT_LIMIT_FILTER_DEPTH_MAX;
 use crate::v1::Entry;
 
 #[derive(Debug, Serialize, Deserialize, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, ToSchema)]
@@ -11,8 +14,14 @@ pub enum Filter {
     Eq(String, String),
     #[serde(alias = "Cnt")]
     Cnt(String, String),
+    #[serde(alias = "Stw")]
+    Stw(String, String),
     #[serde(alias = "Pres")]
     Pres(String),
+    #[serde(alias = "Lt")]
+    Lt(String, String),
+    #[serde(alias = "Gt")]
+    Gt(String, String),
     #[serde(alias = "Or")]
     Or(Vec<Filter>),
     #[serde(alias = "And")]
@@ -23,6 +32,139 @@ pub enum Filter {
     SelfUuid,
 }
 
+impl FromStr for Filter {
+    type Err = String;
+
+    /// Parse an LDAP style filter such as `(&(class=account)(name=william))`, so that a query
+    /// can be written by hand rather than as JSON. `(attr=*)` is a presence test, `(attr=*value*)`
+    /// a substring, `(attr=value*)` a prefix and `(!filter)` a negation. `(self)` matches the
+    /// entry of the caller. A `(`, `)`, `*` or `\` in a value must be escaped as in LDAP, for
+    /// example `\2a` for `*`. Filters may not nest deeper than the server allows.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = FilterParser { rest: s };
+        let filter = parser.filter(DEFAULT_LIMIT_FILTER_DEPTH_MAX as usize)?;
+        match parser.rest.trim() {
+            "" => Ok(filter),
+            rest => Err(format!("unexpected input after filter: {rest}")),
+        }
+    }
+}
+
+struct FilterParser<'a> {
+    rest: &'a str,
+}
+
+impl FilterParser<'_> {
+    fn eat(&mut self, c: char) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn filter(&mut self, depth: usize) -> Result<Filter, String> {
+        let ndepth = depth
+            .checked_sub(1)
+            .ok_or_else(|| format!("filter is nested too deeply at: {}", self.rest))?;
+
+        if !self.eat('(') {
+            return Err(format!("expected '(' at: {}", self.rest));
+        }
+
+        let filter = if self.eat('&') {
+            Filter::And(self.filter_list(ndepth)?)
+        } else if self.eat('|') {
+            Filter::Or(self.filter_list(ndepth)?)
+        } else if self.eat('!') {
+            Filter::AndNot(Box::new(self.filter(ndepth)?))
+        } else {
+            self.item()?
+        };
+
+        if !self.eat(')') {
+            return Err(format!("expected ')' at: {}", self.rest));
+        }
+        Ok(filter)
+    }
+
+    fn filter_list(&mut self, depth: usize) -> Result<Vec<Filter>, String> {
+        let mut filters = Vec::new();
+        while self.rest.trim_start().starts_with('(') {
+            filters.push(self.filter(depth)?);
+        }
+        if filters.is_empty() {
+            return Err(format!("expected at least one filter at: {}", self.rest));
+        }
+        Ok(filters)
+    }
+
+    fn item(&mut self) -> Result<Filter, String> {
+        let end = self
+            .rest
+            .find(')')
+            .ok_or_else(|| format!("unterminated filter: {}", self.rest))?;
+        let (item, rest) = self.rest.split_at(end);
+        self.rest = rest;
+
+        if item.trim() == "self" {
+            return Ok(Filter::SelfUuid);
+        }
+
+        let (attr, value) = item
+            .split_once('=')
+            .ok_or_else(|| format!("expected attr=value, found: {item}"))?;
+        let attr = attr.trim();
+        if attr.is_empty() || attr.contains(['(', '&', '|', '!', '*', '<', '>', '~']) {
+            return Err(format!("invalid attribute name: {attr}"));
+        }
+
+        if value == "*" {
+            return Ok(Filter::Pres(attr.to_string()));
+        }
+
+        if let Some(sub) = value.strip_prefix('*').and_then(|v| v.strip_suffix('*')) {
+            if !sub.is_empty() {
+                return Ok(Filter::Cnt(attr.to_string(), unescape(sub)?));
+            }
+        }
+
+        match value.strip_suffix('*') {
+            Some(prefix) if !prefix.is_empty() => {
+                Ok(Filter::Stw(attr.to_string(), unescape(prefix)?))
+            }
+            _ => Ok(Filter::Eq(attr.to_string(), unescape(value)?)),
+        }
+    }
+}
+
+/// Replace the `\xx` hex escapes of an LDAP filter value with the bytes they represent.
+fn unescape(value: &str) -> Result<String, String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'\\' => {
+                let hex = [iter.next(), iter.next()];
+                let byte = match hex {
+                    [Some(hi), Some(lo)] => std::str::from_utf8(&[hi, lo])
+                        .ok()
+                        .and_then(|h| u8::from_str_radix(h, 16).ok()),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("invalid escape in value: {value}"))?;
+                bytes.push(byte);
+            }
+            b'*' | b'(' => return Err(format!("unescaped '{}' in value: {value}", b as char)),
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("value is not valid utf8: {value}"))
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Modify {
@@ -99,10 +241,29 @@ impl ModifyRequest {
     }
 }
 
+/// The result of a create. The uuids of the created entries are in the order they were given.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateResponse {
+    pub uuids: Vec<Uuid>,
+    /// Warnings about the write, such as the use of deprecated schema. These don't prevent the
+    /// write.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+/// The result of a modify or a delete.
+#[derive(Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct WriteResponse {
+    /// Warnings about the write, such as the use of deprecated schema. These don't prevent the
+    /// write.
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::Filter as ProtoFilter;
-    use crate::constants::ATTR_CLASS;
+    use crate::constants::{ATTR_CLASS, DEFAULT_LIMIT_FILTER_DEPTH_MAX};
 
     #[test]
     fn test_protofilter_simple() {
@@ -110,4 +271,70 @@ mod tests {
 
         println!("{:?}", serde_json::to_string(&pf).expect("JSON failure"));
     }
+
+    #[test]
+    fn test_protofilter_from_str() {
+        assert_eq!(
+            "(&(class=account)(name=william))".parse::<ProtoFilter>(),
+            Ok(ProtoFilter::And(vec![
+                ProtoFilter::Eq("class".to_string(), "account".to_string()),
+                ProtoFilter::Eq("name".to_string(), "william".to_string()),
+            ]))
+        );
+
+        assert_eq!(
+            " ( | (mail=*) (!(displayname=*will*)) (self) ) ".parse::<ProtoFilter>(),
+            Ok(ProtoFilter::Or(vec![
+                ProtoFilter::Pres("mail".to_string()),
+                ProtoFilter::AndNot(Box::new(ProtoFilter::Cnt(
+                    "displayname".to_string(),
+                    "will".to_string()
+                ))),
+                ProtoFilter::SelfUuid,
+            ]))
+        );
+
+        assert_eq!(
+            "(name=wil*)".parse::<ProtoFilter>(),
+            Ok(ProtoFilter::Stw("name".to_string(), "wil".to_string()))
+        );
+
+        assert_eq!(
+            r"(description=a\2a\28b\29)".parse::<ProtoFilter>(),
+            Ok(ProtoFilter::Eq(
+                "description".to_string(),
+                "a*(b)".to_string()
+            ))
+        );
+
+        for invalid in [
+            "",
+            "name=william",
+            "(name=william",
+            "(&)",
+            "(=william)",
+            "(name)",
+            "(name=*wil)",
+            "(name=w*l)",
+            r"(name=\zz)",
+            "(name=william))",
+        ] {
+            assert!(invalid.parse::<ProtoFilter>().is_err(), "{invalid}");
+        }
+
+        // Nesting is limited as it is on the server.
+        let nested = |depth: u64| {
+            let mut f = "(class=*)".to_string();
+            for _i in 1..depth {
+                f = format!("(&{f})");
+            }
+            f
+        };
+        assert!(nested(DEFAULT_LIMIT_FILTER_DEPTH_MAX)
+            .parse::<ProtoFilter>()
+            .is_ok());
+        assert!(nested(DEFAULT_LIMIT_FILTER_DEPTH_MAX + 1)
+            .parse::<ProtoFilter>()
+            .is_err());
+    }
 }
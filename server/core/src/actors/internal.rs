@@ -2,18 +2,24 @@
 //! access controls. Access is *IMPLIED* by the use of these via the internal
 //! admin unixd socket.
 
+use std::path::Path;
+
 use crate::{QueryServerReadV1, QueryServerWriteV1};
 use tracing::{Instrument, Level};
 
+use kanidmd_lib::be::BackendTransaction;
 use kanidmd_lib::prelude::*;
 
 use kanidmd_lib::{
-    event::{PurgeRecycledEvent, PurgeTombstoneEvent},
+    event::{PurgeRecycledEvent, PurgeTombstoneEvent, SearchEvent},
+    filter::Filter,
     idm::delayed::DelayedAction,
 };
 
 use kanidm_proto::internal::{
-    DomainInfo as ProtoDomainInfo, DomainUpgradeCheckReport as ProtoDomainUpgradeCheckReport,
+    BackendStats, DomainInfo as ProtoDomainInfo,
+    DomainUpgradeCheckReport as ProtoDomainUpgradeCheckReport, Filter as ProtoFilter,
+    FilterExplain,
 };
 
 impl QueryServerReadV1 {
@@ -44,9 +50,107 @@ impl QueryServerReadV1 {
 
         idms_prox_read.qs_read.domain_upgrade_check()
     }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_backup(
+        &self,
+        path: &str,
+        eventid: Uuid,
+    ) -> Result<(), OperationError> {
+        if Path::new(path).exists() {
+            error!(?path, "Backup file already exists, will not overwrite it.");
+            return Err(OperationError::InvalidState);
+        }
+
+        // The backup is taken from a single read transaction, so it is consistent while writes
+        // continue.
+        let mut idms_prox_read = self.idms.proxy_read().await;
+        idms_prox_read
+            .qs_read
+            .get_be_txn()
+            .backup(path)
+            .map(|()| info!(?path, "Backup created successfully"))
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_verify(&self, eventid: Uuid) -> Vec<ConsistencyError> {
+        let mut idms_prox_read = self.idms.proxy_read().await;
+        let findings: Vec<_> = idms_prox_read
+            .qs_read
+            .verify()
+            .into_iter()
+            .filter_map(|r| r.err())
+            .collect();
+
+        if findings.is_empty() {
+            info!("Verification passed");
+        } else {
+            warn!(count = findings.len(), "Verification found inconsistencies");
+        }
+        findings
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_backend_stats(
+        &self,
+        eventid: Uuid,
+    ) -> Result<BackendStats, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await;
+        idms_prox_read.qs_read.get_be_txn().stats()
+    }
+
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_filter_explain(
+        &self,
+        filter: ProtoFilter,
+        eventid: Uuid,
+    ) -> Result<FilterExplain, OperationError> {
+        let mut idms_prox_read = self.idms.proxy_read().await;
+        let ident = Identity::from_internal();
+
+        let filter = Filter::from_ro(&ident, &filter, &mut idms_prox_read.qs_read)?;
+        let se =
+            SearchEvent::from_internal_message(ident, &filter, None, &mut idms_prox_read.qs_read)?;
+
+        idms_prox_read.qs_read.explain(&se)
+    }
 }
 
 impl QueryServerWriteV1 {
+    #[instrument(level = "info", skip_all)]
+    pub async fn handle_db_maintenance(&self, vacuum: bool) {
+        match self.idms.db_maintenance(vacuum).await {
+            Ok(res) => {
+                info!(
+                    vacuum = res.vacuum,
+                    size_before = res.size_before,
+                    size_after = res.size_after,
+                    wal_checkpointed = res.wal_checkpointed,
+                    "Database maintenance complete"
+                );
+            }
+            Err(err) => {
+                error!(?err, "Unable to complete database maintenance");
+            }
+        }
+    }
+
     #[instrument(
         level = "info",
         skip_all,
@@ -201,4 +305,20 @@ impl QueryServerWriteV1 {
 
         idms_prox_write.commit()
     }
+
+    #[instrument(
+        level = "info",
+        skip(self, eventid),
+        fields(uuid = ?eventid)
+    )]
+    pub(crate) async fn handle_reindex(&self, eventid: Uuid) -> Result<(), OperationError> {
+        let ct = duration_from_epoch_now();
+        let mut idms_prox_write = self.idms.proxy_write(ct).await;
+
+        // The indexes are rebuilt in this transaction, so searches use the old indexes until
+        // it commits.
+        idms_prox_write.qs_write.reindex()?;
+
+        idms_prox_write.commit()
+    }
 }
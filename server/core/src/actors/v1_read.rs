@@ -119,7 +119,7 @@ impl QueryServerReadV1 {
         // Destructure it.
         // Convert the AuthRequest to an AuthEvent that the idm server
         // can use.
-        let ae = AuthEvent::from_message(sessionid, req).map_err(|e| {
+        let ae = AuthEvent::from_message(eventid, sessionid, req).map_err(|e| {
             admin_error!(err = ?e, "Failed to parse AuthEvent");
             e
         })?;
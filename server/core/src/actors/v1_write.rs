@@ -16,6 +16,7 @@ use kanidmd_lib::{
     event::{CreateEvent, DeleteEvent, ModifyEvent, ReviveRecycledEvent},
     filter::{Filter, FilterInvalid},
     idm::account::DestroySessionTokenEvent,
+    idm::audit::AuditOperation,
     idm::credupdatesession::{
         CredentialUpdateIntentToken, CredentialUpdateSessionToken, InitCredentialUpdateEvent,
         InitCredentialUpdateIntentEvent,
@@ -145,7 +146,7 @@ impl QueryServerWriteV1 {
         client_auth_info: ClientAuthInfo,
         req: CreateRequest,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
+    ) -> Result<(Vec<Uuid>, Vec<String>), OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await;
 
@@ -155,6 +156,7 @@ impl QueryServerWriteV1 {
                 admin_error!(err = ?e, "Invalid identity");
                 e
             })?;
+        let audit_ident = ident.clone();
 
         let crt = match CreateEvent::from_message(ident, &req, &mut idms_prox_write.qs_write) {
             Ok(c) => c,
@@ -166,10 +168,21 @@ impl QueryServerWriteV1 {
 
         trace!(?crt, "Begin create event");
 
-        idms_prox_write
+        let res = idms_prox_write
             .qs_write
-            .create(&crt)
-            .and_then(|_| idms_prox_write.commit())
+            .create_returning_uuids(&crt)
+            .and_then(|uuids| {
+                let warnings = idms_prox_write.qs_write.take_warnings();
+                idms_prox_write.commit().map(|()| (uuids, warnings))
+            });
+        self.idms.audit_operation(
+            &audit_ident,
+            AuditOperation::Create,
+            eventid,
+            &res,
+            audit_location!(),
+        );
+        res
     }
 
     #[instrument(
@@ -182,7 +195,7 @@ impl QueryServerWriteV1 {
         client_auth_info: ClientAuthInfo,
         req: ModifyRequest,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
+    ) -> Result<Vec<String>, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await;
         let ident = idms_prox_write
@@ -191,6 +204,7 @@ impl QueryServerWriteV1 {
                 admin_error!(err = ?e, "Invalid identity");
                 e
             })?;
+        let audit_ident = ident.clone();
 
         let mdf = match ModifyEvent::from_message(ident, &req, &mut idms_prox_write.qs_write) {
             Ok(m) => m,
@@ -202,10 +216,18 @@ impl QueryServerWriteV1 {
 
         trace!(?mdf, "Begin modify event");
 
-        idms_prox_write
-            .qs_write
-            .modify(&mdf)
-            .and_then(|_| idms_prox_write.commit())
+        let res = idms_prox_write.qs_write.modify(&mdf).and_then(|()| {
+            let warnings = idms_prox_write.qs_write.take_warnings();
+            idms_prox_write.commit().map(|()| warnings)
+        });
+        self.idms.audit_operation(
+            &audit_ident,
+            AuditOperation::Modify,
+            eventid,
+            &res,
+            audit_location!(),
+        );
+        res
     }
 
     #[instrument(
@@ -218,7 +240,7 @@ impl QueryServerWriteV1 {
         client_auth_info: ClientAuthInfo,
         req: DeleteRequest,
         eventid: Uuid,
-    ) -> Result<(), OperationError> {
+    ) -> Result<Vec<String>, OperationError> {
         let ct = duration_from_epoch_now();
         let mut idms_prox_write = self.idms.proxy_write(ct).await;
         let ident = idms_prox_write
@@ -227,6 +249,7 @@ impl QueryServerWriteV1 {
                 admin_error!(err = ?e, "Invalid identity");
                 e
             })?;
+        let audit_ident = ident.clone();
         let del = match DeleteEvent::from_message(ident, &req, &mut idms_prox_write.qs_write) {
             Ok(d) => d,
             Err(e) => {
@@ -237,10 +260,18 @@ impl QueryServerWriteV1 {
 
         trace!(?del, "Begin delete event");
 
-        idms_prox_write
-            .qs_write
-            .delete(&del)
-            .and_then(|_| idms_prox_write.commit())
+        let res = idms_prox_write.qs_write.delete(&del).and_then(|()| {
+            let warnings = idms_prox_write.qs_write.take_warnings();
+            idms_prox_write.commit().map(|()| warnings)
+        });
+        self.idms.audit_operation(
+            &audit_ident,
+            AuditOperation::Delete,
+            eventid,
+            &res,
+            audit_location!(),
+        );
+        res
     }
 
     #[instrument(
@@ -264,6 +295,7 @@ impl QueryServerWriteV1 {
                 admin_error!(err = ?e, "Invalid identity");
                 e
             })?;
+        let audit_ident = ident.clone();
 
         // Transform the ProtoEntry to a Modlist
         let modlist =
@@ -281,10 +313,18 @@ impl QueryServerWriteV1 {
 
         trace!(?mdf, "Begin modify event");
 
-        idms_prox_write
+        let res = idms_prox_write
             .qs_write
             .modify(&mdf)
-            .and_then(|_| idms_prox_write.commit())
+            .and_then(|_| idms_prox_write.commit());
+        self.idms.audit_operation(
+            &audit_ident,
+            AuditOperation::Modify,
+            eventid,
+            &res,
+            audit_location!(),
+        );
+        res
     }
 
     #[instrument(
@@ -306,6 +346,7 @@ impl QueryServerWriteV1 {
                 admin_error!(err = ?e, "Invalid identity");
                 e
             })?;
+        let audit_ident = ident.clone();
         let del = match DeleteEvent::from_parts(ident, &filter, &mut idms_prox_write.qs_write) {
             Ok(d) => d,
             Err(e) => {
@@ -316,10 +357,18 @@ impl QueryServerWriteV1 {
 
         trace!(?del, "Begin delete event");
 
-        idms_prox_write
+        let res = idms_prox_write
             .qs_write
             .delete(&del)
-            .and_then(|_| idms_prox_write.commit().map(|_| ()))
+            .and_then(|_| idms_prox_write.commit().map(|_| ()));
+        self.idms.audit_operation(
+            &audit_ident,
+            AuditOperation::Delete,
+            eventid,
+            &res,
+            audit_location!(),
+        );
+        res
     }
 
     #[instrument(
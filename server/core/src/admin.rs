@@ -1,11 +1,18 @@
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
+use crate::audit::AuditStorePool;
 use crate::repl::ReplCtrl;
 use crate::CoreAction;
-use bytes::{BufMut, BytesMut};
+use bytes::{Buf, BufMut, BytesMut};
 use futures::{SinkExt, StreamExt};
 use kanidm_lib_crypto::serialise::x509b64;
+use kanidm_proto::internal::{
+    BackendStats, ConsistencyError, Filter as ProtoFilter, FilterExplain,
+};
 use kanidm_utils_users::get_current_uid;
 use serde::{Deserialize, Serialize};
+use sketching::capture::DebugCapture;
+use sketching::filter::{LogLevels, LogSubsystem};
+use sketching::LogLevel;
 use std::error::Error;
 use std::io;
 use std::path::Path;
@@ -22,16 +29,57 @@ pub use kanidm_proto::internal::{
     DomainUpgradeCheckStatus as ProtoDomainUpgradeCheckStatus,
 };
 
+/// Which audit events to return from the audit store. Times are unix timestamps in seconds.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AuditSearch {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub name: Option<String>,
+    pub spn: Option<String>,
+    /// One of success, denied or error. Only events about an operation have an outcome.
+    pub outcome: Option<String>,
+    pub limit: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub enum AdminTaskRequest {
-    RecoverAccount { name: String },
+    RecoverAccount {
+        name: String,
+    },
     ShowReplicationCertificate,
     RenewReplicationCertificate,
     RefreshReplicationConsumer,
     DomainShow,
     DomainUpgradeCheck,
     DomainRaise,
-    DomainRemigrate { level: Option<u32> },
+    DomainRemigrate {
+        level: Option<u32>,
+    },
+    Reindex,
+    Verify,
+    BackendStats,
+    FilterExplain {
+        filter: ProtoFilter,
+    },
+    Backup {
+        /// Where the server writes the backup. This must not exist.
+        path: String,
+    },
+    AuditSearch {
+        search: AuditSearch,
+    },
+    AuditTail,
+    LogLevelShow,
+    LogLevelSet {
+        /// Only change this subsystem, otherwise the default level is changed.
+        subsystem: Option<LogSubsystem>,
+        level: LogLevel,
+    },
+    DebugCaptureShow,
+    DebugCaptureSet {
+        /// Who to capture the operations of, or stop capturing if not set.
+        capture: Option<DebugCapture>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -51,6 +99,33 @@ pub enum AdminTaskResponse {
     DomainShow {
         domain_info: ProtoDomainInfo,
     },
+    AuditSearch {
+        events: Vec<serde_json::Value>,
+    },
+    /// An audit event, streamed in response to [AdminTaskRequest::AuditTail].
+    AuditEvent {
+        event: serde_json::Value,
+    },
+    /// The client fell behind while tailing, and this many audit events were missed.
+    AuditTailLagged {
+        missed: u64,
+    },
+    LogLevels {
+        levels: LogLevels,
+    },
+    DebugCapture {
+        capture: Option<DebugCapture>,
+    },
+    /// The inconsistencies found by [AdminTaskRequest::Verify], empty if there are none.
+    Verify {
+        findings: Vec<ConsistencyError>,
+    },
+    BackendStats {
+        stats: BackendStats,
+    },
+    FilterExplain {
+        explain: FilterExplain,
+    },
     Success,
     Error,
 }
@@ -64,10 +139,13 @@ impl Decoder for ClientCodec {
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         trace!("Attempting to decode request ...");
-        match serde_json::from_slice::<AdminTaskResponse>(src) {
-            Ok(msg) => {
-                // Clear the buffer for the next message.
-                src.clear();
+        // A streamed response may deliver several messages in one read, so only consume
+        // the first.
+        let mut stream = serde_json::Deserializer::from_slice(src).into_iter::<AdminTaskResponse>();
+        match stream.next() {
+            Some(Ok(msg)) => {
+                let consumed = stream.byte_offset();
+                src.advance(consumed);
                 Ok(Some(msg))
             }
             _ => Ok(None),
@@ -132,6 +210,8 @@ impl AdminActor {
         server_ro: &'static QueryServerReadV1,
         mut broadcast_rx: broadcast::Receiver<CoreAction>,
         repl_ctrl_tx: Option<mpsc::Sender<ReplCtrl>>,
+        audit_store: Option<AuditStorePool>,
+        audit_tail_tx: broadcast::Sender<String>,
     ) -> Result<tokio::task::JoinHandle<()>, ()> {
         debug!("🧹 Cleaning up sockets from previous invocations");
         rm_if_exist(sock_path);
@@ -178,8 +258,10 @@ impl AdminActor {
 
                                 // spawn the worker.
                                 let task_repl_ctrl_tx = repl_ctrl_tx.clone();
+                                let task_audit_store = audit_store.clone();
+                                let task_audit_tail_tx = audit_tail_tx.clone();
                                 tokio::spawn(async move {
-                                    if let Err(e) = handle_client(socket, server_rw, server_ro, task_repl_ctrl_tx).await {
+                                    if let Err(e) = handle_client(socket, server_rw, server_ro, task_repl_ctrl_tx, task_audit_store, task_audit_tail_tx).await {
                                         error!(err = ?e, "admin client error");
                                     }
                                 });
@@ -291,11 +373,61 @@ async fn replication_consumer_refresh(ctrl_tx: &mut mpsc::Sender<ReplCtrl>) -> A
     }
 }
 
+async fn audit_search(audit_store: AuditStorePool, search: AuditSearch) -> AdminTaskResponse {
+    // Sqlite is blocking, so keep it off the async workers.
+    let res = tokio::task::spawn_blocking(move || audit_store.search(&search)).await;
+
+    match res {
+        Ok(Ok(events)) => AdminTaskResponse::AuditSearch { events },
+        Ok(Err(e)) => {
+            error!(err = ?e, "error during audit search");
+            AdminTaskResponse::Error
+        }
+        Err(e) => {
+            error!(err = ?e, "audit search task failed");
+            AdminTaskResponse::Error
+        }
+    }
+}
+
+async fn audit_tail(
+    reqs: &mut Framed<UnixStream, ServerCodec>,
+    mut audit_tail_rx: broadcast::Receiver<String>,
+) -> Result<(), Box<dyn Error>> {
+    info!("Streaming audit events to admin client");
+    loop {
+        let resp = tokio::select! {
+            // Any further request, or the client disconnecting, ends the stream.
+            _ = reqs.next() => break,
+            res = audit_tail_rx.recv() => match res {
+                Ok(json) => match serde_json::from_str(&json) {
+                    Ok(event) => AdminTaskResponse::AuditEvent { event },
+                    Err(e) => {
+                        error!(err = ?e, "unable to parse audit event");
+                        continue;
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    warn!(%missed, "admin client is lagging, audit events were missed");
+                    AdminTaskResponse::AuditTailLagged { missed }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        };
+        reqs.send(resp).await?;
+        reqs.flush().await?;
+    }
+    debug!("Stopped streaming audit events");
+    Ok(())
+}
+
 async fn handle_client(
     sock: UnixStream,
     server_rw: &'static QueryServerWriteV1,
     server_ro: &'static QueryServerReadV1,
     mut repl_ctrl_tx: Option<mpsc::Sender<ReplCtrl>>,
+    audit_store: Option<AuditStorePool>,
+    audit_tail_tx: broadcast::Sender<String>,
 ) -> Result<(), Box<dyn Error>> {
     debug!("Accepted admin socket connection");
 
@@ -307,6 +439,14 @@ async fn handle_client(
         let eventid = Uuid::new_v4();
         let nspan = span!(Level::INFO, "handle_admin_client_request", uuid = ?eventid);
 
+        if let AdminTaskRequest::AuditTail = req {
+            // This streams until the client goes away, so it can't be a single response.
+            audit_tail(&mut reqs, audit_tail_tx.subscribe())
+                .instrument(nspan)
+                .await?;
+            break;
+        }
+
         let resp = async {
             match req {
                 AdminTaskRequest::RecoverAccount { name } => {
@@ -373,6 +513,90 @@ async fn handle_client(
                         }
                     }
                 }
+                AdminTaskRequest::Reindex => match server_rw.handle_reindex(eventid).await {
+                    Ok(()) => AdminTaskResponse::Success,
+                    Err(e) => {
+                        error!(err = ?e, "error during reindex");
+                        AdminTaskResponse::Error
+                    }
+                },
+                AdminTaskRequest::Verify => {
+                    let findings = server_ro.handle_verify(eventid).await;
+                    AdminTaskResponse::Verify { findings }
+                }
+                AdminTaskRequest::BackendStats => {
+                    match server_ro.handle_backend_stats(eventid).await {
+                        Ok(stats) => AdminTaskResponse::BackendStats { stats },
+                        Err(e) => {
+                            error!(err = ?e, "error while collecting backend stats");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::FilterExplain { filter } => {
+                    match server_ro.handle_filter_explain(filter, eventid).await {
+                        Ok(explain) => AdminTaskResponse::FilterExplain { explain },
+                        Err(e) => {
+                            error!(err = ?e, "error while explaining filter");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::Backup { path } => {
+                    match server_ro.handle_backup(&path, eventid).await {
+                        Ok(()) => AdminTaskResponse::Success,
+                        Err(e) => {
+                            error!(err = ?e, "error during backup");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::LogLevelShow => match sketching::filter::log_levels() {
+                    Some(levels) => AdminTaskResponse::LogLevels { levels },
+                    None => {
+                        error!("log levels can not be changed at runtime.");
+                        AdminTaskResponse::Error
+                    }
+                },
+                AdminTaskRequest::LogLevelSet { subsystem, level } => {
+                    match sketching::filter::set_log_level(subsystem, level) {
+                        Ok(levels) => {
+                            info!(?subsystem, %level, "Changed log level");
+                            AdminTaskResponse::LogLevels { levels }
+                        }
+                        Err(e) => {
+                            error!(err = %e, "error during log level change");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                AdminTaskRequest::DebugCaptureShow => AdminTaskResponse::DebugCapture {
+                    capture: sketching::capture::debug_capture(),
+                },
+                AdminTaskRequest::DebugCaptureSet { capture } => {
+                    match sketching::capture::set_debug_capture(capture.clone()) {
+                        Ok(()) => {
+                            match capture.as_ref() {
+                                Some(capture) => info!(%capture, "Started debug capture"),
+                                None => info!("Stopped debug capture"),
+                            }
+                            AdminTaskResponse::DebugCapture { capture }
+                        }
+                        Err(e) => {
+                            error!(err = %e, "error during debug capture change");
+                            AdminTaskResponse::Error
+                        }
+                    }
+                }
+                // Streamed above, this never reaches here.
+                AdminTaskRequest::AuditTail => AdminTaskResponse::Error,
+                AdminTaskRequest::AuditSearch { search } => match audit_store.as_ref() {
+                    Some(audit_store) => audit_search(audit_store.clone(), search).await,
+                    None => {
+                        error!("audit store not configured, unable to search audit events.");
+                        AdminTaskResponse::Error
+                    }
+                },
             }
         }
         .instrument(nspan)
@@ -385,3 +609,28 @@ async fn handle_client(
     debug!("Disconnecting client ...");
     Ok(())
 }
+
+#[test]
+fn test_client_codec_decodes_streamed_responses() {
+    let mut src = BytesMut::new();
+    src.put_slice(br#"{"AuditTailLagged":{"missed":3}}"Success"{"AuditEvent":{"event":"#);
+
+    let mut codec = ClientCodec;
+    assert!(matches!(
+        codec.decode(&mut src),
+        Ok(Some(AdminTaskResponse::AuditTailLagged { missed: 3 }))
+    ));
+    assert!(matches!(
+        codec.decode(&mut src),
+        Ok(Some(AdminTaskResponse::Success))
+    ));
+    // The rest has not arrived yet.
+    assert!(matches!(codec.decode(&mut src), Ok(None)));
+
+    src.put_slice(br#"{"id":1}}}"#);
+    assert!(matches!(
+        codec.decode(&mut src),
+        Ok(Some(AdminTaskResponse::AuditEvent { .. }))
+    ));
+    assert!(src.is_empty());
+}
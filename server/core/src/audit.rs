@@ -0,0 +1,1472 @@
+//! The audit daemon. This receives audit events that the idm server submits during
+//! request processing, and serialises them to the configured output. This work occurs
+//! on a dedicated task so that it does not add latency to the request path.
+
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{NaiveDate, SecondsFormat, Utc};
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Private};
+use openssl::sign::Signer;
+use rusqlite::types::{Type, Value};
+use rusqlite::{params, params_from_iter, Connection};
+use tokio::sync::broadcast;
+
+use kanidmd_lib::idm::audit::{AuditEvent, AuditOperation, AuditSeverity, AuditSource};
+use kanidmd_lib::prelude::IdmServerAudit;
+
+use crate::admin::AuditSearch;
+use crate::config::{AuditEncoding, AuditFormat, AuditLogConfig, AuditSyslogConfig, Configuration};
+use crate::CoreAction;
+
+const AUDIT_VENDOR: &str = "Kanidm";
+const AUDIT_PRODUCT: &str = "kanidmd";
+const AUDIT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The fields common to audit events, used to build the SIEM formats. Events that are not
+/// about an account have no spn, uuid or eventid.
+struct AuditFields<'a> {
+    id: &'static str,
+    name: &'static str,
+    time_ms: i128,
+    protocol: &'static str,
+    source: Option<String>,
+    spn: Option<&'a str>,
+    uuid: Option<String>,
+    eventid: Option<String>,
+}
+
+impl<'a> AuditFields<'a> {
+    fn new(audit_event: &'a AuditEvent) -> Self {
+        match audit_event {
+            AuditEvent::AuthenticationDenied {
+                source,
+                uuid,
+                spn,
+                time,
+                eventid,
+                ..
+            } => {
+                let (protocol, source) = match source {
+                    AuditSource::Internal => ("internal", None),
+                    AuditSource::Https(ip) => ("https", Some(ip.to_string())),
+                    AuditSource::Ldaps(ip) => ("ldaps", Some(ip.to_string())),
+                };
+                AuditFields {
+                    id: "AuthenticationDenied",
+                    name: "Authentication denied",
+                    time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                    protocol,
+                    source,
+                    spn: Some(spn),
+                    uuid: Some(uuid.to_string()),
+                    eventid: Some(eventid.to_string()),
+                }
+            }
+            AuditEvent::Operation {
+                source,
+                uuid,
+                spn,
+                operation,
+                time,
+                eventid,
+                ..
+            } => {
+                let (protocol, source) = match source {
+                    AuditSource::Internal => ("internal", None),
+                    AuditSource::Https(ip) => ("https", Some(ip.to_string())),
+                    AuditSource::Ldaps(ip) => ("ldaps", Some(ip.to_string())),
+                };
+                let (id, name) = match operation {
+                    AuditOperation::Create => ("Create", "Entries created"),
+                    AuditOperation::Modify => ("Modify", "Entries modified"),
+                    AuditOperation::Delete => ("Delete", "Entries deleted"),
+                };
+                AuditFields {
+                    id,
+                    name,
+                    time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                    protocol,
+                    source,
+                    spn: Some(spn),
+                    uuid: Some(uuid.to_string()),
+                    eventid: Some(eventid.to_string()),
+                }
+            }
+            AuditEvent::AuditPurged { time, .. } => AuditFields {
+                id: "AuditPurged",
+                name: "Audit events purged",
+                time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                protocol: "internal",
+                source: None,
+                spn: None,
+                uuid: None,
+                eventid: None,
+            },
+            AuditEvent::AuditDropped { time, .. } => AuditFields {
+                id: "AuditDropped",
+                name: "Audit events dropped",
+                time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                protocol: "internal",
+                source: None,
+                spn: None,
+                uuid: None,
+                eventid: None,
+            },
+            AuditEvent::DbMaintenance { time, .. } => AuditFields {
+                id: "DbMaintenance",
+                name: "Database maintenance completed",
+                time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                protocol: "internal",
+                source: None,
+                spn: None,
+                uuid: None,
+                eventid: None,
+            },
+            AuditEvent::DbMaintenanceFailed { time, .. } => AuditFields {
+                id: "DbMaintenanceFailed",
+                name: "Database maintenance failed",
+                time_ms: time.unix_timestamp_nanos() / 1_000_000,
+                protocol: "internal",
+                source: None,
+                spn: None,
+                uuid: None,
+                eventid: None,
+            },
+        }
+    }
+}
+
+/// CEF and LEEF both express severity as 0 (lowest) to 10 (highest).
+fn siem_severity(severity: AuditSeverity) -> u8 {
+    match severity {
+        AuditSeverity::Trace => 1,
+        AuditSeverity::Info => 3,
+        AuditSeverity::Warn => 6,
+        AuditSeverity::Security => 8,
+    }
+}
+
+fn cef_escape_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|")
+}
+
+fn cef_escape_extension(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('=', "\\=")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn leef_escape(value: &str) -> String {
+    // LEEF has no escaping, so remove anything that would split the attributes.
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+/// Render an audit event as an ArcSight Common Event Format line.
+fn render_cef(audit_event: &AuditEvent) -> String {
+    let fields = AuditFields::new(audit_event);
+    let mut record = format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|rt={} app={}",
+        AUDIT_VENDOR,
+        AUDIT_PRODUCT,
+        AUDIT_VERSION,
+        fields.id,
+        cef_escape_header(fields.name),
+        siem_severity(audit_event.severity()),
+        fields.time_ms,
+        fields.protocol,
+    );
+    if let Some(spn) = fields.spn {
+        record.push_str(&format!(" suser={}", cef_escape_extension(spn)));
+    }
+    if let Some(uuid) = fields.uuid {
+        record.push_str(&format!(" suid={}", uuid));
+    }
+    if let Some(eventid) = fields.eventid {
+        record.push_str(&format!(" externalId={}", eventid));
+    }
+    if let Some(source) = fields.source {
+        record.push_str(&format!(" src={}", source));
+    }
+    if let Some(outcome) = audit_event.outcome() {
+        record.push_str(&format!(" outcome={}", outcome));
+    }
+    record
+}
+
+/// Render an audit event as a QRadar Log Event Extended Format line.
+fn render_leef(audit_event: &AuditEvent) -> String {
+    let fields = AuditFields::new(audit_event);
+    let mut record = format!(
+        "LEEF:1.0|{}|{}|{}|{}|devTime={}\tsev={}\tproto={}",
+        AUDIT_VENDOR,
+        AUDIT_PRODUCT,
+        AUDIT_VERSION,
+        fields.id,
+        fields.time_ms,
+        siem_severity(audit_event.severity()),
+        fields.protocol,
+    );
+    if let Some(spn) = fields.spn {
+        record.push_str(&format!("\tusrName={}", leef_escape(spn)));
+    }
+    if let Some(uuid) = fields.uuid {
+        record.push_str(&format!("\tuserUuid={}", uuid));
+    }
+    if let Some(eventid) = fields.eventid {
+        record.push_str(&format!("\teventId={}", eventid));
+    }
+    if let Some(source) = fields.source {
+        record.push_str(&format!("\tsrc={}", source));
+    }
+    if let Some(outcome) = audit_event.outcome() {
+        record.push_str(&format!("\toutcome={}", outcome));
+    }
+    record
+}
+
+/// Render an audit event in the format a sink requested. The json form is always
+/// produced for the server log, so it is reused rather than serialised again.
+fn render(audit_event: &AuditEvent, format: AuditFormat, json: &str) -> String {
+    match format {
+        AuditFormat::Json => json.to_string(),
+        AuditFormat::Cef => render_cef(audit_event),
+        AuditFormat::Leef => render_leef(audit_event),
+    }
+}
+
+/// Links each audit record to the one before it, by embedding the hash of the previous
+/// line. Any removed or altered line breaks the chain from that point onward.
+struct AuditChain {
+    key: Option<PKey<Private>>,
+    prev_hash: String,
+}
+
+impl AuditChain {
+    fn new(key_path: Option<&Path>, last_line: Option<&str>) -> io::Result<Self> {
+        let key = match key_path {
+            Some(key_path) => {
+                let key = fs::read_to_string(key_path)?;
+                let key = key.trim_end().as_bytes();
+                if key.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "audit hash chain key is empty",
+                    ));
+                }
+                Some(PKey::hmac(key).map_err(io::Error::other)?)
+            }
+            None => None,
+        };
+
+        let mut chain = AuditChain {
+            key,
+            // The first record of a new chain has no predecessor.
+            prev_hash: "0".repeat(64),
+        };
+        if let Some(last_line) = last_line {
+            chain.prev_hash = chain.hash(last_line)?;
+        }
+        Ok(chain)
+    }
+
+    fn hash(&self, line: &str) -> io::Result<String> {
+        let digest = match &self.key {
+            Some(key) => {
+                let mut signer =
+                    Signer::new(MessageDigest::sha256(), key).map_err(io::Error::other)?;
+                signer
+                    .sign_oneshot_to_vec(line.as_bytes())
+                    .map_err(io::Error::other)?
+            }
+            None => openssl::sha::sha256(line.as_bytes()).to_vec(),
+        };
+        Ok(hex::encode(digest))
+    }
+
+    fn link(&mut self, record: &str, format: AuditFormat) -> io::Result<String> {
+        let line = match format {
+            AuditFormat::Json => {
+                format!(r#"{{"prev_hash":"{}","event":{}}}"#, self.prev_hash, record)
+            }
+            AuditFormat::Cef => format!("{} cs1Label=prevHash cs1={}", record, self.prev_hash),
+            AuditFormat::Leef => format!("{}\tprevHash={}", record, self.prev_hash),
+        };
+        self.prev_hash = self.hash(&line)?;
+        Ok(line)
+    }
+}
+
+/// An append only audit log with one event per line, that is rotated once it exceeds a
+/// maximum size, or optionally when the day (UTC) changes.
+pub(crate) struct AuditLogFile {
+    path: PathBuf,
+    format: AuditFormat,
+    max_size: u64,
+    rotate_daily: bool,
+    versions: usize,
+    file: File,
+    size: u64,
+    opened: NaiveDate,
+    chain: Option<AuditChain>,
+}
+
+impl AuditLogFile {
+    pub fn open(cfg: &AuditLogConfig, today: NaiveDate) -> io::Result<Self> {
+        let path = PathBuf::from(&cfg.path);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        // Continue the chain from the last record written before we were restarted.
+        let chain = if cfg.hash_chain {
+            let existing = fs::read_to_string(&path)?;
+            Some(AuditChain::new(
+                cfg.hash_chain_key.as_deref(),
+                existing.lines().last(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(AuditLogFile {
+            path,
+            format: cfg.format,
+            max_size: cfg.max_size,
+            rotate_daily: cfg.rotate_daily,
+            versions: cfg.versions,
+            file,
+            size,
+            opened: today,
+            chain,
+        })
+    }
+
+    pub fn write(&mut self, record: &str, today: NaiveDate) -> io::Result<()> {
+        let chained;
+        let record = match self.chain.as_mut() {
+            Some(chain) => {
+                chained = chain.link(record, self.format)?;
+                chained.as_str()
+            }
+            None => record,
+        };
+
+        let len = record.len() as u64 + 1;
+        let oversize = self.size > 0 && self.size + len > self.max_size;
+        let new_day = self.rotate_daily && today != self.opened;
+        if oversize || new_day {
+            self.rotate(today)?;
+        }
+
+        writeln!(self.file, "{}", record)?;
+        self.file.flush()?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Write the audit log through to the disk.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_all()
+    }
+
+    fn rotated_path(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self, today: NaiveDate) -> io::Result<()> {
+        self.file.sync_all()?;
+
+        // Shuffle path.N-1 -> path.N ... path -> path.1, the oldest falls off the end.
+        if self.versions == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.versions).rev() {
+                let from = self.rotated_path(n);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(n + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        self.opened = today;
+        Ok(())
+    }
+}
+
+/// The syslog facility audit events are sent with, `authpriv` (10).
+const SYSLOG_FACILITY_AUTHPRIV: u8 = 10;
+
+/// The syslog severity for an audit severity. Security events are reported as
+/// `warning` so that they are not mistaken for faults in the server itself.
+fn syslog_severity(severity: AuditSeverity) -> u8 {
+    match severity {
+        AuditSeverity::Trace => 7,
+        AuditSeverity::Info => 6,
+        AuditSeverity::Warn | AuditSeverity::Security => 4,
+    }
+}
+
+enum AuditSyslogSocket {
+    Unix(UnixDatagram),
+    Udp(UdpSocket),
+}
+
+/// Sends audit events to a syslog daemon as RFC5424 messages, over either a unix
+/// datagram socket or udp.
+pub(crate) struct AuditSyslog {
+    socket: AuditSyslogSocket,
+    format: AuditFormat,
+}
+
+impl AuditSyslog {
+    pub fn connect(cfg: &AuditSyslogConfig) -> io::Result<Self> {
+        let socket = if cfg.address.starts_with('/') {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(&cfg.address)?;
+            AuditSyslogSocket::Unix(socket)
+        } else {
+            let addr = cfg.address.to_socket_addrs()?.next().ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "no addresses to send to")
+            })?;
+            let socket = if addr.is_ipv4() {
+                UdpSocket::bind("0.0.0.0:0")?
+            } else {
+                UdpSocket::bind("[::]:0")?
+            };
+            socket.connect(addr)?;
+            AuditSyslogSocket::Udp(socket)
+        };
+        Ok(AuditSyslog {
+            socket,
+            format: cfg.format,
+        })
+    }
+
+    fn format(record: &str, severity: AuditSeverity) -> String {
+        let pri = SYSLOG_FACILITY_AUTHPRIV * 8 + syslog_severity(severity);
+        let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+        // <PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+        format!(
+            "<{}>1 {} - kanidmd {} audit - {}",
+            pri,
+            timestamp,
+            std::process::id(),
+            record
+        )
+    }
+
+    pub fn write(&self, record: &str, severity: AuditSeverity) -> io::Result<()> {
+        let message = Self::format(record, severity);
+        match &self.socket {
+            AuditSyslogSocket::Unix(socket) => socket.send(message.as_bytes()),
+            AuditSyslogSocket::Udp(socket) => socket.send(message.as_bytes()),
+        }
+        .map(|_| ())
+    }
+}
+
+/// The number of events an audit search returns when no limit is requested.
+const AUDIT_SEARCH_DEFAULT_LIMIT: u32 = 100;
+
+/// Records audit events in a dedicated sqlite database, so that past events can be searched
+/// from the admin socket. This is separate to the main database so that recording audit
+/// events never contends with the write transaction.
+pub(crate) struct AuditStore {
+    conn: Connection,
+    encoding: AuditEncoding,
+}
+
+impl AuditStore {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        // The audit daemon writes while the admin socket reads.
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS audit_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                time INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                spn TEXT NOT NULL,
+                outcome TEXT,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS audit_events_time_idx ON audit_events (time);",
+        )?;
+        Ok(AuditStore {
+            conn,
+            encoding: AuditEncoding::default(),
+        })
+    }
+
+    /// Set how new events are encoded. This does not affect searching.
+    pub fn with_encoding(mut self, encoding: AuditEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn insert(&self, audit_event: &AuditEvent, json: &str) -> rusqlite::Result<()> {
+        let fields = AuditFields::new(audit_event);
+        let data = match self.encoding {
+            AuditEncoding::Json => Value::Text(json.to_string()),
+            AuditEncoding::Cbor => Value::Blob(
+                serde_cbor::to_vec(audit_event)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?,
+            ),
+        };
+        self.conn
+            .execute(
+                "INSERT INTO audit_events (time, name, spn, outcome, data) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    (fields.time_ms / 1000) as i64,
+                    fields.id,
+                    fields.spn.unwrap_or_default(),
+                    audit_event.outcome().map(|outcome| outcome.to_string()),
+                    data
+                ],
+            )
+            .map(|_| ())
+    }
+
+    /// Remove events recorded before `before` (unix seconds), returning how many were removed.
+    pub fn purge(&self, before: i64) -> rusqlite::Result<usize> {
+        self.conn
+            .execute("DELETE FROM audit_events WHERE time < ?1", params![before])
+    }
+
+    /// Return the most recent events matching the search, oldest first.
+    pub fn search(&self, search: &AuditSearch) -> rusqlite::Result<Vec<serde_json::Value>> {
+        let mut clauses = Vec::new();
+        let mut values = Vec::new();
+        if let Some(from) = search.from {
+            clauses.push("time >= ?");
+            values.push(Value::Integer(from));
+        }
+        if let Some(to) = search.to {
+            clauses.push("time <= ?");
+            values.push(Value::Integer(to));
+        }
+        if let Some(name) = &search.name {
+            clauses.push("name = ?");
+            values.push(Value::Text(name.clone()));
+        }
+        if let Some(spn) = &search.spn {
+            clauses.push("spn = ?");
+            values.push(Value::Text(spn.clone()));
+        }
+        if let Some(outcome) = &search.outcome {
+            clauses.push("outcome = ?");
+            values.push(Value::Text(outcome.clone()));
+        }
+        values.push(Value::Integer(
+            search.limit.unwrap_or(AUDIT_SEARCH_DEFAULT_LIMIT).into(),
+        ));
+
+        let where_clause = if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT data FROM audit_events {} ORDER BY id DESC LIMIT ?",
+            where_clause
+        ))?;
+
+        let mut events = stmt
+            .query_map(params_from_iter(values), |row| match row.get(0)? {
+                Value::Text(data) => serde_json::from_str(&data).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e))
+                }),
+                // Uuids are bytes in cbor, so this must go through the event to become json.
+                Value::Blob(data) => serde_cbor::from_slice::<AuditEvent>(&data)
+                    .map_err(|e| {
+                        rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e))
+                    })
+                    .and_then(|audit_event| {
+                        serde_json::to_value(audit_event).map_err(|e| {
+                            rusqlite::Error::FromSqlConversionFailure(0, Type::Blob, Box::new(e))
+                        })
+                    }),
+                other => Err(rusqlite::Error::InvalidColumnType(
+                    0,
+                    "data".to_string(),
+                    other.data_type(),
+                )),
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        events.reverse();
+        Ok(events)
+    }
+}
+
+/// Connections to the audit store for searches from the admin socket. A search takes a
+/// connection from the pool, or opens one if none are free, and returns it when done so that
+/// the next search reuses it.
+#[derive(Clone)]
+pub(crate) struct AuditStorePool {
+    path: String,
+    pool: Arc<Mutex<VecDeque<AuditStore>>>,
+}
+
+impl AuditStorePool {
+    pub fn new(path: String) -> Self {
+        AuditStorePool {
+            path,
+            pool: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn search(&self, search: &AuditSearch) -> rusqlite::Result<Vec<serde_json::Value>> {
+        let pooled = match self.pool.lock() {
+            Ok(mut guard) => guard.pop_front(),
+            Err(e) => {
+                error!(err = ?e, "Unable to lock audit store pool");
+                None
+            }
+        };
+        let audit_store = match pooled {
+            Some(audit_store) => audit_store,
+            None => AuditStore::open(&self.path)?,
+        };
+
+        let res = audit_store.search(search);
+
+        match self.pool.lock() {
+            Ok(mut guard) => guard.push_back(audit_store),
+            Err(e) => error!(err = ?e, "Unable to return connection to audit store pool"),
+        }
+        res
+    }
+}
+
+pub(crate) struct AuditdActor {
+    min_severity: AuditSeverity,
+    audit_log: Option<AuditLogFile>,
+    audit_syslog: Option<AuditSyslog>,
+    audit_store: Option<AuditStore>,
+    retention: Option<time::Duration>,
+    /// The total number of dropped audit events that has been reported so far.
+    reported_dropped: u64,
+    /// Mirrors each emitted audit event as json to admin socket clients tailing the audit log.
+    audit_tail: broadcast::Sender<String>,
+}
+
+/// The number of audit events that a slow `kanidmd audit tail` client may fall behind by before
+/// it misses events.
+pub(crate) const AUDIT_TAIL_QUEUE_SIZE: usize = 256;
+
+/// How often the audit store is checked for events that have passed the retention window.
+const AUDIT_PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+impl AuditdActor {
+    pub fn start(
+        mut idms_audit: IdmServerAudit,
+        config: &Configuration,
+        mut rx: broadcast::Receiver<CoreAction>,
+        audit_tail: broadcast::Sender<String>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        let audit_log = match &config.audit_log {
+            Some(cfg) => match AuditLogFile::open(cfg, Utc::now().date_naive()) {
+                Ok(audit_log) => Some(audit_log),
+                Err(e) => {
+                    error!(err = ?e, path = %cfg.path, "Unable to open audit log");
+                    return Err(());
+                }
+            },
+            None => None,
+        };
+
+        let audit_syslog = match &config.audit_syslog {
+            Some(cfg) => match AuditSyslog::connect(cfg) {
+                Ok(audit_syslog) => Some(audit_syslog),
+                Err(e) => {
+                    error!(err = ?e, address = %cfg.address, "Unable to connect to audit syslog");
+                    return Err(());
+                }
+            },
+            None => None,
+        };
+
+        let audit_store = match &config.audit_store {
+            Some(cfg) => match AuditStore::open(&cfg.path).map(|s| s.with_encoding(cfg.encoding)) {
+                Ok(audit_store) => Some(audit_store),
+                Err(e) => {
+                    error!(err = ?e, path = %cfg.path, "Unable to open audit store");
+                    return Err(());
+                }
+            },
+            None => None,
+        };
+
+        let mut actor = AuditdActor {
+            min_severity: config.audit_severity,
+            audit_log,
+            audit_syslog,
+            audit_store,
+            retention: config
+                .audit_store
+                .as_ref()
+                .and_then(|cfg| cfg.retention_days)
+                .map(|days| time::Duration::days(days.into())),
+            reported_dropped: 0,
+            audit_tail,
+        };
+
+        Ok(tokio::spawn(async move {
+            let mut purge_interval = tokio::time::interval(AUDIT_PURGE_INTERVAL);
+            loop {
+                let next = tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => {
+                                let mut queued = Vec::new();
+                                while let Ok(audit_event) = idms_audit.audit_rx().try_recv() {
+                                    queued.push(audit_event);
+                                }
+                                let dropped = idms_audit.audit_rx().dropped();
+                                actor.blocking(move |actor| actor.drain(queued, dropped)).await;
+                                break;
+                            }
+                        }
+                    }
+                    _ = purge_interval.tick() => {
+                        let dropped = idms_audit.audit_rx().dropped();
+                        actor.blocking(move |actor| {
+                            actor.report_dropped(dropped);
+                            actor.purge(time::OffsetDateTime::now_utc());
+                        }).await
+                    }
+                    maybe_audit_event = idms_audit.audit_rx().recv() => {
+                        match maybe_audit_event {
+                            Some(audit_event) => {
+                                let dropped = idms_audit.audit_rx().dropped();
+                                actor.blocking(move |actor| {
+                                    actor.report_dropped(dropped);
+                                    actor.process(&audit_event)
+                                }).await
+                            }
+                            None => {
+                                // All senders have been dropped, there is nothing
+                                // more for us to do.
+                                debug!("Audit event queue has closed");
+                                break;
+                            }
+                        }
+                    }
+                };
+
+                match next {
+                    Some(next) => actor = next,
+                    None => break,
+                }
+            }
+            info!("Stopped {}", super::TaskName::AuditdActor);
+        }))
+    }
+
+    /// The audit store, log file and syslog all block, so run their writes off the async
+    /// workers. The actor is moved to a blocking thread and returned when it is done.
+    async fn blocking<F>(self, f: F) -> Option<Self>
+    where
+        F: FnOnce(&mut Self) + Send + 'static,
+    {
+        let mut actor = self;
+        match tokio::task::spawn_blocking(move || {
+            f(&mut actor);
+            actor
+        })
+        .await
+        {
+            Ok(actor) => Some(actor),
+            Err(e) => {
+                error!(err = ?e, "audit task failed");
+                None
+            }
+        }
+    }
+
+    /// Process the audit events that were still queued, and make sure that everything recorded
+    /// has reached the disk, before stopping.
+    fn drain(&mut self, queued: Vec<AuditEvent>, dropped: u64) {
+        for audit_event in queued {
+            self.process(&audit_event);
+        }
+        self.report_dropped(dropped);
+
+        if let Some(audit_log) = self.audit_log.as_mut() {
+            if let Err(e) = audit_log.sync() {
+                error!(err = ?e, "Unable to sync audit log");
+            }
+        }
+    }
+
+    /// Record an audit event if more audit events have been dropped since the last report, so
+    /// that operators can see when audit is being shed.
+    fn report_dropped(&mut self, total: u64) {
+        if total <= self.reported_dropped {
+            return;
+        }
+        let dropped = total - self.reported_dropped;
+        self.reported_dropped = total;
+        self.process(&AuditEvent::AuditDropped {
+            time: time::OffsetDateTime::now_utc(),
+            dropped,
+            total,
+            location: audit_location!(),
+        });
+    }
+
+    /// Remove events older than the retention window from the audit store. The purge is
+    /// itself audited, so that removal of audit history is never silent.
+    fn purge(&mut self, now: time::OffsetDateTime) {
+        let (Some(audit_store), Some(retention)) = (self.audit_store.as_ref(), self.retention)
+        else {
+            return;
+        };
+
+        let before = now - retention;
+        let removed = match audit_store.purge(before.unix_timestamp()) {
+            Ok(removed) => removed,
+            Err(e) => {
+                error!(err = ?e, "Unable to purge audit store");
+                return;
+            }
+        };
+
+        if removed > 0 {
+            self.process(&AuditEvent::AuditPurged {
+                time: now,
+                before,
+                removed: removed as u64,
+                location: audit_location!(),
+            });
+        }
+    }
+
+    fn process(&mut self, audit_event: &AuditEvent) {
+        let severity = audit_event.severity();
+        if severity < self.min_severity && !audit_event.always_recorded() {
+            return;
+        }
+
+        let json = match serde_json::to_string(audit_event) {
+            Ok(json) => json,
+            Err(e) => {
+                error!(err=?e, "Unable to process audit event to json.");
+                warn!(?audit_event, json = false);
+                return;
+            }
+        };
+        warn!(audit_event = %json);
+
+        if self.audit_tail.receiver_count() > 0 {
+            // This only fails if every client disconnected in the meantime.
+            let _ = self.audit_tail.send(json.clone());
+        }
+
+        if let Some(audit_log) = self.audit_log.as_mut() {
+            let record = render(audit_event, audit_log.format, &json);
+            if let Err(e) = audit_log.write(&record, Utc::now().date_naive()) {
+                error!(err = ?e, "Unable to write audit event to audit log");
+            }
+        }
+        if let Some(audit_syslog) = self.audit_syslog.as_ref() {
+            let record = render(audit_event, audit_syslog.format, &json);
+            if let Err(e) = audit_syslog.write(&record, severity) {
+                error!(err = ?e, "Unable to send audit event to audit syslog");
+            }
+        }
+        if let Some(audit_store) = self.audit_store.as_ref() {
+            if let Err(e) = audit_store.insert(audit_event, &json) {
+                error!(err = ?e, "Unable to record audit event in audit store");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_audit_log_file_rotation() {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.log");
+    let mut cfg = AuditLogConfig::new(path.to_string_lossy().to_string());
+    cfg.max_size = 16;
+    cfg.rotate_daily = true;
+    cfg.versions = 2;
+
+    let day_one = NaiveDate::from_ymd_opt(2024, 1, 1).expect("invalid date");
+    let day_two = NaiveDate::from_ymd_opt(2024, 1, 2).expect("invalid date");
+
+    let mut audit_log = AuditLogFile::open(&cfg, day_one).expect("failed to open audit log");
+    let read = |p: &std::path::Path| fs::read_to_string(p).expect("failed to read audit log");
+
+    // Fits within max_size.
+    audit_log.write("aaaaaaaa", day_one).expect("write failed");
+    assert_eq!(read(&path), "aaaaaaaa\n");
+
+    // Exceeds max_size, so the current file is rotated out.
+    audit_log.write("bbbbbbbb", day_one).expect("write failed");
+    assert_eq!(read(&path), "bbbbbbbb\n");
+    assert_eq!(read(&dir.path().join("audit.log.1")), "aaaaaaaa\n");
+
+    // A new day rotates even though this would fit.
+    audit_log.write("c", day_two).expect("write failed");
+    assert_eq!(read(&path), "c\n");
+    assert_eq!(read(&dir.path().join("audit.log.1")), "bbbbbbbb\n");
+    assert_eq!(read(&dir.path().join("audit.log.2")), "aaaaaaaa\n");
+
+    // Only versions rotated logs are kept.
+    audit_log
+        .write("dddddddddddddddd", day_two)
+        .expect("write failed");
+    assert_eq!(read(&path), "dddddddddddddddd\n");
+    assert_eq!(read(&dir.path().join("audit.log.1")), "c\n");
+    assert_eq!(read(&dir.path().join("audit.log.2")), "bbbbbbbb\n");
+    assert!(!dir.path().join("audit.log.3").exists());
+}
+
+#[test]
+fn test_audit_syslog_unix() {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("syslog.sock");
+    let server = UnixDatagram::bind(&path).expect("failed to bind syslog socket");
+
+    let cfg = AuditSyslogConfig {
+        address: path.to_string_lossy().to_string(),
+        format: AuditFormat::Json,
+    };
+    let audit_syslog = AuditSyslog::connect(&cfg).expect("failed to connect to syslog");
+    audit_syslog
+        .write(r#"{"test":true}"#, AuditSeverity::Security)
+        .expect("failed to send");
+
+    let mut buf = [0u8; 512];
+    let len = server.recv(&mut buf).expect("failed to recv");
+    let message = std::str::from_utf8(&buf[..len]).expect("invalid utf8");
+
+    assert!(message.starts_with("<84>1 "));
+    assert!(message.ends_with(&format!(
+        " - kanidmd {} audit - {{\"test\":true}}",
+        std::process::id()
+    )));
+}
+
+#[test]
+fn test_audit_severity_filter() {
+    use kanidmd_lib::idm::audit::AuditSource;
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.log");
+    let cfg = AuditLogConfig::new(path.to_string_lossy().to_string());
+    let audit_log = AuditLogFile::open(&cfg, Utc::now().date_naive()).expect("failed to open");
+
+    let event = AuditEvent::AuthenticationDenied {
+        source: AuditSource::Internal,
+        uuid: Uuid::new_v4(),
+        spn: "testperson@example.com".to_string(),
+        time: time::OffsetDateTime::UNIX_EPOCH,
+        eventid: Uuid::new_v4(),
+        location: audit_location!(),
+    };
+    assert_eq!(event.severity(), AuditSeverity::Security);
+
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Security,
+        audit_log: Some(audit_log),
+        audit_syslog: None,
+        audit_store: None,
+        retention: None,
+        reported_dropped: 0,
+        audit_tail: broadcast::channel(1).0,
+    };
+    actor.process(&event);
+    assert_eq!(
+        fs::read_to_string(&path)
+            .expect("read failed")
+            .lines()
+            .count(),
+        1
+    );
+
+    // Severities are ordered so that the minimum drops everything below it.
+    assert!(AuditSeverity::Trace < AuditSeverity::Info);
+    assert!(AuditSeverity::Info < AuditSeverity::Warn);
+    assert!(AuditSeverity::Warn < AuditSeverity::Security);
+}
+
+#[test]
+fn test_audit_log_hash_chain() {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.log");
+    let key_path = dir.path().join("audit.key");
+    fs::write(&key_path, "very secret\n").expect("failed to write key");
+
+    let mut cfg = AuditLogConfig::new(path.to_string_lossy().to_string());
+    cfg.hash_chain = true;
+    cfg.hash_chain_key = Some(key_path.clone());
+
+    let today = Utc::now().date_naive();
+    let mut audit_log = AuditLogFile::open(&cfg, today).expect("failed to open audit log");
+    audit_log.write(r#"{"a":1}"#, today).expect("write failed");
+    audit_log.write(r#"{"b":2}"#, today).expect("write failed");
+    drop(audit_log);
+
+    // Reopening continues the chain from the last record.
+    let mut audit_log = AuditLogFile::open(&cfg, today).expect("failed to open audit log");
+    audit_log.write(r#"{"c":3}"#, today).expect("write failed");
+
+    let content = fs::read_to_string(&path).expect("failed to read audit log");
+    let lines: Vec<_> = content.lines().collect();
+    assert_eq!(lines.len(), 3);
+
+    let chain = AuditChain::new(Some(&key_path), None).expect("failed to create chain");
+    let mut prev_hash = "0".repeat(64);
+    for (line, event) in lines.iter().zip([r#"{"a":1}"#, r#"{"b":2}"#, r#"{"c":3}"#]) {
+        assert_eq!(
+            *line,
+            format!(r#"{{"prev_hash":"{}","event":{}}}"#, prev_hash, event)
+        );
+        prev_hash = chain.hash(line).expect("hash failed");
+    }
+
+    // Without the key, the chain can not be reproduced.
+    let unkeyed = AuditChain::new(None, None).expect("failed to create chain");
+    assert_ne!(
+        unkeyed.hash(lines[0]).expect("hash failed"),
+        chain.hash(lines[0]).expect("hash failed")
+    );
+}
+
+#[test]
+fn test_audit_siem_formats() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let uuid = Uuid::new_v4();
+    let eventid = Uuid::new_v4();
+    let event = AuditEvent::AuthenticationDenied {
+        source: AuditSource::Https("192.0.2.1".parse().expect("invalid ip")),
+        uuid,
+        spn: "test|person=1@example.com".to_string(),
+        time: time::OffsetDateTime::UNIX_EPOCH + time::Duration::seconds(1),
+        eventid,
+        location: audit_location!(),
+    };
+
+    assert_eq!(
+        render_cef(&event),
+        format!(
+            "CEF:0|Kanidm|kanidmd|{}|AuthenticationDenied|Authentication denied|8|rt=1000 app=https suser=test|person\\=1@example.com suid={} externalId={} src=192.0.2.1",
+            AUDIT_VERSION, uuid, eventid
+        )
+    );
+
+    assert_eq!(
+        render_leef(&event),
+        format!(
+            "LEEF:1.0|Kanidm|kanidmd|{}|AuthenticationDenied|devTime=1000\tsev=8\tproto=https\tusrName=test|person=1@example.com\tuserUuid={}\teventId={}\tsrc=192.0.2.1",
+            AUDIT_VERSION, uuid, eventid
+        )
+    );
+
+    // Chained siem records carry the previous hash as an extra attribute.
+    let mut chain = AuditChain::new(None, None).expect("failed to create chain");
+    let line = chain
+        .link(&render_cef(&event), AuditFormat::Cef)
+        .expect("link failed");
+    assert!(line.ends_with(&format!(" cs1Label=prevHash cs1={}", "0".repeat(64))));
+}
+
+#[test]
+fn test_audit_store_search() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.db");
+    let audit_store =
+        AuditStore::open(&path.to_string_lossy()).expect("failed to open audit store");
+
+    for (secs, spn) in [
+        (10, "alice@example.com"),
+        (20, "bob@example.com"),
+        (30, "alice@example.com"),
+    ] {
+        let event = AuditEvent::AuthenticationDenied {
+            source: AuditSource::Internal,
+            uuid: Uuid::new_v4(),
+            spn: spn.to_string(),
+            time: time::OffsetDateTime::UNIX_EPOCH + time::Duration::seconds(secs),
+            eventid: Uuid::new_v4(),
+            location: audit_location!(),
+        };
+        let json = serde_json::to_string(&event).expect("failed to serialise");
+        audit_store.insert(&event, &json).expect("insert failed");
+    }
+
+    let spns = |search: AuditSearch| -> Vec<String> {
+        audit_store
+            .search(&search)
+            .expect("search failed")
+            .into_iter()
+            .map(|event| {
+                event["AuthenticationDenied"]["spn"]
+                    .as_str()
+                    .expect("no spn")
+                    .to_string()
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        spns(AuditSearch::default()),
+        ["alice@example.com", "bob@example.com", "alice@example.com"]
+    );
+    assert_eq!(
+        spns(AuditSearch {
+            from: Some(15),
+            to: Some(25),
+            ..Default::default()
+        }),
+        ["bob@example.com"]
+    );
+    assert_eq!(
+        spns(AuditSearch {
+            spn: Some("alice@example.com".to_string()),
+            name: Some("AuthenticationDenied".to_string()),
+            ..Default::default()
+        }),
+        ["alice@example.com", "alice@example.com"]
+    );
+    // The limit keeps the most recent events.
+    assert_eq!(
+        spns(AuditSearch {
+            limit: Some(1),
+            ..Default::default()
+        }),
+        ["alice@example.com"]
+    );
+    assert_eq!(
+        spns(AuditSearch {
+            name: Some("Nothing".to_string()),
+            ..Default::default()
+        }),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_audit_store_purge() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.db");
+    let audit_store =
+        AuditStore::open(&path.to_string_lossy()).expect("failed to open audit store");
+
+    let now = time::OffsetDateTime::UNIX_EPOCH + time::Duration::days(10);
+    for days in [1, 5, 9] {
+        let event = AuditEvent::AuthenticationDenied {
+            source: AuditSource::Internal,
+            uuid: Uuid::new_v4(),
+            spn: format!("day{}@example.com", days),
+            time: time::OffsetDateTime::UNIX_EPOCH + time::Duration::days(days),
+            eventid: Uuid::new_v4(),
+            location: audit_location!(),
+        };
+        let json = serde_json::to_string(&event).expect("failed to serialise");
+        audit_store.insert(&event, &json).expect("insert failed");
+    }
+
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Info,
+        audit_log: None,
+        audit_syslog: None,
+        audit_store: Some(audit_store),
+        retention: Some(time::Duration::days(3)),
+        reported_dropped: 0,
+        audit_tail: broadcast::channel(1).0,
+    };
+    actor.purge(now);
+
+    let events = actor
+        .audit_store
+        .as_ref()
+        .expect("no audit store")
+        .search(&AuditSearch::default())
+        .expect("search failed");
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0]["AuthenticationDenied"]["spn"].as_str(),
+        Some("day9@example.com")
+    );
+    // The purge records itself.
+    assert_eq!(events[1]["AuditPurged"]["removed"].as_u64(), Some(2));
+
+    // Nothing else has expired, so there is nothing more to record.
+    actor.purge(now);
+    let events = actor
+        .audit_store
+        .as_ref()
+        .expect("no audit store")
+        .search(&AuditSearch::default())
+        .expect("search failed");
+    assert_eq!(events.len(), 2);
+}
+
+#[test]
+fn test_audit_store_purge_min_severity() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.db");
+    let audit_store =
+        AuditStore::open(&path.to_string_lossy()).expect("failed to open audit store");
+
+    let event = AuditEvent::AuthenticationDenied {
+        source: AuditSource::Internal,
+        uuid: Uuid::new_v4(),
+        spn: "day1@example.com".to_string(),
+        time: time::OffsetDateTime::UNIX_EPOCH + time::Duration::days(1),
+        eventid: Uuid::new_v4(),
+        location: audit_location!(),
+    };
+    let json = serde_json::to_string(&event).expect("failed to serialise");
+    audit_store.insert(&event, &json).expect("insert failed");
+
+    // Purges are recorded even though they are below the minimum severity.
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Security,
+        audit_log: None,
+        audit_syslog: None,
+        audit_store: Some(audit_store),
+        retention: Some(time::Duration::days(3)),
+        reported_dropped: 0,
+        audit_tail: broadcast::channel(1).0,
+    };
+    actor.purge(time::OffsetDateTime::UNIX_EPOCH + time::Duration::days(10));
+
+    let events = actor
+        .audit_store
+        .as_ref()
+        .expect("no audit store")
+        .search(&AuditSearch::default())
+        .expect("search failed");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["AuditPurged"]["removed"].as_u64(), Some(1));
+}
+
+#[test]
+fn test_audit_report_dropped() {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.log");
+    let cfg = AuditLogConfig::new(path.to_string_lossy().to_string());
+    let audit_log = AuditLogFile::open(&cfg, Utc::now().date_naive()).expect("failed to open");
+
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Info,
+        audit_log: Some(audit_log),
+        audit_syslog: None,
+        audit_store: None,
+        retention: None,
+        reported_dropped: 0,
+        audit_tail: broadcast::channel(1).0,
+    };
+    actor.report_dropped(0);
+    actor.report_dropped(3);
+    actor.report_dropped(3);
+    actor.report_dropped(5);
+
+    let content = fs::read_to_string(&path).expect("read failed");
+    let reports: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("invalid json"))
+        .collect();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["AuditDropped"]["dropped"].as_u64(), Some(3));
+    assert_eq!(reports[1]["AuditDropped"]["dropped"].as_u64(), Some(2));
+    assert_eq!(reports[1]["AuditDropped"]["total"].as_u64(), Some(5));
+}
+
+#[test]
+fn test_audit_report_dropped_min_severity() {
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.log");
+    let cfg = AuditLogConfig::new(path.to_string_lossy().to_string());
+    let audit_log = AuditLogFile::open(&cfg, Utc::now().date_naive()).expect("failed to open");
+
+    // Dropped events are reported even though the report is below the minimum severity.
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Security,
+        audit_log: Some(audit_log),
+        audit_syslog: None,
+        audit_store: None,
+        retention: None,
+        reported_dropped: 0,
+        audit_tail: broadcast::channel(1).0,
+    };
+    actor.report_dropped(4);
+
+    let content = fs::read_to_string(&path).expect("read failed");
+    let reports: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("invalid json"))
+        .collect();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0]["AuditDropped"]["dropped"].as_u64(), Some(4));
+}
+
+#[test]
+fn test_audit_tail() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let (audit_tail, mut tail_rx) = broadcast::channel(AUDIT_TAIL_QUEUE_SIZE);
+    let mut actor = AuditdActor {
+        min_severity: AuditSeverity::Info,
+        audit_log: None,
+        audit_syslog: None,
+        audit_store: None,
+        retention: None,
+        reported_dropped: 0,
+        audit_tail,
+    };
+
+    let event = AuditEvent::AuthenticationDenied {
+        source: AuditSource::Internal,
+        uuid: Uuid::new_v4(),
+        spn: "testperson@example.com".to_string(),
+        time: time::OffsetDateTime::UNIX_EPOCH,
+        eventid: Uuid::new_v4(),
+        location: audit_location!(),
+    };
+    actor.process(&event);
+
+    let json = tail_rx.try_recv().expect("no audit event was mirrored");
+    assert_eq!(
+        json,
+        serde_json::to_string(&event).expect("failed to serialise")
+    );
+}
+
+#[test]
+fn test_audit_store_cbor() {
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.db");
+
+    let event = AuditEvent::AuthenticationDenied {
+        source: AuditSource::Internal,
+        uuid: Uuid::new_v4(),
+        spn: "testperson@example.com".to_string(),
+        time: time::OffsetDateTime::UNIX_EPOCH,
+        eventid: Uuid::new_v4(),
+        location: audit_location!(),
+    };
+    let json = serde_json::to_string(&event).expect("failed to serialise");
+
+    // Events stored before and after changing encoding are both returned.
+    AuditStore::open(&path.to_string_lossy())
+        .expect("failed to open audit store")
+        .insert(&event, &json)
+        .expect("insert failed");
+    let audit_store = AuditStore::open(&path.to_string_lossy())
+        .expect("failed to open audit store")
+        .with_encoding(AuditEncoding::Cbor);
+    audit_store.insert(&event, &json).expect("insert failed");
+
+    let data_type: String = audit_store
+        .conn
+        .query_row(
+            "SELECT typeof(data) FROM audit_events ORDER BY id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .expect("query failed");
+    assert_eq!(data_type, "blob");
+
+    let expected: serde_json::Value = serde_json::from_str(&json).expect("invalid json");
+    let events = audit_store
+        .search(&AuditSearch::default())
+        .expect("search failed");
+    assert_eq!(events, [expected.clone(), expected]);
+}
+
+#[test]
+fn test_audit_store_search_outcome() {
+    use kanidmd_lib::idm::audit::AuditOutcome;
+    use kanidmd_lib::prelude::Uuid;
+
+    let dir = tempfile::tempdir().expect("failed to create tempdir");
+    let path = dir.path().join("audit.db");
+    let audit_store =
+        AuditStore::open(&path.to_string_lossy()).expect("failed to open audit store");
+
+    for (operation, outcome) in [
+        (AuditOperation::Create, AuditOutcome::Success),
+        (AuditOperation::Modify, AuditOutcome::Denied),
+        (AuditOperation::Delete, AuditOutcome::Error),
+    ] {
+        let event = AuditEvent::Operation {
+            source: AuditSource::Internal,
+            uuid: Uuid::new_v4(),
+            spn: "alice@example.com".to_string(),
+            operation,
+            outcome,
+            time: time::OffsetDateTime::UNIX_EPOCH + time::Duration::seconds(10),
+            eventid: Uuid::new_v4(),
+            location: audit_location!(),
+        };
+        let json = serde_json::to_string(&event).expect("failed to serialise");
+        audit_store.insert(&event, &json).expect("insert failed");
+    }
+    drop(audit_store);
+
+    let pool = AuditStorePool::new(path.to_string_lossy().to_string());
+    let operations = |search: AuditSearch| -> Vec<String> {
+        pool.search(&search)
+            .expect("search failed")
+            .into_iter()
+            .map(|event| {
+                event["Operation"]["operation"]
+                    .as_str()
+                    .expect("no operation")
+                    .to_string()
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        operations(AuditSearch {
+            outcome: Some("denied".to_string()),
+            ..Default::default()
+        }),
+        ["Modify"]
+    );
+    assert_eq!(
+        operations(AuditSearch {
+            name: Some("Delete".to_string()),
+            ..Default::default()
+        }),
+        ["Delete"]
+    );
+
+    // Searches reuse the connection the first search opened.
+    assert_eq!(pool.pool.lock().expect("poisoned").len(), 1);
+}
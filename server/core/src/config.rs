@@ -9,17 +9,29 @@ use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::Duration;
 
 use kanidm_proto::constants::DEFAULT_SERVER_ADDRESS;
-use kanidm_proto::internal::FsType;
+use kanidm_proto::internal::{DbDurability, DbJournalMode, FsType};
 use kanidm_proto::messages::ConsoleOutputMode;
+use kanidmd_lib::be::{
+    DbEncryptionKeyEnv, DbEncryptionKeyFile, DbEncryptionKeyProvider, DB_ENCRYPTION_KEY_ENV,
+};
+use kanidmd_lib::constants::{RECYCLEBIN_MIN_AGE, TOMBSTONE_MIN_AGE};
+use kanidmd_lib::idm::audit::AuditSeverity;
+use kanidmd_lib::time::duration_from_epoch_now;
 
 use serde::Deserialize;
-use sketching::LogLevel;
+use sketching::security::SecurityLogConfig;
+use sketching::{LogFormat, LogLevel};
 use url::Url;
 
 use crate::repl::config::ReplicationConfiguration;
 
+/// The `db_path` that keeps the database in memory rather than in a file. Nothing is written to
+/// disk, and all content is lost when the server stops.
+pub const DB_PATH_IN_MEMORY: &str = ":memory:";
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct OnlineBackup {
     /// The destination folder for your backups, defaults to the db_path dir if not set
@@ -73,6 +85,160 @@ fn default_online_backup_versions() -> usize {
     7
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct DbMaintenance {
+    /// The schedule to run database maintenance, in the same format as [OnlineBackup::schedule].
+    /// Defaults to every day at 03:00 UTC: `"00 03 * * *"`
+    #[serde(default = "default_db_maintenance_schedule")]
+    pub schedule: String,
+    /// Also vacuum the database to release free space to the filesystem, rather than only
+    /// checkpointing the write ahead log. Writes wait while a vacuum runs. Enabled by default
+    #[serde(default = "default_db_maintenance_vacuum")]
+    pub vacuum: bool,
+    /// Enabled by default
+    #[serde(default = "default_db_maintenance_enabled")]
+    pub enabled: bool,
+}
+
+impl Default for DbMaintenance {
+    fn default() -> Self {
+        DbMaintenance {
+            schedule: default_db_maintenance_schedule(),
+            vacuum: default_db_maintenance_vacuum(),
+            enabled: default_db_maintenance_enabled(),
+        }
+    }
+}
+
+fn default_db_maintenance_schedule() -> String {
+    "00 03 * * *".to_string()
+}
+
+fn default_db_maintenance_vacuum() -> bool {
+    true
+}
+
+fn default_db_maintenance_enabled() -> bool {
+    true
+}
+
+/// The format that audit events are written in.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditFormat {
+    /// One json object per event.
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// ArcSight Common Event Format.
+    #[serde(rename = "cef")]
+    Cef,
+    /// QRadar Log Event Extended Format.
+    #[serde(rename = "leef")]
+    Leef,
+}
+
+impl fmt::Display for AuditFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditFormat::Json => "json",
+            AuditFormat::Cef => "cef",
+            AuditFormat::Leef => "leef",
+        })
+    }
+}
+
+/// How audit events are encoded in the audit store.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AuditEncoding {
+    /// Compact json.
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// CBOR, which is smaller and cheaper to encode than json.
+    #[serde(rename = "cbor")]
+    Cbor,
+}
+
+impl fmt::Display for AuditEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditEncoding::Json => "json",
+            AuditEncoding::Cbor => "cbor",
+        })
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuditLogConfig {
+    /// The file that audit events are appended to, one event per line.
+    pub path: String,
+    /// The format to write audit events in, defaults to json
+    #[serde(default)]
+    pub format: AuditFormat,
+    /// Rotate the audit log once it would exceed this size in bytes, defaults to 64MiB
+    #[serde(default = "default_audit_log_max_size")]
+    pub max_size: u64,
+    /// Rotate the audit log at the start of each day (UTC), defaults to false
+    #[serde(default)]
+    pub rotate_daily: bool,
+    /// How many rotated audit logs to keep, defaults to 7
+    #[serde(default = "default_audit_log_versions")]
+    pub versions: usize,
+    /// Include the hash of the previous record in each record, so that removing or altering
+    /// audit history can be detected, defaults to false
+    #[serde(default)]
+    pub hash_chain: bool,
+    /// A file containing a secret key. If set, records are chained with HMAC-SHA256 rather
+    /// than SHA256 so that the chain can not be recomputed without the key.
+    pub hash_chain_key: Option<PathBuf>,
+}
+
+impl AuditLogConfig {
+    pub fn new(path: String) -> Self {
+        AuditLogConfig {
+            path,
+            format: AuditFormat::default(),
+            max_size: default_audit_log_max_size(),
+            rotate_daily: false,
+            versions: default_audit_log_versions(),
+            hash_chain: false,
+            hash_chain_key: None,
+        }
+    }
+}
+
+fn default_audit_log_max_size() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_audit_log_versions() -> usize {
+    7
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuditStoreConfig {
+    /// The sqlite database that audit events are recorded in, so that they can be searched
+    /// with `kanidmd audit search`.
+    pub path: String,
+    /// Audit events older than this many days are purged from the store. If unset, the
+    /// store grows without bound.
+    pub retention_days: Option<u32>,
+    /// How audit events are encoded in the store. Events that were stored with a different
+    /// encoding can still be searched.
+    #[serde(default)]
+    pub encoding: AuditEncoding,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct AuditSyslogConfig {
+    /// Where to send audit events. Either the path to a unix datagram socket such as
+    /// `/dev/log`, or a `host:port` to send to over udp.
+    pub address: String,
+    /// The format of the audit event in the syslog message, defaults to json
+    #[serde(default)]
+    pub format: AuditFormat,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct TlsConfiguration {
     pub chain: PathBuf,
@@ -96,8 +262,19 @@ pub struct ServerConfig {
     /// *REQUIRED* - The user-facing HTTPS URL for this server, eg <https://idm.example.com>
     // TODO  -this should be URL
     pub origin: Option<String>,
-    /// File path of the database file
+    /// File path of the database file, or `:memory:` to keep the database in memory.
     pub db_path: Option<String>,
+    /// File path of a key that encrypts the entries stored in the database. The file contains 32
+    /// bytes encoded as hex, eg. the output of `openssl rand -hex 32`. If not set, entries are
+    /// stored unencrypted. The key can instead be set as hex in the `KANIDM_DB_ENCRYPTION_KEY`
+    /// environment variable, but not both.
+    pub db_encryption_key_path: Option<String>,
+    /// File path of a key that encrypts backups, in the same form as `db_encryption_key_path`.
+    /// The same key is needed to restore them. If not set, backups are unencrypted.
+    pub backup_encryption_key_path: Option<String>,
+    /// Compress the entries stored in the database, which reduces its size when entries have
+    /// many values. Defaults to false if unset.
+    pub db_compress_entries: Option<bool>,
     ///  *REQUIRED* - The file path to the TLS Certificate Chain
     pub tls_chain: Option<String>,
     ///  *REQUIRED* - The file path to the TLS Private Key
@@ -122,9 +299,37 @@ pub struct ServerConfig {
     pub role: ServerRole,
     /// The log level, one of info, debug, trace. Defaults to "info" if not set.
     pub log_level: Option<LogLevel>,
+    /// How logs are written to stdout, one of forest or json. Defaults to "forest" if not set.
+    pub log_format: Option<LogFormat>,
+    /// Security log configuration, see [SecurityLogConfig] for details on sub-keys. If set,
+    /// security relevant events are also written to their own file.
+    pub security_log: Option<SecurityLogConfig>,
+    /// Operations that take longer than this many milliseconds are logged at warn level, along
+    /// with the timings of everything within them. If not set, slow operations are not reported.
+    pub slow_operation_threshold_ms: Option<u64>,
 
     /// Backup Configuration, see [OnlineBackup] for details on sub-keys.
     pub online_backup: Option<OnlineBackup>,
+    /// Scheduled database maintenance, see [DbMaintenance] for details on sub-keys. If not set,
+    /// the write ahead log is only checkpointed automatically, and free space in the database is
+    /// not released.
+    pub db_maintenance: Option<DbMaintenance>,
+
+    /// Audit log configuration, see [AuditLogConfig] for details on sub-keys. If not set, audit
+    /// events are only emitted to the server log.
+    pub audit_log: Option<AuditLogConfig>,
+
+    /// Audit store configuration, see [AuditStoreConfig] for details on sub-keys. If set, audit
+    /// events are also recorded in a database that can be searched from the admin socket.
+    pub audit_store: Option<AuditStoreConfig>,
+
+    /// Only audit events at or above this severity are emitted. One of trace, info, warn or
+    /// security, defaults to info.
+    pub audit_severity: Option<AuditSeverity>,
+
+    /// Audit syslog configuration, see [AuditSyslogConfig] for details on sub-keys. If set, audit
+    /// events are also sent to syslog in RFC5424 format.
+    pub audit_syslog: Option<AuditSyslogConfig>,
 
     /// Trust the X-Forwarded-For header for client IP address. Defaults to false if unset.
     pub trust_x_forward_for: Option<bool>,
@@ -132,6 +337,52 @@ pub struct ServerConfig {
     /// The filesystem type, either "zfs" or "generic". Defaults to "generic" if unset. I you change this, run a database vacuum.
     pub db_fs_type: Option<kanidm_proto::internal::FsType>,
 
+    /// How durable database writes are, one of "full", "normal" or "off". Defaults to "full" if
+    /// unset. With "normal" the most recent writes may be lost if the operating system crashes,
+    /// and with "off" the database may be corrupted, so it is only for tests and bulk loads.
+    pub db_durability: Option<DbDurability>,
+
+    /// How database writes are journalled, either "wal" or "delete". Defaults to "wal" if unset.
+    /// Combined with a durability of "full", "delete" syncs every write to the database file
+    /// itself before it completes.
+    pub db_journal_mode: Option<DbJournalMode>,
+
+    /// The most candidate entries a single search may load. A search with more candidates, such
+    /// as an unindexed search of a large database, fails instead of consuming the memory to hold
+    /// them. This applies to the searches of the server itself, so it must be well above the
+    /// number of entries in the database. Unlimited if unset.
+    pub db_search_max_candidates: Option<usize>,
+
+    /// The number of seconds tombstones are kept before they are purged. Replication needs these
+    /// to converge, so every server in a topology must use the same value, and a replica that has
+    /// not replicated within it must be refreshed. Defaults to 7 days if unset.
+    pub db_tombstone_max_age: Option<u64>,
+
+    /// The number of seconds deleted entries stay in the recycle bin, where they can be revived,
+    /// before they are purged to tombstones. Defaults to 7 days if unset.
+    pub db_recyclebin_max_age: Option<u64>,
+
+    /// The most live entries the database may hold, not counting recycled entries or tombstones.
+    /// Once reached, creating or reviving an entry fails, but entries can still be changed and
+    /// deleted. Unlimited if unset.
+    pub db_max_entries: Option<u64>,
+
+    /// The most bytes the database may use, not counting free space that a vacuum would release.
+    /// Once reached, any change that adds entries or values fails, but entries can still be
+    /// deleted. Unlimited if unset.
+    pub db_max_size: Option<u64>,
+
+    /// The most attributes an entry written by a client may have. Unlimited if unset.
+    pub entry_max_attributes: Option<usize>,
+
+    /// The most values a single attribute of an entry written by a client may have. Unlimited
+    /// if unset.
+    pub entry_max_values: Option<usize>,
+
+    /// The most bytes a single value of an entry written by a client may use when stored.
+    /// Unlimited if unset.
+    pub entry_max_value_bytes: Option<usize>,
+
     /// The path to the "admin" socket, used for local communication when performing certain server control tasks. Default is set on build, based on the system target.
     pub adminbindpath: Option<String>,
 
@@ -148,6 +399,9 @@ pub struct ServerConfig {
     pub repl_config: Option<ReplicationConfiguration>,
     /// An optional OpenTelemetry collector (GRPC) url to send trace and log data to, eg `http://localhost:4317`. If not set, disables the feature.
     pub otel_grpc_url: Option<String>,
+    /// The ratio of traces, between 0.0 and 1.0, that are sent to the OpenTelemetry collector.
+    /// Defaults to 1.0 (every trace) if unset. The server log is not affected by this setting.
+    pub otel_sample_ratio: Option<f64>,
 }
 
 impl ServerConfig {
@@ -280,6 +534,23 @@ impl ServerConfig {
                 "DB_PATH" => {
                     self.db_path = Some(value.to_string());
                 }
+                "DB_ENCRYPTION_KEY_PATH" => {
+                    self.db_encryption_key_path = Some(value.to_string());
+                }
+                "DB_ENCRYPTION_KEY" => {
+                    // Read when the backend starts, so the key is never kept in the config.
+                }
+                "BACKUP_ENCRYPTION_KEY_PATH" => {
+                    self.backup_encryption_key_path = Some(value.to_string());
+                }
+                "DB_COMPRESS_ENTRIES" => {
+                    self.db_compress_entries = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_COMPRESS_ENTRIES as bool".to_string()
+                        })
+                        .ok();
+                }
                 "TLS_CHAIN" => {
                     self.tls_chain = Some(value.to_string());
                 }
@@ -307,6 +578,31 @@ impl ServerConfig {
                         })
                         .ok();
                 }
+                "LOG_FORMAT" => {
+                    self.log_format = LogFormat::from_str(&value)
+                        .map_err(|err| {
+                            format!("Failed to parse KANIDM_LOG_FORMAT as LogFormat: {}", err)
+                        })
+                        .ok();
+                }
+                "SECURITY_LOG_PATH" => {
+                    if let Some(security_log) = &mut self.security_log {
+                        security_log.path = PathBuf::from(value);
+                    } else {
+                        self.security_log = Some(SecurityLogConfig {
+                            path: PathBuf::from(value),
+                            level: LogLevel::default(),
+                        });
+                    }
+                }
+                "SLOW_OPERATION_THRESHOLD_MS" => {
+                    self.slow_operation_threshold_ms = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_SLOW_OPERATION_THRESHOLD_MS as u64".to_string()
+                        })
+                        .ok();
+                }
                 "ONLINE_BACKUP_PATH" => {
                     if let Some(backup) = &mut self.online_backup {
                         backup.path = Some(value.to_string());
@@ -340,6 +636,57 @@ impl ServerConfig {
                         })
                     }
                 }
+                "DB_MAINTENANCE_SCHEDULE" => {
+                    self.db_maintenance
+                        .get_or_insert_with(Default::default)
+                        .schedule = value.to_string();
+                }
+                "DB_MAINTENANCE_VACUUM" => {
+                    let vacuum = value.parse().map_err(|_| {
+                        "Failed to parse KANIDM_DB_MAINTENANCE_VACUUM as bool".to_string()
+                    })?;
+                    self.db_maintenance
+                        .get_or_insert_with(Default::default)
+                        .vacuum = vacuum;
+                }
+                "AUDIT_LOG_PATH" => {
+                    if let Some(audit_log) = &mut self.audit_log {
+                        audit_log.path = value.to_string();
+                    } else {
+                        self.audit_log = Some(AuditLogConfig::new(value.to_string()));
+                    }
+                }
+                "AUDIT_SEVERITY" => {
+                    self.audit_severity = AuditSeverity::from_str(&value)
+                        .map_err(|err| {
+                            format!(
+                                "Failed to parse KANIDM_AUDIT_SEVERITY as AuditSeverity: {}",
+                                err
+                            )
+                        })
+                        .ok();
+                }
+                "AUDIT_STORE_PATH" => {
+                    if let Some(audit_store) = &mut self.audit_store {
+                        audit_store.path = value.to_string();
+                    } else {
+                        self.audit_store = Some(AuditStoreConfig {
+                            path: value.to_string(),
+                            retention_days: None,
+                            encoding: AuditEncoding::default(),
+                        });
+                    }
+                }
+                "AUDIT_SYSLOG_ADDRESS" => {
+                    if let Some(audit_syslog) = &mut self.audit_syslog {
+                        audit_syslog.address = value.to_string();
+                    } else {
+                        self.audit_syslog = Some(AuditSyslogConfig {
+                            address: value.to_string(),
+                            format: AuditFormat::default(),
+                        });
+                    }
+                }
                 "TRUST_X_FORWARD_FOR" => {
                     self.trust_x_forward_for = value
                         .parse()
@@ -355,6 +702,80 @@ impl ServerConfig {
                         })
                         .ok();
                 }
+                "DB_DURABILITY" => {
+                    self.db_durability = DbDurability::try_from(value.as_str())
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_DURABILITY env var to valid value!"
+                                .to_string()
+                        })
+                        .ok();
+                }
+                "DB_JOURNAL_MODE" => {
+                    self.db_journal_mode = DbJournalMode::try_from(value.as_str())
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_JOURNAL_MODE env var to valid value!"
+                                .to_string()
+                        })
+                        .ok();
+                }
+                "DB_SEARCH_MAX_CANDIDATES" => {
+                    self.db_search_max_candidates = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_SEARCH_MAX_CANDIDATES as value".to_string()
+                        })
+                        .ok();
+                }
+                "DB_TOMBSTONE_MAX_AGE" => {
+                    self.db_tombstone_max_age = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_TOMBSTONE_MAX_AGE as value".to_string()
+                        })
+                        .ok();
+                }
+                "DB_RECYCLEBIN_MAX_AGE" => {
+                    self.db_recyclebin_max_age = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_DB_RECYCLEBIN_MAX_AGE as value".to_string()
+                        })
+                        .ok();
+                }
+                "DB_MAX_ENTRIES" => {
+                    self.db_max_entries = value
+                        .parse()
+                        .map_err(|_| "Failed to parse KANIDM_DB_MAX_ENTRIES as value".to_string())
+                        .ok();
+                }
+                "DB_MAX_SIZE" => {
+                    self.db_max_size = value
+                        .parse()
+                        .map_err(|_| "Failed to parse KANIDM_DB_MAX_SIZE as value".to_string())
+                        .ok();
+                }
+                "ENTRY_MAX_ATTRIBUTES" => {
+                    self.entry_max_attributes = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_ENTRY_MAX_ATTRIBUTES as value".to_string()
+                        })
+                        .ok();
+                }
+                "ENTRY_MAX_VALUES" => {
+                    self.entry_max_values = value
+                        .parse()
+                        .map_err(|_| "Failed to parse KANIDM_ENTRY_MAX_VALUES as value".to_string())
+                        .ok();
+                }
+                "ENTRY_MAX_VALUE_BYTES" => {
+                    self.entry_max_value_bytes = value
+                        .parse()
+                        .map_err(|_| {
+                            "Failed to parse KANIDM_ENTRY_MAX_VALUE_BYTES as value".to_string()
+                        })
+                        .ok();
+                }
                 "DB_ARC_SIZE" => {
                     self.db_arc_size = value
                         .parse()
@@ -409,6 +830,12 @@ impl ServerConfig {
                 "OTEL_GRPC_URL" => {
                     self.otel_grpc_url = Some(value.to_string());
                 }
+                "OTEL_SAMPLE_RATIO" => {
+                    self.otel_sample_ratio = value
+                        .parse()
+                        .map_err(|_| "Failed to parse KANIDM_OTEL_SAMPLE_RATIO as f64".to_string())
+                        .ok();
+                }
 
                 _ => eprintln!("Ignoring env var {}", key),
             }
@@ -480,13 +907,31 @@ pub struct Configuration {
     pub threads: usize,
     // db type later
     pub db_path: String,
+    pub db_encryption_key_path: Option<String>,
+    pub backup_encryption_key_path: Option<String>,
+    pub db_compress_entries: bool,
     pub db_fs_type: Option<FsType>,
+    pub db_durability: DbDurability,
+    pub db_journal_mode: DbJournalMode,
+    pub db_search_max_candidates: Option<usize>,
+    pub db_tombstone_max_age: Option<u64>,
+    pub db_recyclebin_max_age: Option<u64>,
+    pub db_max_entries: Option<u64>,
+    pub db_max_size: Option<u64>,
+    pub entry_max_attributes: Option<usize>,
+    pub entry_max_values: Option<usize>,
+    pub entry_max_value_bytes: Option<usize>,
     pub db_arc_size: Option<usize>,
     pub maximum_request: usize,
     pub trust_x_forward_for: bool,
     pub tls_config: Option<TlsConfiguration>,
     pub integration_test_config: Option<Box<IntegrationTestConfig>>,
     pub online_backup: Option<OnlineBackup>,
+    pub db_maintenance: Option<DbMaintenance>,
+    pub audit_log: Option<AuditLogConfig>,
+    pub audit_syslog: Option<AuditSyslogConfig>,
+    pub audit_store: Option<AuditStoreConfig>,
+    pub audit_severity: AuditSeverity,
     pub domain: String,
     pub origin: String,
     pub role: ServerRole,
@@ -512,7 +957,57 @@ impl fmt::Display for Configuration {
         write!(f, "origin: {} ", self.origin)?;
         write!(f, "admin bind path: {}, ", self.adminbindpath)?;
         write!(f, "thread count: {}, ", self.threads)?;
-        write!(f, "dbpath: {}, ", self.db_path)?;
+        if self.db_in_memory() {
+            write!(f, "dbpath: in memory, ")?;
+        } else {
+            write!(f, "dbpath: {}, ", self.db_path)?;
+        }
+        write!(
+            f,
+            "db encryption: {}, ",
+            self.db_encryption_key_path.is_some()
+                || std::env::var_os(DB_ENCRYPTION_KEY_ENV).is_some()
+        )?;
+        write!(
+            f,
+            "backup encryption: {}, ",
+            self.backup_encryption_key_path.is_some()
+        )?;
+        write!(f, "db compression: {}, ", self.db_compress_entries)?;
+        write!(f, "db durability: {:?}, ", self.db_durability)?;
+        write!(f, "db journal mode: {:?}, ", self.db_journal_mode)?;
+        match self.db_search_max_candidates {
+            Some(v) => write!(f, "db search max candidates: {}, ", v),
+            None => write!(f, "db search max candidates: unlimited, "),
+        }?;
+        match self.db_tombstone_max_age {
+            Some(v) => write!(f, "db tombstone max age: {}s, ", v),
+            None => write!(f, "db tombstone max age: default, "),
+        }?;
+        match self.db_recyclebin_max_age {
+            Some(v) => write!(f, "db recyclebin max age: {}s, ", v),
+            None => write!(f, "db recyclebin max age: default, "),
+        }?;
+        match self.db_max_entries {
+            Some(v) => write!(f, "db max entries: {}, ", v),
+            None => write!(f, "db max entries: unlimited, "),
+        }?;
+        match self.db_max_size {
+            Some(v) => write!(f, "db max size: {}b, ", v),
+            None => write!(f, "db max size: unlimited, "),
+        }?;
+        match self.entry_max_attributes {
+            Some(v) => write!(f, "entry max attributes: {}, ", v),
+            None => write!(f, "entry max attributes: unlimited, "),
+        }?;
+        match self.entry_max_values {
+            Some(v) => write!(f, "entry max values: {}, ", v),
+            None => write!(f, "entry max values: unlimited, "),
+        }?;
+        match self.entry_max_value_bytes {
+            Some(v) => write!(f, "entry max value size: {}b, ", v),
+            None => write!(f, "entry max value size: unlimited, "),
+        }?;
         match self.db_arc_size {
             Some(v) => write!(f, "arcsize: {}, ", v),
             None => write!(f, "arcsize: AUTO, "),
@@ -531,6 +1026,51 @@ impl fmt::Display for Configuration {
             ),
             None => write!(f, "online_backup: disabled, "),
         }?;
+        match &self.db_maintenance {
+            Some(maint) => write!(
+                f,
+                "db_maintenance: enabled: {} - schedule: {} vacuum: {}, ",
+                maint.enabled, maint.schedule, maint.vacuum,
+            ),
+            None => write!(f, "db_maintenance: disabled, "),
+        }?;
+        match &self.audit_log {
+            Some(audit_log) => write!(
+                f,
+                "audit_log: path: {} format: {} max_size: {}b rotate_daily: {} versions: {} hash_chain: {}, ",
+                audit_log.path,
+                audit_log.format,
+                audit_log.max_size,
+                audit_log.rotate_daily,
+                audit_log.versions,
+                audit_log.hash_chain,
+            ),
+            None => write!(f, "audit_log: disabled, "),
+        }?;
+        match &self.audit_store {
+            Some(audit_store) => match audit_store.retention_days {
+                Some(days) => write!(
+                    f,
+                    "audit_store: {} ({}, retention {} days), ",
+                    audit_store.path, audit_store.encoding, days
+                ),
+                None => write!(
+                    f,
+                    "audit_store: {} ({}), ",
+                    audit_store.path, audit_store.encoding
+                ),
+            },
+            None => write!(f, "audit_store: disabled, "),
+        }?;
+        write!(f, "audit_severity: {}, ", self.audit_severity)?;
+        match &self.audit_syslog {
+            Some(audit_syslog) => write!(
+                f,
+                "audit_syslog: {} format: {}, ",
+                audit_syslog.address, audit_syslog.format
+            ),
+            None => write!(f, "audit_syslog: disabled, "),
+        }?;
         write!(
             f,
             "integration mode: {}, ",
@@ -578,13 +1118,31 @@ impl Configuration {
                     4
                 }),
             db_path: String::from(""),
+            db_encryption_key_path: None,
+            backup_encryption_key_path: None,
+            db_compress_entries: false,
             db_fs_type: None,
+            db_durability: DbDurability::default(),
+            db_journal_mode: DbJournalMode::default(),
+            db_search_max_candidates: None,
+            db_tombstone_max_age: None,
+            db_recyclebin_max_age: None,
+            db_max_entries: None,
+            db_max_size: None,
+            entry_max_attributes: None,
+            entry_max_values: None,
+            entry_max_value_bytes: None,
             db_arc_size: None,
             maximum_request: 256 * 1024, // 256k
             trust_x_forward_for: false,
             tls_config: None,
             integration_test_config: None,
             online_backup: None,
+            db_maintenance: None,
+            audit_log: None,
+            audit_syslog: None,
+            audit_store: None,
+            audit_severity: AuditSeverity::default(),
             domain: "idm.example.com".to_string(),
             origin: "https://idm.example.com".to_string(),
             output_mode: ConsoleOutputMode::default(),
@@ -634,6 +1192,26 @@ impl Configuration {
         }
     }
 
+    pub fn update_db_maintenance(&mut self, cfg: &Option<DbMaintenance>) {
+        self.db_maintenance = cfg.clone();
+    }
+
+    pub fn update_audit_log(&mut self, cfg: &Option<AuditLogConfig>) {
+        self.audit_log = cfg.clone();
+    }
+
+    pub fn update_audit_syslog(&mut self, cfg: &Option<AuditSyslogConfig>) {
+        self.audit_syslog = cfg.clone();
+    }
+
+    pub fn update_audit_store(&mut self, cfg: &Option<AuditStoreConfig>) {
+        self.audit_store = cfg.clone();
+    }
+
+    pub fn update_audit_severity(&mut self, severity: &Option<AuditSeverity>) {
+        self.audit_severity = severity.unwrap_or_default();
+    }
+
     pub fn update_log_level(&mut self, level: &Option<LogLevel>) {
         self.log_level = level.unwrap_or_default();
     }
@@ -646,6 +1224,11 @@ impl Configuration {
         self.update_bind(&sconfig.bindaddress);
         self.update_ldapbind(&sconfig.ldapbindaddress);
         self.update_online_backup(&sconfig.online_backup);
+        self.update_db_maintenance(&sconfig.db_maintenance);
+        self.update_audit_log(&sconfig.audit_log);
+        self.update_audit_syslog(&sconfig.audit_syslog);
+        self.update_audit_store(&sconfig.audit_store);
+        self.update_audit_severity(&sconfig.audit_severity);
         self.update_log_level(&sconfig.log_level);
     }
 
@@ -653,8 +1236,48 @@ impl Configuration {
         self.trust_x_forward_for = t.unwrap_or(false);
     }
 
+    /// The database is kept in memory rather than in a file.
+    pub fn db_in_memory(&self) -> bool {
+        self.db_path.is_empty() || self.db_path == DB_PATH_IN_MEMORY
+    }
+
     pub fn update_db_path(&mut self, p: &str) {
-        self.db_path = p.to_string();
+        // The backend keeps the database in memory when it is given no path.
+        self.db_path = if p == DB_PATH_IN_MEMORY {
+            String::new()
+        } else {
+            p.to_string()
+        };
+    }
+
+    pub fn update_db_encryption_key_path(&mut self, p: &Option<String>) {
+        self.db_encryption_key_path = p.to_owned();
+    }
+
+    /// Where the db encryption key is loaded from, either the environment or a file. None if
+    /// entries are stored unencrypted.
+    pub fn db_encryption_key_provider(
+        &self,
+    ) -> Result<Option<Box<dyn DbEncryptionKeyProvider>>, String> {
+        let from_env = std::env::var_os(DB_ENCRYPTION_KEY_ENV).is_some();
+        match (from_env, self.db_encryption_key_path.as_deref()) {
+            (true, Some(_)) => Err(format!(
+                "Only one of {DB_ENCRYPTION_KEY_ENV} and db_encryption_key_path may be set"
+            )),
+            (true, None) => Ok(Some(Box::new(DbEncryptionKeyEnv::new(
+                DB_ENCRYPTION_KEY_ENV,
+            )))),
+            (false, Some(p)) => Ok(Some(Box::new(DbEncryptionKeyFile::new(Path::new(p))))),
+            (false, None) => Ok(None),
+        }
+    }
+
+    pub fn update_backup_encryption_key_path(&mut self, p: &Option<String>) {
+        self.backup_encryption_key_path = p.to_owned();
+    }
+
+    pub fn update_db_compress_entries(&mut self, c: Option<bool>) {
+        self.db_compress_entries = c.unwrap_or(false);
     }
 
     pub fn update_db_arc_size(&mut self, v: Option<usize>) {
@@ -665,6 +1288,86 @@ impl Configuration {
         self.db_fs_type = p.to_owned();
     }
 
+    pub fn update_db_durability(&mut self, d: Option<DbDurability>) {
+        self.db_durability = d.unwrap_or_default();
+    }
+
+    pub fn update_db_journal_mode(&mut self, j: Option<DbJournalMode>) {
+        self.db_journal_mode = j.unwrap_or_default();
+    }
+
+    pub fn update_db_search_max_candidates(&mut self, v: Option<usize>) {
+        self.db_search_max_candidates = v;
+    }
+
+    pub fn update_db_tombstone_max_age(&mut self, v: Option<u64>) {
+        self.db_tombstone_max_age = v;
+    }
+
+    /// Tombstones must be kept for at least the minimum, and for longer than the replication
+    /// interval so that a consumer sees them before they are purged. The backend checks the
+    /// upper bound against the clock when it starts.
+    pub fn check_db_tombstone_max_age(&self) -> Result<(), String> {
+        let Some(age) = self.db_tombstone_max_age else {
+            return Ok(());
+        };
+
+        let repl_interval = self
+            .repl_config
+            .as_ref()
+            .map(|repl| repl.get_task_poll_interval().as_secs())
+            .unwrap_or_default();
+        let min = TOMBSTONE_MIN_AGE.max(repl_interval.saturating_add(1));
+
+        if age < min {
+            Err(format!(
+                "db_tombstone_max_age must be at least {min} seconds, the minimum or longer than the replication interval"
+            ))
+        } else if Duration::from_secs(age) >= duration_from_epoch_now() {
+            Err("db_tombstone_max_age must be less than the time since the unix epoch".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn update_db_recyclebin_max_age(&mut self, v: Option<u64>) {
+        self.db_recyclebin_max_age = v;
+    }
+
+    /// Deleted entries must stay in the recycle bin for at least the minimum, so that they can
+    /// be revived. The backend checks the upper bound against the clock when it starts.
+    pub fn check_db_recyclebin_max_age(&self) -> Result<(), String> {
+        let Some(age) = self.db_recyclebin_max_age else {
+            return Ok(());
+        };
+
+        if age < RECYCLEBIN_MIN_AGE {
+            Err(format!(
+                "db_recyclebin_max_age must be at least {RECYCLEBIN_MIN_AGE} seconds"
+            ))
+        } else if Duration::from_secs(age) >= duration_from_epoch_now() {
+            Err("db_recyclebin_max_age must be less than the time since the unix epoch".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn update_db_quota(&mut self, max_entries: Option<u64>, max_size: Option<u64>) {
+        self.db_max_entries = max_entries;
+        self.db_max_size = max_size;
+    }
+
+    pub fn update_entry_limits(
+        &mut self,
+        max_attributes: Option<usize>,
+        max_values: Option<usize>,
+        max_value_bytes: Option<usize>,
+    ) {
+        self.entry_max_attributes = max_attributes;
+        self.entry_max_values = max_values;
+        self.entry_max_value_bytes = max_value_bytes;
+    }
+
     pub fn update_bind(&mut self, b: &Option<String>) {
         self.address = b
             .as_ref()
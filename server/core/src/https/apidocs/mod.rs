@@ -210,6 +210,7 @@ impl Modify for SecurityAddon {
             internal::ApiTokenPurpose,
             internal::BackupCodesView,
             internal::CreateRequest,
+            internal::CreateResponse,
             internal::CredentialDetail,
             internal::CredentialDetailType,
             internal::CredentialStatus,
@@ -237,6 +238,7 @@ impl Modify for SecurityAddon {
             internal::TotpSecret,
             internal::UatPurpose,
             internal::UserAuthToken,
+            internal::WriteResponse,
             v1::AccountUnixExtend,
             v1::ApiTokenGenerate,
             v1::AuthAllowed,
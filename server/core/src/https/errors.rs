@@ -4,6 +4,7 @@
 use axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN;
 use axum::http::{HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
+use kanidm_proto::constants::KERRORCODE;
 use kanidm_proto::internal::OperationError;
 use utoipa::ToSchema;
 
@@ -60,12 +61,19 @@ impl IntoResponse for WebError {
                     }
                     _ => (StatusCode::INTERNAL_SERVER_ERROR, None),
                 };
+                let error_code = HeaderValue::from_str(&inner.code());
                 let body = serde_json::to_string(&inner).unwrap_or(inner.to_string());
 
-                match headers {
+                let mut res = match headers {
                     Some(headers) => (code, headers, body).into_response(),
                     None => (code, body).into_response(),
+                };
+                // The code is sent in a header so that the body keeps the shape that existing
+                // clients expect.
+                if let Ok(error_code) = error_code {
+                    res.headers_mut().insert(KERRORCODE, error_code);
                 }
+                res
             }
         }
     }
@@ -79,6 +79,8 @@ impl FromRequestParts<ServerState> for TrustedClientIp {
             addr.ip()
         };
 
+        sketching::capture::capture_client_ip(ip_addr);
+
         Ok(TrustedClientIp(ip_addr))
     }
 }
@@ -134,6 +136,8 @@ impl FromRequestParts<ServerState> for VerifiedClientInformation {
             addr.ip()
         };
 
+        sketching::capture::capture_client_ip(ip_addr);
+
         let (basic_authz, bearer_token) = if let Some(header) = parts.headers.get(AUTHORISATION) {
             if let Some((authz_type, authz_data)) = header
                 .to_str()
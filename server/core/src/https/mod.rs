@@ -33,7 +33,6 @@ use axum::{
 
 use axum_extra::extract::cookie::CookieJar;
 use compact_jwt::{JwsCompact, JwsHs256Signer, JwsVerifier};
-use futures::pin_mut;
 use hashbrown::HashMap;
 use hyper::body::Incoming;
 use hyper_util::rt::{TokioExecutor, TokioIo};
@@ -47,10 +46,11 @@ use kanidm_lib_crypto::x509_cert::{der::Decode, x509_public_key_s256, Certificat
 use sketching::*;
 use tokio::{
     net::{TcpListener, TcpStream},
-    sync::broadcast,
+    sync::{broadcast, watch},
     task,
 };
 use tokio_openssl::SslStream;
+use tokio_util::task::TaskTracker;
 use tower::Service;
 use tower_http::{services::ServeDir, trace::TraceLayer};
 use uuid::Uuid;
@@ -345,51 +345,58 @@ pub async fn create_https_server(
     info!("Starting the web server...");
 
     Ok(task::spawn(async move {
-        tokio::select! {
-            Ok(action) = rx.recv() => {
-                match action {
-                    CoreAction::Shutdown => {},
+        match config.tls_config {
+            Some(tls_param) => {
+                // This isn't optimal, but we can't share this with the
+                // other path for integration tests because that doesn't
+                // do tls (yet?)
+                let listener = match TcpListener::bind(addr).await {
+                    Ok(l) => l,
+                    Err(err) => {
+                        error!(?err, "Failed to bind tcp listener");
+                        return;
+                    }
+                };
+                // The server loop handles the shutdown itself, so that the requests in flight
+                // are able to finish.
+                if let Err(err) = server_loop(tls_param, listener, app, rx).await {
+                    error!("Web server exited with {:?}", err);
+                    if let Err(err) = server_message_tx.send(CoreAction::Shutdown) {
+                        error!("Web server failed to send shutdown message! {:?}", err)
+                    };
                 }
             }
-            res = match config.tls_config {
-                Some(tls_param) => {
-                    // This isn't optimal, but we can't share this with the
-                    // other path for integration tests because that doesn't
-                    // do tls (yet?)
-                    let listener = match TcpListener::bind(addr).await {
-                        Ok(l) => l,
-                        Err(err) => {
-                            error!(?err, "Failed to bind tcp listener");
-                            return
+            None => {
+                let handle = axum_server::Handle::new();
+                let server = axum_server::bind(addr).handle(handle.clone()).serve(app);
+                tokio::pin!(server);
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => {
+                                // Stop accepting connections, and close the open connections once
+                                // the requests in flight on them have finished.
+                                handle.graceful_shutdown(Some(crate::SHUTDOWN_GRACE_PERIOD));
+                                if let Err(err) = server.await {
+                                    error!("Web server exited with {:?}", err);
+                                }
+                            },
                         }
-                    };
-                    task::spawn(server_loop(tls_param, listener, app))
-                },
-                None => {
-                    task::spawn(axum_server::bind(addr).serve(app))
-                }
-            } => {
-                match res {
-                    Ok(res_inner) => {
-                        match res_inner {
+                    }
+                    res = server.as_mut() => {
+                        match res {
                             Ok(_) => debug!("Web server exited OK"),
                             Err(err) => {
                                 error!("Web server exited with {:?}", err);
                             }
-                        }
-
-                    },
-                    Err(err) => {
-                        error!("Web server exited with {:?}", err);
+                        };
+                        if let Err(err) = server_message_tx.send(CoreAction::Shutdown) {
+                            error!("Web server failed to send shutdown message! {:?}", err)
+                        };
                     }
                 };
-                if let Err(err) = server_message_tx.send(CoreAction::Shutdown) {
-                    error!("Web server failed to send shutdown message! {:?}", err)
-                };
             }
-
-
-        };
+        }
 
         info!("Stopped {}", super::TaskName::HttpsServer);
     }))
@@ -399,6 +406,7 @@ async fn server_loop(
     tls_param: TlsConfiguration,
     listener: TcpListener,
     app: IntoMakeServiceWithConnectInfo<Router, ClientConnInfo>,
+    mut rx: broadcast::Receiver<CoreAction>,
 ) -> Result<(), std::io::Error> {
     let mut tls_builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
 
@@ -533,15 +541,41 @@ async fn server_loop(
     }
 
     let tls_acceptor = tls_builder.build();
-    pin_mut!(listener);
+    let connections = TaskTracker::new();
+    let (drain_tx, drain_rx) = watch::channel(false);
 
     loop {
-        if let Ok((stream, addr)) = listener.accept().await {
-            let tls_acceptor = tls_acceptor.clone();
-            let app = app.clone();
-            task::spawn(handle_conn(tls_acceptor, stream, app, addr));
+        tokio::select! {
+            Ok(action) = rx.recv() => {
+                match action {
+                    CoreAction::Shutdown => break,
+                }
+            }
+            accept_result = listener.accept() => {
+                if let Ok((stream, addr)) = accept_result {
+                    let tls_acceptor = tls_acceptor.clone();
+                    let app = app.clone();
+                    connections.spawn(handle_conn(tls_acceptor, stream, app, addr, drain_rx.clone()));
+                }
+            }
         }
     }
+
+    // Stop accepting connections, then close the open connections once the requests in flight
+    // on them have finished, including idle keep-alive connections.
+    drop(listener);
+    let _ = drain_tx.send(true);
+    connections.close();
+    if tokio::time::timeout(crate::SHUTDOWN_GRACE_PERIOD, connections.wait())
+        .await
+        .is_err()
+    {
+        warn!(
+            open_connections = connections.len(),
+            "Web server connections did not finish before shutdown"
+        );
+    }
+    Ok(())
 }
 
 /// This handles an individual connection.
@@ -550,6 +584,7 @@ pub(crate) async fn handle_conn(
     stream: TcpStream,
     mut app: IntoMakeServiceWithConnectInfo<Router, ClientConnInfo>,
     addr: SocketAddr,
+    mut drain_rx: watch::Receiver<bool>,
 ) -> Result<(), std::io::Error> {
     let ssl = Ssl::new(acceptor.context()).map_err(|e| {
         error!("Failed to create TLS context: {:?}", e);
@@ -622,13 +657,23 @@ pub(crate) async fn handle_conn(
                 svc.clone().call(request)
             });
 
-            hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
-                .serve_connection_with_upgrades(stream, hyper_service)
-                .await
-                .map_err(|e| {
-                    debug!("Failed to complete connection: {:?}", e);
-                    std::io::Error::from(ErrorKind::ConnectionAborted)
-                })
+            let builder = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new());
+            let conn = builder.serve_connection_with_upgrades(stream, hyper_service);
+            tokio::pin!(conn);
+
+            let res = tokio::select! {
+                res = conn.as_mut() => res,
+                Ok(()) = drain_rx.changed() => {
+                    // The server is shutting down. The request in flight is finished, but the
+                    // connection is closed rather than kept alive for further requests.
+                    conn.as_mut().graceful_shutdown();
+                    conn.await
+                }
+            };
+            res.map_err(|e| {
+                debug!("Failed to complete connection: {:?}", e);
+                std::io::Error::from(ErrorKind::ConnectionAborted)
+            })
         }
         Err(error) => {
             trace!("Failed to handle connection: {:?}", error);
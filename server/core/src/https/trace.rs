@@ -1,7 +1,7 @@
 //! Reimplementation of tower-http's DefaultMakeSpan that only runs at "INFO" level for our own needs.
 
-use axum::http::Request;
-use kanidm_proto::constants::KOPID;
+use axum::http::{Request, StatusCode};
+use kanidm_proto::constants::{KERRORCODE, KOPID};
 use sketching::event_dynamic_lvl;
 use tower_http::LatencyUnit;
 use tracing::{Level, Span};
@@ -34,10 +34,44 @@ impl<B> tower_http::trace::MakeSpan<B> for DefaultMakeSpanKanidmd {
             method = %request.method(),
             uri = %request.uri(),
             version = ?request.version(),
+            result = tracing::field::Empty,
+            status_code = tracing::field::Empty,
+            error_code = tracing::field::Empty,
         )
     }
 }
 
+/// The outcome of a request, as recorded on its span.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RequestResult {
+    Success,
+    /// Authentication is required, or the client is not permitted to do this.
+    Denied,
+    Error,
+}
+
+impl RequestResult {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RequestResult::Success => "success",
+            RequestResult::Denied => "denied",
+            RequestResult::Error => "error",
+        }
+    }
+}
+
+impl From<StatusCode> for RequestResult {
+    fn from(status: StatusCode) -> Self {
+        if status.is_informational() || status.is_success() || status.is_redirection() {
+            RequestResult::Success
+        } else if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+            RequestResult::Denied
+        } else {
+            RequestResult::Error
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct DefaultOnResponseKanidmd {
     #[allow(dead_code)]
@@ -70,7 +104,7 @@ impl<B> tower_http::trace::OnResponse<B> for DefaultOnResponseKanidmd {
         self,
         response: &axum::response::Response<B>,
         latency: std::time::Duration,
-        _span: &Span,
+        span: &Span,
     ) {
         let kopid = match response.headers().get(KOPID) {
             Some(val) => val.to_str().unwrap_or("<invalid kopid>"),
@@ -89,12 +123,52 @@ impl<B> tower_http::trace::OnResponse<B> for DefaultOnResponseKanidmd {
                     }
                 }
             };
+        // Record the outcome on the request span, so that failed operations can be filtered
+        // on without parsing the log message.
+        let result = RequestResult::from(response.status());
+        span.record("result", result.as_str());
+        span.record("status_code", response.status().as_u16());
+        // Matches the error code header sent to the client.
+        let error_code = response
+            .headers()
+            .get(KERRORCODE)
+            .and_then(|val| val.to_str().ok());
+        if let Some(error_code) = error_code {
+            span.record("error_code", error_code);
+        }
         event_dynamic_lvl!(
             level,
             ?latency,
+            result = result.as_str(),
             status_code = response.status().as_u16(),
+            error_code,
             kopid = kopid,
             msg
         );
     }
 }
+
+#[test]
+fn test_request_result_from_status() {
+    assert_eq!(RequestResult::from(StatusCode::OK), RequestResult::Success);
+    assert_eq!(
+        RequestResult::from(StatusCode::FOUND),
+        RequestResult::Success
+    );
+    assert_eq!(
+        RequestResult::from(StatusCode::UNAUTHORIZED),
+        RequestResult::Denied
+    );
+    assert_eq!(
+        RequestResult::from(StatusCode::FORBIDDEN),
+        RequestResult::Denied
+    );
+    assert_eq!(
+        RequestResult::from(StatusCode::BAD_REQUEST),
+        RequestResult::Error
+    );
+    assert_eq!(
+        RequestResult::from(StatusCode::INTERNAL_SERVER_ERROR),
+        RequestResult::Error
+    );
+}
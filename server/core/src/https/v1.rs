@@ -9,15 +9,16 @@ use axum::{Extension, Json, Router};
 use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use compact_jwt::{Jwk, Jws, JwsSigner};
 use kanidm_proto::constants::uri::V1_AUTH_VALID;
+use kanidm_proto::constants::KWARNING;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
 use uuid::Uuid;
 
 use kanidm_proto::internal::{
     ApiToken, AppLink, CUIntentToken, CURequest, CUSessionToken, CUStatus, CreateRequest,
-    CredentialStatus, DeleteRequest, IdentifyUserRequest, IdentifyUserResponse, ModifyRequest,
-    RadiusAuthToken, SearchRequest, SearchResponse, UserAuthToken, COOKIE_AUTH_SESSION_ID,
-    COOKIE_BEARER_TOKEN,
+    CreateResponse, CredentialStatus, DeleteRequest, IdentifyUserRequest, IdentifyUserResponse,
+    ModifyRequest, RadiusAuthToken, SearchRequest, SearchResponse, UserAuthToken, WriteResponse,
+    COOKIE_AUTH_SESSION_ID, COOKIE_BEARER_TOKEN,
 };
 use kanidm_proto::v1::{
     AccountUnixExtend, ApiTokenGenerate, AuthIssueSession, AuthRequest, AuthResponse,
@@ -41,29 +42,48 @@ pub(crate) struct SessionId {
     pub sessionid: Uuid,
 }
 
+/// Each warning about a write, such as the use of deprecated schema, is also returned in a
+/// header of the response, so that it is visible to clients that don't read the body.
+fn warning_headers(warnings: Vec<String>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for warning in warnings {
+        if let Ok(hv) = HeaderValue::from_str(&warning) {
+            headers.append(KWARNING, hv);
+        }
+    }
+    headers
+}
+
 #[utoipa::path(
     post,
     path = "/v1/raw/create",
     responses(
-        DefaultApiResponse,
+        (status=200, body=CreateResponse, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=CreateRequest,
     security(("token_jwt" = [])),
     tag = "v1/raw",
     operation_id="raw_create"
 )]
-/// Raw request to the system, be warned this can be dangerous!
+/// Raw request to the system, be warned this can be dangerous! Returns the uuids of the created
+/// entries, including those assigned by the server.
 pub async fn raw_create(
     State(state): State<ServerState>,
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(msg): Json<CreateRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<(HeaderMap, Json<CreateResponse>), WebError> {
     state
         .qe_w_ref
         .handle_create(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|(uuids, warnings)| {
+            (
+                warning_headers(warnings.clone()),
+                Json(CreateResponse { uuids, warnings }),
+            )
+        })
         .map_err(WebError::from)
 }
 
@@ -71,7 +91,8 @@ pub async fn raw_create(
     post,
     path = "/v1/raw/modify",
     responses(
-        DefaultApiResponse,
+        (status=200, body=WriteResponse, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=ModifyRequest,
     security(("token_jwt" = [])),
@@ -84,12 +105,17 @@ pub async fn raw_modify(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(msg): Json<ModifyRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<(HeaderMap, Json<WriteResponse>), WebError> {
     state
         .qe_w_ref
         .handle_modify(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|warnings| {
+            (
+                warning_headers(warnings.clone()),
+                Json(WriteResponse { warnings }),
+            )
+        })
         .map_err(WebError::from)
 }
 
@@ -97,7 +123,8 @@ pub async fn raw_modify(
     post,
     path = "/v1/raw/delete",
     responses(
-        DefaultApiResponse,
+        (status=200, body=WriteResponse, content_type="application/json"),
+        ApiResponseWithout200,
     ),
     request_body=DeleteRequest,
     security(("token_jwt" = [])),
@@ -110,12 +137,17 @@ pub async fn raw_delete(
     Extension(kopid): Extension<KOpId>,
     VerifiedClientInformation(client_auth_info): VerifiedClientInformation,
     Json(msg): Json<DeleteRequest>,
-) -> Result<Json<()>, WebError> {
+) -> Result<(HeaderMap, Json<WriteResponse>), WebError> {
     state
         .qe_w_ref
         .handle_delete(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|warnings| {
+            (
+                warning_headers(warnings.clone()),
+                Json(WriteResponse { warnings }),
+            )
+        })
         .map_err(WebError::from)
 }
 
@@ -343,7 +375,7 @@ pub async fn json_rest_event_post(
         .qe_w_ref
         .handle_create(client_auth_info, msg, kopid.eventid)
         .await
-        .map(Json::from)
+        .map(|_| Json(()))
         .map_err(WebError::from)
 }
 
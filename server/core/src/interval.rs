@@ -11,7 +11,7 @@ use cron::Schedule;
 use tokio::sync::broadcast;
 use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
 
-use crate::config::OnlineBackup;
+use crate::config::{DbMaintenance, OnlineBackup};
 use crate::CoreAction;
 
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
@@ -70,40 +70,7 @@ impl IntervalActor {
             }
         };
         let versions = online_backup_config.versions;
-        let crono_expr = online_backup_config.schedule.as_str().to_string();
-        let mut crono_expr_values = crono_expr.split_ascii_whitespace().collect::<Vec<&str>>();
-        let chrono_expr_uses_standard_syntax = crono_expr_values.len() == 5;
-        if chrono_expr_uses_standard_syntax {
-            // we add a 0 element at the beginning to simulate the standard crono syntax which always runs
-            // commands at seconds 00
-            crono_expr_values.insert(0, "0");
-            crono_expr_values.push("*");
-        }
-        let crono_expr_schedule = crono_expr_values.join(" ");
-        if chrono_expr_uses_standard_syntax {
-            info!(
-                "Provided online backup schedule is: {}, now being transformed to: {}",
-                crono_expr, crono_expr_schedule
-            );
-        }
-        // Cron expression handling
-        let cron_expr = Schedule::from_str(crono_expr_schedule.as_str()).map_err(|e| {
-            error!("Online backup schedule parse error: {}", e);
-            error!("valid formats are:");
-            error!("sec  min   hour   day of month   month   day of week   year");
-            error!("min   hour   day of month   month   day of week");
-            error!("@hourly | @daily | @weekly");
-        })?;
-
-        info!("Online backup schedule parsed as: {}", cron_expr);
-
-        if cron_expr.upcoming(Utc).next().is_none() {
-            error!(
-                "Online backup schedule error: '{}' will not match any date.",
-                cron_expr
-            );
-            return Err(());
-        }
+        let cron_expr = parse_schedule("Online backup", &online_backup_config.schedule)?;
 
         // Output path handling
         let op = Path::new(&outpath);
@@ -163,4 +130,80 @@ impl IntervalActor {
 
         Ok(handle)
     }
+
+    #[allow(clippy::result_unit_err)]
+    pub fn start_db_maintenance(
+        server: &'static QueryServerWriteV1,
+        db_maintenance_config: &DbMaintenance,
+        mut rx: broadcast::Receiver<CoreAction>,
+    ) -> Result<tokio::task::JoinHandle<()>, ()> {
+        let vacuum = db_maintenance_config.vacuum;
+        let cron_expr = parse_schedule("Database maintenance", &db_maintenance_config.schedule)?;
+
+        let handle = tokio::spawn(async move {
+            for next_time in cron_expr.upcoming(Utc) {
+                let wait_seconds = 1 + (next_time - Utc::now()).num_seconds() as u64;
+                info!(
+                    "Database maintenance next run on {}, wait_time = {}s",
+                    next_time, wait_seconds
+                );
+
+                tokio::select! {
+                    Ok(action) = rx.recv() => {
+                        match action {
+                            CoreAction::Shutdown => break,
+                        }
+                    }
+                    _ = sleep(Duration::from_secs(wait_seconds)) => {
+                        server.handle_db_maintenance(vacuum).await;
+                    }
+                }
+            }
+            info!("Stopped {}", super::TaskName::DbMaintenanceActor);
+        });
+
+        Ok(handle)
+    }
+}
+
+/// Parse a schedule in either the standard five field cron syntax, or the extended syntax
+/// with seconds and years. `task` names the schedule in any errors.
+fn parse_schedule(task: &str, schedule: &str) -> Result<Schedule, ()> {
+    let mut crono_expr_values = schedule.split_ascii_whitespace().collect::<Vec<&str>>();
+    let chrono_expr_uses_standard_syntax = crono_expr_values.len() == 5;
+    if chrono_expr_uses_standard_syntax {
+        // we add a 0 element at the beginning to simulate the standard crono syntax which always runs
+        // commands at seconds 00
+        crono_expr_values.insert(0, "0");
+        crono_expr_values.push("*");
+    }
+    let crono_expr_schedule = crono_expr_values.join(" ");
+    if chrono_expr_uses_standard_syntax {
+        info!(
+            "Provided {} schedule is: {}, now being transformed to: {}",
+            task.to_lowercase(),
+            schedule,
+            crono_expr_schedule
+        );
+    }
+    // Cron expression handling
+    let cron_expr = Schedule::from_str(crono_expr_schedule.as_str()).map_err(|e| {
+        error!("{} schedule parse error: {}", task, e);
+        error!("valid formats are:");
+        error!("sec  min   hour   day of month   month   day of week   year");
+        error!("min   hour   day of month   month   day of week");
+        error!("@hourly | @daily | @weekly");
+    })?;
+
+    info!("{} schedule parsed as: {}", task, cron_expr);
+
+    if cron_expr.upcoming(Utc).next().is_none() {
+        error!(
+            "{} schedule error: '{}' will not match any date.",
+            task, cron_expr
+        );
+        return Err(());
+    }
+
+    Ok(cron_expr)
 }
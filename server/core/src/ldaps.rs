@@ -3,19 +3,21 @@ use std::pin::Pin;
 use std::str::FromStr;
 
 use crate::actors::QueryServerReadV1;
+use crate::https::trace::RequestResult;
 use futures_util::sink::SinkExt;
 use futures_util::stream::StreamExt;
 use kanidmd_lib::idm::ldap::{LdapBoundToken, LdapResponseState};
 use kanidmd_lib::prelude::*;
-use ldap3_proto::proto::LdapMsg;
+use ldap3_proto::proto::{LdapMsg, LdapOp, LdapResult, LdapResultCode};
 use ldap3_proto::LdapCodec;
 use openssl::ssl::{Ssl, SslAcceptor};
 use tokio::net::{TcpListener, TcpStream};
 use tokio_openssl::SslStream;
 use tokio_util::codec::{FramedRead, FramedWrite};
+use tokio_util::task::TaskTracker;
 
 use crate::CoreAction;
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, watch};
 
 struct LdapSession {
     uat: Option<LdapBoundToken>,
@@ -30,7 +32,43 @@ impl LdapSession {
     }
 }
 
-#[instrument(name = "ldap-request", skip(client_address, qe_r_ref))]
+/// The result carried by an LDAP response message, if it has one.
+fn ldap_result(rmsg: &LdapMsg) -> Option<&LdapResult> {
+    match &rmsg.op {
+        LdapOp::BindResponse(r) => Some(&r.res),
+        LdapOp::ExtendedResponse(r) => Some(&r.res),
+        LdapOp::SearchResultDone(r) | LdapOp::CompareResult(r) => Some(r),
+        _ => None,
+    }
+}
+
+impl From<Option<&LdapResponseState>> for RequestResult {
+    fn from(state: Option<&LdapResponseState>) -> Self {
+        let rmsg = match state {
+            Some(LdapResponseState::Unbind) => return RequestResult::Success,
+            Some(LdapResponseState::Disconnect(rmsg))
+            | Some(LdapResponseState::Bind(_, rmsg))
+            | Some(LdapResponseState::Respond(rmsg)) => Some(rmsg),
+            // The final message of a multi part response carries the result.
+            Some(LdapResponseState::MultiPartResponse(v))
+            | Some(LdapResponseState::BindMultiPartResponse(_, v)) => v.last(),
+            None => None,
+        };
+        match rmsg.and_then(ldap_result).map(|r| &r.code) {
+            Some(LdapResultCode::Success)
+            | Some(LdapResultCode::CompareTrue)
+            | Some(LdapResultCode::CompareFalse) => RequestResult::Success,
+            Some(LdapResultCode::InvalidCredentials) => RequestResult::Denied,
+            _ => RequestResult::Error,
+        }
+    }
+}
+
+#[instrument(
+    name = "ldap-request",
+    skip(client_address, qe_r_ref),
+    fields(result = tracing::field::Empty)
+)]
 async fn client_process_msg(
     uat: Option<LdapBoundToken>,
     client_address: net::SocketAddr,
@@ -38,14 +76,19 @@ async fn client_process_msg(
     qe_r_ref: &'static QueryServerReadV1,
 ) -> Option<LdapResponseState> {
     let eventid = sketching::tracing_forest::id();
+    sketching::capture::capture_client_ip(client_address.ip());
     security_info!(
         client_ip = %client_address.ip(),
         client_port = %client_address.port(),
         "LDAP client"
     );
-    qe_r_ref
+    let res = qe_r_ref
         .handle_ldaprequest(eventid, protomsg, uat, client_address.ip())
-        .await
+        .await;
+    // Record the outcome on the request span, as is done for https requests.
+    let result = RequestResult::from(res.as_ref());
+    tracing::Span::current().record("result", result.as_str());
+    res
 }
 
 async fn client_process(
@@ -53,6 +96,7 @@ async fn client_process(
     tls_acceptor: SslAcceptor,
     client_address: net::SocketAddr,
     qe_r_ref: &'static QueryServerReadV1,
+    mut drain_rx: watch::Receiver<bool>,
 ) {
     // Start the event
     // From the parameters we need to create an SslContext.
@@ -76,7 +120,17 @@ async fn client_process(
     // This is a connected client session. we need to associate some state to the session
     let mut session = LdapSession::new();
     // Now that we have the session we begin an event loop to process input OR we return.
-    while let Some(Ok(protomsg)) = r.next().await {
+    loop {
+        let protomsg = tokio::select! {
+            biased;
+            // The server is shutting down. The connection is closed between requests, rather
+            // than waiting for further requests on it.
+            Ok(()) = drain_rx.changed() => break,
+            msg = r.next() => match msg {
+                Some(Ok(protomsg)) => protomsg,
+                _ => break,
+            },
+        };
         // Start the event
         let uat = session.uat.clone();
         let caddr = client_address;
@@ -132,6 +186,8 @@ async fn tls_acceptor(
     qe_r_ref: &'static QueryServerReadV1,
     mut rx: broadcast::Receiver<CoreAction>,
 ) {
+    let connections = TaskTracker::new();
+    let (drain_tx, drain_rx) = watch::channel(false);
     loop {
         tokio::select! {
             Ok(action) = rx.recv() => {
@@ -143,7 +199,7 @@ async fn tls_acceptor(
                 match accept_result {
                     Ok((tcpstream, client_socket_addr)) => {
                         let clone_tls_acceptor = tls_acceptor.clone();
-                        tokio::spawn(client_process(tcpstream, clone_tls_acceptor, client_socket_addr, qe_r_ref));
+                        connections.spawn(client_process(tcpstream, clone_tls_acceptor, client_socket_addr, qe_r_ref, drain_rx.clone()));
                     }
                     Err(e) => {
                         error!("LDAP acceptor error, continuing -> {:?}", e);
@@ -152,6 +208,21 @@ async fn tls_acceptor(
             }
         }
     }
+
+    // Stop accepting connections, then close the open connections once the requests in flight
+    // on them have finished.
+    drop(listener);
+    let _ = drain_tx.send(true);
+    connections.close();
+    if tokio::time::timeout(crate::SHUTDOWN_GRACE_PERIOD, connections.wait())
+        .await
+        .is_err()
+    {
+        warn!(
+            open_connections = connections.len(),
+            "LDAP connections did not finish before shutdown"
+        );
+    }
     info!("Stopped {}", super::TaskName::LdapActor);
 }
 
@@ -193,3 +264,38 @@ pub(crate) async fn create_ldap_server(
     info!("Created LDAP interface");
     Ok(ldap_acceptor_handle)
 }
+
+#[test]
+fn test_request_result_from_ldap_response() {
+    let response = |code| LdapMsg {
+        msgid: 1,
+        op: LdapOp::SearchResultDone(LdapResult {
+            code,
+            matcheddn: "".to_string(),
+            message: "".to_string(),
+            referral: vec![],
+        }),
+        ctrl: vec![],
+    };
+
+    assert_eq!(
+        RequestResult::from(Some(&LdapResponseState::Respond(response(
+            LdapResultCode::Success
+        )))),
+        RequestResult::Success
+    );
+    assert_eq!(
+        RequestResult::from(Some(&LdapResponseState::MultiPartResponse(vec![
+            response(LdapResultCode::Success),
+            response(LdapResultCode::InvalidCredentials),
+        ]))),
+        RequestResult::Denied
+    );
+    assert_eq!(
+        RequestResult::from(Some(&LdapResponseState::Respond(response(
+            LdapResultCode::Other
+        )))),
+        RequestResult::Error
+    );
+    assert_eq!(RequestResult::from(None), RequestResult::Error);
+}
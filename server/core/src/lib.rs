@@ -28,6 +28,7 @@ extern crate kanidmd_lib;
 
 mod actors;
 pub mod admin;
+mod audit;
 pub mod config;
 mod crypto;
 mod https;
@@ -37,12 +38,17 @@ mod repl;
 mod utils;
 
 use std::fmt::{Display, Formatter};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::utils::touch_file_or_quit;
 use compact_jwt::{JwsHs256Signer, JwsSigner};
 use kanidm_proto::internal::OperationError;
-use kanidmd_lib::be::{Backend, BackendConfig, BackendTransaction};
+use kanidm_proto::v1::Entry as ProtoEntry;
+use kanidmd_lib::be::{
+    Backend, BackendConfig, BackendTransaction, ChangelogOperation, DbEncryptionKey,
+};
 use kanidmd_lib::idm::ldap::LdapServer;
 use kanidmd_lib::prelude::*;
 use kanidmd_lib::schema::Schema;
@@ -56,6 +62,7 @@ use tokio::task;
 
 use crate::actors::{QueryServerReadV1, QueryServerWriteV1};
 use crate::admin::AdminActor;
+use crate::audit::{AuditStorePool, AuditdActor, AUDIT_TAIL_QUEUE_SIZE};
 use crate::config::{Configuration, ServerRole};
 use crate::interval::IntervalActor;
 
@@ -75,14 +82,50 @@ fn setup_backend_vacuum(
     let schema_txn = schema.write();
     let idxmeta = schema_txn.reload_idxmeta();
 
-    let pool_size: u32 = config.threads as u32;
+    // Every connection to an in memory database opens a private database of its own, so a
+    // read connection would never see what was written. Keep to a single connection.
+    let pool_size: u32 = if config.db_in_memory() {
+        1
+    } else {
+        config.threads as u32
+    };
+
+    let encryption_key = config
+        .db_encryption_key_provider()
+        .map_err(|e| {
+            error!("{}", e);
+            OperationError::CryptographyError
+        })?
+        .map(|provider| provider.load_key())
+        .transpose()?;
+
+    let backup_encryption_key = config
+        .backup_encryption_key_path
+        .as_deref()
+        .map(|p| DbEncryptionKey::from_file(Path::new(p)))
+        .transpose()?;
 
     let cfg = BackendConfig::new(
         config.db_path.as_str(),
         pool_size,
         config.db_fs_type.unwrap_or_default(),
         config.db_arc_size,
-    );
+    )
+    .encryption_key(encryption_key)
+    .compress_entries(config.db_compress_entries)
+    .durability(config.db_durability)
+    .journal_mode(config.db_journal_mode)
+    .search_max_candidates(config.db_search_max_candidates)
+    .tombstone_max_age(config.db_tombstone_max_age)
+    .recyclebin_max_age(config.db_recyclebin_max_age)
+    .backup_encryption_key(backup_encryption_key)
+    .max_entries(config.db_max_entries)
+    .max_db_size(config.db_max_size)
+    .entry_limits(EntryLimits {
+        max_attributes: config.entry_max_attributes,
+        max_values: config.entry_max_values,
+        max_value_bytes: config.entry_max_value_bytes,
+    });
 
     Backend::new(cfg, idxmeta, vacuum)
 }
@@ -369,7 +412,7 @@ pub fn backup_server_core(config: &Configuration, dst_path: &str) {
     // Let the txn abort, even on success.
 }
 
-pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
+pub async fn restore_server_core(config: &Configuration, dst_path: &str, allow_unverified: bool) {
     touch_file_or_quit(config.db_path.as_str());
 
     // First, we provide the in-memory schema so that core attrs are indexed correctly.
@@ -399,7 +442,9 @@ pub async fn restore_server_core(config: &Configuration, dst_path: &str) {
             return;
         }
     };
-    let r = be_wr_txn.restore(dst_path).and_then(|_| be_wr_txn.commit());
+    let r = be_wr_txn
+        .restore(dst_path, allow_unverified)
+        .and_then(|_| be_wr_txn.commit());
 
     if r.is_err() {
         error!("Failed to restore database: {:?}", r);
@@ -583,6 +628,158 @@ pub async fn domain_rename_core(config: &Configuration) {
     };
 }
 
+pub async fn import_server_core(config: &Configuration, src_path: &str) {
+    // The entries are a json list, in the same form as the entries of a create request.
+    let proto_entries: Vec<ProtoEntry> = match std::fs::read(src_path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_slice(&data).map_err(|e| e.to_string()))
+    {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!(%err, "Failed to read entries from {}", src_path);
+            std::process::exit(1);
+        }
+    };
+
+    let schema = match Schema::new() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("Failed to setup in memory schema: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Start the backend.
+    let be = match setup_backend(config, &schema) {
+        Ok(be) => be,
+        Err(e) => {
+            error!("Failed to setup BE: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // Setup the qs, and perform any migrations and changes we may have.
+    let qs = match setup_qs(be, schema, config).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Unable to setup query server -> {:?}", e);
+            std::process::exit(1);
+        }
+    };
+
+    eprintln!("Importing {} entries ...", proto_entries.len());
+
+    // All entries are created in a single transaction, so nothing is imported if any fail.
+    let mut qs_write = qs.write(duration_from_epoch_now()).await;
+    let r = proto_entries
+        .iter()
+        .map(|pe| Entry::from_proto_entry(pe, &mut qs_write))
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|entries| qs_write.internal_import_create(entries))
+        .and_then(|_| qs_write.commit());
+
+    match r {
+        Ok(_) => info!("✅ Import Success!"),
+        Err(e) => {
+            error!("Import Failed - Rollback has occurred: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+}
+
+async fn domain_level(qs: &QueryServer) -> Result<u32, OperationError> {
+    match qs.read().await.internal_search_uuid(UUID_DOMAIN_INFO) {
+        Ok(e) => Ok(e.get_ava_single_uint32(Attribute::Version).unwrap_or(0)),
+        Err(OperationError::NoMatchingEntries) => Ok(0),
+        Err(e) => Err(e),
+    }
+}
+
+async fn migrate_db(config: &Configuration) -> Result<(), OperationError> {
+    let format_before = Backend::read_format_version(&config.db_path)?;
+
+    let schema = Schema::new()?;
+    // Opening the backend migrates the format of the database.
+    let be = setup_backend(config, &schema)?;
+    let curtime = duration_from_epoch_now();
+    let qs = QueryServer::new(be.clone(), schema, config.domain.clone(), curtime)?;
+
+    let level_before = domain_level(&qs).await?;
+    let changelog_seq = be.read()?.changelog_max_seq()?;
+
+    // Then the migrations of the entries to the domain level of this server.
+    qs.initialise_helper(curtime, DOMAIN_TGT_LEVEL).await?;
+
+    let format_after = Backend::read_format_version(&config.db_path)?;
+    let level_after = domain_level(&qs).await?;
+    let changes = be.read()?.changelog(changelog_seq)?;
+    let count = |op| changes.iter().filter(|(_, c)| c.operation == op).count();
+
+    info!(
+        before = format_before.id2entry,
+        after = format_after.id2entry,
+        "database format"
+    );
+    info!(
+        before = format_before.index,
+        after = format_after.index,
+        reindexed = format_before.index != format_after.index,
+        "index version"
+    );
+    info!(before = level_before, after = level_after, "domain level");
+    info!(
+        created = count(ChangelogOperation::Create),
+        modified = count(ChangelogOperation::Modify),
+        deleted = count(ChangelogOperation::Delete),
+        "entries changed"
+    );
+    Ok(())
+}
+
+pub async fn migrate_server_core(config: &Configuration, dry_run: bool) {
+    if !Path::new(&config.db_path).exists() {
+        info!(
+            db_path = %config.db_path,
+            "No database exists, it is created when the server starts"
+        );
+        return;
+    }
+
+    let mut config = config.clone();
+    let snapshot_path = format!("{}.dry-run", config.db_path);
+    let remove_snapshot = || {
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{snapshot_path}{suffix}"));
+        }
+    };
+
+    if dry_run {
+        // The copy is discarded after.
+        remove_snapshot();
+        if let Err(err) = Backend::snapshot(&config.db_path, &snapshot_path) {
+            error!(?err, "Failed to copy the database for the dry run");
+            std::process::exit(1);
+        }
+        config.db_path.clone_from(&snapshot_path);
+        info!("Dry run, the migrations are run on a copy and the database is not modified");
+    }
+
+    let r = migrate_db(&config).await;
+
+    if dry_run {
+        remove_snapshot();
+    }
+
+    match r {
+        Ok(()) if dry_run => info!("✅ Dry Run Success!"),
+        Ok(()) => info!("✅ Migrate Success!"),
+        Err(e) => {
+            error!("Migrate Failed - Rollback has occurred: {:?}", e);
+            std::process::exit(1);
+        }
+    };
+}
+
 pub async fn verify_server_core(config: &Configuration) {
     let curtime = duration_from_epoch_now();
     // setup the qs - without initialise!
@@ -720,10 +917,15 @@ pub enum CoreAction {
     Shutdown,
 }
 
+/// How long the HTTPS and LDAP servers wait for requests that are in flight to finish once a
+/// shutdown has been requested.
+pub(crate) const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(15);
+
 pub(crate) enum TaskName {
     AdminSocket,
     AuditdActor,
     BackupActor,
+    DbMaintenanceActor,
     DelayedActionActor,
     HttpsServer,
     IntervalActor,
@@ -740,6 +942,7 @@ impl Display for TaskName {
                 TaskName::AdminSocket => "Admin Socket",
                 TaskName::AuditdActor => "Auditd Actor",
                 TaskName::BackupActor => "Backup Actor",
+                TaskName::DbMaintenanceActor => "Database Maintenance Actor",
                 TaskName::DelayedActionActor => "Delayed Action Actor",
                 TaskName::HttpsServer => "HTTPS Server",
                 TaskName::IntervalActor => "Interval Actor",
@@ -755,6 +958,10 @@ pub struct CoreHandle {
     pub tx: broadcast::Sender<CoreAction>,
     /// This stores a name for the handle, and the handle itself so we can tell which failed/succeeded at the end.
     handles: Vec<(TaskName, task::JoinHandle<()>)>,
+    /// The audit daemon is stopped separately, after every other task has finished, so that the
+    /// audit events of the last requests are still recorded.
+    auditd_tx: broadcast::Sender<CoreAction>,
+    auditd_handle: Option<task::JoinHandle<()>>,
 }
 
 impl CoreHandle {
@@ -771,6 +978,21 @@ impl CoreHandle {
             }
         }
 
+        // Nothing else can raise audit events now, so the audit daemon can write out what
+        // remains in its queue and stop.
+        if let Some(handle) = self.auditd_handle.take() {
+            if self.auditd_tx.send(CoreAction::Shutdown).is_err() {
+                eprintln!("Audit daemon did not ack shutdown request.");
+            }
+            if let Err(error) = handle.await {
+                eprintln!(
+                    "Task {} failed to finish: {:?}",
+                    TaskName::AuditdActor,
+                    error
+                );
+            }
+        }
+
         self.clean_shutdown = true;
     }
 }
@@ -842,14 +1064,13 @@ pub async fn create_server_core(
         }
     };
     // Start the IDM server.
-    let (_qs, idms, mut idms_delayed, mut idms_audit) =
-        match setup_qs_idms(be, schema, &config).await {
-            Ok(t) => t,
-            Err(e) => {
-                error!("Unable to setup query server or idm server -> {:?}", e);
-                return Err(());
-            }
-        };
+    let (_qs, idms, mut idms_delayed, idms_audit) = match setup_qs_idms(be, schema, &config).await {
+        Ok(t) => t,
+        Err(e) => {
+            error!("Unable to setup query server or idm server -> {:?}", e);
+            return Err(());
+        }
+    };
 
     // Extract any configuration from the IDMS that we may need.
     // For now we just do this per run, but we need to extract this from the db later.
@@ -975,32 +1196,9 @@ pub async fn create_server_core(
         info!("Stopped {}", TaskName::DelayedActionActor);
     });
 
-    let mut broadcast_rx = broadcast_tx.subscribe();
-
-    let auditd_handle = task::spawn(async move {
-        loop {
-            tokio::select! {
-                Ok(action) = broadcast_rx.recv() => {
-                    match action {
-                        CoreAction::Shutdown => break,
-                    }
-                }
-                audit_event = idms_audit.audit_rx().recv() => {
-                    match serde_json::to_string(&audit_event) {
-                        Ok(audit_event) => {
-                            warn!(%audit_event);
-                        }
-                        Err(e) => {
-                            error!(err=?e, "Unable to process audit event to json.");
-                            warn!(?audit_event, json=false);
-                        }
-                    }
-
-                }
-            }
-        }
-        info!("Stopped {}", TaskName::AuditdActor);
-    });
+    let (audit_tail_tx, _) = broadcast::channel(AUDIT_TAIL_QUEUE_SIZE);
+    let (auditd_tx, auditd_rx) = broadcast::channel(1);
+    let auditd_handle = AuditdActor::start(idms_audit, &config, auditd_rx, audit_tail_tx.clone())?;
 
     // Setup timed events associated to the write thread
     let interval_handle = IntervalActor::start(server_write_ref, broadcast_tx.subscribe());
@@ -1025,6 +1223,21 @@ pub async fn create_server_core(
         }
     };
 
+    let maybe_db_maintenance_handle = match &config.db_maintenance {
+        Some(db_maintenance_config) if db_maintenance_config.enabled => {
+            let handle = IntervalActor::start_db_maintenance(
+                server_write_ref,
+                db_maintenance_config,
+                broadcast_tx.subscribe(),
+            )?;
+            Some(handle)
+        }
+        _ => {
+            debug!("Database maintenance not requested, skipping");
+            None
+        }
+    };
+
     // If we have been requested to init LDAP, configure it now.
     let maybe_ldap_acceptor_handle = match &config.ldapaddress {
         Some(la) => {
@@ -1114,6 +1327,11 @@ pub async fn create_server_core(
             server_read_ref,
             broadcast_rx,
             maybe_repl_ctrl_tx,
+            config
+                .audit_store
+                .as_ref()
+                .map(|cfg| AuditStorePool::new(cfg.path.clone())),
+            audit_tail_tx,
         )
         .await?;
 
@@ -1125,13 +1343,16 @@ pub async fn create_server_core(
     let mut handles: Vec<(TaskName, task::JoinHandle<()>)> = vec![
         (TaskName::IntervalActor, interval_handle),
         (TaskName::DelayedActionActor, delayed_handle),
-        (TaskName::AuditdActor, auditd_handle),
     ];
 
     if let Some(backup_handle) = maybe_backup_handle {
         handles.push((TaskName::BackupActor, backup_handle))
     }
 
+    if let Some(db_maintenance_handle) = maybe_db_maintenance_handle {
+        handles.push((TaskName::DbMaintenanceActor, db_maintenance_handle))
+    }
+
     if let Some(admin_sock_handle) = maybe_admin_sock_handle {
         handles.push((TaskName::AdminSocket, admin_sock_handle))
     }
@@ -1152,5 +1373,7 @@ pub async fn create_server_core(
         clean_shutdown: false,
         tx: broadcast_tx,
         handles,
+        auditd_tx,
+        auditd_handle: Some(auditd_handle),
     })
 }
@@ -21,7 +21,10 @@ static ALLOC: dhat::Alloc = dhat::Alloc;
 use std::fs::{metadata, File};
 // This works on both unix and windows.
 use fs4::FileExt;
+use kanidm_proto::internal::Filter as ProtoFilter;
 use kanidm_proto::messages::ConsoleOutputMode;
+use sketching::capture::DebugCapture;
+use sketching::filter::LogSubsystem;
 use sketching::otel::TracingPipelineGuard;
 use sketching::LogLevel;
 #[cfg(target_family = "unix")]
@@ -29,22 +32,23 @@ use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
 use std::process::ExitCode;
 use std::str::FromStr;
+use std::time::Duration;
 
 use clap::{Args, Parser, Subcommand};
 use futures::{SinkExt, StreamExt};
 #[cfg(not(target_family = "windows"))] // not needed for windows builds
 use kanidm_utils_users::{get_current_gid, get_current_uid, get_effective_gid, get_effective_uid};
 use kanidmd_core::admin::{
-    AdminTaskRequest, AdminTaskResponse, ClientCodec, ProtoDomainInfo,
+    AdminTaskRequest, AdminTaskResponse, AuditSearch, ClientCodec, ProtoDomainInfo,
     ProtoDomainUpgradeCheckReport, ProtoDomainUpgradeCheckStatus,
 };
-use kanidmd_core::config::{Configuration, ServerConfig};
+use kanidmd_core::config::{Configuration, ServerConfig, DB_PATH_IN_MEMORY};
 use kanidmd_core::{
     backup_server_core, cert_generate_core, create_server_core, dbscan_get_id2entry_core,
     dbscan_list_id2entry_core, dbscan_list_index_analysis_core, dbscan_list_index_core,
     dbscan_list_indexes_core, dbscan_list_quarantined_core, dbscan_quarantine_id2entry_core,
-    dbscan_restore_quarantined_core, domain_rename_core, reindex_server_core, restore_server_core,
-    vacuum_server_core, verify_server_core,
+    dbscan_restore_quarantined_core, domain_rename_core, import_server_core, migrate_server_core,
+    reindex_server_core, restore_server_core, vacuum_server_core, verify_server_core,
 };
 use sketching::tracing_forest::util::*;
 use tokio::net::UnixStream;
@@ -75,6 +79,12 @@ impl KanidmdOpt {
             KanidmdOpt::Database {
                 commands: DbCommands::Restore(ropt),
             } => &ropt.commonopts,
+            KanidmdOpt::Database {
+                commands: DbCommands::Import(iopt),
+            } => &iopt.commonopts,
+            KanidmdOpt::Database {
+                commands: DbCommands::Migrate(mopt),
+            } => &mopt.commonopts,
             KanidmdOpt::DbScan {
                 commands: DbScanOpt::QuarantineId2Entry { commonopts, .. },
             }
@@ -115,9 +125,43 @@ impl KanidmdOpt {
             | KanidmdOpt::Database {
                 commands: DbCommands::Reindex(sopt),
             } => sopt,
+            KanidmdOpt::Database {
+                commands: DbCommands::OnlineReindex(sopt),
+            }
+            | KanidmdOpt::Database {
+                commands: DbCommands::OnlineVerify(sopt),
+            }
+            | KanidmdOpt::Database {
+                commands: DbCommands::Stats(sopt),
+            } => sopt,
+            KanidmdOpt::Database {
+                commands: DbCommands::OnlineBackup(bopt),
+            } => &bopt.commonopts,
+            KanidmdOpt::Database {
+                commands: DbCommands::Explain(eopt),
+            } => &eopt.commonopts,
             KanidmdOpt::Database {
                 commands: DbCommands::Vacuum(copt),
             } => copt,
+            KanidmdOpt::Audit {
+                commands: AuditCmds::Search { commonopts, .. },
+            } => commonopts,
+            KanidmdOpt::Audit {
+                commands: AuditCmds::Tail { commonopts },
+            } => commonopts,
+            KanidmdOpt::LogLevel {
+                commands: LogLevelCmds::Show { commonopts },
+            }
+            | KanidmdOpt::LogLevel {
+                commands: LogLevelCmds::Set { commonopts, .. },
+            } => commonopts,
+            KanidmdOpt::DebugCapture {
+                commands:
+                    DebugCaptureCmds::Show { commonopts }
+                    | DebugCaptureCmds::Account { commonopts, .. }
+                    | DebugCaptureCmds::ClientIp { commonopts, .. }
+                    | DebugCaptureCmds::Clear { commonopts },
+            } => commonopts,
             KanidmdOpt::HealthCheck(hcopt) => &hcopt.commonopts,
             KanidmdOpt::Version(copt) => copt,
         }
@@ -133,7 +177,10 @@ fn get_user_details_windows() {
     );
 }
 
-async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: ConsoleOutputMode) {
+async fn send_admin_req(
+    path: &str,
+    req: AdminTaskRequest,
+) -> Option<Framed<UnixStream, ClientCodec>> {
     // Connect to the socket.
     let stream = match UnixStream::connect(path).await {
         Ok(s) => s,
@@ -141,7 +188,7 @@ async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: Consol
             error!(err = ?e, %path, "Unable to connect to socket path");
             let diag = kanidm_lib_file_permissions::diagnose_path(path.as_ref());
             info!(%diag);
-            return;
+            return None;
         }
     };
 
@@ -149,15 +196,53 @@ async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: Consol
 
     if let Err(e) = reqs.send(req).await {
         error!(err = ?e, "Unable to send request");
-        return;
+        return None;
     };
 
     if let Err(e) = reqs.flush().await {
         error!(err = ?e, "Unable to flush request");
-        return;
+        return None;
     }
 
     trace!("flushed, waiting ...");
+    Some(reqs)
+}
+
+/// Print audit events as they are emitted, until the server or user ends the stream.
+async fn tail_admin_audit(path: &str, output_mode: ConsoleOutputMode) {
+    let Some(mut reqs) = send_admin_req(path, AdminTaskRequest::AuditTail).await else {
+        return;
+    };
+
+    loop {
+        match reqs.next().await {
+            Some(Ok(AdminTaskResponse::AuditEvent { event })) => match output_mode {
+                ConsoleOutputMode::JSON => println!("{}", event),
+                ConsoleOutputMode::Text => info!("{}", event),
+            },
+            Some(Ok(AdminTaskResponse::AuditTailLagged { missed })) => {
+                warn!(%missed, "Audit events were missed while catching up")
+            }
+            Some(Ok(resp)) => {
+                error!(?resp, "Unexpected response from admin socket");
+                break;
+            }
+            Some(Err(err)) => {
+                error!(?err, "Error during admin task operation");
+                break;
+            }
+            None => {
+                info!("Audit stream closed by the server");
+                break;
+            }
+        }
+    }
+}
+
+async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: ConsoleOutputMode) {
+    let Some(mut reqs) = send_admin_req(path, req).await else {
+        return;
+    };
 
     match reqs.next().await {
         Some(Ok(AdminTaskResponse::RecoverAccount { password })) => match output_mode {
@@ -255,6 +340,120 @@ async fn submit_admin_req(path: &str, req: AdminTaskRequest, output_mode: Consol
                 info!("domain_level  : {}", level);
             }
         },
+        Some(Ok(AdminTaskResponse::AuditSearch { events })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "audit_events": events
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                for event in events {
+                    info!("{}", event);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::LogLevels { levels })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "log_levels": levels
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!("default: {}", levels.level);
+                for (subsystem, level) in levels.subsystems {
+                    info!("{}: {}", subsystem, level);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::DebugCapture { capture })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "debug_capture": capture
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => match capture {
+                Some(capture) => info!("capturing operations of {}", capture),
+                None => info!("not capturing"),
+            },
+        },
+        Some(Ok(AdminTaskResponse::Verify { findings })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "findings": findings
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                if findings.is_empty() {
+                    info!("Verification passed!");
+                } else {
+                    for finding in findings {
+                        error!(?finding);
+                    }
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::BackendStats { stats })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "stats": stats
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!(entries = stats.entries);
+                info!(db_size = stats.db_size, db_free = stats.db_free);
+                match stats
+                    .last_vacuum
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                {
+                    Some(t) => info!(last_vacuum = %t.to_rfc3339()),
+                    None => info!("last_vacuum: never"),
+                }
+                for index in stats.indexes {
+                    info!(attr = index.attr, itype = index.itype, keys = index.keys);
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::FilterExplain { explain })) => match output_mode {
+            ConsoleOutputMode::JSON => {
+                let json_output = serde_json::json!({
+                    "explain": explain
+                });
+                println!("{}", json_output);
+            }
+            ConsoleOutputMode::Text => {
+                info!(
+                    candidates = explain.candidates,
+                    full_scan = explain.full_scan,
+                    filter_test = explain.filter_test
+                );
+                // Each term is indented beneath the term that contains it.
+                let mut terms = vec![(0, &explain.plan)];
+                while let Some((depth, term)) = terms.pop() {
+                    let label = match &term.attr {
+                        Some(attr) => format!("{} {}", term.term, attr),
+                        None => term.term.clone(),
+                    };
+                    info!(
+                        "{:indent$}{} index={:?} candidates={}",
+                        "",
+                        label,
+                        term.index,
+                        term.candidates,
+                        indent = depth * 2
+                    );
+                    terms.extend(term.terms.iter().rev().map(|t| (depth + 1, t)));
+                }
+            }
+        },
+        Some(Ok(AdminTaskResponse::AuditEvent { .. }))
+        | Some(Ok(AdminTaskResponse::AuditTailLagged { .. })) => {
+            error!("Unexpected streamed response from admin socket");
+        }
         Some(Ok(AdminTaskResponse::Success)) => match output_mode {
             ConsoleOutputMode::JSON => {
                 eprintln!("\"success\"")
@@ -339,17 +538,43 @@ fn main() -> ExitCode {
         None => LogLevel::Info,
     };
 
-    println!("Log filter: {:?}", log_filter);
+    let log_format = sconfig
+        .as_ref()
+        .and_then(|config| config.log_format)
+        .unwrap_or_default();
+
+    // Only the server itself records security events, not the admin commands.
+    let security_log = match &opt.commands {
+        KanidmdOpt::Server(_) => sconfig
+            .as_ref()
+            .and_then(|config| config.security_log.clone()),
+        _ => None,
+    };
+    let slow_operation_threshold = match &opt.commands {
+        KanidmdOpt::Server(_) => sconfig
+            .as_ref()
+            .and_then(|config| config.slow_operation_threshold_ms)
+            .map(Duration::from_millis),
+        _ => None,
+    };
+
+    // Json logs must be one object per line, so anything else goes to stderr.
+    eprintln!("Log filter: {:?}", log_filter);
 
     // if we have a server config and it has an otel url, then we'll start the logging pipeline
     let otel_grpc_url = sconfig
         .as_ref()
         .and_then(|config| config.otel_grpc_url.clone());
+    let otel_sample_ratio = sconfig.as_ref().and_then(|config| config.otel_sample_ratio);
 
     // TODO: only send to stderr when we're not in a TTY
     let sub = match sketching::otel::start_logging_pipeline(
         otel_grpc_url,
+        otel_sample_ratio,
         log_filter,
+        log_format,
+        security_log,
+        slow_operation_threshold,
         "kanidmd".to_string(),
     ) {
         Err(err) => {
@@ -452,8 +677,10 @@ fn main() -> ExitCode {
     if let Some(db_path) = sconfig.db_path.clone() {
         #[allow(clippy::expect_used)]
         let db_pathbuf = PathBuf::from(db_path.as_str());
-        // We can't check the db_path permissions because it may not exist yet!
-        if let Some(db_parent_path) = db_pathbuf.parent() {
+        if db_path == DB_PATH_IN_MEMORY {
+            warn!("The database is in memory, all content will be lost when the server stops.");
+        } else if let Some(db_parent_path) = db_pathbuf.parent() {
+            // We can't check the db_path permissions because it may not exist yet!
             if !db_parent_path.exists() {
                 warn!(
                     "DB folder {} may not exist, server startup may FAIL!",
@@ -511,6 +738,20 @@ fn main() -> ExitCode {
         return ExitCode::FAILURE;
     }
 
+    config.update_db_encryption_key_path(&sconfig.db_encryption_key_path);
+    config.update_backup_encryption_key_path(&sconfig.backup_encryption_key_path);
+    config.update_db_compress_entries(sconfig.db_compress_entries);
+    config.update_db_durability(sconfig.db_durability);
+    config.update_db_journal_mode(sconfig.db_journal_mode);
+    config.update_db_search_max_candidates(sconfig.db_search_max_candidates);
+    config.update_db_tombstone_max_age(sconfig.db_tombstone_max_age);
+    config.update_db_recyclebin_max_age(sconfig.db_recyclebin_max_age);
+    config.update_db_quota(sconfig.db_max_entries, sconfig.db_max_size);
+    config.update_entry_limits(
+        sconfig.entry_max_attributes,
+        sconfig.entry_max_values,
+        sconfig.entry_max_value_bytes,
+    );
     config.update_db_arc_size(sconfig.get_db_arc_size());
     config.update_role(sconfig.role);
     config.update_output_mode(opt.commands.commonopt().output_mode.to_owned().into());
@@ -518,6 +759,16 @@ fn main() -> ExitCode {
     config.update_admin_bind_path(&sconfig.adminbindpath);
     config.update_replication_config(sconfig.repl_config.clone());
 
+    if let Err(err) = config.check_db_tombstone_max_age() {
+        error!("{err}, server startup will FAIL!");
+        return ExitCode::FAILURE;
+    }
+
+    if let Err(err) = config.check_db_recyclebin_max_age() {
+        error!("{err}, server startup will FAIL!");
+        return ExitCode::FAILURE;
+    }
+
     // We always set threads to 1 unless it's the main server.
     if matches!(&opt.commands, KanidmdOpt::Server(_)) {
         // If not updated, will default to maximum
@@ -534,13 +785,24 @@ fn main() -> ExitCode {
         | KanidmdOpt::RenewReplicationCertificate { .. }
         | KanidmdOpt::RefreshReplicationConsumer { .. }
         | KanidmdOpt::RecoverAccount { .. }
+        | KanidmdOpt::Audit { .. }
+        | KanidmdOpt::LogLevel { .. }
+        | KanidmdOpt::DebugCapture { .. }
+        | KanidmdOpt::Database {
+            commands:
+                DbCommands::OnlineReindex(_)
+                | DbCommands::OnlineBackup(_)
+                | DbCommands::OnlineVerify(_)
+                | DbCommands::Stats(_)
+                | DbCommands::Explain(_),
+        }
         | KanidmdOpt::HealthCheck(_) => (),
         _ => {
             // Okay - Lets now create our lock and go.
             #[allow(clippy::expect_used)]
             let klock_path = match sconfig.db_path.clone() {
-                Some(val) => format!("{}.klock", val),
-                None => std::env::temp_dir()
+                Some(val) if val != DB_PATH_IN_MEMORY => format!("{}.klock", val),
+                _ => std::env::temp_dir()
                     .join("kanidmd.klock")
                     .to_str()
                     .expect("Unable to create klock path")
@@ -801,7 +1063,26 @@ async fn kanidm_main(
                     return ExitCode::FAILURE;
                 }
             };
-            restore_server_core(&config, p).await;
+            restore_server_core(&config, p, ropt.allow_unverified).await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Import(iopt),
+        } => {
+            info!("Running in import mode ...");
+            let p = match iopt.path.to_str() {
+                Some(p) => p,
+                None => {
+                    error!("Invalid import path");
+                    return ExitCode::FAILURE;
+                }
+            };
+            import_server_core(&config, p).await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Migrate(mopt),
+        } => {
+            info!("Running in migrate mode ...");
+            migrate_server_core(&config, mopt.dry_run).await;
         }
         KanidmdOpt::Database {
             commands: DbCommands::Verify(_vopt),
@@ -864,6 +1145,77 @@ async fn kanidm_main(
             info!("Running in reindex mode ...");
             reindex_server_core(&config).await;
         }
+        KanidmdOpt::Database {
+            commands: DbCommands::OnlineReindex(copt),
+        } => {
+            info!("Running online reindex ...");
+            let output_mode: ConsoleOutputMode = copt.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::Reindex,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::OnlineVerify(copt),
+        } => {
+            info!("Running online verify ...");
+            let output_mode: ConsoleOutputMode = copt.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::Verify,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Stats(copt),
+        } => {
+            let output_mode: ConsoleOutputMode = copt.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::BackendStats,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::Explain(eopt),
+        } => {
+            let output_mode: ConsoleOutputMode = eopt.commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::FilterExplain {
+                    filter: eopt.filter.clone(),
+                },
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::Database {
+            commands: DbCommands::OnlineBackup(bopt),
+        } => {
+            info!("Running online backup ...");
+            let output_mode: ConsoleOutputMode = bopt.commonopts.output_mode.to_owned().into();
+            // The server writes the backup, so it must be given a path that does not depend on
+            // where this was run from.
+            let path = match std::env::current_dir() {
+                Ok(cwd) => cwd.join(&bopt.path),
+                Err(e) => {
+                    error!(?e, "Unable to resolve the backup path");
+                    return ExitCode::FAILURE;
+                }
+            };
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::Backup {
+                    path: path.to_string_lossy().into_owned(),
+                },
+                output_mode,
+            )
+            .await;
+        }
         KanidmdOpt::DbScan {
             commands: DbScanOpt::ListIndexes(_),
         } => {
@@ -975,6 +1327,141 @@ async fn kanidm_main(
             .await;
         }
 
+        KanidmdOpt::Audit {
+            commands:
+                AuditCmds::Search {
+                    commonopts,
+                    from,
+                    to,
+                    name,
+                    spn,
+                    outcome,
+                    limit,
+                },
+        } => {
+            info!("Running audit search ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::AuditSearch {
+                    search: AuditSearch {
+                        from: *from,
+                        to: *to,
+                        name: name.clone(),
+                        spn: spn.clone(),
+                        outcome: outcome.clone(),
+                        limit: *limit,
+                    },
+                },
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::Audit {
+            commands: AuditCmds::Tail { commonopts },
+        } => {
+            info!("Running audit tail ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            tail_admin_audit(config.adminbindpath.as_str(), output_mode).await;
+        }
+
+        KanidmdOpt::LogLevel {
+            commands: LogLevelCmds::Show { commonopts },
+        } => {
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::LogLevelShow,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::LogLevel {
+            commands:
+                LogLevelCmds::Set {
+                    commonopts,
+                    level,
+                    subsystem,
+                },
+        } => {
+            let level = match LogLevel::from_str(level) {
+                Ok(level) => level,
+                Err(err) => {
+                    error!(%err, "Invalid log level {}", level);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let subsystem = match subsystem.as_deref().map(LogSubsystem::from_str) {
+                Some(Ok(subsystem)) => Some(subsystem),
+                Some(Err(err)) => {
+                    error!(%err, "Invalid log subsystem");
+                    return ExitCode::FAILURE;
+                }
+                None => None,
+            };
+            info!("Running log level change ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::LogLevelSet { subsystem, level },
+                output_mode,
+            )
+            .await;
+        }
+
+        KanidmdOpt::DebugCapture {
+            commands: DebugCaptureCmds::Show { commonopts },
+        } => {
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::DebugCaptureShow,
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::DebugCapture {
+            commands: DebugCaptureCmds::Account { name, commonopts },
+        } => {
+            info!("Starting debug capture ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::DebugCaptureSet {
+                    capture: Some(DebugCapture::Account(name.clone())),
+                },
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::DebugCapture {
+            commands: DebugCaptureCmds::ClientIp { ip, commonopts },
+        } => {
+            info!("Starting debug capture ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::DebugCaptureSet {
+                    capture: Some(DebugCapture::ClientIp(*ip)),
+                },
+                output_mode,
+            )
+            .await;
+        }
+        KanidmdOpt::DebugCapture {
+            commands: DebugCaptureCmds::Clear { commonopts },
+        } => {
+            info!("Stopping debug capture ...");
+            let output_mode: ConsoleOutputMode = commonopts.output_mode.to_owned().into();
+            submit_admin_req(
+                config.adminbindpath.as_str(),
+                AdminTaskRequest::DebugCaptureSet { capture: None },
+                output_mode,
+            )
+            .await;
+        }
+
         KanidmdOpt::Database {
             commands: DbCommands::Vacuum(_copt),
         } => {
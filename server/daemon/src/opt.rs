@@ -22,6 +22,37 @@ struct RestoreOpt {
     #[clap(value_parser)]
     /// Restore from this path. Should be created with "backup".
     path: PathBuf,
+    #[clap(long)]
+    /// Restore a backup that is not encrypted, even though a backup encryption key is
+    /// configured. Such a backup can not be verified, so only use this for trusted backups.
+    allow_unverified: bool,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct ImportOpt {
+    #[clap(value_parser)]
+    /// Import the entries in this file, a json list of entries.
+    path: PathBuf,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct ExplainOpt {
+    #[clap(value_parser = parse_filter)]
+    /// The filter to explain, either as json or in ldap syntax, eg '(&(class=person)(name=*ill*))'
+    filter: ProtoFilter,
+    #[clap(flatten)]
+    commonopts: CommonOpt,
+}
+
+#[derive(Debug, Args)]
+struct MigrateOpt {
+    #[clap(long)]
+    /// Report the changes the migrations would make, without modifying the database.
+    dry_run: bool,
     #[clap(flatten)]
     commonopts: CommonOpt,
 }
@@ -66,6 +97,106 @@ enum DomainSettingsCmds {
     },
 }
 
+fn parse_rfc3339(value: &str) -> Result<i64, String> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|time| time.timestamp())
+        .map_err(|e| e.to_string())
+}
+
+fn parse_filter(value: &str) -> Result<ProtoFilter, String> {
+    if value.trim_start().starts_with('(') {
+        value.parse()
+    } else {
+        serde_json::from_str(value).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum LogLevelCmds {
+    /// Show the log levels of the running server
+    #[clap(name = "show")]
+    Show {
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Change the log level of the running server, until it is restarted
+    #[clap(name = "set")]
+    Set {
+        /// The new level, one of info, debug, trace
+        level: String,
+        /// Only change this subsystem, one of be, schema, server, http
+        #[clap(long)]
+        subsystem: Option<String>,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DebugCaptureCmds {
+    /// Show who the running server is capturing the operations of
+    #[clap(name = "show")]
+    Show {
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Log every operation of this account at every level, regardless of the log level
+    #[clap(name = "account")]
+    Account {
+        /// The name, spn or uuid of the account
+        name: String,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Log every operation from this client address at every level, regardless of the log level
+    #[clap(name = "ip")]
+    ClientIp {
+        ip: std::net::IpAddr,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Stop capturing operations
+    #[clap(name = "clear")]
+    Clear {
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuditCmds {
+    /// Search the audit store for past audit events, newest last
+    #[clap(name = "search")]
+    Search {
+        /// Only show events at or after this time, eg 2024-01-01T00:00:00Z
+        #[clap(long, value_parser = parse_rfc3339)]
+        from: Option<i64>,
+        /// Only show events at or before this time, eg 2024-01-02T00:00:00Z
+        #[clap(long, value_parser = parse_rfc3339)]
+        to: Option<i64>,
+        /// Only show events of this type, eg AuthenticationDenied
+        #[clap(long)]
+        name: Option<String>,
+        /// Only show events for this account spn
+        #[clap(long)]
+        spn: Option<String>,
+        /// Only show operations with this outcome, one of success, denied or error
+        #[clap(long)]
+        outcome: Option<String>,
+        /// The maximum number of events to show (default 100)
+        #[clap(long)]
+        limit: Option<u32>,
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+    /// Show audit events as they are emitted by the server, until interrupted
+    #[clap(name = "tail")]
+    Tail {
+        #[clap(flatten)]
+        commonopts: CommonOpt,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 enum DbCommands {
     #[clap(name = "vacuum")]
@@ -77,12 +208,33 @@ enum DbCommands {
     #[clap(name = "restore")]
     /// Restore the database content (offline)
     Restore(RestoreOpt),
+    #[clap(name = "import")]
+    /// Create a large number of entries in a single transaction (offline)
+    Import(ImportOpt),
+    #[clap(name = "migrate")]
+    /// Migrate a database created by an older server, reporting what changed (offline)
+    Migrate(MigrateOpt),
     #[clap(name = "verify")]
     /// Verify database and entity consistency.
     Verify(CommonOpt),
     #[clap(name = "reindex")]
     /// Reindex the database (offline)
     Reindex(CommonOpt),
+    #[clap(name = "reindex-online")]
+    /// Reindex the database of the running server
+    OnlineReindex(CommonOpt),
+    #[clap(name = "backup-online")]
+    /// Backup the database content of the running server
+    OnlineBackup(BackupOpt),
+    #[clap(name = "verify-online")]
+    /// Verify database and entity consistency of the running server
+    OnlineVerify(CommonOpt),
+    #[clap(name = "stats")]
+    /// Show the entry count, index sizes and database size of the running server
+    Stats(CommonOpt),
+    #[clap(name = "explain")]
+    /// Show how the running server would execute a search filter, to help decide what to index
+    Explain(ExplainOpt),
 }
 
 #[derive(Debug, Args)]
@@ -199,8 +351,15 @@ impl KanidmdParser {
                 DbCommands::Vacuum(ref c) => c.config_path.clone(),
                 DbCommands::Backup(ref c) => c.commonopts.config_path.clone(),
                 DbCommands::Restore(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::Import(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::Migrate(ref c) => c.commonopts.config_path.clone(),
                 DbCommands::Verify(ref c) => c.config_path.clone(),
                 DbCommands::Reindex(ref c) => c.config_path.clone(),
+                DbCommands::OnlineReindex(ref c) => c.config_path.clone(),
+                DbCommands::OnlineBackup(ref c) => c.commonopts.config_path.clone(),
+                DbCommands::OnlineVerify(ref c) => c.config_path.clone(),
+                DbCommands::Stats(ref c) => c.config_path.clone(),
+                DbCommands::Explain(ref c) => c.commonopts.config_path.clone(),
             },
             KanidmdOpt::DomainSettings { ref commands } => match commands {
                 DomainSettingsCmds::Show { ref commonopts } => commonopts.config_path.clone(),
@@ -211,6 +370,20 @@ impl KanidmdParser {
                     commonopts.config_path.clone()
                 }
             },
+            KanidmdOpt::Audit { ref commands } => match commands {
+                AuditCmds::Search { ref commonopts, .. } => commonopts.config_path.clone(),
+                AuditCmds::Tail { ref commonopts } => commonopts.config_path.clone(),
+            },
+            KanidmdOpt::LogLevel { ref commands } => match commands {
+                LogLevelCmds::Show { ref commonopts } => commonopts.config_path.clone(),
+                LogLevelCmds::Set { ref commonopts, .. } => commonopts.config_path.clone(),
+            },
+            KanidmdOpt::DebugCapture { ref commands } => match commands {
+                DebugCaptureCmds::Show { ref commonopts }
+                | DebugCaptureCmds::Account { ref commonopts, .. }
+                | DebugCaptureCmds::ClientIp { ref commonopts, .. }
+                | DebugCaptureCmds::Clear { ref commonopts } => commonopts.config_path.clone(),
+            },
             KanidmdOpt::HealthCheck(ref c) => c.commonopts.config_path.clone(),
             KanidmdOpt::Version(ref c) => c.config_path.clone(),
         }
@@ -278,6 +451,25 @@ enum KanidmdOpt {
         #[clap(subcommand)]
         commands: DomainSettingsCmds,
     },
+    /// Inspect audit events recorded in the audit store
+    #[clap(name = "audit")]
+    Audit {
+        #[clap(subcommand)]
+        commands: AuditCmds,
+    },
+    /// Inspect and change the log levels of the running server
+    #[clap(name = "log-level")]
+    LogLevel {
+        #[clap(subcommand)]
+        commands: LogLevelCmds,
+    },
+    /// Log the operations of one account or client address at every level, to debug their
+    /// problems without enabling verbose logging for everyone
+    #[clap(name = "debug-capture")]
+    DebugCapture {
+        #[clap(subcommand)]
+        commands: DebugCaptureCmds,
+    },
 
     /// Load the server config and check services are listening
     #[clap(name = "healthcheck")]
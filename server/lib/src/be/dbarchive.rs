@@ -0,0 +1,220 @@
+//! Backup archives.
+//!
+//! A backup is written as a manifest on its first line, followed by the payload, the serialised
+//! [`DbBackup`]. The manifest records the SHA-256 checksum of the payload, so that a damaged
+//! backup is refused before anything is restored. A checksum can be recomputed by anyone who
+//! alters the backup, so when a backup key is configured the payload is also sealed with
+//! AES-256-GCM, with the manifest as the additional data. The tag then authenticates both, and
+//! a backup that is unencrypted, and so can not be authenticated, is refused unless the restore
+//! explicitly allows it.
+
+use std::borrow::Cow;
+
+use openssl::sha::sha256;
+use serde::{Deserialize, Serialize};
+
+use crate::be::dbcrypt::DbEncryptionKey;
+use crate::be::dbentry::DbBackup;
+use crate::prelude::*;
+
+/// The prefix of the additional data of a sealed payload, so that a sealed entry can't be
+/// given as a backup.
+const BACKUP_AAD: &[u8] = b"kanidm-backup";
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct DbBackupManifest {
+    /// The server version that created the backup.
+    pub version: String,
+    /// The number of entries in the backup.
+    pub entries: usize,
+    pub encrypted: bool,
+    /// The SHA-256 checksum of the payload before it is sealed, encoded as hex.
+    pub sha256: String,
+}
+
+fn backup_aad(manifest: &[u8]) -> Vec<u8> {
+    [BACKUP_AAD, manifest].concat()
+}
+
+fn payload_from_json(data: &[u8]) -> Result<DbBackup, OperationError> {
+    serde_json::from_slice(data).map_err(|e| {
+        admin_error!(?e, "serde_json error");
+        OperationError::SerdeJsonError
+    })
+}
+
+/// Write a backup as an archive, sealing it if a key is given.
+pub(crate) fn seal(
+    bak: &DbBackup,
+    version: String,
+    entries: usize,
+    key: Option<&DbEncryptionKey>,
+) -> Result<Vec<u8>, OperationError> {
+    let data = serde_json::to_vec(bak).map_err(|e| {
+        admin_error!(?e, "serde error");
+        OperationError::SerdeJsonError
+    })?;
+
+    let manifest = DbBackupManifest {
+        version,
+        entries,
+        encrypted: key.is_some(),
+        sha256: hex::encode(sha256(&data)),
+    };
+
+    // Serialised without whitespace, so the manifest is always a single line.
+    let mut archive = serde_json::to_vec(&manifest).map_err(|e| {
+        admin_error!(?e, "serde error");
+        OperationError::SerdeJsonError
+    })?;
+
+    let payload = match key {
+        Some(key) => key.seal_with_aad(&backup_aad(&archive), &data)?,
+        None => data,
+    };
+
+    archive.push(b'\n');
+    archive.extend_from_slice(&payload);
+    Ok(archive)
+}
+
+/// Verify a backup and read its content. Nothing in the backup is trusted until this succeeds.
+/// When a key is given, a backup that can't be authenticated with it is refused unless
+/// `allow_unverified` is set.
+pub(crate) fn open(
+    archive: &[u8],
+    key: Option<&DbEncryptionKey>,
+    allow_unverified: bool,
+) -> Result<DbBackup, OperationError> {
+    let parsed = archive.iter().position(|b| *b == b'\n').and_then(|split| {
+        let (header, payload) = archive.split_at(split);
+        serde_json::from_slice::<DbBackupManifest>(header)
+            .ok()
+            .map(|manifest| (manifest, header, &payload[1..]))
+    });
+
+    let Some((manifest, header, payload)) = parsed else {
+        // Written before archives existed.
+        if key.is_some() && !allow_unverified {
+            admin_error!(
+                "The backup has no manifest, so it can not be verified with the backup key"
+            );
+            return Err(OperationError::DB0009BackupUnverified);
+        }
+        warn!("The backup has no manifest, so its integrity can not be verified.");
+        return payload_from_json(archive);
+    };
+
+    info!(?manifest, "Verifying backup archive");
+
+    let data = if manifest.encrypted {
+        let key = key.ok_or_else(|| {
+            admin_error!("Backup is encrypted, but no backup encryption key is configured");
+            OperationError::DB0005BackupEncryptionKeyRequired
+        })?;
+        let data = key
+            .open_with_aad(&backup_aad(header), payload)
+            .map_err(|_| {
+                admin_error!("Unable to decrypt backup, it was altered or the backup key is wrong");
+                OperationError::DB0004BackupChecksumMismatch
+            })?;
+        Cow::Owned(data)
+    } else {
+        if key.is_some() && !allow_unverified {
+            admin_error!(
+                "The backup is not encrypted, so it can not be verified with the backup key"
+            );
+            return Err(OperationError::DB0009BackupUnverified);
+        }
+        Cow::Borrowed(payload)
+    };
+
+    if hex::encode(sha256(&data)) != manifest.sha256 {
+        admin_error!("Backup payload does not match the checksum of the manifest");
+        return Err(OperationError::DB0004BackupChecksumMismatch);
+    }
+
+    payload_from_json(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{open, seal};
+    use crate::be::dbcrypt::DbEncryptionKey;
+    use crate::be::dbentry::DbBackup;
+    use crate::prelude::*;
+
+    /// Replace the line of the manifest, or the payload after it.
+    fn alter(archive: &[u8], f: impl Fn(&mut Vec<u8>, &mut Vec<u8>)) -> Vec<u8> {
+        let split = archive
+            .iter()
+            .position(|b| *b == b'\n')
+            .expect("no manifest");
+        let mut header = archive[..split].to_vec();
+        let mut payload = archive[split + 1..].to_vec();
+        f(&mut header, &mut payload);
+        [header, b"\n".to_vec(), payload].concat()
+    }
+
+    #[test]
+    fn test_db_backup_archive() {
+        let bak = DbBackup::V1(Vec::new());
+
+        let archive = seal(&bak, "1.0".to_string(), 0, None).expect("seal");
+        assert!(matches!(
+            open(&archive, None, false),
+            Ok(DbBackup::V1(e)) if e.is_empty()
+        ));
+
+        // A damaged payload no longer matches the checksum.
+        let damaged = alter(&archive, |_, payload| payload.insert(1, b' '));
+        assert_eq!(
+            open(&damaged, None, false).err(),
+            Some(OperationError::DB0004BackupChecksumMismatch)
+        );
+
+        // An encrypted archive needs the key it was sealed with.
+        let key = DbEncryptionKey::generate();
+        let sealed = seal(&bak, "1.0".to_string(), 0, Some(&key)).expect("seal");
+        assert!(open(&sealed, Some(&key), false).is_ok());
+        assert_eq!(
+            open(&sealed, None, false).err(),
+            Some(OperationError::DB0005BackupEncryptionKeyRequired)
+        );
+        assert_eq!(
+            open(&sealed, Some(&DbEncryptionKey::generate()), false).err(),
+            Some(OperationError::DB0004BackupChecksumMismatch)
+        );
+
+        // The tag covers the manifest as well as the payload.
+        let altered = alter(&sealed, |header, _| {
+            let h = String::from_utf8(header.clone()).unwrap();
+            *header = h.replace("\"entries\":0", "\"entries\":1").into_bytes();
+        });
+        assert_ne!(altered, sealed);
+        assert_eq!(
+            open(&altered, Some(&key), false).err(),
+            Some(OperationError::DB0004BackupChecksumMismatch)
+        );
+        let altered = alter(&sealed, |_, payload| payload[8] ^= 1);
+        assert_eq!(
+            open(&altered, Some(&key), false).err(),
+            Some(OperationError::DB0004BackupChecksumMismatch)
+        );
+
+        // With a key configured, backups that can't be authenticated are refused unless allowed.
+        assert_eq!(
+            open(&archive, Some(&key), false).err(),
+            Some(OperationError::DB0009BackupUnverified)
+        );
+        assert!(open(&archive, Some(&key), true).is_ok());
+
+        let plain = serde_json::to_vec(&bak).unwrap();
+        assert!(open(&plain, None, false).is_ok());
+        assert_eq!(
+            open(&plain, Some(&key), false).err(),
+            Some(OperationError::DB0009BackupUnverified)
+        );
+        assert!(open(&plain, Some(&key), true).is_ok());
+    }
+}
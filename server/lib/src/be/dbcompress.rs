@@ -0,0 +1,75 @@
+//! Compression of the entries stored in the database.
+//!
+//! Entries with many values, such as groups with large memberships, repeat much of their
+//! serialised content, so they are stored deflated when compression is enabled. Compression is
+//! applied before an entry is sealed, as encrypted data can not be compressed.
+
+use std::io::{Read, Write};
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+
+use crate::prelude::*;
+
+/// The prefix of a compressed entry. Entries are serialised as json, so an uncompressed entry
+/// can never start with this.
+const COMPRESSED_ENTRY_MAGIC: &[u8] = b"kdf1";
+
+pub(crate) fn compress(id: u64, data: &[u8]) -> Result<Vec<u8>, OperationError> {
+    let mut encoder = DeflateEncoder::new(COMPRESSED_ENTRY_MAGIC.to_vec(), Compression::fast());
+    encoder
+        .write_all(data)
+        .and_then(|()| encoder.finish())
+        .map_err(|err| {
+            admin_error!(?err, %id, "Unable to compress entry");
+            OperationError::BackendEngine
+        })
+}
+
+pub(crate) fn decompress(id: u64, data: Vec<u8>) -> Result<Vec<u8>, OperationError> {
+    let Some(body) = data.strip_prefix(COMPRESSED_ENTRY_MAGIC) else {
+        // Entries written while compression was disabled are stored as is.
+        return Ok(data);
+    };
+
+    let mut decompressed = Vec::with_capacity(body.len() * 4);
+    DeflateDecoder::new(body)
+        .read_to_end(&mut decompressed)
+        .map_err(|err| {
+            admin_error!(?err, %id, "Unable to decompress entry");
+            OperationError::CorruptedEntry(id)
+        })?;
+    Ok(decompressed)
+}
+
+pub(crate) fn is_compressed(data: &[u8]) -> bool {
+    data.starts_with(COMPRESSED_ENTRY_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress, is_compressed};
+
+    #[test]
+    fn test_db_compression_roundtrip() {
+        let data = br#"{"attrs":{"member":["a","a","a","a","a","a","a","a","a","a"]}}"#;
+
+        let compressed = compress(1, data).expect("Failed to compress");
+        assert!(is_compressed(&compressed));
+        assert!(compressed.len() < data.len());
+        assert_eq!(
+            decompress(1, compressed.clone()).expect("Failed to decompress"),
+            data
+        );
+
+        // Uncompressed entries are read as they are.
+        assert!(!is_compressed(data));
+        assert_eq!(
+            decompress(1, data.to_vec()).expect("Failed to decompress"),
+            data
+        );
+
+        assert!(decompress(1, compressed[..6].to_vec()).is_err());
+    }
+}
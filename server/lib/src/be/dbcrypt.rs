@@ -0,0 +1,255 @@
+//! Encryption of the entries stored in the database.
+//!
+//! When a key is configured each entry is sealed with AES-256-GCM as it is written to id2entry,
+//! using the id of the entry as the additional data so that a sealed entry can not be moved to
+//! another id. Only entries are encrypted, the indexes still contain the values of indexed
+//! attributes. Backups can be sealed in the same way, with a key of their own.
+
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+
+use crate::prelude::*;
+
+/// The prefix of a sealed entry. Entries are serialised as json, so a plain entry can never
+/// start with this.
+const SEALED_ENTRY_MAGIC: &[u8] = b"kse1";
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// The environment variable that the db encryption key can be supplied in, as hex.
+pub const DB_ENCRYPTION_KEY_ENV: &str = "KANIDM_DB_ENCRYPTION_KEY";
+
+/// A source that a key can be loaded from when the server starts.
+pub trait DbEncryptionKeyProvider {
+    fn load_key(&self) -> Result<DbEncryptionKey, OperationError>;
+}
+
+/// A key stored in a file, as 32 bytes encoded as hex.
+#[derive(Debug, Clone)]
+pub struct DbEncryptionKeyFile {
+    path: PathBuf,
+}
+
+impl DbEncryptionKeyFile {
+    pub fn new(path: &Path) -> Self {
+        DbEncryptionKeyFile {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl DbEncryptionKeyProvider for DbEncryptionKeyFile {
+    fn load_key(&self) -> Result<DbEncryptionKey, OperationError> {
+        DbEncryptionKey::from_file(&self.path)
+    }
+}
+
+/// A key supplied in an environment variable, as 32 bytes encoded as hex. The variable is read
+/// when the key is loaded, so the key is never held in the configuration of the server.
+#[derive(Debug, Clone)]
+pub struct DbEncryptionKeyEnv {
+    var: String,
+}
+
+impl DbEncryptionKeyEnv {
+    pub fn new(var: &str) -> Self {
+        DbEncryptionKeyEnv {
+            var: var.to_string(),
+        }
+    }
+}
+
+impl DbEncryptionKeyProvider for DbEncryptionKeyEnv {
+    fn load_key(&self) -> Result<DbEncryptionKey, OperationError> {
+        let var = self.var.as_str();
+        let content = env::var(var).map_err(|err| {
+            admin_error!(?err, %var, "Unable to read db encryption key");
+            OperationError::CryptographyError
+        })?;
+
+        DbEncryptionKey::from_hex(&content).map_err(|err| {
+            admin_error!(%var, "The db encryption key was read from the environment");
+            err
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct DbEncryptionKey {
+    key: [u8; KEY_LEN],
+}
+
+impl fmt::Debug for DbEncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DbEncryptionKey").finish_non_exhaustive()
+    }
+}
+
+impl DbEncryptionKey {
+    /// Load the key from a file containing 32 bytes encoded as hex, such as the output of
+    /// `openssl rand -hex 32`.
+    pub fn from_file(path: &Path) -> Result<Self, OperationError> {
+        let content = fs::read_to_string(path).map_err(|err| {
+            admin_error!(?err, ?path, "Unable to read db encryption key");
+            OperationError::CryptographyError
+        })?;
+
+        DbEncryptionKey::from_hex(&content).map_err(|err| {
+            admin_error!(?path, "The db encryption key was read from a file");
+            err
+        })
+    }
+
+    /// Load the key from 32 bytes encoded as hex. Surrounding whitespace is ignored.
+    pub fn from_hex(content: &str) -> Result<Self, OperationError> {
+        let key = hex::decode(content.trim())
+            .ok()
+            .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                admin_error!(
+                    "The db encryption key must be {} bytes encoded as hex",
+                    KEY_LEN
+                );
+                OperationError::CryptographyError
+            })?;
+
+        Ok(DbEncryptionKey { key })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn generate() -> Self {
+        let mut key = [0; KEY_LEN];
+        #[allow(clippy::expect_used)]
+        rand_bytes(&mut key).expect("Unable to generate db encryption key");
+        DbEncryptionKey { key }
+    }
+
+    pub(crate) fn seal(&self, id: u64, data: &[u8]) -> Result<Vec<u8>, OperationError> {
+        self.seal_with_aad(&id.to_be_bytes(), data).map_err(|err| {
+            admin_error!(%id, "Unable to encrypt entry");
+            err
+        })
+    }
+
+    /// Seal data with additional data that must be given again to open it.
+    pub(crate) fn seal_with_aad(&self, aad: &[u8], data: &[u8]) -> Result<Vec<u8>, OperationError> {
+        let mut nonce = [0; NONCE_LEN];
+        rand_bytes(&mut nonce).map_err(|err| {
+            admin_error!(?err, "Unable to generate nonce");
+            OperationError::CryptographyError
+        })?;
+
+        let mut tag = [0; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.key,
+            Some(&nonce),
+            aad,
+            data,
+            &mut tag,
+        )
+        .map_err(|err| {
+            admin_error!(?err, "Unable to encrypt");
+            OperationError::CryptographyError
+        })?;
+
+        let mut sealed =
+            Vec::with_capacity(SEALED_ENTRY_MAGIC.len() + NONCE_LEN + ciphertext.len() + TAG_LEN);
+        sealed.extend_from_slice(SEALED_ENTRY_MAGIC);
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        sealed.extend_from_slice(&tag);
+        Ok(sealed)
+    }
+
+    pub(crate) fn open(&self, id: u64, data: &[u8]) -> Result<Vec<u8>, OperationError> {
+        self.open_with_aad(&id.to_be_bytes(), data).map_err(|err| {
+            admin_error!(%id, "Unable to decrypt entry, is the db encryption key correct?");
+            err
+        })
+    }
+
+    pub(crate) fn open_with_aad(&self, aad: &[u8], data: &[u8]) -> Result<Vec<u8>, OperationError> {
+        let body = data
+            .strip_prefix(SEALED_ENTRY_MAGIC)
+            .filter(|body| body.len() >= NONCE_LEN + TAG_LEN)
+            .ok_or_else(|| {
+                admin_error!("Sealed data is truncated");
+                OperationError::CryptographyError
+            })?;
+
+        let (nonce, rest) = body.split_at(NONCE_LEN);
+        let (ciphertext, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.key,
+            Some(nonce),
+            aad,
+            ciphertext,
+            tag,
+        )
+        .map_err(|err| {
+            admin_error!(?err, "Unable to decrypt");
+            OperationError::CryptographyError
+        })
+    }
+}
+
+pub(crate) fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(SEALED_ENTRY_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_sealed, DbEncryptionKey, DbEncryptionKeyEnv, DbEncryptionKeyProvider, KEY_LEN};
+
+    #[test]
+    fn test_db_encryption_seal_open() {
+        let key = DbEncryptionKey::generate();
+        let data = br#"{"attrs":{}}"#;
+
+        let sealed = key.seal(1, data).expect("Failed to seal");
+        assert!(is_sealed(&sealed));
+        assert!(!is_sealed(data));
+        assert_eq!(key.open(1, &sealed).expect("Failed to open"), data);
+
+        // A sealed entry can't be moved to another id, or opened by another key.
+        assert!(key.open(2, &sealed).is_err());
+        assert!(DbEncryptionKey::generate().open(1, &sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        if let Some(b) = tampered.last_mut() {
+            *b ^= 1;
+        }
+        assert!(key.open(1, &tampered).is_err());
+        assert!(key.open(1, &sealed[..8]).is_err());
+    }
+
+    #[test]
+    fn test_db_encryption_key_sources() {
+        let hex_key = hex::encode([7u8; KEY_LEN]);
+        let key = DbEncryptionKey::from_hex(&format!("{}\n", hex_key)).expect("Invalid key");
+        let sealed = key.seal(1, b"data").expect("Failed to seal");
+
+        assert!(DbEncryptionKey::from_hex("").is_err());
+        assert!(DbEncryptionKey::from_hex("zz").is_err());
+        assert!(DbEncryptionKey::from_hex(&hex_key[2..]).is_err());
+
+        // The same key loaded from the environment opens the same entries.
+        let var = "KANIDM_TEST_DB_ENCRYPTION_KEY_SOURCES";
+        let provider = DbEncryptionKeyEnv::new(var);
+        assert!(provider.load_key().is_err());
+
+        std::env::set_var(var, &hex_key);
+        let env_key = provider.load_key().expect("Failed to load key");
+        std::env::remove_var(var);
+        assert_eq!(env_key.open(1, &sealed).expect("Failed to open"), b"data");
+    }
+}
@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::convert::TryInto;
@@ -10,7 +11,7 @@ use concread::cowcell::*;
 use hashbrown::HashMap;
 use idlset::v2::IDLBitRange;
 use idlset::AndNot;
-use kanidm_proto::internal::{ConsistencyError, OperationError};
+use kanidm_proto::internal::{BackendStats, ConsistencyError, OperationError};
 use tracing::trace;
 use uuid::Uuid;
 
@@ -21,7 +22,9 @@ use crate::be::idxkey::{
     IdlCacheKey, IdlCacheKeyRef, IdlCacheKeyToRef, IdxKey, IdxKeyRef, IdxKeyToRef, IdxSlope,
 };
 use crate::be::keystorage::{KeyHandle, KeyHandleId};
-use crate::be::{BackendConfig, IdList, IdRawEntry};
+use crate::be::{
+    BackendConfig, ChangelogRecord, DbFormatVersion, DbMaintenanceResult, IdList, IdRawEntry,
+};
 use crate::entry::{Entry, EntryCommitted, EntrySealed};
 use crate::prelude::*;
 use crate::value::{IndexType, Value};
@@ -340,6 +343,16 @@ pub trait IdlArcSqliteTransaction {
         idl: &IdList,
     ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError>;
 
+    /// As [get_identry](IdlArcSqliteTransaction::get_identry), but entries that are not cached
+    /// are not added to the cache, so that scanning the whole database does not evict the
+    /// working set.
+    fn get_identry_scan(
+        &mut self,
+        idl: IDLBitRange,
+    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError>;
+
+    fn get_allids(&self) -> &IDLBitRange;
+
     fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError>;
 
     // fn exists_idx(&mut self, attr: &str, itype: IndexType) -> Result<bool, OperationError>;
@@ -351,6 +364,15 @@ pub trait IdlArcSqliteTransaction {
         idx_key: &str,
     ) -> Result<Option<IDLBitRange>, OperationError>;
 
+    /// The union of the idls of the ordering index keys of `attr` that are less than, or greater
+    /// than, `idx_key`. These ranges are not cached.
+    fn get_idl_ord(
+        &mut self,
+        attr: &str,
+        idx_key: &str,
+        ordering: Ordering,
+    ) -> Result<Option<IDLBitRange>, OperationError>;
+
     fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError>;
 
     fn get_db_d_uuid(&self) -> Result<Option<Uuid>, OperationError>;
@@ -373,6 +395,14 @@ pub trait IdlArcSqliteTransaction {
 
     fn list_idxs(&self) -> Result<Vec<String>, OperationError>;
 
+    fn get_backend_stats(&self) -> Result<BackendStats, OperationError>;
+
+    fn get_db_changelog(&self, since: u64) -> Result<Vec<(u64, ChangelogRecord)>, OperationError>;
+
+    fn get_db_changelog_max_seq(&self) -> Result<u64, OperationError>;
+
+    fn get_db_size_used(&self) -> Result<u64, OperationError>;
+
     fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError>;
 
     fn list_quarantined(&self) -> Result<Vec<(u64, String)>, OperationError>;
@@ -393,6 +423,17 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteReadTransaction<'a> {
         get_identry!(self, idl, true)
     }
 
+    fn get_identry_scan(
+        &mut self,
+        idl: IDLBitRange,
+    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+        get_identry!(self, &IdList::Partial(idl), false)
+    }
+
+    fn get_allids(&self) -> &IDLBitRange {
+        &self.allids
+    }
+
     fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError> {
         get_identry_raw!(self, idl)
     }
@@ -411,6 +452,20 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteReadTransaction<'a> {
         get_idl!(self, attr, itype, idx_key)
     }
 
+    #[instrument(level = "trace", skip_all)]
+    fn get_idl_ord(
+        &mut self,
+        attr: &str,
+        idx_key: &str,
+        ordering: Ordering,
+    ) -> Result<Option<IDLBitRange>, OperationError> {
+        let idls = self.db.get_idl_ord(attr, idx_key, ordering)?;
+        Ok(idls.map(|idls| {
+            idls.into_iter()
+                .fold(IDLBitRange::new(), |acc, (_, idl)| acc | idl)
+        }))
+    }
+
     fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError> {
         self.db.get_db_s_uuid()
     }
@@ -456,6 +511,23 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteReadTransaction<'a> {
         self.db.list_idxs()
     }
 
+    fn get_backend_stats(&self) -> Result<BackendStats, OperationError> {
+        self.db.get_backend_stats()
+    }
+
+    fn get_db_changelog(&self, since: u64) -> Result<Vec<(u64, ChangelogRecord)>, OperationError> {
+        // The changelog is not cached.
+        self.db.get_db_changelog(since)
+    }
+
+    fn get_db_changelog_max_seq(&self) -> Result<u64, OperationError> {
+        self.db.get_db_changelog_max_seq()
+    }
+
+    fn get_db_size_used(&self) -> Result<u64, OperationError> {
+        self.db.get_db_size_used()
+    }
+
     fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError> {
         // This is only used in tests or debug tools, so bypass the cache.
         self.db.list_id2entry()
@@ -488,6 +560,17 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteWriteTransaction<'a> {
         get_identry!(self, idl, false)
     }
 
+    fn get_identry_scan(
+        &mut self,
+        idl: IDLBitRange,
+    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+        get_identry!(self, &IdList::Partial(idl), false)
+    }
+
+    fn get_allids(&self) -> &IDLBitRange {
+        &self.allids
+    }
+
     fn get_identry_raw(&self, idl: &IdList) -> Result<Vec<IdRawEntry>, OperationError> {
         get_identry_raw!(self, idl)
     }
@@ -506,6 +589,35 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteWriteTransaction<'a> {
         get_idl!(self, attr, itype, idx_key)
     }
 
+    #[instrument(level = "trace", skip_all)]
+    fn get_idl_ord(
+        &mut self,
+        attr: &str,
+        idx_key: &str,
+        ordering: Ordering,
+    ) -> Result<Option<IDLBitRange>, OperationError> {
+        let Some(idls) = self.db.get_idl_ord(attr, idx_key, ordering)? else {
+            return Ok(None);
+        };
+        let mut idls: BTreeMap<String, IDLBitRange> = idls.into_iter().collect();
+        // Changes made in this transaction are only written to the db at commit, so they
+        // replace what the db holds.
+        self.idl_cache.iter_dirty().for_each(|(k, maybe_idl)| {
+            if k.a.as_str() == attr
+                && k.i == IndexType::Ordering
+                && k.k.as_str().cmp(idx_key) == ordering
+            {
+                if let Some(idl) = maybe_idl {
+                    idls.insert(k.k.clone(), idl.as_ref().clone());
+                }
+            }
+        });
+        Ok(Some(
+            idls.into_values()
+                .fold(IDLBitRange::new(), |acc, idl| acc | idl),
+        ))
+    }
+
     fn get_db_s_uuid(&self) -> Result<Option<Uuid>, OperationError> {
         self.db.get_db_s_uuid()
     }
@@ -554,6 +666,23 @@ impl<'a> IdlArcSqliteTransaction for IdlArcSqliteWriteTransaction<'a> {
         self.db.list_idxs()
     }
 
+    fn get_backend_stats(&self) -> Result<BackendStats, OperationError> {
+        self.db.get_backend_stats()
+    }
+
+    fn get_db_changelog(&self, since: u64) -> Result<Vec<(u64, ChangelogRecord)>, OperationError> {
+        // The changelog is not cached.
+        self.db.get_db_changelog(since)
+    }
+
+    fn get_db_changelog_max_seq(&self) -> Result<u64, OperationError> {
+        self.db.get_db_changelog_max_seq()
+    }
+
+    fn get_db_size_used(&self) -> Result<u64, OperationError> {
+        self.db.get_db_size_used()
+    }
+
     fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError> {
         // This is only used in tests or debug tools, so bypass the cache.
         self.db.list_id2entry()
@@ -691,6 +820,21 @@ impl<'a> IdlArcSqliteWriteTransaction<'a> {
         self.db.get_db_ruv()
     }
 
+    pub fn write_db_changelog<'b, I>(&mut self, records: I) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = &'b ChangelogRecord>,
+    {
+        self.db.write_db_changelog(records)
+    }
+
+    pub fn trim_db_changelog(&mut self, trim_cid: &Cid) -> Result<usize, OperationError> {
+        self.db.trim_db_changelog(trim_cid)
+    }
+
+    pub fn danger_purge_changelog(&mut self) -> Result<(), OperationError> {
+        self.db.danger_purge_changelog()
+    }
+
     pub fn write_db_ruv<I, J>(&mut self, added: I, removed: J) -> Result<(), OperationError>
     where
         I: Iterator<Item = Cid>,
@@ -1292,6 +1436,18 @@ impl IdlArcSqlite {
         self.name_cache.try_quiesce();
     }
 
+    pub fn maintenance(&self, vacuum: bool) -> Result<DbMaintenanceResult, OperationError> {
+        self.db.maintenance(vacuum)
+    }
+
+    pub fn read_format_version(path: &str) -> Result<DbFormatVersion, OperationError> {
+        IdlSqlite::read_format_version(path)
+    }
+
+    pub fn snapshot(src: &str, dst: &str) -> Result<(), OperationError> {
+        IdlSqlite::snapshot(src, dst)
+    }
+
     pub fn read(&self) -> Result<IdlArcSqliteReadTransaction, OperationError> {
         // IMPORTANT! Always take entrycache FIRST
         let entry_cache_read = self.entry_cache.read();
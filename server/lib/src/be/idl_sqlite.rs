@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::sync::Arc;
@@ -9,14 +10,19 @@ use super::keystorage::{KeyHandle, KeyHandleId};
 // use crate::valueset;
 use hashbrown::HashMap;
 use idlset::v2::IDLBitRange;
-use kanidm_proto::internal::{ConsistencyError, OperationError};
+use kanidm_proto::internal::{BackendStats, ConsistencyError, IndexStats, OperationError};
 use rusqlite::vtab::array::Array;
 use rusqlite::{Connection, OpenFlags, OptionalExtension};
 use uuid::Uuid;
 
+use crate::be::dbcompress;
+use crate::be::dbcrypt::{self, DbEncryptionKey};
 use crate::be::dbentry::{DbEntry, DbIdentSpn};
 use crate::be::dbvalue::DbCidV1;
-use crate::be::{BackendConfig, IdList, IdRawEntry, IdxKey, IdxSlope};
+use crate::be::{
+    BackendConfig, ChangelogOperation, ChangelogRecord, DbFormatVersion, DbMaintenanceResult,
+    IdList, IdRawEntry, IdxKey, IdxSlope,
+};
 use crate::entry::{Entry, EntryCommitted, EntrySealed};
 use crate::prelude::*;
 use crate::value::{IndexType, Value};
@@ -26,6 +32,11 @@ use crate::value::{IndexType, Value};
 const DBV_ID2ENTRY: &str = "id2entry";
 const DBV_INDEXV: &str = "indexv";
 
+/// The writes of a transaction reuse their prepared statements, so that committing many entries
+/// does not parse the same statements again for each one. There is a statement per index table,
+/// so this must be large enough to hold one for every index.
+const PREPARED_STATEMENT_CACHE_CAPACITY: usize = 512;
+
 #[allow(clippy::needless_pass_by_value)] // needs to accept value from `map_err`
 pub(super) fn sqlite_error(e: rusqlite::Error) -> OperationError {
     admin_error!(?e, "SQLite Error");
@@ -90,18 +101,23 @@ impl TryFrom<IdRawEntry> for IdSqliteEntry {
 pub struct IdlSqlite {
     pool: ConnPool,
     db_name: &'static str,
+    entry_key: Option<Arc<DbEncryptionKey>>,
+    compress_entries: bool,
 }
 
 pub struct IdlSqliteReadTransaction {
     pool: ConnPool,
     conn: Option<Connection>,
     db_name: &'static str,
+    entry_key: Option<Arc<DbEncryptionKey>>,
 }
 
 pub struct IdlSqliteWriteTransaction {
     pool: ConnPool,
     conn: Option<Connection>,
     db_name: &'static str,
+    entry_key: Option<Arc<DbEncryptionKey>>,
+    compress_entries: bool,
 }
 
 pub(crate) trait IdlSqliteTransaction {
@@ -109,6 +125,28 @@ pub(crate) trait IdlSqliteTransaction {
 
     fn get_conn(&self) -> Result<&Connection, OperationError>;
 
+    fn get_entry_key(&self) -> Option<&DbEncryptionKey>;
+
+    /// Open an entry as stored in id2entry if it was sealed. The result may still be compressed.
+    fn unseal_identry(&self, id: u64, data: Vec<u8>) -> Result<Vec<u8>, OperationError> {
+        if !dbcrypt::is_sealed(&data) {
+            return Ok(data);
+        }
+        let key = self.get_entry_key().ok_or_else(|| {
+            admin_error!(%id, "Entry is encrypted but no db encryption key is configured");
+            OperationError::CryptographyError
+        })?;
+        key.open(id, &data)
+    }
+
+    /// Convert an entry as stored in id2entry, opening and decompressing it as needed.
+    fn open_identry(&self, ise: IdSqliteEntry) -> Result<IdRawEntry, OperationError> {
+        let mut raw = IdRawEntry::try_from(ise)?;
+        let data = self.unseal_identry(raw.id, raw.data)?;
+        raw.data = dbcompress::decompress(raw.id, data)?;
+        Ok(raw)
+    }
+
     fn get_identry(&self, idl: &IdList) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
         self.get_identry_raw(idl)?
             .into_iter()
@@ -139,7 +177,7 @@ pub(crate) trait IdlSqliteTransaction {
                     .map(|v| {
                         v.map_err(sqlite_error).and_then(|ise| {
                             // Convert the idsqlite to id raw
-                            ise.try_into()
+                            self.open_identry(ise)
                         })
                     })
                     .collect()
@@ -187,7 +225,7 @@ pub(crate) trait IdlSqliteTransaction {
                     match row {
                         Ok(ise) => {
                             // Convert the idsqlite to id raw
-                            results.push(ise.try_into()?);
+                            results.push(self.open_identry(ise)?);
                         }
                         // TODO: make this a better error
                         Err(e) => {
@@ -268,6 +306,54 @@ pub(crate) trait IdlSqliteTransaction {
         Ok(Some(idl))
     }
 
+    /// The idls of the keys of the ordering index of `attr` that are less than, or greater
+    /// than, `idx_key` as `ordering` requests. The keys sort in the order of the values they
+    /// index, so this is a range of the index table.
+    #[instrument(level = "trace", skip_all)]
+    fn get_idl_ord(
+        &self,
+        attr: &str,
+        idx_key: &str,
+        ordering: Ordering,
+    ) -> Result<Option<Vec<(String, IDLBitRange)>>, OperationError> {
+        if !(self.exists_idx(attr, IndexType::Ordering)?) {
+            debug!("IdlSqliteTransaction: Index Ordering {:?} not found", attr);
+            return Ok(None);
+        }
+
+        let op = match ordering {
+            Ordering::Less => "<",
+            Ordering::Equal => "=",
+            Ordering::Greater => ">",
+        };
+        let query = format!(
+            "SELECT key, idl FROM {}.idx_{}_{} WHERE key {} :idx_key",
+            self.get_db_name(),
+            IndexType::Ordering.as_idx_str(),
+            attr,
+            op
+        );
+        let mut stmt = self.get_conn()?.prepare(&query).map_err(sqlite_error)?;
+        let idx_iter = stmt
+            .query_map(&[(":idx_key", &idx_key)], |row| {
+                Ok(KeyIdl {
+                    key: row.get(0)?,
+                    data: row.get(1)?,
+                })
+            })
+            .map_err(sqlite_error)?;
+        idx_iter
+            .map(|v| {
+                v.map_err(sqlite_error).and_then(|KeyIdl { key, data }| {
+                    serde_json::from_slice(data.as_slice())
+                        .map_err(serde_json_error)
+                        .map(|idl| (key, idl))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some)
+    }
+
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
         // The table exists - lets now get the actual index itself.
         let mut stmt = self
@@ -529,6 +615,139 @@ pub(crate) trait IdlSqliteTransaction {
         idx_table_iter.map(|v| v.map_err(sqlite_error)).collect()
     }
 
+    fn get_db_changelog(&self, since: u64) -> Result<Vec<(u64, ChangelogRecord)>, OperationError> {
+        let mut stmt = self
+            .get_conn()?
+            .prepare(&format!(
+                "SELECT seq, cid, id, uuid, operation, attrs FROM {}.changelog WHERE seq > :since ORDER BY seq ASC",
+                self.get_db_name()
+            ))
+            .map_err(sqlite_error)?;
+
+        let since = i64::try_from(since).map_err(|_| OperationError::InvalidEntryId)?;
+        let cl_iter = stmt
+            .query_map(named_params! { ":since": since }, |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                ))
+            })
+            .map_err(sqlite_error)?;
+
+        cl_iter
+            .map(|v| {
+                let (seq, ser_cid, id, uuid, operation, ser_attrs) = v.map_err(sqlite_error)?;
+                let db_cid: DbCidV1 = serde_json::from_str(&ser_cid).map_err(serde_json_error)?;
+                let uuid = Uuid::parse_str(&uuid).map_err(|_| OperationError::InvalidUuid)?;
+                let attrs: Vec<String> =
+                    serde_json::from_str(&ser_attrs).map_err(serde_json_error)?;
+                Ok((
+                    u64::try_from(seq).map_err(|_| OperationError::InvalidEntryId)?,
+                    ChangelogRecord {
+                        cid: db_cid.into(),
+                        id: u64::try_from(id).map_err(|_| OperationError::InvalidEntryId)?,
+                        uuid,
+                        operation: ChangelogOperation::try_from(operation.as_str())?,
+                        attrs: attrs.into_iter().map(AttrString::from).collect(),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    fn get_db_changelog_max_seq(&self) -> Result<u64, OperationError> {
+        let seq: Option<i64> = self
+            .get_conn()?
+            .query_row(
+                &format!("SELECT MAX(seq) FROM {}.changelog", self.get_db_name()),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_error)?;
+        u64::try_from(seq.unwrap_or(0)).map_err(|_| OperationError::InvalidEntryId)
+    }
+
+    fn get_db_size_used(&self) -> Result<u64, OperationError> {
+        let conn = self.get_conn()?;
+        let db_name = self.get_db_name();
+        let pragma = |name: &str| -> Result<u64, OperationError> {
+            conn.query_row(&format!("PRAGMA {db_name}.{name}"), [], |row| row.get(0))
+                .map_err(sqlite_error)
+        };
+        Ok((pragma("page_count")? - pragma("freelist_count")?) * pragma("page_size")?)
+    }
+
+    fn get_backend_stats(&self) -> Result<BackendStats, OperationError> {
+        let conn = self.get_conn()?;
+        let db_name = self.get_db_name();
+
+        let entries = conn
+            .query_row(
+                &format!("SELECT COUNT(id) FROM {db_name}.id2entry"),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_error)?;
+
+        let pragma = |name: &str| -> Result<u64, OperationError> {
+            conn.query_row(&format!("PRAGMA {db_name}.{name}"), [], |row| row.get(0))
+                .map_err(sqlite_error)
+        };
+        let page_size = pragma("page_size")?;
+        let db_size = pragma("page_count")? * page_size;
+        let db_free = pragma("freelist_count")? * page_size;
+
+        let last_vacuum: Option<Vec<u8>> = conn
+            .query_row(
+                &format!("SELECT data FROM {db_name}.db_maintenance WHERE id = 1"),
+                [],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sqlite_error)?;
+        let last_vacuum = last_vacuum
+            .map(|d| serde_json::from_slice(d.as_slice()).map_err(serde_json_error))
+            .transpose()?;
+
+        // Attribute index tables are named idx_<itype>_<attr>, the other idx_ tables are the
+        // name and uuid lookups.
+        let mut indexes = self
+            .list_idxs()?
+            .iter()
+            .filter_map(|idx_name| {
+                let (itype, attr) = idx_name.strip_prefix("idx_")?.split_once('_')?;
+                matches!(itype, "eq" | "pres" | "sub" | "ord").then_some((idx_name, itype, attr))
+            })
+            .map(|(idx_name, itype, attr)| {
+                let keys = conn
+                    .query_row(
+                        &format!("SELECT COUNT(*) FROM {db_name}.{idx_name}"),
+                        [],
+                        |row| row.get(0),
+                    )
+                    .map_err(sqlite_error)?;
+                Ok(IndexStats {
+                    attr: attr.to_string(),
+                    itype: itype.to_string(),
+                    keys,
+                })
+            })
+            .collect::<Result<Vec<_>, OperationError>>()?;
+        indexes.sort_unstable_by(|a, b| (&a.attr, &a.itype).cmp(&(&b.attr, &b.itype)));
+
+        Ok(BackendStats {
+            entries,
+            db_size,
+            db_free,
+            last_vacuum,
+            indexes,
+        })
+    }
+
     fn list_id2entry(&self) -> Result<Vec<(u64, String)>, OperationError> {
         let allids = self.get_identry_raw(&IdList::AllIds)?;
         allids
@@ -559,7 +778,7 @@ pub(crate) trait IdlSqliteTransaction {
             .map(|v| {
                 v.map_err(sqlite_error).and_then(|ise| {
                     // Convert the idsqlite to id raw
-                    ise.try_into()
+                    self.open_identry(ise)
                 })
             })
             .collect::<Result<Vec<IdRawEntry>, _>>()?;
@@ -653,6 +872,10 @@ impl IdlSqliteTransaction for IdlSqliteReadTransaction {
             .as_ref()
             .ok_or(OperationError::TransactionAlreadyCommitted)
     }
+
+    fn get_entry_key(&self) -> Option<&DbEncryptionKey> {
+        self.entry_key.as_deref()
+    }
 }
 
 impl Drop for IdlSqliteReadTransaction {
@@ -680,6 +903,7 @@ impl IdlSqliteReadTransaction {
         pool: ConnPool,
         conn: Connection,
         db_name: &'static str,
+        entry_key: Option<Arc<DbEncryptionKey>>,
     ) -> Result<Self, OperationError> {
         // Start the transaction
         //
@@ -695,6 +919,7 @@ impl IdlSqliteReadTransaction {
             pool,
             conn: Some(conn),
             db_name,
+            entry_key,
         })
     }
 }
@@ -709,6 +934,10 @@ impl IdlSqliteTransaction for IdlSqliteWriteTransaction {
             .as_ref()
             .ok_or(OperationError::TransactionAlreadyCommitted)
     }
+
+    fn get_entry_key(&self) -> Option<&DbEncryptionKey> {
+        self.entry_key.as_deref()
+    }
 }
 
 impl Drop for IdlSqliteWriteTransaction {
@@ -736,6 +965,8 @@ impl IdlSqliteWriteTransaction {
         pool: ConnPool,
         conn: Connection,
         db_name: &'static str,
+        entry_key: Option<Arc<DbEncryptionKey>>,
+        compress_entries: bool,
     ) -> Result<Self, OperationError> {
         // Start the transaction
         conn.execute("BEGIN EXCLUSIVE TRANSACTION", [])
@@ -744,6 +975,8 @@ impl IdlSqliteWriteTransaction {
             pool,
             conn: Some(conn),
             db_name,
+            entry_key,
+            compress_entries,
         })
     }
 
@@ -821,13 +1054,19 @@ impl IdlSqliteWriteTransaction {
     {
         let mut stmt = self
             .get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "INSERT OR REPLACE INTO {}.id2entry (id, data) VALUES(:id, :data)",
                 self.get_db_name()
             ))
             .map_err(sqlite_error)?;
 
-        entries.try_for_each(|e| {
+        entries.try_for_each(|mut e| {
+            if self.compress_entries {
+                e.data = dbcompress::compress(e.id, &e.data)?;
+            }
+            if let Some(key) = self.get_entry_key() {
+                e.data = key.seal(e.id, &e.data)?;
+            }
             IdSqliteEntry::try_from(e).and_then(|ser_ent| {
                 stmt.execute(named_params! {
                     ":id": &ser_ent.id,
@@ -843,7 +1082,7 @@ impl IdlSqliteWriteTransaction {
     pub fn delete_identry(&self, id: u64) -> Result<(), OperationError> {
         let mut stmt = self
             .get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "DELETE FROM {}.id2entry WHERE id = :id",
                 self.get_db_name()
             ))
@@ -883,7 +1122,7 @@ impl IdlSqliteWriteTransaction {
             );
 
             self.get_conn()?
-                .prepare(query.as_str())
+                .prepare_cached(query.as_str())
                 .and_then(|mut stmt| stmt.execute(&[(":key", &idx_key)]))
                 .map_err(sqlite_error)
         } else {
@@ -899,7 +1138,7 @@ impl IdlSqliteWriteTransaction {
             );
 
             self.get_conn()?
-                .prepare(query.as_str())
+                .prepare_cached(query.as_str())
                 .and_then(|mut stmt| {
                     stmt.execute(named_params! {
                         ":key": &idx_key,
@@ -926,7 +1165,7 @@ impl IdlSqliteWriteTransaction {
         let uuids = uuid.as_hyphenated().to_string();
 
         self.get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "INSERT OR REPLACE INTO {}.idx_name2uuid (name, uuid) VALUES(:name, :uuid)",
                 self.get_db_name()
             ))
@@ -942,7 +1181,7 @@ impl IdlSqliteWriteTransaction {
 
     pub fn write_name2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
         self.get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "DELETE FROM {}.idx_name2uuid WHERE name = :name",
                 self.get_db_name()
             ))
@@ -965,7 +1204,7 @@ impl IdlSqliteWriteTransaction {
         let uuids = uuid.as_hyphenated().to_string();
 
         self.get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "INSERT OR REPLACE INTO {}.idx_externalid2uuid (eid, uuid) VALUES(:eid, :uuid)",
                 self.get_db_name()
             ))
@@ -981,7 +1220,7 @@ impl IdlSqliteWriteTransaction {
 
     pub fn write_externalid2uuid_rem(&self, name: &str) -> Result<(), OperationError> {
         self.get_conn()?
-            .prepare(&format!(
+            .prepare_cached(&format!(
                 "DELETE FROM {}.idx_externalid2uuid WHERE eid = :eid",
                 self.get_db_name()
             ))
@@ -1003,6 +1242,56 @@ impl IdlSqliteWriteTransaction {
             .map_err(sqlite_error)
     }
 
+    fn rewrite_identries_to_format(&self) -> Result<(), OperationError> {
+        let mut stmt = self
+            .get_conn()?
+            .prepare(&format!(
+                "SELECT id, data FROM {}.id2entry",
+                self.get_db_name()
+            ))
+            .map_err(sqlite_error)?;
+
+        let stale_entries = stmt
+            .query_map([], |row| {
+                Ok(IdSqliteEntry {
+                    id: row.get(0)?,
+                    data: row.get(1)?,
+                })
+            })
+            .map_err(sqlite_error)?
+            .filter_map(|ise| {
+                ise.map_err(sqlite_error)
+                    .and_then(IdRawEntry::try_from)
+                    .and_then(|raw| {
+                        let sealed = dbcrypt::is_sealed(&raw.data);
+                        let data = self.unseal_identry(raw.id, raw.data)?;
+                        let compressed = dbcompress::is_compressed(&data);
+
+                        if (self.entry_key.is_some() && !sealed)
+                            || (self.compress_entries && !compressed)
+                        {
+                            let data = dbcompress::decompress(raw.id, data)?;
+                            Ok(Some(IdRawEntry { id: raw.id, data }))
+                        } else {
+                            Ok(None)
+                        }
+                    })
+                    .transpose()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if !stale_entries.is_empty() {
+            info!(
+                count = stale_entries.len(),
+                encrypt = self.entry_key.is_some(),
+                compress = self.compress_entries,
+                "Rewriting stored entries"
+            );
+            self.write_identries_raw(stale_entries.into_iter())?;
+        }
+        Ok(())
+    }
+
     fn migrate_dbentryv1_to_dbentryv2(&self) -> Result<(), OperationError> {
         let allids = self.get_identry_raw(&IdList::AllIds)?;
         let raw_entries: Result<Vec<IdRawEntry>, _> = allids
@@ -1047,7 +1336,7 @@ impl IdlSqliteWriteTransaction {
                 let dbv1: DbIdentSpn = k.to_db_ident_spn();
                 let data = serde_json::to_vec(&dbv1).map_err(serde_json_error)?;
                 self.get_conn()?
-                    .prepare(&format!(
+                    .prepare_cached(&format!(
                         "INSERT OR REPLACE INTO {}.idx_uuid2spn (uuid, spn) VALUES(:uuid, :spn)",
                         self.get_db_name()
                     ))
@@ -1062,7 +1351,7 @@ impl IdlSqliteWriteTransaction {
             }
             None => self
                 .get_conn()?
-                .prepare(&format!(
+                .prepare_cached(&format!(
                     "DELETE FROM {}.idx_uuid2spn WHERE uuid = :uuid",
                     self.get_db_name()
                 ))
@@ -1090,7 +1379,7 @@ impl IdlSqliteWriteTransaction {
         match k {
             Some(k) => self
                 .get_conn()?
-                .prepare(&format!(
+                .prepare_cached(&format!(
                     "INSERT OR REPLACE INTO {}.idx_uuid2rdn (uuid, rdn) VALUES(:uuid, :rdn)",
                     self.get_db_name()
                 ))
@@ -1099,7 +1388,7 @@ impl IdlSqliteWriteTransaction {
                 .map_err(sqlite_error),
             None => self
                 .get_conn()?
-                .prepare(&format!(
+                .prepare_cached(&format!(
                     "DELETE FROM {}.idx_uuid2rdn WHERE uuid = :uuid",
                     self.get_db_name()
                 ))
@@ -1152,6 +1441,116 @@ impl IdlSqliteWriteTransaction {
             .collect()
     }
 
+    pub(crate) fn create_db_changelog(&self) -> Result<(), OperationError> {
+        // The sequence is never reused, even after the newest changes are removed.
+        self.get_conn()?
+            .execute(
+                &format!(
+                    "CREATE TABLE IF NOT EXISTS {}.changelog (
+                        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                        cid TEXT NOT NULL,
+                        s_uuid TEXT NOT NULL,
+                        id INTEGER NOT NULL,
+                        uuid TEXT NOT NULL,
+                        operation TEXT NOT NULL
+                    )
+                    ",
+                    self.get_db_name()
+                ),
+                [],
+            )
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn write_db_changelog<'b, I>(&mut self, mut records: I) -> Result<(), OperationError>
+    where
+        I: Iterator<Item = &'b ChangelogRecord>,
+    {
+        let mut stmt = self
+            .get_conn()?
+            .prepare_cached(&format!(
+                "INSERT INTO {}.changelog (cid, s_uuid, id, uuid, operation, attrs) VALUES(:cid, :s_uuid, :id, :uuid, :operation, :attrs)",
+                self.get_db_name()
+            ))
+            .map_err(sqlite_error)?;
+
+        records.try_for_each(|record| {
+            let db_cid: DbCidV1 = record.cid.clone().into();
+            let ser_cid = serde_json::to_string(&db_cid).map_err(serde_json_error)?;
+            let id = i64::try_from(record.id).map_err(|_| OperationError::InvalidEntryId)?;
+            let attrs: Vec<&str> = record.attrs.iter().map(|a| a.as_str()).collect();
+            let ser_attrs = serde_json::to_string(&attrs).map_err(serde_json_error)?;
+
+            stmt.execute(named_params! {
+                ":cid": &ser_cid,
+                ":s_uuid": &record.cid.s_uuid.as_hyphenated().to_string(),
+                ":id": &id,
+                ":uuid": &record.uuid.as_hyphenated().to_string(),
+                ":operation": record.operation.as_str(),
+                ":attrs": &ser_attrs,
+            })
+            .map(|_| ())
+            .map_err(sqlite_error)
+        })
+    }
+
+    /// Remove the changes that are older than `trim_cid` from the changelog, returning how many
+    /// were removed. The cid is stored serialised, so each record is compared as it is read.
+    pub fn trim_db_changelog(&mut self, trim_cid: &Cid) -> Result<usize, OperationError> {
+        let conn = self.get_conn()?;
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT seq, cid FROM {}.changelog",
+                self.get_db_name()
+            ))
+            .map_err(sqlite_error)?;
+
+        let expired: Vec<i64> = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(sqlite_error)?
+            .filter_map(|v| {
+                v.map_err(sqlite_error)
+                    .and_then(|(seq, ser_cid)| {
+                        let db_cid: DbCidV1 =
+                            serde_json::from_str(&ser_cid).map_err(serde_json_error)?;
+                        Ok((Cid::from(db_cid) < *trim_cid).then_some(seq))
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let mut stmt = conn
+            .prepare_cached(&format!(
+                "DELETE FROM {}.changelog WHERE seq = :seq",
+                self.get_db_name()
+            ))
+            .map_err(sqlite_error)?;
+
+        expired.iter().try_for_each(|seq| {
+            stmt.execute(named_params! { ":seq": seq })
+                .map(|_| ())
+                .map_err(sqlite_error)
+        })?;
+
+        Ok(expired.len())
+    }
+
+    /// ⚠️  - This function will destroy the changelog.
+    ///
+    /// It should only be called internally by the backend in limited and
+    /// specific situations.
+    #[instrument(level = "trace", skip_all)]
+    pub fn danger_purge_changelog(&self) -> Result<(), OperationError> {
+        self.get_conn()?
+            .execute(&format!("DELETE FROM {}.changelog", self.get_db_name()), [])
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
     pub fn write_db_ruv<I, J>(&mut self, mut added: I, mut removed: J) -> Result<(), OperationError>
     where
         I: Iterator<Item = Cid>,
@@ -1726,10 +2125,54 @@ impl IdlSqliteWriteTransaction {
             dbv_id2entry = 10;
             info!(entry = %dbv_id2entry, "dbv_id2entry migrated (db_ruv)");
         }
-        //   * if v10 -> complete
+        //   * if v10 -> create the maintenance record.
+        if dbv_id2entry == 10 {
+            self.get_conn()?
+                .execute(
+                    &format!(
+                        "CREATE TABLE IF NOT EXISTS {}.db_maintenance (
+                        id INTEGER PRIMARY KEY ASC,
+                        data BLOB NOT NULL
+                    )
+                    ",
+                        self.get_db_name()
+                    ),
+                    [],
+                )
+                .map_err(sqlite_error)?;
+            dbv_id2entry = 11;
+            info!(entry = %dbv_id2entry, "dbv_id2entry migrated (db_maintenance)");
+        }
+        //   * if v11 -> create the changelog.
+        if dbv_id2entry == 11 {
+            self.create_db_changelog()?;
+            dbv_id2entry = 12;
+            info!(entry = %dbv_id2entry, "dbv_id2entry migrated (changelog)");
+        }
+        //   * if v12 -> record the attributes changed by each change.
+        if dbv_id2entry == 12 {
+            self.get_conn()?
+                .execute(
+                    &format!(
+                        "ALTER TABLE {}.changelog ADD COLUMN attrs TEXT NOT NULL DEFAULT '[]'",
+                        self.get_db_name()
+                    ),
+                    [],
+                )
+                .map_err(sqlite_error)?;
+            dbv_id2entry = 13;
+            info!(entry = %dbv_id2entry, "dbv_id2entry migrated (changelog attrs)");
+        }
+        //   * if v13 -> complete
 
         self.set_db_version_key(DBV_ID2ENTRY, dbv_id2entry)?;
 
+        // Entries written before the key or compression were configured are rewritten now, so
+        // that none remain in the clear or uncompressed.
+        if self.entry_key.is_some() || self.compress_entries {
+            self.rewrite_identries_to_format()?;
+        }
+
         // NOTE: Indexing is configured in a different step!
         // Indexing uses a db version flag to represent the version
         // of the indexes representation on disk in case we change
@@ -1746,7 +2189,7 @@ impl IdlSqlite {
         // If provided, set the page size to match the tuning we want. By default we use 4096. The VACUUM
         // immediately after is so that on db create the page size takes effect.
         //
-        // Enable WAL mode, which is just faster and better for our needs.
+        // Enable WAL mode by default, which is just faster and better for our needs.
         let mut flags = OpenFlags::default();
         // Open with multi thread flags and locking options.
 
@@ -1759,6 +2202,8 @@ impl IdlSqlite {
         // is 32MB (constrst the SQLite default of 2MB)
         let cache_pages = 33554432 / fs_page_size;
         let checkpoint_pages = cfg.fstype.checkpoint_pages();
+        let synchronous = cfg.durability.synchronous();
+        let journal_mode = cfg.journal_mode.journal_mode();
 
         // Initial setup routines.
         {
@@ -1770,7 +2215,7 @@ impl IdlSqlite {
                     format!(
                         "PRAGMA page_size={fs_page_size};
                          PRAGMA cache_size={cache_pages};
-                         PRAGMA journal_mode=WAL;
+                         PRAGMA journal_mode={journal_mode};
                          PRAGMA wal_autocheckpoint={checkpoint_pages};
                          PRAGMA wal_checkpoint(RESTART);"
                     )
@@ -1830,7 +2275,7 @@ impl IdlSqlite {
             })?;
 
             vconn
-                .pragma_update(None, "journal_mode", "WAL")
+                .pragma_update(None, "journal_mode", journal_mode)
                 .map_err(|e| {
                     admin_error!(?e, "rusqlite journal_mode update error");
                     OperationError::SqliteError
@@ -1852,16 +2297,19 @@ impl IdlSqlite {
                     Connection::open_with_flags(cfg.path.as_str(), flags).map_err(sqlite_error);
                 match conn {
                     Ok(conn) => {
-                        // We need to set the cachesize at this point as well.
+                        // We need to set the cachesize at this point as well. Synchronous is
+                        // also per connection.
                         conn
                             .execute_batch(
                                 format!(
-                                    "PRAGMA cache_size={cache_pages};"
+                                    "PRAGMA cache_size={cache_pages};
+                                     PRAGMA synchronous={synchronous};"
                                 )
                                 .as_str(),
                             )
                             .map_err(sqlite_error)?;
 
+                        conn.set_prepared_statement_cache_capacity(PREPARED_STATEMENT_CACHE_CAPACITY);
 
                         // load the rusqlite vtab module to allow for virtual tables
                         rusqlite::vtab::array::load_module(&conn).map_err(|e| {
@@ -1892,6 +2340,8 @@ impl IdlSqlite {
         Ok(IdlSqlite {
             pool,
             db_name: cfg.db_name,
+            entry_key: cfg.encryption_key.clone().map(Arc::new),
+            compress_entries: cfg.compress_entries,
         })
     }
 
@@ -1910,6 +2360,122 @@ impl IdlSqlite {
             .map_err(sqlite_error)
     }
 
+    fn open_read_only(path: &str) -> Result<Connection, OperationError> {
+        Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .map_err(|e| {
+            admin_error!(?e, %path, "Unable to open database");
+            OperationError::SqliteError
+        })
+    }
+
+    pub(crate) fn read_format_version(path: &str) -> Result<DbFormatVersion, OperationError> {
+        let conn = Self::open_read_only(path)?;
+        let version = |key: &str| {
+            conn.query_row(
+                "SELECT version FROM main.db_version WHERE id = :id",
+                &[(":id", &key)],
+                |row| row.get(0),
+            )
+            // The value or the table is missing, default to 0.
+            .unwrap_or(0)
+        };
+
+        Ok(DbFormatVersion {
+            id2entry: version(DBV_ID2ENTRY),
+            index: version(DBV_INDEXV),
+        })
+    }
+
+    pub(crate) fn snapshot(src: &str, dst: &str) -> Result<(), OperationError> {
+        Self::open_read_only(src)?
+            .execute("VACUUM main INTO :dst", named_params! { ":dst": dst })
+            .map(|_| ())
+            .map_err(sqlite_error)
+    }
+
+    pub fn maintenance(&self, vacuum: bool) -> Result<DbMaintenanceResult, OperationError> {
+        let conn = {
+            let mut guard = self.pool.lock().map_err(|e| {
+                error!(err = ?e, "Unable to lock connection pool.");
+                OperationError::BackendEngine
+            })?;
+            guard.pop_front().ok_or_else(|| {
+                error!("Unable to retrieve connection from pool.");
+                OperationError::BackendEngine
+            })?
+        };
+
+        let res = self.maintenance_conn(&conn, vacuum);
+
+        // Always return the connection, even if maintenance failed.
+        match self.pool.lock() {
+            Ok(mut guard) => guard.push_back(conn),
+            Err(e) => error!(err = ?e, "Unable to return connection to pool."),
+        }
+
+        res
+    }
+
+    fn maintenance_conn(
+        &self,
+        conn: &Connection,
+        vacuum: bool,
+    ) -> Result<DbMaintenanceResult, OperationError> {
+        let db_size = |conn: &Connection| -> Result<u64, OperationError> {
+            conn.query_row(
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(sqlite_error)
+        };
+
+        let size_before = db_size(conn)?;
+
+        if vacuum {
+            // Vacuum writes the new content of the database through the write ahead log, so the
+            // checkpoint must follow it.
+            conn.execute_batch("VACUUM").map_err(|e| {
+                admin_error!(?e, "rusqlite vacuum error");
+                OperationError::SqliteError
+            })?;
+
+            let now = time::OffsetDateTime::now_utc().unix_timestamp();
+            let data = serde_json::to_vec(&now).map_err(serde_json_error)?;
+            conn.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO {}.db_maintenance (id, data) VALUES(:id, :data)",
+                    self.db_name
+                ),
+                named_params! {
+                    ":id": &1,
+                    ":data": &data,
+                },
+            )
+            .map_err(sqlite_error)?;
+        }
+
+        // The first column is 1 if the checkpoint was blocked by a reader.
+        let busy: i64 = conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+            .map_err(|e| {
+                admin_error!(?e, "rusqlite wal_checkpoint error");
+                OperationError::SqliteError
+            })?;
+
+        let size_after = db_size(conn)?;
+
+        Ok(DbMaintenanceResult {
+            vacuum,
+            size_before,
+            size_after,
+            wal_checkpointed: busy == 0,
+        })
+    }
+
     pub fn read(&self) -> Result<IdlSqliteReadTransaction, OperationError> {
         // This can't fail because we should only get here if a pool conn is available.
         let mut guard = self.pool.lock().map_err(|e| {
@@ -1922,7 +2488,12 @@ impl IdlSqlite {
             OperationError::BackendEngine
         })?;
 
-        IdlSqliteReadTransaction::new(self.pool.clone(), conn, self.db_name)
+        IdlSqliteReadTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.db_name,
+            self.entry_key.clone(),
+        )
     }
 
     pub fn write(&self) -> Result<IdlSqliteWriteTransaction, OperationError> {
@@ -1937,14 +2508,23 @@ impl IdlSqlite {
             OperationError::BackendEngine
         })?;
 
-        IdlSqliteWriteTransaction::new(self.pool.clone(), conn, self.db_name)
+        IdlSqliteWriteTransaction::new(
+            self.pool.clone(),
+            conn,
+            self.db_name,
+            self.entry_key.clone(),
+            self.compress_entries,
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::be::dbcompress;
+    use crate::be::dbcrypt::{self, DbEncryptionKey};
     use crate::be::idl_sqlite::{IdlSqlite, IdlSqliteTransaction};
-    use crate::be::BackendConfig;
+    use crate::be::{BackendConfig, IdList, IdRawEntry};
+    use kanidm_proto::internal::{DbDurability, DbJournalMode, FsType};
 
     #[test]
     fn test_idl_sqlite_verify() {
@@ -1955,4 +2535,217 @@ mod tests {
         let r = be_w.verify();
         assert!(r.is_empty());
     }
+
+    #[test]
+    fn test_idl_sqlite_encrypted_entries() {
+        sketching::test_init();
+        let mut cfg = BackendConfig::new_test("main");
+        cfg.encryption_key = Some(DbEncryptionKey::generate());
+        let be = IdlSqlite::new(&cfg, false).unwrap();
+        let be_w = be.write().unwrap();
+        be_w.setup().unwrap();
+
+        let data = br#"{"attrs":{}}"#.to_vec();
+        be_w.write_identries_raw(
+            vec![IdRawEntry {
+                id: 1,
+                data: data.clone(),
+            }]
+            .into_iter(),
+        )
+        .unwrap();
+
+        // The entry is sealed in the database, but is read back in the clear.
+        let stored: Vec<u8> = be_w
+            .get_conn()
+            .unwrap()
+            .query_row("SELECT data FROM main.id2entry WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(dbcrypt::is_sealed(&stored));
+
+        let mut entries = be_w.get_identry_raw(&IdList::AllIds).unwrap();
+        assert_eq!(entries.pop().map(|e| e.data), Some(data));
+    }
+
+    #[test]
+    fn test_idl_sqlite_compressed_entries() {
+        sketching::test_init();
+        let mut cfg = BackendConfig::new_test("main");
+        cfg.compress_entries = true;
+        let be = IdlSqlite::new(&cfg, false).unwrap();
+        let be_w = be.write().unwrap();
+        be_w.setup().unwrap();
+
+        let data = br#"{"attrs":{"member":["a","a","a","a","a","a","a","a","a"]}}"#.to_vec();
+        be_w.write_identries_raw(
+            vec![IdRawEntry {
+                id: 1,
+                data: data.clone(),
+            }]
+            .into_iter(),
+        )
+        .unwrap();
+
+        let stored: Vec<u8> = be_w
+            .get_conn()
+            .unwrap()
+            .query_row("SELECT data FROM main.id2entry WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert!(dbcompress::is_compressed(&stored));
+        assert!(stored.len() < data.len());
+
+        let mut entries = be_w.get_identry_raw(&IdList::AllIds).unwrap();
+        assert_eq!(entries.pop().map(|e| e.data), Some(data));
+    }
+
+    #[test]
+    fn test_idl_sqlite_maintenance() {
+        sketching::test_init();
+        let db_path = format!(
+            "{}/.maintenance_test.db",
+            option_env!("OUT_DIR").unwrap_or("/tmp")
+        );
+        let _ = std::fs::remove_file(&db_path);
+        let cfg =
+            BackendConfig::new(&db_path, 1, FsType::Generic, None).durability(DbDurability::Normal);
+        let be = IdlSqlite::new(&cfg, false).unwrap();
+
+        // The durability applies to each pooled connection, NORMAL is 1.
+        let synchronous: i64 = be
+            .read()
+            .unwrap()
+            .get_conn()
+            .unwrap()
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1);
+
+        let be_w = be.write().unwrap();
+        be_w.setup().unwrap();
+        be_w.write_identries_raw((1..=256).map(|id| IdRawEntry {
+            id,
+            data: vec![b'a'; 4096],
+        }))
+        .unwrap();
+        be_w.commit().unwrap();
+
+        let stats = be.read().unwrap().get_backend_stats().unwrap();
+        assert_eq!(stats.entries, 256);
+        assert_eq!(stats.last_vacuum, None);
+        assert!(stats.db_size > 256 * 4096);
+
+        let be_w = be.write().unwrap();
+        for id in 1..=256 {
+            be_w.delete_identry(id).unwrap();
+        }
+        be_w.commit().unwrap();
+
+        // The deleted entries are released from the database.
+        let res = be.maintenance(true).unwrap();
+        assert!(res.vacuum);
+        assert!(res.wal_checkpointed);
+        assert!(res.size_after < res.size_before);
+
+        // And the connection is returned to the pool.
+        let stats = be.read().unwrap().get_backend_stats().unwrap();
+        assert_eq!(stats.entries, 0);
+        assert!(stats.last_vacuum.is_some());
+        assert_eq!(stats.db_size, res.size_after);
+        let _ = std::fs::remove_file(&db_path);
+    }
+
+    #[test]
+    fn test_idl_sqlite_journal_mode() {
+        sketching::test_init();
+        let db_path = format!(
+            "{}/.journal_mode_test.db",
+            option_env!("OUT_DIR").unwrap_or("/tmp")
+        );
+        let _ = std::fs::remove_file(&db_path);
+        let cfg = BackendConfig::new(&db_path, 1, FsType::Generic, None)
+            .journal_mode(DbJournalMode::Delete);
+        let be = IdlSqlite::new(&cfg, false).unwrap();
+
+        // The journal mode is stored in the database, so every connection uses it.
+        let journal_mode: String = be
+            .read()
+            .unwrap()
+            .get_conn()
+            .unwrap()
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(journal_mode, "delete");
+        let _ = std::fs::remove_file(&db_path);
+    }
+
+    #[test]
+    fn test_idl_sqlite_snapshot() {
+        sketching::test_init();
+        let db_path = format!(
+            "{}/.snapshot_test.db",
+            option_env!("OUT_DIR").unwrap_or("/tmp")
+        );
+        let snapshot_path = format!("{db_path}.dry-run");
+        let _ = std::fs::remove_file(&db_path);
+        let _ = std::fs::remove_file(&snapshot_path);
+
+        let cfg = BackendConfig::new(&db_path, 1, FsType::Generic, None);
+        let be = IdlSqlite::new(&cfg, false).unwrap();
+        let be_w = be.write().unwrap();
+        be_w.setup().unwrap();
+        be_w.write_identries_raw((1..=3).map(|id| IdRawEntry {
+            id,
+            data: br#"{"attrs":{}}"#.to_vec(),
+        }))
+        .unwrap();
+        be_w.commit().unwrap();
+
+        // The snapshot is taken while the source is open, and has the same format.
+        IdlSqlite::snapshot(&db_path, &snapshot_path).unwrap();
+        let version = IdlSqlite::read_format_version(&db_path).unwrap();
+        assert!(version.id2entry > 0);
+        assert_eq!(
+            IdlSqlite::read_format_version(&snapshot_path).unwrap(),
+            version
+        );
+
+        // An existing snapshot is never overwritten.
+        assert!(IdlSqlite::snapshot(&db_path, &snapshot_path).is_err());
+
+        // Changing the snapshot leaves the source untouched.
+        let snapshot_cfg = BackendConfig::new(&snapshot_path, 1, FsType::Generic, None);
+        let snapshot = IdlSqlite::new(&snapshot_cfg, false).unwrap();
+        let snapshot_w = snapshot.write().unwrap();
+        assert_eq!(
+            snapshot_w.get_identry_raw(&IdList::AllIds).unwrap().len(),
+            3
+        );
+        for id in 1..=3 {
+            snapshot_w.delete_identry(id).unwrap();
+        }
+        snapshot_w.commit().unwrap();
+
+        assert_eq!(
+            snapshot
+                .read()
+                .unwrap()
+                .get_backend_stats()
+                .unwrap()
+                .entries,
+            0
+        );
+        assert_eq!(be.read().unwrap().get_backend_stats().unwrap().entries, 3);
+
+        drop(snapshot);
+        drop(be);
+        for path in [&db_path, &snapshot_path] {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{path}{suffix}"));
+            }
+        }
+    }
 }
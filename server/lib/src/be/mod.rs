@@ -4,7 +4,8 @@
 //! is to persist content safely to disk, load that content, and execute queries
 //! utilising indexes in the most effective way possible.
 
-use std::collections::BTreeMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::ops::DerefMut;
 use std::sync::Arc;
@@ -14,7 +15,10 @@ use concread::cowcell::*;
 use hashbrown::{HashMap as Map, HashSet};
 use idlset::v2::IDLBitRange;
 use idlset::AndNot;
-use kanidm_proto::internal::{ConsistencyError, OperationError};
+use kanidm_proto::internal::{
+    BackendStats, ConsistencyError, FilterExplain, FilterExplainIndex, FilterExplainTerm,
+    OperationError,
+};
 use smartstring::alias::String as AttrString;
 use tracing::{trace, trace_span};
 use uuid::Uuid;
@@ -32,6 +36,9 @@ use crate::repl::ruv::{
 };
 use crate::value::{IndexType, Value};
 
+mod dbarchive;
+mod dbcompress;
+mod dbcrypt;
 pub(crate) mod dbentry;
 pub(crate) mod dbrepl;
 pub(crate) mod dbvalue;
@@ -41,17 +48,24 @@ mod idl_sqlite;
 pub(crate) mod idxkey;
 pub(crate) mod keystorage;
 
+pub use self::dbcrypt::{
+    DbEncryptionKey, DbEncryptionKeyEnv, DbEncryptionKeyFile, DbEncryptionKeyProvider,
+    DB_ENCRYPTION_KEY_ENV,
+};
 pub(crate) use self::idxkey::{IdxKey, IdxKeyRef, IdxKeyToRef, IdxSlope};
 use crate::be::idl_arc_sqlite::{
     IdlArcSqlite, IdlArcSqliteReadTransaction, IdlArcSqliteTransaction,
     IdlArcSqliteWriteTransaction,
 };
-use kanidm_proto::internal::FsType;
+use kanidm_proto::internal::{DbDurability, DbJournalMode, FsType};
 
 // Currently disabled due to improvements in idlset for intersection handling.
 const FILTER_SEARCH_TEST_THRESHOLD: usize = 0;
 const FILTER_EXISTS_TEST_THRESHOLD: usize = 0;
 
+/// The number of candidates a search loads from the database at a time.
+const SEARCH_CURSOR_BATCH_SIZE: usize = 256;
+
 #[derive(Debug, Clone)]
 /// Limits on the resources a single event can consume. These are defined per-event
 /// as they are derived from the userAuthToken based on that individual session
@@ -60,6 +74,9 @@ pub struct Limits {
     pub search_max_results: usize,
     pub search_max_filter_test: usize,
     pub filter_max_elements: usize,
+    /// If searches are exempt from the backend `search_max_candidates`. Only internal
+    /// operations such as purges and migrations are exempt.
+    pub search_max_candidates_exempt: bool,
 }
 
 impl Default for Limits {
@@ -69,6 +86,7 @@ impl Default for Limits {
             search_max_results: DEFAULT_LIMIT_SEARCH_MAX_RESULTS as usize,
             search_max_filter_test: DEFAULT_LIMIT_SEARCH_MAX_FILTER_TEST as usize,
             filter_max_elements: DEFAULT_LIMIT_FILTER_MAX_ELEMENTS as usize,
+            search_max_candidates_exempt: false,
         }
     }
 }
@@ -80,6 +98,26 @@ impl Limits {
             search_max_results: usize::MAX >> 1,
             search_max_filter_test: usize::MAX >> 1,
             filter_max_elements: usize::MAX,
+            search_max_candidates_exempt: false,
+        }
+    }
+
+    /// The limits of the server itself. These are unlimited, and are also exempt from the
+    /// backend `search_max_candidates`.
+    pub fn internal() -> Self {
+        Limits {
+            search_max_candidates_exempt: true,
+            ..Limits::unlimited()
+        }
+    }
+
+    /// The limits of a synchronisation account. A sync agent may need to read its
+    /// whole synced set, so results are unbounded, but it must use indexed filters
+    /// and remains subject to the backend `search_max_candidates`.
+    pub fn sync() -> Self {
+        Limits {
+            unindexed_allow: false,
+            ..Limits::unlimited()
         }
     }
 
@@ -89,6 +127,7 @@ impl Limits {
             search_max_results: DEFAULT_LIMIT_API_SEARCH_MAX_RESULTS as usize,
             search_max_filter_test: DEFAULT_LIMIT_API_SEARCH_MAX_FILTER_TEST as usize,
             filter_max_elements: DEFAULT_LIMIT_FILTER_MAX_ELEMENTS as usize,
+            search_max_candidates_exempt: false,
         }
     }
 }
@@ -107,6 +146,144 @@ pub struct IdRawEntry {
     data: Vec<u8>,
 }
 
+/// The kind of change recorded in the changelog. Deleting an entry through the server moves it
+/// to the recycle bin and then to a tombstone, which are both modifications. The entry is only
+/// deleted when the tombstone is reaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangelogOperation {
+    Create,
+    Modify,
+    Delete,
+}
+
+impl ChangelogOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangelogOperation::Create => "create",
+            ChangelogOperation::Modify => "modify",
+            ChangelogOperation::Delete => "delete",
+        }
+    }
+}
+
+impl TryFrom<&str> for ChangelogOperation {
+    type Error = OperationError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        match s {
+            "create" => Ok(ChangelogOperation::Create),
+            "modify" => Ok(ChangelogOperation::Modify),
+            "delete" => Ok(ChangelogOperation::Delete),
+            _ => {
+                admin_error!(operation = %s, "Invalid changelog operation");
+                Err(OperationError::InvalidDbState)
+            }
+        }
+    }
+}
+
+/// A change to an entry, written to the changelog when the transaction commits. The server
+/// that originated the change is the server of the cid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangelogRecord {
+    pub cid: Cid,
+    pub id: u64,
+    pub uuid: Uuid,
+    pub operation: ChangelogOperation,
+    /// The attributes that a modify changed. This is empty for a create or a delete.
+    pub attrs: BTreeSet<AttrString>,
+}
+
+/// The versions of the stored format of the database. These are raised by the migrations run
+/// when a database created by an older server is opened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbFormatVersion {
+    pub id2entry: i64,
+    pub index: i64,
+}
+
+/// The outcome of a database maintenance run. Sizes are in bytes, and exclude the write ahead log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbMaintenanceResult {
+    pub vacuum: bool,
+    pub size_before: u64,
+    pub size_after: u64,
+    /// False if the write ahead log could not be fully checkpointed because of active readers.
+    /// The remainder is checkpointed on a later run.
+    pub wal_checkpointed: bool,
+}
+
+/// The entries matching a search, created by [BackendTransaction::search_cursor]. Each item is
+/// either a matching entry, or the error that ended the search.
+pub struct SearchCursor<'a, BE: BackendTransaction> {
+    be: &'a mut BE,
+    filt: &'a Filter<FilterValidResolved>,
+    ids: std::vec::IntoIter<u64>,
+    batch: std::vec::IntoIter<Arc<EntrySealedCommitted>>,
+    /// Don't cache the candidates, as every entry is a candidate.
+    scan: bool,
+    /// The candidates were not fully resolved by the indexes, so must be tested against the filter.
+    filter_test: bool,
+    max_results: usize,
+    matched: usize,
+}
+
+impl<'a, BE: BackendTransaction> SearchCursor<'a, BE> {
+    fn fail(
+        &mut self,
+        err: OperationError,
+    ) -> Option<Result<Arc<EntrySealedCommitted>, OperationError>> {
+        // Nothing more is yielded after an error.
+        self.ids = Vec::new().into_iter();
+        self.batch = Vec::new().into_iter();
+        Some(Err(err))
+    }
+}
+
+impl<'a, BE: BackendTransaction> Iterator for SearchCursor<'a, BE> {
+    type Item = Result<Arc<EntrySealedCommitted>, OperationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            for entry in self.batch.by_ref() {
+                if self.filter_test {
+                    if !entry.entry_match_no_index(self.filt) {
+                        continue;
+                    }
+                    // If the idl was not indexed, apply the resource limit now.
+                    self.matched += 1;
+                    if self.matched > self.max_results {
+                        admin_error!("filter (search) is resolved and greater than search_max_results allowed by resource limits");
+                        return self.fail(OperationError::ResourceLimit);
+                    }
+                }
+                return Some(Ok(entry));
+            }
+
+            let batch_ids: IDLBitRange = self.ids.by_ref().take(SEARCH_CURSOR_BATCH_SIZE).collect();
+            if batch_ids.is_empty() {
+                return None;
+            }
+
+            let batch = if self.scan {
+                self.be.get_idlayer().get_identry_scan(batch_ids)
+            } else {
+                self.be
+                    .get_idlayer()
+                    .get_identry(&IdList::Indexed(batch_ids))
+            };
+
+            match batch {
+                Ok(batch) => self.batch = batch.into_iter(),
+                Err(e) => {
+                    admin_error!(?e, "get_identry failed");
+                    return self.fail(e);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct IdxMeta {
     pub idxkeys: Map<IdxKey, IdxSlope>,
@@ -126,6 +303,24 @@ pub struct BackendConfig {
     fstype: FsType,
     // Cachesizes?
     arcsize: Option<usize>,
+    encryption_key: Option<DbEncryptionKey>,
+    compress_entries: bool,
+    durability: DbDurability,
+    journal_mode: DbJournalMode,
+    /// The most candidates a single search may load before it is refused.
+    search_max_candidates: Option<usize>,
+    /// How many seconds tombstones and entry change states are kept before they are purged.
+    tombstone_max_age: Option<u64>,
+    /// How many seconds deleted entries stay in the recycle bin before they become tombstones.
+    recyclebin_max_age: Option<u64>,
+    /// The key that backups are sealed with, or they are written unencrypted.
+    backup_encryption_key: Option<DbEncryptionKey>,
+    /// The most live entries the database may hold before creates are refused.
+    max_entries: Option<u64>,
+    /// The most bytes the database may use before writes that grow it are refused.
+    max_db_size: Option<u64>,
+    /// The size limits on entries written by clients.
+    entry_limits: EntryLimits,
 }
 
 impl BackendConfig {
@@ -136,16 +331,123 @@ impl BackendConfig {
             db_name: "main",
             fstype,
             arcsize,
+            encryption_key: None,
+            compress_entries: false,
+            durability: DbDurability::default(),
+            journal_mode: DbJournalMode::default(),
+            search_max_candidates: None,
+            tombstone_max_age: None,
+            recyclebin_max_age: None,
+            backup_encryption_key: None,
+            max_entries: None,
+            max_db_size: None,
+            entry_limits: EntryLimits::default(),
+        }
+    }
+
+    pub fn encryption_key(mut self, encryption_key: Option<DbEncryptionKey>) -> Self {
+        self.encryption_key = encryption_key;
+        self
+    }
+
+    pub fn compress_entries(mut self, compress_entries: bool) -> Self {
+        self.compress_entries = compress_entries;
+        self
+    }
+
+    pub fn durability(mut self, durability: DbDurability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    pub fn journal_mode(mut self, journal_mode: DbJournalMode) -> Self {
+        self.journal_mode = journal_mode;
+        self
+    }
+
+    pub fn search_max_candidates(mut self, search_max_candidates: Option<usize>) -> Self {
+        self.search_max_candidates = search_max_candidates;
+        self
+    }
+
+    pub fn tombstone_max_age(mut self, tombstone_max_age: Option<u64>) -> Self {
+        self.tombstone_max_age = tombstone_max_age;
+        self
+    }
+
+    pub fn recyclebin_max_age(mut self, recyclebin_max_age: Option<u64>) -> Self {
+        self.recyclebin_max_age = recyclebin_max_age;
+        self
+    }
+
+    pub fn backup_encryption_key(mut self, backup_encryption_key: Option<DbEncryptionKey>) -> Self {
+        self.backup_encryption_key = backup_encryption_key;
+        self
+    }
+
+    pub fn max_entries(mut self, max_entries: Option<u64>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn max_db_size(mut self, max_db_size: Option<u64>) -> Self {
+        self.max_db_size = max_db_size;
+        self
+    }
+
+    pub fn entry_limits(mut self, entry_limits: EntryLimits) -> Self {
+        self.entry_limits = entry_limits;
+        self
+    }
+
+    /// Tombstones must be kept long enough for replicas to see them, and the age must be
+    /// subtractable from the current time to find the point to trim up to.
+    fn check_tombstone_max_age(&self, ct: Duration) -> Result<(), OperationError> {
+        let Some(age) = self.tombstone_max_age else {
+            return Ok(());
+        };
+
+        if age < TOMBSTONE_MIN_AGE {
+            admin_error!(
+                %age,
+                min = %TOMBSTONE_MIN_AGE,
+                "db_tombstone_max_age is less than the minimum"
+            );
+            Err(OperationError::DB0008InvalidTombstoneMaxAge)
+        } else if age >= ct.as_secs() {
+            admin_error!(%age, "db_tombstone_max_age is greater than the time since the epoch");
+            Err(OperationError::DB0008InvalidTombstoneMaxAge)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// As with tombstones, the age must be subtractable from the current time to find the
+    /// entries to purge.
+    fn check_recyclebin_max_age(&self, ct: Duration) -> Result<(), OperationError> {
+        let Some(age) = self.recyclebin_max_age else {
+            return Ok(());
+        };
+
+        if age < RECYCLEBIN_MIN_AGE {
+            admin_error!(
+                %age,
+                min = %RECYCLEBIN_MIN_AGE,
+                "db_recyclebin_max_age is less than the minimum"
+            );
+            Err(OperationError::DB0010InvalidRecycleBinMaxAge)
+        } else if age >= ct.as_secs() {
+            admin_error!(%age, "db_recyclebin_max_age is greater than the time since the epoch");
+            Err(OperationError::DB0010InvalidRecycleBinMaxAge)
+        } else {
+            Ok(())
         }
     }
 
     pub(crate) fn new_test(db_name: &'static str) -> Self {
         BackendConfig {
-            pool_size: 1,
-            path: "".to_string(),
             db_name,
-            fstype: FsType::Generic,
-            arcsize: Some(1024),
+            ..BackendConfig::new("", 1, FsType::Generic, Some(1024))
         }
     }
 }
@@ -170,6 +472,8 @@ pub struct BackendReadTransaction<'a> {
     idlayer: IdlArcSqliteReadTransaction<'a>,
     idxmeta: CowCellReadTxn<IdxMeta>,
     ruv: ReplicationUpdateVectorReadTransaction<'a>,
+    search_max_candidates: Option<usize>,
+    backup_encryption_key: Option<&'a DbEncryptionKey>,
 }
 
 unsafe impl<'a> Sync for BackendReadTransaction<'a> {}
@@ -180,6 +484,12 @@ pub struct BackendWriteTransaction<'a> {
     idlayer: IdlArcSqliteWriteTransaction<'a>,
     idxmeta_wr: CowCellWriteTxn<'a, IdxMeta>,
     ruv: ReplicationUpdateVectorWriteTransaction<'a>,
+    search_max_candidates: Option<usize>,
+    backup_encryption_key: Option<&'a DbEncryptionKey>,
+    max_entries: Option<u64>,
+    max_db_size: Option<u64>,
+    /// The changes made in this transaction, in the order they were made.
+    changelog: Vec<ChangelogRecord>,
 }
 
 impl IdRawEntry {
@@ -213,6 +523,47 @@ pub trait BackendTransaction {
 
     fn get_idxmeta_ref(&self) -> &IdxMeta;
 
+    fn get_search_max_candidates(&self) -> Option<usize>;
+
+    fn get_backup_encryption_key(&self) -> Option<&DbEncryptionKey>;
+
+    /// Refuse a search that would load more candidates than the backend allows. This applies
+    /// to the searches of every client, including those with unlimited resource limits, so that
+    /// a single search can not exhaust the memory of the server. Internal searches are exempt,
+    /// as purges and migrations must be able to see every entry they act on.
+    fn check_search_max_candidates(
+        &mut self,
+        erl: &Limits,
+        idl: &IdList,
+    ) -> Result<(), OperationError> {
+        if erl.search_max_candidates_exempt {
+            return Ok(());
+        }
+
+        let Some(limit) = self.get_search_max_candidates() else {
+            return Ok(());
+        };
+
+        // Exactly limit candidates are allowed.
+        let threshold = limit.saturating_add(1);
+        let below_limit = match idl {
+            IdList::AllIds => self.get_idlayer().get_allids().below_threshold(threshold),
+            IdList::Partial(idl_br)
+            | IdList::PartialThreshold(idl_br)
+            | IdList::Indexed(idl_br) => idl_br.below_threshold(threshold),
+        };
+
+        if below_limit {
+            Ok(())
+        } else {
+            admin_error!(
+                %limit,
+                "filter has more candidates than db_search_max_candidates allows"
+            );
+            Err(OperationError::DB0003SearchCandidateLimitExceeded)
+        }
+    }
+
     /// Recursively apply a filter, transforming into IdList's on the way. This builds a query
     /// execution log, so that it can be examined how an operation proceeded.
     #[allow(clippy::cognitive_complexity)]
@@ -243,33 +594,10 @@ pub trait BackendTransaction {
                     (IdList::AllIds, FilterPlan::EqUnindexed(attr.clone()))
                 }
             }
-            FilterResolved::Cnt(attr, subvalue, idx) => {
-                if idx.is_some() {
-                    // Get the idx_key
-                    let idx_key = subvalue.get_idx_sub_key();
-                    // Get the idl for this
-                    match self
-                        .get_idlayer()
-                        .get_idl(attr, IndexType::SubString, &idx_key)?
-                    {
-                        Some(idl) => (
-                            IdList::Indexed(idl),
-                            FilterPlan::SubIndexed(attr.clone(), idx_key),
-                        ),
-                        None => (IdList::AllIds, FilterPlan::SubCorrupt(attr.clone())),
-                    }
-                } else {
-                    // Schema believes this is not indexed
-                    (IdList::AllIds, FilterPlan::SubUnindexed(attr.clone()))
-                }
-            }
-            FilterResolved::Stw(attr, _subvalue, _idx) => {
-                // Schema believes this is not indexed
-                (IdList::AllIds, FilterPlan::SubUnindexed(attr.clone()))
-            }
-            FilterResolved::Enw(attr, _subvalue, _idx) => {
-                // Schema believes this is not indexed
-                (IdList::AllIds, FilterPlan::SubUnindexed(attr.clone()))
+            FilterResolved::Cnt(attr, subvalue, idx)
+            | FilterResolved::Stw(attr, subvalue, idx)
+            | FilterResolved::Enw(attr, subvalue, idx) => {
+                self.filter2idl_sub(attr, subvalue, idx.is_some())?
             }
             FilterResolved::Pres(attr, idx) => {
                 if idx.is_some() {
@@ -283,9 +611,48 @@ pub trait BackendTransaction {
                     (IdList::AllIds, FilterPlan::PresUnindexed(attr.clone()))
                 }
             }
-            FilterResolved::LessThan(attr, _subvalue, _idx) => {
-                // We have no process for indexing this right now.
-                (IdList::AllIds, FilterPlan::LessThanUnindexed(attr.clone()))
+            FilterResolved::LessThan(attr, subvalue, idx) => {
+                // Values of a syntax that is not ordered have no key.
+                match subvalue.get_idx_ord_key().filter(|_| idx.is_some()) {
+                    Some(idx_key) => {
+                        // The keys sort as their values do, so this is the union of the idls
+                        // of every key on that side of the value.
+                        match self
+                            .get_idlayer()
+                            .get_idl_ord(attr, &idx_key, Ordering::Less)?
+                        {
+                            Some(idl) => (
+                                IdList::Indexed(idl),
+                                FilterPlan::LessThanIndexed(attr.clone(), idx_key),
+                            ),
+                            None => (IdList::AllIds, FilterPlan::LessThanCorrupt(attr.clone())),
+                        }
+                    }
+                    // Schema believes this is not indexed
+                    None => (IdList::AllIds, FilterPlan::LessThanUnindexed(attr.clone())),
+                }
+            }
+            FilterResolved::GreaterThan(attr, subvalue, idx) => {
+                // Values of a syntax that is not ordered have no key.
+                match subvalue.get_idx_ord_key().filter(|_| idx.is_some()) {
+                    Some(idx_key) => {
+                        match self
+                            .get_idlayer()
+                            .get_idl_ord(attr, &idx_key, Ordering::Greater)?
+                        {
+                            Some(idl) => (
+                                IdList::Indexed(idl),
+                                FilterPlan::GreaterThanIndexed(attr.clone(), idx_key),
+                            ),
+                            None => (IdList::AllIds, FilterPlan::GreaterThanCorrupt(attr.clone())),
+                        }
+                    }
+                    // Schema believes this is not indexed
+                    None => (
+                        IdList::AllIds,
+                        FilterPlan::GreaterThanUnindexed(attr.clone()),
+                    ),
+                }
             }
             FilterResolved::Or(l, _) => {
                 // Importantly if this has no inner elements, this returns
@@ -579,12 +946,84 @@ pub trait BackendTransaction {
         })
     }
 
+    /// Resolve a substring, starts with or ends with term through the substring index. Each of
+    /// these only matches an entry that has every trigram of the value, so the trigram idls are
+    /// intersected to find the candidates.
+    fn filter2idl_sub(
+        &mut self,
+        attr: &AttrString,
+        subvalue: &PartialValue,
+        indexed: bool,
+    ) -> Result<(IdList, FilterPlan), OperationError> {
+        // A value shorter than a trigram has no keys, and must be unindexed.
+        let idx_keys = subvalue.get_idx_sub_keys();
+        if !indexed || idx_keys.is_empty() {
+            // Schema believes this is not indexed
+            return Ok((IdList::AllIds, FilterPlan::SubUnindexed(attr.clone())));
+        }
+
+        // Entries containing the value have every one of its trigrams, but an
+        // entry with all the trigrams may not contain the value, so the result
+        // is partial and candidates are filtered afterwards.
+        let mut result: Option<IDLBitRange> = None;
+        for idx_key in idx_keys.iter() {
+            let Some(idl) = self
+                .get_idlayer()
+                .get_idl(attr, IndexType::SubString, idx_key)?
+            else {
+                return Ok((IdList::AllIds, FilterPlan::SubCorrupt(attr.clone())));
+            };
+            let idl = match result.take() {
+                Some(prev) => prev & idl,
+                None => idl,
+            };
+            let exhausted = idl.is_empty();
+            result = Some(idl);
+            if exhausted {
+                break;
+            }
+        }
+        let idl = result.unwrap_or_default();
+        let plan = FilterPlan::SubIndexed(attr.clone(), subvalue.get_idx_eq_key());
+        if idl.is_empty() {
+            // No entry has every trigram, so none can match.
+            Ok((IdList::Indexed(idl), plan))
+        } else {
+            Ok((IdList::Partial(idl), plan))
+        }
+    }
+
     #[instrument(level = "debug", name = "be::search", skip_all)]
     fn search(
         &mut self,
         erl: &Limits,
         filt: &Filter<FilterValidResolved>,
-    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError>
+    where
+        Self: Sized,
+    {
+        let mut entries_filtered = self
+            .search_cursor(erl, filt)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Trim any excess capacity if needed
+        entries_filtered.shrink_to_fit();
+
+        Ok(entries_filtered)
+    }
+
+    /// Search for the entries matching a filter, yielding them as they are loaded. Candidates
+    /// are loaded and tested a batch at a time, so that a search with many candidates does not
+    /// need to hold all of them in memory before they are reduced by the caller.
+    #[instrument(level = "debug", name = "be::search_cursor", skip_all)]
+    fn search_cursor<'a>(
+        &'a mut self,
+        erl: &Limits,
+        filt: &'a Filter<FilterValidResolved>,
+    ) -> Result<SearchCursor<'a, Self>, OperationError>
+    where
+        Self: Sized,
+    {
         // Unlike DS, even if we don't get the index back, we can just pass
         // to the in-memory filter test and be done.
 
@@ -627,49 +1066,108 @@ pub trait BackendTransaction {
             }
         };
 
-        let entries = self.get_idlayer().get_identry(&idl).map_err(|e| {
-            admin_error!(?e, "get_identry failed");
-            e
-        })?;
+        self.check_search_max_candidates(erl, &idl)?;
 
-        let mut entries_filtered = match idl {
-            IdList::AllIds => trace_span!("be::search<entry::ftest::allids>").in_scope(|| {
-                entries
-                    .into_iter()
-                    .filter(|e| e.entry_match_no_index(filt))
-                    .collect()
-            }),
-            IdList::Partial(_) => trace_span!("be::search<entry::ftest::partial>").in_scope(|| {
-                entries
-                    .into_iter()
-                    .filter(|e| e.entry_match_no_index(filt))
-                    .collect()
-            }),
-            IdList::PartialThreshold(_) => trace_span!("be::search<entry::ftest::thresh>")
-                .in_scope(|| {
-                    entries
-                        .into_iter()
-                        .filter(|e| e.entry_match_no_index(filt))
-                        .collect()
-                }),
+        let (ids, scan, filter_test): (Vec<u64>, _, _) = match &idl {
+            IdList::AllIds => (
+                self.get_idlayer().get_allids().into_iter().collect(),
+                true,
+                true,
+            ),
+            IdList::Partial(idl_br) | IdList::PartialThreshold(idl_br) => {
+                (idl_br.into_iter().collect(), false, true)
+            }
             // Since the index fully resolved, we can shortcut the filter test step here!
-            IdList::Indexed(_) => {
+            IdList::Indexed(idl_br) => {
                 filter_trace!("filter (search) was fully indexed 👏");
-                entries
+                (idl_br.into_iter().collect(), false, false)
             }
         };
 
-        // If the idl was not indexed, apply the resource limit now. Avoid the needless match since the
-        // if statement is quick.
-        if entries_filtered.len() > erl.search_max_results {
-            admin_error!("filter (search) is resolved and greater than search_max_results allowed by resource limits");
-            return Err(OperationError::ResourceLimit);
-        }
+        Ok(SearchCursor {
+            be: self,
+            filt,
+            ids: ids.into_iter(),
+            batch: Vec::new().into_iter(),
+            scan,
+            filter_test,
+            max_results: erl.search_max_results,
+            matched: 0,
+        })
+    }
 
-        // Trim any excess capacity if needed
-        entries_filtered.shrink_to_fit();
+    /// Resolve a filter against the indexes as a search would, and report the plan and how
+    /// many candidates would be loaded, without loading them. Resource limits are not
+    /// applied, so that the plan of a search that would be refused can still be examined.
+    #[instrument(level = "debug", name = "be::explain", skip_all)]
+    fn explain(
+        &mut self,
+        filt: &Filter<FilterValidResolved>,
+    ) -> Result<FilterExplain, OperationError> {
+        trace!(filter_optimised = ?filt);
 
-        Ok(entries_filtered)
+        let plan = self.explain_term(filt.to_inner())?;
+
+        Ok(FilterExplain {
+            candidates: plan.candidates,
+            full_scan: plan.index == FilterExplainIndex::Unindexed,
+            filter_test: plan.index != FilterExplainIndex::Indexed,
+            plan,
+        })
+    }
+
+    /// Resolve a term of a filter, and each term within it, on its own, so that the terms
+    /// that need an index can be found.
+    fn explain_term(&mut self, filt: &FilterResolved) -> Result<FilterExplainTerm, OperationError> {
+        let (term, attr, inner) = match filt {
+            FilterResolved::Eq(attr, _, _) => ("eq", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::Cnt(attr, _, _) => ("cnt", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::Stw(attr, _, _) => ("stw", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::Enw(attr, _, _) => ("enw", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::Pres(attr, _) => ("pres", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::LessThan(attr, _, _) => ("lt", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::GreaterThan(attr, _, _) => ("gt", Some(attr), Vec::with_capacity(0)),
+            FilterResolved::Or(l, _) => ("or", None, l.iter().collect()),
+            FilterResolved::And(l, _) => ("and", None, l.iter().collect()),
+            FilterResolved::Inclusion(l, _) => ("inclusion", None, l.iter().collect()),
+            FilterResolved::AndNot(f, _) => {
+                // An andnot can only be resolved within an and, so report what it excludes.
+                let excluded = self.explain_term(f)?;
+                return Ok(FilterExplainTerm {
+                    term: "andnot".to_string(),
+                    attr: None,
+                    index: excluded.index,
+                    candidates: excluded.candidates,
+                    terms: vec![excluded],
+                });
+            }
+        };
+
+        let terms = inner
+            .into_iter()
+            .map(|f| self.explain_term(f))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (idl, _fplan) = self.filter2idl(filt, FILTER_SEARCH_TEST_THRESHOLD)?;
+
+        let (candidates, index) = match &idl {
+            IdList::AllIds => (
+                self.get_idlayer().get_allids().len(),
+                FilterExplainIndex::Unindexed,
+            ),
+            IdList::Partial(idl_br) | IdList::PartialThreshold(idl_br) => {
+                (idl_br.len(), FilterExplainIndex::Partial)
+            }
+            IdList::Indexed(idl_br) => (idl_br.len(), FilterExplainIndex::Indexed),
+        };
+
+        Ok(FilterExplainTerm {
+            term: term.to_string(),
+            attr: attr.map(|a| a.to_string()),
+            index,
+            candidates: candidates as u64,
+            terms,
+        })
     }
 
     /// Given a filter, assert some condition exists.
@@ -719,6 +1217,8 @@ pub trait BackendTransaction {
         match &idl {
             IdList::Indexed(idl) => Ok(!idl.is_empty()),
             _ => {
+                self.check_search_max_candidates(erl, &idl)?;
+
                 let entries = self.get_idlayer().get_identry(&idl).map_err(|e| {
                     admin_error!(?e, "get_identry failed");
                     e
@@ -830,17 +1330,136 @@ pub trait BackendTransaction {
             };
         }
 
-        // Check the other entry:attr indexes are valid
-        //
-        // This is actually pretty hard to check, because we can check a value *should*
-        // exist, but not that a value should NOT be present in the index. Thought needed ...
+        // The other entry:attr indexes are checked as a whole by verify_attr_indexes, as
+        // an id that should NOT be present in an index can only be found from the index.
 
         // Got here? Ok!
         Ok(())
     }
 
+    fn verify_attr_indexes(
+        &mut self,
+        entries: &[Arc<EntrySealedCommitted>],
+        results: &mut Vec<Result<(), ConsistencyError>>,
+    ) {
+        // Build the content that each index must have from the entries, then compare
+        // it to what is stored, so that both missing and stale ids are found.
+        let idxmeta = &self.get_idxmeta_ref().idxkeys;
+
+        let mut expected: BTreeMap<(AttrString, IndexType), BTreeMap<String, IDLBitRange>> =
+            idxmeta
+                .keys()
+                .map(|ikey| ((ikey.attr.clone(), ikey.itype), BTreeMap::new()))
+                .collect();
+
+        for e in entries {
+            for (attr, itype, idx_key) in Entry::idx_diff(idxmeta, None, Some(e))
+                .into_iter()
+                .filter_map(|act| act.ok())
+            {
+                expected
+                    .entry((attr.clone(), itype))
+                    .or_default()
+                    .entry(idx_key)
+                    .or_default()
+                    .insert_id(e.get_id());
+            }
+        }
+
+        let idx_table_set: HashSet<_> = match self.get_idlayer().list_idxs() {
+            Ok(list) => list.into_iter().collect(),
+            Err(err) => {
+                admin_error!(?err, "Unable to list indexes");
+                results.push(Err(ConsistencyError::BackendIndexSync));
+                return;
+            }
+        };
+
+        for ((attr, itype), expected_keys) in expected {
+            let index = format!("idx_{}_{}", itype.as_idx_str(), attr);
+
+            // An index that is yet to be created is not used by searches until a reindex.
+            if !idx_table_set.contains(&index) {
+                warn!(%index, "Index has not been created, skipping verification");
+                continue;
+            }
+
+            // The stored keys are listed from the db, but their idls are read through the
+            // cache, as a write transaction may not have flushed them yet.
+            let stored_keys = match self.get_idlayer().list_index_content(&index) {
+                Ok(content) => content.into_iter().map(|(key, _)| key),
+                Err(err) => {
+                    admin_error!(?err, %index, "Unable to list index content");
+                    results.push(Err(ConsistencyError::BackendIndexSync));
+                    continue;
+                }
+            };
+
+            let keys: BTreeSet<String> = expected_keys.keys().cloned().chain(stored_keys).collect();
+
+            for key in keys {
+                let stored = match self.get_idlayer().get_idl(&attr, itype, &key) {
+                    Ok(Some(idl)) => idl,
+                    r => {
+                        admin_error!(state = ?r, %index, %key, "Invalid index state");
+                        results.push(Err(ConsistencyError::BackendIndexSync));
+                        continue;
+                    }
+                };
+                let expected = expected_keys.get(&key);
+
+                for id in expected.into_iter().flatten() {
+                    if !stored.contains(id) {
+                        admin_error!(%index, %key, %id, "Entry is missing from index");
+                        results.push(Err(ConsistencyError::BackendIndexMissingId {
+                            index: index.clone(),
+                            key: key.clone(),
+                            id,
+                        }));
+                    }
+                }
+
+                for id in &stored {
+                    if !expected.is_some_and(|e| e.contains(id)) {
+                        admin_error!(%index, %key, %id, "Index contains an entry it should not");
+                        results.push(Err(ConsistencyError::BackendIndexStaleId {
+                            index: index.clone(),
+                            key: key.clone(),
+                            id,
+                        }));
+                    }
+                }
+            }
+        }
+    }
+
     fn verify_indexes(&mut self) -> Vec<Result<(), ConsistencyError>> {
         let idl = IdList::AllIds;
+
+        // Entries can only be damaged in the db, so check that each stored entry can be
+        // loaded before the cached entries are compared to the indexes.
+        let raw_entries = match self.get_idlayer().get_identry_raw(&idl) {
+            Ok(s) => s,
+            Err(e) => {
+                admin_error!(?e, "get_identry_raw failure");
+                return vec![Err(ConsistencyError::Unknown)];
+            }
+        };
+
+        let mut results: Vec<_> = raw_entries
+            .into_iter()
+            .filter_map(|raw| {
+                let id = raw.id;
+                raw.into_entry()
+                    .err()
+                    .map(|_| Err(ConsistencyError::BackendEntryCorrupt(id)))
+            })
+            .collect();
+
+        if !results.is_empty() {
+            return results;
+        }
+
         let entries = match self.get_idlayer().get_identry(&idl) {
             Ok(s) => s,
             Err(e) => {
@@ -849,13 +1468,15 @@ pub trait BackendTransaction {
             }
         };
 
-        let r = entries.iter().try_for_each(|e| self.verify_entry_index(e));
-
-        if r.is_err() {
-            vec![r]
-        } else {
-            Vec::with_capacity(0)
+        for e in entries.iter() {
+            if let Err(err) = self.verify_entry_index(e) {
+                results.push(Err(err));
+            }
         }
+
+        self.verify_attr_indexes(&entries, &mut results);
+
+        results
     }
 
     fn verify_ruv(&mut self, results: &mut Vec<Result<(), ConsistencyError>>) {
@@ -873,6 +1494,22 @@ pub trait BackendTransaction {
         self.get_ruv().verify(&entries, results);
     }
 
+    /// Statistics about the stored entries and indexes, and the size of the database.
+    fn stats(&mut self) -> Result<BackendStats, OperationError> {
+        self.get_idlayer().get_backend_stats()
+    }
+
+    /// The committed changes recorded in the changelog after the change with sequence number
+    /// `since`, in the order they were committed. Sequence numbers start at 1.
+    fn changelog(&mut self, since: u64) -> Result<Vec<(u64, ChangelogRecord)>, OperationError> {
+        self.get_idlayer().get_db_changelog(since)
+    }
+
+    /// The sequence number of the most recent change in the changelog, or 0 if it is empty.
+    fn changelog_max_seq(&mut self) -> Result<u64, OperationError> {
+        self.get_idlayer().get_db_changelog_max_seq()
+    }
+
     fn backup(&mut self, dst_path: &str) -> Result<(), OperationError> {
         let repl_meta = self.get_ruv().to_db_backup_ruv();
 
@@ -904,6 +1541,7 @@ pub trait BackendTransaction {
 
         let keyhandles = idlayer.get_key_handles()?;
 
+        let entry_count = entries.len();
         let bak = DbBackup::V5 {
             // remember env is evaled at compile time.
             version: env!("KANIDM_PKG_SERIES").to_string(),
@@ -915,17 +1553,17 @@ pub trait BackendTransaction {
             entries,
         };
 
-        let serialized_entries_str = serde_json::to_string(&bak).map_err(|e| {
-            admin_error!(?e, "serde error");
-            OperationError::SerdeJsonError
-        })?;
+        let archive = dbarchive::seal(
+            &bak,
+            env!("KANIDM_PKG_SERIES").to_string(),
+            entry_count,
+            self.get_backup_encryption_key(),
+        )?;
 
-        fs::write(dst_path, serialized_entries_str)
-            .map(|_| ())
-            .map_err(|e| {
-                admin_error!(?e, "fs::write error");
-                OperationError::FsError
-            })
+        fs::write(dst_path, archive).map(|_| ()).map_err(|e| {
+            admin_error!(?e, "fs::write error");
+            OperationError::FsError
+        })
     }
 
     fn name2uuid(&mut self, name: &str) -> Result<Option<Uuid>, OperationError> {
@@ -960,6 +1598,14 @@ impl<'a> BackendTransaction for BackendReadTransaction<'a> {
     fn get_idxmeta_ref(&self) -> &IdxMeta {
         &self.idxmeta
     }
+
+    fn get_search_max_candidates(&self) -> Option<usize> {
+        self.search_max_candidates
+    }
+
+    fn get_backup_encryption_key(&self) -> Option<&DbEncryptionKey> {
+        self.backup_encryption_key
+    }
 }
 
 impl<'a> BackendReadTransaction<'a> {
@@ -1002,6 +1648,14 @@ impl<'a> BackendTransaction for BackendWriteTransaction<'a> {
     fn get_idxmeta_ref(&self) -> &IdxMeta {
         &self.idxmeta_wr
     }
+
+    fn get_search_max_candidates(&self) -> Option<usize> {
+        self.search_max_candidates
+    }
+
+    fn get_backup_encryption_key(&self) -> Option<&DbEncryptionKey> {
+        self.backup_encryption_key
+    }
 }
 
 impl<'a> BackendWriteTransaction<'a> {
@@ -1014,6 +1668,95 @@ impl<'a> BackendWriteTransaction<'a> {
         &mut self,
         cid: &Cid,
         entries: Vec<EntrySealedNew>,
+    ) -> Result<Vec<EntrySealedCommitted>, OperationError> {
+        self.create_inner(cid, entries, true)
+    }
+
+    /// Create entries without indexing them, for bulk loads that [index](Self::index_created)
+    /// the entries once they are all created. Until then the entries can not be found by a search.
+    #[instrument(level = "debug", name = "be::create_unindexed", skip_all)]
+    pub fn create_unindexed(
+        &mut self,
+        cid: &Cid,
+        entries: Vec<EntrySealedNew>,
+    ) -> Result<Vec<EntrySealedCommitted>, OperationError> {
+        self.create_inner(cid, entries, false)
+    }
+
+    /// Add the index entries of entries created by [create_unindexed](Self::create_unindexed).
+    /// Only these entries are indexed, the rest of the database is untouched.
+    #[instrument(level = "debug", name = "be::index_created", skip_all)]
+    pub fn index_created(
+        &mut self,
+        entries: &[EntrySealedCommitted],
+    ) -> Result<(), OperationError> {
+        entries
+            .iter()
+            .try_for_each(|e| self.entry_index(None, Some(e)))?;
+        self.idlayer.optimise_dirty_idls();
+        Ok(())
+    }
+
+    /// Refuse a write that adds `count` live entries, or grows the database, once the database
+    /// has reached its quota. Deletes are never refused, so that space can be recovered. The
+    /// server does not apply this while it migrates, so that migrations can always complete.
+    pub(crate) fn check_quota(&mut self, count: usize) -> Result<(), OperationError> {
+        if let Some(max_entries) = self.max_entries.filter(|_| count > 0) {
+            let entries = self.count_live_entries()?;
+            if entries + count as u64 > max_entries {
+                admin_error!(
+                    %entries,
+                    %count,
+                    %max_entries,
+                    "Unable to create entries, the database would exceed db_max_entries"
+                );
+                return Err(OperationError::DB0006EntryQuotaExceeded);
+            }
+        }
+
+        if let Some(max_db_size) = self.max_db_size {
+            let db_size = self.get_idlayer().get_db_size_used()?;
+            if db_size >= max_db_size {
+                admin_error!(
+                    %db_size,
+                    %max_db_size,
+                    "Unable to create entries, the database has reached db_max_size"
+                );
+                return Err(OperationError::DB0007SizeQuotaExceeded);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The number of entries that are neither recycled nor tombstones, including those created
+    /// earlier in this transaction. Entries that are not yet indexed are counted as live.
+    pub(crate) fn count_live_entries(&mut self) -> Result<u64, OperationError> {
+        let mut deleted = IDLBitRange::new();
+        for class in [EntryClass::Recycled, EntryClass::Tombstone] {
+            if let Some(idl) = self.get_idlayer().get_idl(
+                Attribute::Class.as_ref(),
+                IndexType::Equality,
+                class.as_ref(),
+            )? {
+                deleted = deleted | idl;
+            }
+        }
+
+        Ok(self.get_idlayer().get_allids().andnot(deleted).len() as u64)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn set_quota(&mut self, max_entries: Option<u64>, max_db_size: Option<u64>) {
+        self.max_entries = max_entries;
+        self.max_db_size = max_db_size;
+    }
+
+    fn create_inner(
+        &mut self,
+        cid: &Cid,
+        entries: Vec<EntrySealedNew>,
+        index: bool,
     ) -> Result<Vec<EntrySealedCommitted>, OperationError> {
         if entries.is_empty() {
             admin_error!("No entries provided to BE to create, invalid server call!");
@@ -1055,9 +1798,15 @@ impl<'a> BackendWriteTransaction<'a> {
 
         self.idlayer.set_id2entry_max_id(id_max);
 
-        // Now update the indexes as required.
         for e in c_entries.iter() {
-            self.entry_index(None, Some(e))?
+            self.changelog_record(cid, ChangelogOperation::Create, e, BTreeSet::new());
+        }
+
+        // Now update the indexes as required.
+        if index {
+            for e in c_entries.iter() {
+                self.entry_index(None, Some(e))?
+            }
         }
 
         Ok(c_entries)
@@ -1094,6 +1843,12 @@ impl<'a> BackendWriteTransaction<'a> {
         // Update the RUV with all the changestates of the affected entries.
         for e in c_entries.iter() {
             self.get_ruv().update_entry_changestate(e)?;
+            self.changelog_record(
+                e.get_changestate().latest_cid(),
+                ChangelogOperation::Create,
+                e,
+                BTreeSet::new(),
+            );
         }
 
         // Now update the indexes as required.
@@ -1140,6 +1895,11 @@ impl<'a> BackendWriteTransaction<'a> {
         // Now, given the list of id's, update them
         self.get_idlayer().write_identries(post_entries.iter())?;
 
+        // Unlike the RUV, changes to attributes that are not replicated are recorded too.
+        for (pre, post) in pre_entries.iter().zip(post_entries.iter()) {
+            self.changelog_record(cid, ChangelogOperation::Modify, post, pre.diff_attrs(post));
+        }
+
         // Finally, we now reindex all the changed entries. We do this by iterating and zipping
         // over the set, because we know the list is in the same order.
         pre_entries
@@ -1250,6 +2010,12 @@ impl<'a> BackendWriteTransaction<'a> {
             // Update the RUV with all the changestates of the affected entries.
             for e in c_entries.iter() {
                 self.get_ruv().update_entry_changestate(e)?;
+                self.changelog_record(
+                    e.get_changestate().latest_cid(),
+                    ChangelogOperation::Create,
+                    e,
+                    BTreeSet::new(),
+                );
             }
 
             // Now update the indexes as required.
@@ -1263,8 +2029,15 @@ impl<'a> BackendWriteTransaction<'a> {
             self.get_idlayer()
                 .write_identries(update_entries.iter().map(|(up, _)| up))?;
 
-            for (e, _) in update_entries.iter() {
-                self.get_ruv().update_entry_changestate(e)?;
+            for (post, pre) in update_entries.iter() {
+                self.get_ruv().update_entry_changestate(post)?;
+                let attrs = pre.diff_attrs(post);
+                self.changelog_record(
+                    post.get_changestate().latest_cid(),
+                    ChangelogOperation::Modify,
+                    post,
+                    attrs,
+                );
             }
 
             for (post, pre) in update_entries.iter() {
@@ -1293,6 +2066,11 @@ impl<'a> BackendWriteTransaction<'a> {
             e
         })?;
 
+        // Changes older than the trim point are no longer needed, and would otherwise be kept
+        // forever.
+        let trimmed = self.get_idlayer().trim_db_changelog(trim_cid)?;
+        debug!(%trimmed, "Trimmed changelog");
+
         let entries = self
             .get_idlayer()
             .get_identry(&IdList::Indexed(idl))
@@ -1353,6 +2131,10 @@ impl<'a> BackendWriteTransaction<'a> {
         let sz = id_list.len();
         self.get_idlayer().delete_identry(id_list.into_iter())?;
 
+        for e in tombstones.iter() {
+            self.changelog_record(cid, ChangelogOperation::Delete, e, BTreeSet::new());
+        }
+
         // Finally, purge the indexes from the entries we removed. These still have
         // indexes due to class=tombstone.
         tombstones
@@ -1362,6 +2144,22 @@ impl<'a> BackendWriteTransaction<'a> {
         Ok(sz)
     }
 
+    fn changelog_record(
+        &mut self,
+        cid: &Cid,
+        operation: ChangelogOperation,
+        e: &EntrySealedCommitted,
+        attrs: BTreeSet<AttrString>,
+    ) {
+        self.changelog.push(ChangelogRecord {
+            cid: cid.clone(),
+            id: e.get_id(),
+            uuid: e.get_uuid(),
+            operation,
+            attrs,
+        });
+    }
+
     #[instrument(level = "debug", name = "be::update_idxmeta", skip_all)]
     pub fn update_idxmeta(&mut self, idxkeys: Vec<IdxKey>) -> Result<(), OperationError> {
         if self.is_idx_slopeyness_generated()? {
@@ -1665,6 +2463,7 @@ impl<'a> BackendWriteTransaction<'a> {
         })?;
 
         let mut count = 0;
+        let total = entries.len();
 
         entries
             .iter()
@@ -1672,6 +2471,9 @@ impl<'a> BackendWriteTransaction<'a> {
                 count += 1;
                 if count % 2500 == 0 {
                     limmediate_warning!("{}", count);
+                    // The progress is also logged, since the dots are not seen when a running
+                    // server is reindexed.
+                    admin_info!(count, total, "reindex progress");
                 } else if count % 250 == 0 {
                     limmediate_warning!(".");
                 }
@@ -1682,6 +2484,7 @@ impl<'a> BackendWriteTransaction<'a> {
                 e
             })?;
         limmediate_warning!("done ✅: reindexed {} entries\n", count);
+        admin_info!(count, "reindexed entries");
         limmediate_warning!("Optimising Indexes ... ");
         self.idlayer.optimise_dirty_idls();
         limmediate_warning!("done ✅\n");
@@ -1709,8 +2512,11 @@ impl<'a> BackendWriteTransaction<'a> {
     /// specific situations.
     pub(crate) fn danger_delete_all_db_content(&mut self) -> Result<(), OperationError> {
         self.get_ruv().clear();
+        // The ids in the changelog are about to be reused.
+        self.changelog.clear();
         self.get_idlayer()
             .danger_purge_id2entry()
+            .and_then(|_| self.get_idlayer().danger_purge_changelog())
             .and_then(|_| self.danger_purge_idxs())
     }
 
@@ -1738,28 +2544,30 @@ impl<'a> BackendWriteTransaction<'a> {
         Ok(slope)
     }
 
-    pub fn restore(&mut self, src_path: &str) -> Result<(), OperationError> {
-        let serialized_string = fs::read_to_string(src_path).map_err(|e| {
-            admin_error!("fs::read_to_string {:?}", e);
+    /// Restore the database from a backup. When a backup encryption key is configured, a
+    /// backup that can't be authenticated with it is refused unless `allow_unverified` is set.
+    pub fn restore(
+        &mut self,
+        src_path: &str,
+        allow_unverified: bool,
+    ) -> Result<(), OperationError> {
+        let archive = fs::read(src_path).map_err(|e| {
+            admin_error!("fs::read {:?}", e);
             OperationError::FsError
         })?;
 
+        // load all entries into RAM, may need to change this later
+        // if the size of the database compared to RAM is an issue
+
+        // The archive is verified before the database is changed.
+        let dbbak = dbarchive::open(&archive, self.backup_encryption_key, allow_unverified)?;
+
         self.danger_delete_all_db_content().map_err(|e| {
             admin_error!("delete_all_db_content failed {:?}", e);
             e
         })?;
 
         let idlayer = self.get_idlayer();
-        // load all entries into RAM, may need to change this later
-        // if the size of the database compared to RAM is an issue
-
-        let dbbak_option: Result<DbBackup, serde_json::Error> =
-            serde_json::from_str(&serialized_string);
-
-        let dbbak = dbbak_option.map_err(|e| {
-            admin_error!("serde_json error {:?}", e);
-            OperationError::SerdeJsonError
-        })?;
 
         let (dbentries, repl_meta, maybe_version) = match dbbak {
             DbBackup::V1(dbentries) => (dbentries, None, None),
@@ -1948,8 +2756,15 @@ impl<'a> BackendWriteTransaction<'a> {
             mut idlayer,
             idxmeta_wr,
             ruv,
+            search_max_candidates: _,
+            backup_encryption_key: _,
+            max_entries: _,
+            max_db_size: _,
+            changelog,
         } = self;
 
+        idlayer.write_db_changelog(changelog.iter())?;
+
         // write the ruv content back to the db.
         idlayer.write_db_ruv(ruv.added(), ruv.removed())?;
 
@@ -2040,6 +2855,7 @@ fn get_idx_slope_default(ikey: &IdxKey) -> IdxSlope {
         (ATTR_CLASS, IndexType::Equality) => 180,
         (_, IndexType::Equality) => 45,
         (_, IndexType::SubString) => 90,
+        (_, IndexType::Ordering) => 90,
         (_, IndexType::Presence) => 90,
     }
 }
@@ -2062,6 +2878,9 @@ impl Backend {
             cfg.pool_size = 1;
         }
 
+        cfg.check_tombstone_max_age(duration_from_epoch_now())?;
+        cfg.check_recyclebin_max_age(duration_from_epoch_now())?;
+
         // Setup idxkeys here. By default we set these all to "max slope" aka
         // all indexes are "equal" but also worse case unless analysed.
         //
@@ -2119,15 +2938,53 @@ impl Backend {
         self.cfg.pool_size
     }
 
+    /// The number of seconds after which tombstones and the change state of entries can be
+    /// removed, as replication no longer needs them to converge.
+    pub fn get_tombstone_max_age(&self) -> u64 {
+        self.cfg.tombstone_max_age.unwrap_or(CHANGELOG_MAX_AGE)
+    }
+
+    /// The number of seconds after which deleted entries are removed from the recycle bin,
+    /// and can no longer be revived.
+    pub fn get_recyclebin_max_age(&self) -> u64 {
+        self.cfg.recyclebin_max_age.unwrap_or(RECYCLEBIN_MAX_AGE)
+    }
+
+    /// The size limits on entries written by clients.
+    pub fn get_entry_limits(&self) -> EntryLimits {
+        self.cfg.entry_limits
+    }
+
     pub fn try_quiesce(&self) {
         self.idlayer.try_quiesce();
     }
 
+    /// Checkpoint the write ahead log into the database, and optionally vacuum the database to
+    /// release free pages. The caller must hold the write ticket, so that no write transaction
+    /// is open, and a db ticket so that a connection is available.
+    pub fn maintenance(&self, vacuum: bool) -> Result<DbMaintenanceResult, OperationError> {
+        self.idlayer.maintenance(vacuum)
+    }
+
+    /// Read the format versions of the database at `path`, without migrating or otherwise
+    /// modifying it.
+    pub fn read_format_version(path: &str) -> Result<DbFormatVersion, OperationError> {
+        IdlArcSqlite::read_format_version(path)
+    }
+
+    /// Copy a consistent snapshot of the database at `src` to `dst`, which must not exist. The
+    /// source is only read, so this is safe while the database is in use.
+    pub fn snapshot(src: &str, dst: &str) -> Result<(), OperationError> {
+        IdlArcSqlite::snapshot(src, dst)
+    }
+
     pub fn read(&self) -> Result<BackendReadTransaction, OperationError> {
         Ok(BackendReadTransaction {
             idlayer: self.idlayer.read()?,
             idxmeta: self.idxmeta.read(),
             ruv: self.ruv.read(),
+            search_max_candidates: self.cfg.search_max_candidates,
+            backup_encryption_key: self.cfg.backup_encryption_key.as_ref(),
         })
     }
 
@@ -2136,6 +2993,11 @@ impl Backend {
             idlayer: self.idlayer.write()?,
             idxmeta_wr: self.idxmeta.write(),
             ruv: self.ruv.write(),
+            search_max_candidates: self.cfg.search_max_candidates,
+            backup_encryption_key: self.cfg.backup_encryption_key.as_ref(),
+            max_entries: self.cfg.max_entries,
+            max_db_size: self.cfg.max_db_size,
+            changelog: Vec::new(),
         })
     }
 }
@@ -2144,6 +3006,7 @@ impl Backend {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
     use std::fs;
     use std::iter::FromIterator;
     use std::sync::Arc;
@@ -2154,9 +3017,10 @@ mod tests {
     use super::super::entry::{Entry, EntryInit, EntryNew};
     use super::Limits;
     use super::{
-        Backend, BackendConfig, BackendTransaction, BackendWriteTransaction, DbBackup, IdList,
-        IdxKey, OperationError,
+        Backend, BackendConfig, BackendTransaction, BackendWriteTransaction, ChangelogOperation,
+        ChangelogRecord, DbBackup, IdList, IdxKey, OperationError, SEARCH_CURSOR_BATCH_SIZE,
     };
+    use crate::filter::FilterPlan;
     use crate::prelude::*;
     use crate::repl::cid::Cid;
     use crate::value::{IndexType, PartialValue, Value};
@@ -2203,6 +3067,10 @@ mod tests {
                     attr: Attribute::TestNumber.into(),
                     itype: IndexType::Equality,
                 },
+                IdxKey {
+                    attr: Attribute::TestNumber.into(),
+                    itype: IndexType::Ordering,
+                },
             ];
 
             let be = Backend::new(BackendConfig::new_test("main"), idxmeta, false)
@@ -2551,7 +3419,8 @@ mod tests {
             }
 
             be.backup(&db_backup_file_name).expect("Backup failed!");
-            be.restore(&db_backup_file_name).expect("Restore failed!");
+            be.restore(&db_backup_file_name, false)
+                .expect("Restore failed!");
 
             assert!(be.verify().is_empty());
         });
@@ -2617,9 +3486,10 @@ mod tests {
             be.backup(&db_backup_file_name).expect("Backup failed!");
 
             // Now here, we need to tamper with the file.
-            let serialized_string = fs::read_to_string(&db_backup_file_name).unwrap();
-            trace!(?serialized_string);
-            let mut dbbak: DbBackup = serde_json::from_str(&serialized_string).unwrap();
+            let archive = fs::read(&db_backup_file_name).unwrap();
+            let split = archive.iter().position(|b| *b == b'\n').unwrap();
+            let (manifest, payload) = archive.split_at(split);
+            let mut dbbak: DbBackup = serde_json::from_slice(&payload[1..]).unwrap();
 
             match &mut dbbak {
                 DbBackup::V5 {
@@ -2639,10 +3509,25 @@ mod tests {
                 }
             };
 
+            // Any change to the payload, even only to its formatting, no longer matches the
+            // checksum of its manifest, so it is refused without changing the database.
+            let mut tampered = manifest.to_vec();
+            tampered.push(b'\n');
+            tampered.extend(serde_json::to_vec_pretty(&dbbak).unwrap());
+            fs::write(&db_backup_file_name, tampered).unwrap();
+
+            assert_eq!(
+                be.restore(&db_backup_file_name, false),
+                Err(OperationError::DB0004BackupChecksumMismatch)
+            );
+            assert!(entry_exists!(be, e3));
+
+            // A backup without a manifest can't be verified, but can still be restored.
             let serialized_entries_str = serde_json::to_string_pretty(&dbbak).unwrap();
             fs::write(&db_backup_file_name, serialized_entries_str).unwrap();
 
-            be.restore(&db_backup_file_name).expect("Restore failed!");
+            be.restore(&db_backup_file_name, false)
+                .expect("Restore failed!");
 
             assert!(be.verify().is_empty());
         });
@@ -2798,6 +3683,59 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_be_verify_indexes() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            assert!(be.reindex().is_ok());
+
+            let mut e1: Entry<EntryInit, EntryNew> = Entry::new();
+            e1.add_ava(Attribute::Name, Value::new_iname("william"));
+            e1.add_ava(
+                Attribute::Uuid,
+                Value::from("db237e8a-0079-4b8c-8a56-593b22aa44d1"),
+            );
+            let e1 = e1.into_sealed_new();
+
+            assert!(be.create(&CID_ZERO, vec![e1]).is_ok());
+            assert!(be.verify_indexes().is_empty());
+
+            // Damage the indexes so that one holds an id it should not, and another
+            // is missing the id of the entry.
+            be.get_idlayer()
+                .write_idl(
+                    Attribute::Name.as_ref(),
+                    IndexType::Equality,
+                    "william",
+                    &IDLBitRange::from_iter(vec![1, 2]),
+                )
+                .unwrap();
+            be.get_idlayer()
+                .write_idl(
+                    Attribute::Name.as_ref(),
+                    IndexType::Presence,
+                    "_",
+                    &IDLBitRange::new(),
+                )
+                .unwrap();
+
+            assert_eq!(
+                be.verify_indexes(),
+                vec![
+                    Err(ConsistencyError::BackendIndexStaleId {
+                        index: "idx_eq_name".to_string(),
+                        key: "william".to_string(),
+                        id: 2,
+                    }),
+                    Err(ConsistencyError::BackendIndexMissingId {
+                        index: "idx_pres_name".to_string(),
+                        key: "_".to_string(),
+                        id: 1,
+                    }),
+                ]
+            );
+        })
+    }
+
     #[test]
     fn test_be_index_create_delete_simple() {
         run_test!(|be: &mut BackendWriteTransaction| {
@@ -3153,6 +4091,64 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_be_index_search_substring() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            assert!(be.reindex().is_ok());
+
+            let entries: Vec<_> = ["william", "claire", "willow", "abcxbcd"]
+                .into_iter()
+                .map(|name| {
+                    let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                    e.add_ava(Attribute::Name, Value::new_iname(name));
+                    e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                    e.into_sealed_new()
+                })
+                .collect();
+            let mut rset = be.create(&CID_ZERO, entries).unwrap();
+
+            let sub =
+                |v: &str| filter_resolved!(f_sub(Attribute::Name, PartialValue::new_iname(v)));
+
+            // Candidates are the entries with every trigram of the value.
+            let (r, _plan) = be.filter2idl(sub("ill").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Partial(idl) if idl == IDLBitRange::from_iter(vec![1, 3])));
+
+            let (r, _plan) = be.filter2idl(sub("illi").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Partial(idl) if idl == IDLBitRange::from_iter(vec![1])));
+
+            // No entry has these trigrams.
+            let (r, _plan) = be.filter2idl(sub("xyz").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Indexed(idl) if idl.is_empty()));
+
+            // Shorter than a trigram, so the index can not be used.
+            let (r, _plan) = be.filter2idl(sub("wi").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::AllIds));
+
+            // An entry can have all the trigrams without containing the value.
+            let (r, _plan) = be.filter2idl(sub("abcd").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Partial(idl) if idl == IDLBitRange::from_iter(vec![4])));
+
+            let lims = Limits::unlimited();
+            assert!(be.search(&lims, &sub("abcd")).unwrap().is_empty());
+            assert!(be.search(&lims, &sub("WILL")).unwrap().len() == 2);
+
+            // Renaming an entry replaces its trigrams.
+            let pre = Arc::new(rset.remove(2));
+            let mut post = pre.as_ref().clone().into_invalid();
+            post.purge_ava(Attribute::Name);
+            post.add_ava(Attribute::Name, Value::new_iname("wallow"));
+            let post = post.into_sealed_committed();
+            assert!(be.modify(&CID_ONE, &[pre], &[post]).is_ok());
+
+            let (r, _plan) = be.filter2idl(sub("ill").to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Partial(idl) if idl == IDLBitRange::from_iter(vec![1])));
+            assert!(be.search(&lims, &sub("allo")).unwrap().len() == 1);
+
+            assert!(be.verify_indexes().is_empty());
+        });
+    }
+
     #[test]
     fn test_be_index_search_simple() {
         run_test!(|be: &mut BackendWriteTransaction| {
@@ -3448,6 +4444,47 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_be_index_search_ordering() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            assert!(be.reindex().is_ok());
+
+            let entries: Vec<_> = [1, 5, 10]
+                .into_iter()
+                .map(|n| {
+                    let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                    e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                    e.add_ava(Attribute::TestNumber, Value::Uint32(n));
+                    e.into_sealed_new()
+                })
+                .collect();
+            let _rset = be.create(&CID_ZERO, entries).unwrap();
+
+            // The index is resolved from the changes of this transaction, as they are not
+            // yet written to the database.
+            let f_lt = filter_resolved!(f_lt(Attribute::TestNumber, PartialValue::Uint32(5)));
+            let (r, plan) = be.filter2idl(f_lt.to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Indexed(idl) if idl == IDLBitRange::from_iter(vec![1])));
+            assert!(matches!(plan, FilterPlan::LessThanIndexed(..)));
+
+            let f_gt = filter_resolved!(f_gt(Attribute::TestNumber, PartialValue::Uint32(1)));
+            let (r, plan) = be.filter2idl(f_gt.to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Indexed(idl) if idl == IDLBitRange::from_iter(vec![2, 3])));
+            assert!(matches!(plan, FilterPlan::GreaterThanIndexed(..)));
+
+            // Ordering by value, not by the text of the value.
+            let f_gt = filter_resolved!(f_gt(Attribute::TestNumber, PartialValue::Uint32(9)));
+            let (r, _plan) = be.filter2idl(f_gt.to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::Indexed(idl) if idl == IDLBitRange::from_iter(vec![3])));
+
+            // Unindexed attributes still need every entry to be tested.
+            let f_un = filter_resolved!(f_lt(Attribute::NoIndex, PartialValue::Uint32(5)));
+            let (r, plan) = be.filter2idl(f_un.to_inner(), 0).unwrap();
+            assert!(matches!(r, IdList::AllIds));
+            assert!(matches!(plan, FilterPlan::LessThanUnindexed(..)));
+        });
+    }
+
     #[test]
     fn test_be_index_slope_generation() {
         run_test!(|be: &mut BackendWriteTransaction| {
@@ -3590,6 +4627,305 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_be_search_max_candidates() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            let entries: Vec<_> = (0..3)
+                .map(|i| {
+                    let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                    e.add_ava(Attribute::UserId, Value::from(format!("user{i}").as_str()));
+                    e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                    e.add_ava(Attribute::NonExist, Value::from("x"));
+                    e.into_sealed_new()
+                })
+                .collect();
+            assert!(be.create(&CID_ZERO, entries).is_ok());
+
+            let lims = Limits::unlimited();
+            let indexed = filter_resolved!(f_pres(Attribute::Uuid));
+            let unindexed =
+                filter_resolved!(f_eq(Attribute::NonExist, PartialValue::new_utf8s("y")));
+
+            // Exactly the limit is allowed.
+            be.search_max_candidates = Some(3);
+            assert_eq!(be.search(&lims, &indexed).map(|r| r.len()), Ok(3));
+            assert_eq!(be.exists(&lims, &unindexed), Ok(false));
+
+            // Beyond it the search is refused, even when it would match nothing.
+            be.search_max_candidates = Some(2);
+            assert_eq!(
+                be.search(&lims, &indexed).map(|r| r.len()),
+                Err(OperationError::DB0003SearchCandidateLimitExceeded)
+            );
+            assert_eq!(
+                be.search(&lims, &unindexed).map(|r| r.len()),
+                Err(OperationError::DB0003SearchCandidateLimitExceeded)
+            );
+            assert_eq!(
+                be.exists(&lims, &unindexed),
+                Err(OperationError::DB0003SearchCandidateLimitExceeded)
+            );
+
+            // Internal searches are exempt.
+            let lims = Limits::internal();
+            assert_eq!(be.search(&lims, &indexed).map(|r| r.len()), Ok(3));
+            assert_eq!(be.exists(&lims, &unindexed), Ok(false));
+        })
+    }
+
+    #[test]
+    fn test_be_quota() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            let new_entries = |n: usize| -> Vec<_> {
+                (0..n)
+                    .map(|_| {
+                        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                        e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                        e.into_sealed_new()
+                    })
+                    .collect()
+            };
+
+            be.max_entries = Some(3);
+            assert!(be.check_quota(2).is_ok());
+            assert!(be.create(&CID_ZERO, new_entries(2)).is_ok());
+            // The whole create is refused if it would exceed the quota.
+            assert_eq!(
+                be.check_quota(2),
+                Err(OperationError::DB0006EntryQuotaExceeded)
+            );
+            assert!(be.check_quota(1).is_ok());
+            let mut rset = be.create(&CID_ZERO, new_entries(1)).unwrap();
+            assert_eq!(
+                be.check_quota(1),
+                Err(OperationError::DB0006EntryQuotaExceeded)
+            );
+            // Entries can still grow while the entry count is at the quota.
+            assert!(be.check_quota(0).is_ok());
+
+            be.max_entries = None;
+            be.max_db_size = Some(1);
+            assert_eq!(
+                be.check_quota(1),
+                Err(OperationError::DB0007SizeQuotaExceeded)
+            );
+            assert_eq!(
+                be.check_quota(0),
+                Err(OperationError::DB0007SizeQuotaExceeded)
+            );
+
+            // The backend itself does not refuse changes, that is left to the server.
+            let pre = Arc::new(rset.remove(0));
+            let mut post = pre.as_ref().clone().into_invalid();
+            post.add_ava(Attribute::TestAttr, Value::from("changed"));
+            assert!(be
+                .modify(&CID_ONE, &[pre], &[post.into_sealed_committed()])
+                .is_ok());
+        })
+    }
+
+    #[test]
+    fn test_be_changelog() {
+        sketching::test_init();
+        let be = Backend::new(BackendConfig::new_test("main"), Vec::new(), false)
+            .expect("Failed to setup backend");
+
+        let mut be_txn = be.write().unwrap();
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::UserId, Value::from("william"));
+        e.add_ava(
+            Attribute::Uuid,
+            Value::from("db237e8a-0079-4b8c-8a56-593b22aa44d1"),
+        );
+        let mut created = be_txn
+            .create(&CID_ZERO, vec![e.into_sealed_new()])
+            .expect("Failed to create");
+        let pre = Arc::new(created.remove(0));
+        let mut post = pre.as_ref().clone().into_invalid();
+        post.add_ava(Attribute::TestAttr, Value::from("modified"));
+        be_txn
+            .modify(&CID_ONE, &[pre.clone()], &[post.into_sealed_committed()])
+            .expect("Failed to modify");
+
+        // Nothing is recorded until the commit.
+        assert!(be_txn.changelog(0).unwrap().is_empty());
+        be_txn.commit().unwrap();
+
+        let mut be_txn = be.read().unwrap();
+        let changelog = be_txn.changelog(0).unwrap();
+        assert_eq!(
+            changelog,
+            vec![
+                (
+                    1,
+                    ChangelogRecord {
+                        cid: CID_ZERO.clone(),
+                        id: pre.get_id(),
+                        uuid: pre.get_uuid(),
+                        operation: ChangelogOperation::Create,
+                        attrs: BTreeSet::new(),
+                    }
+                ),
+                (
+                    2,
+                    ChangelogRecord {
+                        cid: CID_ONE.clone(),
+                        id: pre.get_id(),
+                        uuid: pre.get_uuid(),
+                        operation: ChangelogOperation::Modify,
+                        attrs: BTreeSet::from([Attribute::TestAttr.into()]),
+                    }
+                ),
+            ]
+        );
+        assert_eq!(be_txn.changelog(1).unwrap(), changelog[1..]);
+        assert!(be_txn.changelog(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_be_tombstone_max_age_bounds() {
+        sketching::test_init();
+        let ct = Duration::from_secs(TOMBSTONE_MIN_AGE * 10);
+
+        for (age, valid) in [
+            (None, true),
+            (Some(0), false),
+            (Some(TOMBSTONE_MIN_AGE - 1), false),
+            (Some(TOMBSTONE_MIN_AGE), true),
+            (Some(ct.as_secs() - 1), true),
+            (Some(ct.as_secs()), false),
+            (Some(u64::MAX), false),
+        ] {
+            let cfg = BackendConfig::new_test("main").tombstone_max_age(age);
+            assert_eq!(cfg.check_tombstone_max_age(ct).is_ok(), valid, "{age:?}");
+        }
+
+        // The backend refuses to start with either extreme.
+        for age in [0, u64::MAX] {
+            let cfg = BackendConfig::new_test("main").tombstone_max_age(Some(age));
+            assert_eq!(
+                Backend::new(cfg, Vec::new(), false).err(),
+                Some(OperationError::DB0008InvalidTombstoneMaxAge)
+            );
+        }
+    }
+
+    #[test]
+    fn test_be_recyclebin_max_age_bounds() {
+        sketching::test_init();
+        let ct = Duration::from_secs(RECYCLEBIN_MIN_AGE * 10);
+
+        for (age, valid) in [
+            (None, true),
+            (Some(0), false),
+            (Some(RECYCLEBIN_MIN_AGE - 1), false),
+            (Some(RECYCLEBIN_MIN_AGE), true),
+            (Some(ct.as_secs() - 1), true),
+            (Some(ct.as_secs()), false),
+            (Some(u64::MAX), false),
+        ] {
+            let cfg = BackendConfig::new_test("main").recyclebin_max_age(age);
+            assert_eq!(cfg.check_recyclebin_max_age(ct).is_ok(), valid, "{age:?}");
+        }
+
+        for age in [0, u64::MAX] {
+            let cfg = BackendConfig::new_test("main").recyclebin_max_age(Some(age));
+            assert_eq!(
+                Backend::new(cfg, Vec::new(), false).err(),
+                Some(OperationError::DB0010InvalidRecycleBinMaxAge)
+            );
+        }
+    }
+
+    #[test]
+    fn test_be_changelog_trim() {
+        sketching::test_init();
+        let be = Backend::new(BackendConfig::new_test("main"), Vec::new(), false)
+            .expect("Failed to setup backend");
+
+        let mut be_txn = be.write().unwrap();
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::UserId, Value::from("william"));
+        e.add_ava(
+            Attribute::Uuid,
+            Value::from("3f3a3b8e-1b0c-4f4f-9d5e-4a7c1f3b2d10"),
+        );
+        let mut created = be_txn
+            .create(&CID_ZERO, vec![e.into_sealed_new()])
+            .expect("Failed to create");
+        let pre = Arc::new(created.remove(0));
+        let mut post = pre.as_ref().clone().into_invalid();
+        post.add_ava(Attribute::TestAttr, Value::from("one"));
+        let mut modified = be_txn
+            .modify(&CID_ONE, &[pre], &[post.into_sealed_committed()])
+            .expect("Failed to modify");
+        let pre = Arc::new(modified.remove(0));
+        let mut post = pre.as_ref().clone().into_invalid();
+        post.add_ava(Attribute::TestAttr, Value::from("two"));
+        be_txn
+            .modify(&CID_TWO, &[pre], &[post.into_sealed_committed()])
+            .expect("Failed to modify");
+        be_txn.commit().unwrap();
+
+        let mut be_txn = be.write().unwrap();
+        assert_eq!(be_txn.changelog(0).unwrap().len(), 3);
+
+        // Only the changes before the trim point are removed.
+        assert!(matches!(be_txn.reap_tombstones(&CID_ADV, &CID_TWO), Ok(0)));
+        be_txn.commit().unwrap();
+
+        let mut be_txn = be.read().unwrap();
+        let changelog = be_txn.changelog(0).unwrap();
+        assert_eq!(changelog.len(), 1);
+        assert_eq!(changelog[0].0, 3);
+        assert_eq!(changelog[0].1.cid, *CID_TWO);
+    }
+
+    #[test]
+    fn test_be_search_cursor() {
+        run_test!(|be: &mut BackendWriteTransaction| {
+            // Enough entries that the search spans several batches.
+            let entries: Vec<_> = (0..(SEARCH_CURSOR_BATCH_SIZE * 2 + 10))
+                .map(|i| {
+                    let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+                    e.add_ava(Attribute::UserId, Value::from(format!("user{i}").as_str()));
+                    e.add_ava(Attribute::Uuid, Value::Uuid(Uuid::new_v4()));
+                    if i % 2 == 0 {
+                        e.add_ava(Attribute::NonExist, Value::from("x"));
+                    }
+                    e.into_sealed_new()
+                })
+                .collect();
+            assert!(be.create(&CID_ZERO, entries).is_ok());
+
+            // Entries are yielded across batches, and only those that match the
+            // unindexed filter are returned.
+            let filt = filter_resolved!(f_eq(Attribute::NonExist, PartialValue::new_utf8s("x")));
+            let count = be
+                .search_cursor(&Limits::unlimited(), &filt)
+                .expect("failed to search")
+                .collect::<Result<Vec<_>, _>>()
+                .expect("failed to load entry")
+                .len();
+            assert_eq!(count, SEARCH_CURSOR_BATCH_SIZE + 5);
+
+            // Once the matches exceed the result limit the cursor ends with an error.
+            let mut lim = Limits::unlimited();
+            lim.search_max_results = 10;
+            let res: Vec<_> = be
+                .search_cursor(&lim, &filt)
+                .expect("failed to search")
+                .collect();
+            assert_eq!(res.len(), 11);
+            assert!(res[..10].iter().all(|r| r.is_ok()));
+            assert_eq!(res[10], Err(OperationError::ResourceLimit));
+            assert_eq!(
+                be.search(&lim, &filt).map(|r| r.len()),
+                Err(OperationError::ResourceLimit)
+            );
+        })
+    }
+
     #[test]
     fn test_be_limits_results_max() {
         run_test!(|be: &mut BackendWriteTransaction| {
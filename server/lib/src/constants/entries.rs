@@ -65,9 +65,11 @@ pub enum Attribute {
     ClassName,
     Cn,
     CookiePrivateKey,
+    CreatedAtCid,
     CredentialUpdateIntentToken,
     CredentialTypeMinimum,
     DeniedName,
+    Deprecated,
     Description,
     DirectMemberOf,
     DisplayName,
@@ -256,9 +258,11 @@ impl<'a> TryFrom<&'a str> for Attribute {
             ATTR_CLASSNAME => Attribute::ClassName,
             ATTR_CN => Attribute::Cn,
             ATTR_COOKIE_PRIVATE_KEY => Attribute::CookiePrivateKey,
+            ATTR_CREATED_AT_CID => Attribute::CreatedAtCid,
             ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN => Attribute::CredentialUpdateIntentToken,
             ATTR_CREDENTIAL_TYPE_MINIMUM => Attribute::CredentialTypeMinimum,
             ATTR_DENIED_NAME => Attribute::DeniedName,
+            ATTR_DEPRECATED => Attribute::Deprecated,
             ATTR_DESCRIPTION => Attribute::Description,
             ATTR_DIRECTMEMBEROF => Attribute::DirectMemberOf,
             ATTR_DISPLAYNAME => Attribute::DisplayName,
@@ -431,9 +435,11 @@ impl From<Attribute> for &'static str {
             Attribute::ClassName => ATTR_CLASSNAME,
             Attribute::Cn => ATTR_CN,
             Attribute::CookiePrivateKey => ATTR_COOKIE_PRIVATE_KEY,
+            Attribute::CreatedAtCid => ATTR_CREATED_AT_CID,
             Attribute::CredentialUpdateIntentToken => ATTR_CREDENTIAL_UPDATE_INTENT_TOKEN,
             Attribute::CredentialTypeMinimum => ATTR_CREDENTIAL_TYPE_MINIMUM,
             Attribute::DeniedName => ATTR_DENIED_NAME,
+            Attribute::Deprecated => ATTR_DEPRECATED,
             Attribute::Description => ATTR_DESCRIPTION,
             Attribute::DirectMemberOf => ATTR_DIRECTMEMBEROF,
             Attribute::DisplayName => ATTR_DISPLAYNAME,
@@ -22,8 +22,9 @@ use std::time::Duration;
 
 // This value no longer requires incrementing during releases. It only
 // serves as a "once off" marker so that we know when the initial db
-// index is performed on first-run.
-pub const SYSTEM_INDEX_VERSION: i64 = 31;
+// index is performed on first-run. It was last raised when substring indexes
+// began to be generated, as they were previously always empty.
+pub const SYSTEM_INDEX_VERSION: i64 = 32;
 
 /*
  * domain functional levels
@@ -111,6 +112,14 @@ pub const CHANGELOG_MAX_AGE: u64 = 600;
 /// A replica may be up to 7 days out of sync before being denied updates.
 pub const CHANGELOG_MAX_AGE: u64 = 7 * 86400;
 
+/// The shortest time tombstones may be kept. A replica must be able to see a tombstone at least
+/// once before it is purged, so this must not be less than the replication interval.
+pub const TOMBSTONE_MIN_AGE: u64 = 60;
+
+/// The shortest time deleted entries may stay in the recycle bin, so that an entry deleted by
+/// mistake can still be revived.
+pub const RECYCLEBIN_MIN_AGE: u64 = 60;
+
 #[cfg(test)]
 /// In test, we limit the recyclebin to 5 minutes.
 pub const RECYCLEBIN_MAX_AGE: u64 = 300;
@@ -163,8 +172,5 @@ pub const DEFAULT_LIMIT_API_SEARCH_MAX_FILTER_TEST: u64 = 16384;
 /// The maximum number of items in a filter, regardless of nesting level.
 pub const DEFAULT_LIMIT_FILTER_MAX_ELEMENTS: u64 = 32;
 
-/// The maximum amount of recursion allowed in a filter.
-pub const DEFAULT_LIMIT_FILTER_DEPTH_MAX: u64 = 12;
-
 /// The maximum number of sessions allowed on a single entry.
 pub(crate) const SESSION_MAXIMUM: usize = 48;
@@ -9,6 +9,7 @@ use rand::prelude::*;
 
 use crate::be::dbvalue::{DbTotpAlgoV1, DbTotpV1};
 use crate::repl::proto::{ReplTotpAlgoV1, ReplTotpV1};
+use crate::value::Redacted;
 
 // This is 64 bits of entropy, as the examples in https://tools.ietf.org/html/rfc6238 show.
 const SECRET_SIZE_BYTES: usize = 8;
@@ -87,7 +88,7 @@ impl TotpAlgo {
 }
 
 /// <https://tools.ietf.org/html/rfc6238> which relies on <https://tools.ietf.org/html/rfc4226>
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Totp {
     secret: Vec<u8>,
     pub(crate) step: u64,
@@ -95,6 +96,17 @@ pub struct Totp {
     digits: TotpDigits,
 }
 
+impl std::fmt::Debug for Totp {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Totp")
+            .field("secret", &Redacted)
+            .field("step", &self.step)
+            .field("algo", &self.algo)
+            .field("digits", &self.digits)
+            .finish()
+    }
+}
+
 impl TryFrom<DbTotpV1> for Totp {
     type Error = ();
 
@@ -168,16 +168,15 @@ pub struct EntryReduced {
 pub type Eattrs = Map<AttrString, ValueSet>;
 
 pub(crate) fn compare_attrs(left: &Eattrs, right: &Eattrs) -> bool {
-    // We can't shortcut based on len because cid mod may not be present.
+    // We can't shortcut based on len because cid mod or create may not be present.
     // Build the set of all keys between both.
+    let not_cid_attr = |k: &&AttrString| {
+        k != &Attribute::LastModifiedCid.as_ref() && k != &Attribute::CreatedAtCid.as_ref()
+    };
     let allkeys: Set<&str> = left
         .keys()
-        .filter(|k| k != &Attribute::LastModifiedCid.as_ref())
-        .chain(
-            right
-                .keys()
-                .filter(|k| k != &Attribute::LastModifiedCid.as_ref()),
-        )
+        .filter(not_cid_attr)
+        .chain(right.keys().filter(not_cid_attr))
         .map(|s| s.as_ref())
         .collect();
 
@@ -194,6 +193,41 @@ pub(crate) fn compare_attrs(left: &Eattrs, right: &Eattrs) -> bool {
     })
 }
 
+/// Limits on the size of entries written by clients. Unset limits are not enforced.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EntryLimits {
+    /// The most attributes an entry may have.
+    pub max_attributes: Option<usize>,
+    /// The most values a single attribute may have.
+    pub max_values: Option<usize>,
+    /// The most bytes a single value may use when stored.
+    pub max_value_bytes: Option<usize>,
+}
+
+/// Attributes that are derived or maintained by the server rather than supplied by clients.
+/// These are never counted against the entry limits, so that a write is not refused because
+/// of values the client did not give.
+const LIMITS_EXEMPT_ATTRS: [Attribute; 5] = [
+    Attribute::MemberOf,
+    Attribute::DirectMemberOf,
+    Attribute::Spn,
+    Attribute::LastModifiedCid,
+    Attribute::CreatedAtCid,
+];
+
+fn is_limits_exempt(attr: &str) -> bool {
+    LIMITS_EXEMPT_ATTRS.iter().any(|a| a.as_ref() == attr)
+}
+
+/// The number of bytes a value uses when it is stored.
+fn stored_value_size(v: &Value) -> usize {
+    valueset::from_value_iter(std::iter::once(v.clone()))
+        .ok()
+        .and_then(|vs| serde_json::to_vec(&vs.to_db_valueset_v2()).ok())
+        .map(|data| data.len())
+        .unwrap_or_default()
+}
+
 /// Entry is the core data storage type of the server. Almost every aspect of the server is
 /// designed to read, handle and manipulate entries.
 ///
@@ -527,12 +561,26 @@ impl Entry<EntryInit, EntryNew> {
     }
 
     /// Assign the Change Identifier to this Entry, allowing it to be modified and then
-    /// written to the `Backend`
+    /// written to the `Backend`. When `record_created` is set, the cid is also recorded as
+    /// the point the entry was created at, unless the create is `relaxed` and one was given.
     pub fn assign_cid(
-        self,
+        mut self,
         cid: Cid,
         schema: &dyn SchemaTransaction,
+        record_created: bool,
+        relaxed: bool,
     ) -> Entry<EntryInvalid, EntryNew> {
+        // Any value given by the creator is replaced, the server decides when an entry
+        // was created. Relaxed creates (imports) keep the value if one was given.
+        if !relaxed || !self.attrs.contains_key(Attribute::CreatedAtCid.as_ref()) {
+            if record_created {
+                self.attrs
+                    .insert(Attribute::CreatedAtCid.into(), vs_cid![cid.clone()]);
+            } else {
+                self.attrs.remove(Attribute::CreatedAtCid.as_ref());
+            }
+        }
+
         /*
          * Create the change log. This must be the last thing BEFORE we return!
          * This is because we need to capture the set_last_changed attribute in
@@ -1343,6 +1391,42 @@ impl<STATE> Entry<EntrySealed, STATE> {
             attrs: self.attrs,
         }
     }
+
+    /// Check that this entry is within the configured size limits, given the changes a client
+    /// `supplied` to it. Only the attributes and values the client supplied are checked, and
+    /// values are measured as they are stored.
+    pub(crate) fn check_limits<VALID>(
+        &self,
+        limits: &EntryLimits,
+        supplied: &ModifyList<VALID>,
+    ) -> Result<(), SchemaError> {
+        if let Some(max_attributes) = limits.max_attributes {
+            let count = self.attrs.keys().filter(|k| !is_limits_exempt(k)).count();
+            if count > max_attributes {
+                return Err(SchemaError::TooManyAttributes(count));
+            }
+        }
+
+        if let Some(max_values) = limits.max_values {
+            for attr in supplied.changed_attrs() {
+                let count = self.attrs.get(&attr).map(|vs| vs.len()).unwrap_or(0);
+                if count > max_values && !is_limits_exempt(&attr) {
+                    return Err(SchemaError::TooManyValues(attr.to_string()));
+                }
+            }
+        }
+
+        if let Some(max_value_bytes) = limits.max_value_bytes {
+            if let Some((attr, _)) = supplied
+                .present_values()
+                .find(|(_, v)| stored_value_size(v) > max_value_bytes)
+            {
+                return Err(SchemaError::ValueTooLarge(attr.to_string()));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Entry<EntrySealed, EntryCommitted> {
@@ -1646,7 +1730,16 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     IndexType::Presence => {
                                         vec![Err((&ikey.attr, ikey.itype, "_".to_string()))]
                                     }
-                                    IndexType::SubString => Vec::with_capacity(0),
+                                    IndexType::SubString => vs
+                                        .generate_idx_sub_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Err((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
+                                    IndexType::Ordering => vs
+                                        .generate_idx_ord_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Err((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
                                 };
                                 changes
                             }
@@ -1682,7 +1775,16 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     IndexType::Presence => {
                                         vec![Ok((&ikey.attr, ikey.itype, "_".to_string()))]
                                     }
-                                    IndexType::SubString => Vec::with_capacity(0),
+                                    IndexType::SubString => vs
+                                        .generate_idx_sub_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Ok((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
+                                    IndexType::Ordering => vs
+                                        .generate_idx_ord_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Ok((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
                                 };
                                 // For each value
                                 //
@@ -1728,7 +1830,16 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     IndexType::Presence => {
                                         vec![Err((&ikey.attr, ikey.itype, "_".to_string()))]
                                     }
-                                    IndexType::SubString => Vec::with_capacity(0),
+                                    IndexType::SubString => pre_vs
+                                        .generate_idx_sub_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Err((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
+                                    IndexType::Ordering => pre_vs
+                                        .generate_idx_ord_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Err((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
                                 };
                                 changes
                             }
@@ -1747,16 +1858,37 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     IndexType::Presence => {
                                         vec![Ok((&ikey.attr, ikey.itype, "_".to_string()))]
                                     }
-                                    IndexType::SubString => Vec::with_capacity(0),
+                                    IndexType::SubString => post_vs
+                                        .generate_idx_sub_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Ok((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
+                                    IndexType::Ordering => post_vs
+                                        .generate_idx_ord_keys()
+                                        .into_iter()
+                                        .map(|idx_key| Ok((&ikey.attr, ikey.itype, idx_key)))
+                                        .collect(),
                                 };
                                 changes
                             }
                             (Some(pre_vs), Some(post_vs)) => {
                                 // it exists in both, we need to work out the difference within the attr.
 
-                                let mut pre_idx_keys = pre_vs.generate_idx_eq_keys();
+                                let (mut pre_idx_keys, mut post_idx_keys) = match ikey.itype {
+                                    IndexType::SubString => (
+                                        pre_vs.generate_idx_sub_keys(),
+                                        post_vs.generate_idx_sub_keys(),
+                                    ),
+                                    IndexType::Ordering => (
+                                        pre_vs.generate_idx_ord_keys(),
+                                        post_vs.generate_idx_ord_keys(),
+                                    ),
+                                    _ => (
+                                        pre_vs.generate_idx_eq_keys(),
+                                        post_vs.generate_idx_eq_keys(),
+                                    ),
+                                };
                                 pre_idx_keys.sort_unstable();
-                                let mut post_idx_keys = post_vs.generate_idx_eq_keys();
                                 post_idx_keys.sort_unstable();
 
                                 let sz = if pre_idx_keys.len() > post_idx_keys.len() {
@@ -1812,7 +1944,9 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     Vec::with_capacity(removed_vs.len() + added_vs.len());
 
                                 match ikey.itype {
-                                    IndexType::Equality => {
+                                    IndexType::Equality
+                                    | IndexType::SubString
+                                    | IndexType::Ordering => {
                                         removed_vs
                                             .into_iter()
                                             .map(|idx_key| Err((&ikey.attr, ikey.itype, idx_key)))
@@ -1825,7 +1959,6 @@ impl Entry<EntrySealed, EntryCommitted> {
                                     IndexType::Presence => {
                                         // No action - we still are "present", so nothing to do!
                                     }
-                                    IndexType::SubString => {}
                                 };
                                 // Return the diff
                                 diff
@@ -2972,6 +3105,16 @@ impl<VALID, STATE> Entry<VALID, STATE> {
             .unwrap_or(false)
     }
 
+    #[inline(always)]
+    /// Assert if an attribute of this name is present, and one of its values is greater than
+    /// the following partial value
+    pub fn attribute_greaterthan(&self, attr: Attribute, subvalue: &PartialValue) -> bool {
+        self.attrs
+            .get(attr.as_ref())
+            .map(|vset| vset.greaterthan(subvalue))
+            .unwrap_or(false)
+    }
+
     // Since EntryValid/Invalid is just about class adherenece, not Value correctness, we
     // can now apply filters to invalid entries - why? Because even if they aren't class
     // valid, we still have strict typing checks between the filter -> entry to guarantee
@@ -3032,6 +3175,13 @@ impl<VALID, STATE> Entry<VALID, STATE> {
                     false
                 }
             },
+            FilterResolved::GreaterThan(attr, subvalue, _) => match attr.try_into() {
+                Ok(a) => self.attribute_greaterthan(a, subvalue),
+                Err(_) => {
+                    admin_error!("Failed to convert {} to attribute!", attr);
+                    false
+                }
+            },
             // Check with ftweedal about or filter zero len correctness.
             FilterResolved::Or(l, _) => l.iter().any(|f| self.entry_match_no_index_inner(f)),
             // Check with ftweedal about and filter zero len correctness.
@@ -3130,6 +3280,97 @@ impl<VALID, STATE> Entry<VALID, STATE> {
         Ok(mods)
     }
 
+    /// The attributes whose values differ between this entry and `other`. These are the
+    /// attributes changed by [`gen_modlist_diff`](Self::gen_modlist_diff), found by comparing
+    /// the valuesets without generating the modifications.
+    pub fn diff_attrs<VALIDO, STATEO>(
+        &self,
+        other: &Entry<VALIDO, STATEO>,
+    ) -> BTreeSet<AttrString> {
+        self.attrs
+            .iter()
+            .filter(|(k, vs)| other.attrs.get(*k) != Some(*vs))
+            .map(|(k, _)| k)
+            .chain(other.attrs.keys().filter(|k| !self.attrs.contains_key(*k)))
+            .cloned()
+            .collect()
+    }
+
+    /// Given this entry, generate the modification list that would change it into the state
+    /// of `other`. Only the values that differ are present in the list, so that it describes
+    /// exactly what changed between two versions of an entry. A value that keeps its partial
+    /// value but changes its content, such as a credential that is updated in place, is removed
+    /// and then added again.
+    pub fn gen_modlist_diff<VALIDO, STATEO>(
+        &self,
+        other: &Entry<VALIDO, STATEO>,
+    ) -> ModifyList<ModifyInvalid> {
+        let mut mods = ModifyList::new();
+
+        for (k, vs) in self.attrs.iter() {
+            let Some(other_vs) = other.attrs.get(k) else {
+                mods.push_mod(Modify::Purged(k.clone()));
+                continue;
+            };
+
+            // Valueset equality compares the full content of the values.
+            if vs == other_vs {
+                continue;
+            }
+
+            // Both iterators walk the same set, so each value is paired with its own
+            // partial value.
+            let values: BTreeMap<_, _> =
+                vs.to_partialvalue_iter().zip(vs.to_value_iter()).collect();
+            let other_values: BTreeMap<_, _> = other_vs
+                .to_partialvalue_iter()
+                .zip(other_vs.to_value_iter())
+                .collect();
+
+            values
+                .iter()
+                .filter(|(pv, v)| {
+                    !other_values
+                        .get(*pv)
+                        .map(|ov| Self::value_equal(v, ov))
+                        .unwrap_or(false)
+                })
+                .for_each(|(pv, _)| mods.push_mod(Modify::Removed(k.clone(), pv.clone())));
+
+            other_values
+                .into_iter()
+                .filter(|(pv, ov)| {
+                    !values
+                        .get(pv)
+                        .map(|v| Self::value_equal(v, ov))
+                        .unwrap_or(false)
+                })
+                .for_each(|(_, ov)| mods.push_mod(Modify::Present(k.clone(), ov)));
+        }
+
+        for (k, other_vs) in other.attrs.iter() {
+            if !self.attrs.contains_key(k) {
+                other_vs
+                    .to_value_iter()
+                    .for_each(|v| mods.push_mod(Modify::Present(k.clone(), v)));
+            }
+        }
+
+        mods
+    }
+
+    /// Value equality only compares the identifying part of some values, such as the label of
+    /// a credential, so the values are compared as valuesets which compare their full content.
+    fn value_equal(v: &Value, other: &Value) -> bool {
+        match (
+            valueset::from_value_iter(std::iter::once(v.clone())),
+            valueset::from_value_iter(std::iter::once(other.clone())),
+        ) {
+            (Ok(vs), Ok(other_vs)) => vs == other_vs,
+            _ => false,
+        }
+    }
+
     /// Determine if this entry is recycled or a tombstone, and map that to "None". This allows
     /// filter_map to effectively remove entries that should not be considered as "alive".
     pub fn mask_recycled_ts(&self) -> Option<&Self> {
@@ -3400,6 +3641,10 @@ impl From<&SchemaAttribute> for Entry<EntryInit, EntryNew> {
         attrs.insert(Attribute::SyncAllowed.into(), sync_allowed_v);
         attrs.insert(Attribute::Replicated.into(), replicated_v);
         attrs.insert(Attribute::Unique.into(), unique_v);
+        // Only recorded when set, as the attribute is unknown before domain level 8.
+        if s.deprecated {
+            attrs.insert(Attribute::Deprecated.into(), vs_bool![true]);
+        }
         if let Some(vs) = index_v {
             attrs.insert(Attribute::Index.into(), vs);
         }
@@ -3435,6 +3680,9 @@ impl From<&SchemaClass> for Entry<EntryInit, EntryNew> {
         attrs.insert(Attribute::ClassName.into(), name_v);
         attrs.insert(Attribute::Description.into(), desc_v);
         attrs.insert(Attribute::SyncAllowed.into(), sync_allowed_v);
+        if s.deprecated {
+            attrs.insert(Attribute::Deprecated.into(), vs_bool![true]);
+        }
         attrs.insert(Attribute::Uuid.into(), uuid_v);
         attrs.insert(
             Attribute::Class.into(),
@@ -3470,8 +3718,10 @@ mod tests {
     use std::collections::BTreeSet as Set;
 
     use hashbrown::HashMap;
+    use kanidm_lib_crypto::CryptoPolicy;
 
     use crate::be::{IdxKey, IdxSlope};
+    use crate::credential::Credential;
     use crate::entry::{Entry, EntryInit, EntryInvalid, EntryNew};
     use crate::modify::{Modify, ModifyList};
     use crate::value::{IndexType, PartialValue, Value};
@@ -3659,6 +3909,63 @@ mod tests {
         assert!(e.attrs.get(Attribute::Attr.as_ref()).is_none());
     }
 
+    #[test]
+    fn test_entry_gen_modlist_diff() {
+        let mut e1: Entry<EntryInvalid, EntryNew> = Entry::new().into_invalid_new();
+        e1.add_ava(Attribute::UserId, Value::from("william"));
+        e1.add_ava(Attribute::Class, Value::new_iutf8("test"));
+        e1.add_ava(Attribute::Class, Value::new_iutf8("multi_test"));
+        e1.add_ava(Attribute::Attr, Value::new_iutf8("value"));
+
+        let mut e2 = e1.clone();
+        e2.remove_ava(Attribute::Class, &PartialValue::new_iutf8("test"));
+        e2.add_ava(Attribute::Class, Value::new_iutf8("other_test"));
+        e2.purge_ava(Attribute::Attr);
+        e2.add_ava(Attribute::Extra, Value::from("extra"));
+
+        // An entry has no difference to itself.
+        assert!(e1.gen_modlist_diff(&e1).is_empty());
+        assert!(e1.diff_attrs(&e1).is_empty());
+
+        let attrs = e1.diff_attrs(&e2);
+        assert!(attrs
+            .iter()
+            .map(|a| a.as_str())
+            .eq(["attr", "class", "extra"]));
+
+        let modlist = e1.gen_modlist_diff(&e2);
+        assert_eq!(modlist.len(), 4);
+
+        assert!(e1.apply_modlist(&modlist.into_valid()).is_ok());
+        assert!(e1.gen_modlist_diff(&e2).is_empty());
+        assert!(e1.diff_attrs(&e2).is_empty());
+        assert!(e1.attribute_equality(Attribute::Class, &PartialValue::new_iutf8("other_test")));
+        assert!(!e1.attribute_equality(Attribute::Class, &PartialValue::new_iutf8("test")));
+        assert!(!e1.attribute_pres(Attribute::Attr));
+        assert!(e1.attribute_pres(Attribute::Extra));
+
+        // A credential that keeps its label but changes its content is replaced.
+        let p = CryptoPolicy::minimum();
+        let cred_a = Credential::new_password_only(&p, "test_password_a").unwrap();
+        let cred_b = Credential::new_password_only(&p, "test_password_b").unwrap();
+        let mut e3 = e1.clone();
+        e3.add_ava(
+            Attribute::PrimaryCredential,
+            Value::new_credential("primary", cred_a),
+        );
+        let mut e4 = e1.clone();
+        e4.add_ava(
+            Attribute::PrimaryCredential,
+            Value::new_credential("primary", cred_b),
+        );
+
+        let modlist = e3.gen_modlist_diff(&e4);
+        assert_eq!(modlist.len(), 2);
+        assert_eq!(e3.diff_attrs(&e4).len(), 1);
+        assert!(e3.apply_modlist(&modlist.into_valid()).is_ok());
+        assert!(e3.gen_modlist_diff(&e4).is_empty());
+    }
+
     #[test]
     fn test_entry_idx_diff() {
         let mut e1: Entry<EntryInit, EntryNew> = Entry::new();
@@ -3995,4 +4302,67 @@ mod tests {
                 == Some(Ok("spn=renameperson@example.com".to_string()))
         );
     }
+
+    #[test]
+    fn test_entry_check_limits() {
+        let mut e: Entry<EntryInit, EntryNew> = Entry::new();
+        e.add_ava(Attribute::Name, Value::new_iname("testperson"));
+        e.add_ava(
+            Attribute::Mail,
+            Value::EmailAddress("a@example.com".to_string(), true),
+        );
+        e.add_ava(
+            Attribute::Mail,
+            Value::EmailAddress("b@example.com".to_string(), false),
+        );
+        // A client creating this entry supplied all of its values.
+        let supplied = Entry::new().gen_modlist_diff(&e);
+
+        // Values maintained by the server are never limited.
+        e.add_ava(Attribute::MemberOf, Value::Refer(Uuid::new_v4()));
+        e.add_ava(Attribute::MemberOf, Value::Refer(Uuid::new_v4()));
+        e.add_ava(Attribute::MemberOf, Value::Refer(Uuid::new_v4()));
+        let e = e.into_sealed_new();
+
+        assert!(e.check_limits(&EntryLimits::default(), &supplied).is_ok());
+
+        let limits = EntryLimits {
+            max_attributes: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            e.check_limits(&limits, &supplied),
+            Err(SchemaError::TooManyAttributes(2))
+        );
+
+        let limits = EntryLimits {
+            max_values: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            e.check_limits(&limits, &supplied),
+            Err(SchemaError::TooManyValues(Attribute::Mail.to_string()))
+        );
+        // Only the attributes the client changed are checked.
+        let supplied_name =
+            ModifyList::new_purge_and_set(Attribute::Name, Value::new_iname("testperson"));
+        assert!(e.check_limits(&limits, &supplied_name).is_ok());
+
+        // Values are measured as they are stored, which is larger than the value itself.
+        let limits = EntryLimits {
+            max_value_bytes: Some(13),
+            ..Default::default()
+        };
+        assert_eq!(
+            e.check_limits(&limits, &supplied),
+            Err(SchemaError::ValueTooLarge(Attribute::Mail.to_string()))
+        );
+
+        let limits = EntryLimits {
+            max_attributes: Some(2),
+            max_values: Some(2),
+            max_value_bytes: Some(128),
+        };
+        assert!(e.check_limits(&limits, &supplied).is_ok());
+    }
 }
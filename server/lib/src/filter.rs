@@ -55,6 +55,10 @@ pub fn f_sub<'a>(a: Attribute, v: PartialValue) -> FC<'a> {
     FC::Cnt(a.into(), v)
 }
 
+pub fn f_stw<'a>(a: Attribute, v: PartialValue) -> FC<'a> {
+    FC::Stw(a.into(), v)
+}
+
 pub fn f_pres<'a>(a: Attribute) -> FC<'a> {
     FC::Pres(a.into())
 }
@@ -63,6 +67,10 @@ pub fn f_lt<'a>(a: Attribute, v: PartialValue) -> FC<'a> {
     FC::LessThan(a.into(), v)
 }
 
+pub fn f_gt<'a>(a: Attribute, v: PartialValue) -> FC<'a> {
+    FC::GreaterThan(a.into(), v)
+}
+
 pub fn f_or(vs: Vec<FC>) -> FC {
     FC::Or(vs)
 }
@@ -110,8 +118,10 @@ pub fn f_spn_name(id: &str) -> FC<'static> {
 pub enum FC<'a> {
     Eq(&'a str, PartialValue),
     Cnt(&'a str, PartialValue),
+    Stw(&'a str, PartialValue),
     Pres(&'a str),
     LessThan(&'a str, PartialValue),
+    GreaterThan(&'a str, PartialValue),
     Or(Vec<FC<'a>>),
     And(Vec<FC<'a>>),
     Inclusion(Vec<FC<'a>>),
@@ -130,6 +140,7 @@ enum FilterComp {
     Enw(AttrString, PartialValue),
     Pres(AttrString),
     LessThan(AttrString, PartialValue),
+    GreaterThan(AttrString, PartialValue),
     Or(Vec<FilterComp>),
     And(Vec<FilterComp>),
     Inclusion(Vec<FilterComp>),
@@ -160,6 +171,9 @@ impl fmt::Debug for FilterComp {
             FilterComp::LessThan(attr, pv) => {
                 write!(f, "{} lt {:?}", attr, pv)
             }
+            FilterComp::GreaterThan(attr, pv) => {
+                write!(f, "{} gt {:?}", attr, pv)
+            }
             FilterComp::And(list) => {
                 write!(f, "(")?;
                 for (i, fc) in list.iter().enumerate() {
@@ -219,6 +233,7 @@ pub enum FilterResolved {
     Enw(AttrString, PartialValue, Option<NonZeroU8>),
     Pres(AttrString, Option<NonZeroU8>),
     LessThan(AttrString, PartialValue, Option<NonZeroU8>),
+    GreaterThan(AttrString, PartialValue, Option<NonZeroU8>),
     Or(Vec<FilterResolved>, Option<NonZeroU8>),
     And(Vec<FilterResolved>, Option<NonZeroU8>),
     // All terms must have 1 or more items, or the inclusion is false!
@@ -277,6 +292,15 @@ impl fmt::Debug for FilterResolved {
                     pv
                 )
             }
+            FilterResolved::GreaterThan(attr, pv, idx) => {
+                write!(
+                    f,
+                    "(s{} {} gt {:?})",
+                    idx.unwrap_or(NonZeroU8::MAX),
+                    attr,
+                    pv
+                )
+            }
             FilterResolved::And(list, idx) => {
                 write!(f, "(s{} ", idx.unwrap_or(NonZeroU8::MAX))?;
                 for (i, fc) in list.iter().enumerate() {
@@ -343,7 +367,12 @@ pub enum FilterPlan {
     PresIndexed(AttrString),
     PresUnindexed(AttrString),
     PresCorrupt(AttrString),
+    LessThanIndexed(AttrString, String),
     LessThanUnindexed(AttrString),
+    LessThanCorrupt(AttrString),
+    GreaterThanIndexed(AttrString, String),
+    GreaterThanUnindexed(AttrString),
+    GreaterThanCorrupt(AttrString),
     OrUnindexed(Vec<FilterPlan>),
     OrIndexed(Vec<FilterPlan>),
     OrPartial(Vec<FilterPlan>),
@@ -615,6 +644,7 @@ impl Filter<FilterInvalid> {
             (Attribute::MemberOf.into(), IndexType::Presence),
             (Attribute::DirectMemberOf.into(), IndexType::Equality),
             (Attribute::DirectMemberOf.into(), IndexType::Presence),
+            (Attribute::TestNumber.into(), IndexType::Ordering),
         ];
 
         let idxmeta_ref = idxmeta.iter().map(|(attr, itype)| (attr, itype)).collect();
@@ -722,8 +752,10 @@ impl FilterComp {
         match fc {
             FC::Eq(a, v) => FilterComp::Eq(AttrString::from(a), v),
             FC::Cnt(a, v) => FilterComp::Cnt(AttrString::from(a), v),
+            FC::Stw(a, v) => FilterComp::Stw(AttrString::from(a), v),
             FC::Pres(a) => FilterComp::Pres(AttrString::from(a)),
             FC::LessThan(a, v) => FilterComp::LessThan(AttrString::from(a), v),
+            FC::GreaterThan(a, v) => FilterComp::GreaterThan(AttrString::from(a), v),
             FC::Or(v) => FilterComp::Or(v.into_iter().map(FilterComp::new).collect()),
             FC::And(v) => FilterComp::And(v.into_iter().map(FilterComp::new).collect()),
             FC::Inclusion(v) => FilterComp::Inclusion(v.into_iter().map(FilterComp::new).collect()),
@@ -756,7 +788,8 @@ impl FilterComp {
             | FilterComp::Stw(attr, _)
             | FilterComp::Enw(attr, _)
             | FilterComp::Pres(attr)
-            | FilterComp::LessThan(attr, _) => {
+            | FilterComp::LessThan(attr, _)
+            | FilterComp::GreaterThan(attr, _) => {
                 r_set.insert(attr.as_str());
             }
             FilterComp::Or(vs) => vs.iter().for_each(|f| f.get_attr_set(r_set)),
@@ -868,6 +901,15 @@ impl FilterComp {
                     None => Err(SchemaError::InvalidAttribute(attr_norm.to_string())),
                 }
             }
+            FilterComp::GreaterThan(attr, value) => {
+                let attr_norm = schema.normalise_attr_name(attr);
+                match schema_attributes.get(&attr_norm) {
+                    Some(schema_a) => schema_a
+                        .validate_partialvalue(attr_norm.as_str(), value)
+                        .map(|_| FilterComp::GreaterThan(attr_norm, value.clone())),
+                    None => Err(SchemaError::InvalidAttribute(attr_norm.to_string())),
+                }
+            }
             FilterComp::Or(filters) => {
                 // * If all filters are okay, return Ok(Filter::Or())
                 // * Any filter is invalid, return the error.
@@ -940,10 +982,25 @@ impl FilterComp {
                 let v = qs.clone_partialvalue(nk.as_str(), v)?;
                 FilterComp::Cnt(nk, v)
             }
+            ProtoFilter::Stw(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::Stw(nk, v)
+            }
             ProtoFilter::Pres(a) => {
                 let nk = qs.get_schema().normalise_attr_name(a);
                 FilterComp::Pres(nk)
             }
+            ProtoFilter::Lt(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::LessThan(nk, v)
+            }
+            ProtoFilter::Gt(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::GreaterThan(nk, v)
+            }
             ProtoFilter::Or(l) => {
                 *elems = (*elems)
                     .checked_sub(l.len())
@@ -992,10 +1049,25 @@ impl FilterComp {
                 let v = qs.clone_partialvalue(nk.as_str(), v)?;
                 FilterComp::Cnt(nk, v)
             }
+            ProtoFilter::Stw(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::Stw(nk, v)
+            }
             ProtoFilter::Pres(a) => {
                 let nk = qs.get_schema().normalise_attr_name(a);
                 FilterComp::Pres(nk)
             }
+            ProtoFilter::Lt(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::LessThan(nk, v)
+            }
+            ProtoFilter::Gt(a, v) => {
+                let nk = qs.get_schema().normalise_attr_name(a);
+                let v = qs.clone_partialvalue(nk.as_str(), v)?;
+                FilterComp::GreaterThan(nk, v)
+            }
             ProtoFilter::Or(l) => {
                 *elems = (*elems)
                     .checked_sub(l.len())
@@ -1096,13 +1168,21 @@ impl FilterComp {
 
                 FilterComp::And(terms)
             }
-            LdapFilter::GreaterOrEqual(_, _) => {
-                admin_error!("Unsupported filter operation - greater or equal");
-                return Err(OperationError::FilterGeneration);
+            LdapFilter::GreaterOrEqual(a, v) => {
+                let a = ldap_attr_filter_map(a);
+                let v = qs.clone_partialvalue(a.as_str(), v)?;
+                FilterComp::Or(vec![
+                    FilterComp::Eq(a.clone(), v.clone()),
+                    FilterComp::GreaterThan(a, v),
+                ])
             }
-            LdapFilter::LessOrEqual(_, _) => {
-                admin_error!("Unsupported filter operation - less or equal");
-                return Err(OperationError::FilterGeneration);
+            LdapFilter::LessOrEqual(a, v) => {
+                let a = ldap_attr_filter_map(a);
+                let v = qs.clone_partialvalue(a.as_str(), v)?;
+                FilterComp::Or(vec![
+                    FilterComp::Eq(a.clone(), v.clone()),
+                    FilterComp::LessThan(a, v),
+                ])
             }
             LdapFilter::Approx(_, _) => {
                 admin_error!("Unsupported filter operation - approximate");
@@ -1152,11 +1232,14 @@ impl PartialEq for FilterResolved {
     fn eq(&self, rhs: &FilterResolved) -> bool {
         match (self, rhs) {
             (FilterResolved::Eq(a1, v1, _), FilterResolved::Eq(a2, v2, _)) => a1 == a2 && v1 == v2,
-            (FilterResolved::Cnt(a1, v1, _), FilterResolved::Cnt(a2, v2, _)) => {
+            (FilterResolved::Cnt(a1, v1, _), FilterResolved::Cnt(a2, v2, _))
+            | (FilterResolved::Stw(a1, v1, _), FilterResolved::Stw(a2, v2, _))
+            | (FilterResolved::Enw(a1, v1, _), FilterResolved::Enw(a2, v2, _)) => {
                 a1 == a2 && v1 == v2
             }
             (FilterResolved::Pres(a1, _), FilterResolved::Pres(a2, _)) => a1 == a2,
-            (FilterResolved::LessThan(a1, v1, _), FilterResolved::LessThan(a2, v2, _)) => {
+            (FilterResolved::LessThan(a1, v1, _), FilterResolved::LessThan(a2, v2, _))
+            | (FilterResolved::GreaterThan(a1, v1, _), FilterResolved::GreaterThan(a2, v2, _)) => {
                 a1 == a2 && v1 == v2
             }
             (FilterResolved::And(vs1, _), FilterResolved::And(vs2, _)) => vs1 == vs2,
@@ -1197,12 +1280,16 @@ impl Ord for FilterResolved {
             match (self, rhs) {
                 (FilterResolved::Eq(a1, v1, _), FilterResolved::Eq(a2, v2, _))
                 | (FilterResolved::Cnt(a1, v1, _), FilterResolved::Cnt(a2, v2, _))
-                | (FilterResolved::LessThan(a1, v1, _), FilterResolved::LessThan(a2, v2, _)) => {
-                    match a1.cmp(a2) {
-                        Ordering::Equal => v1.cmp(v2),
-                        o => o,
-                    }
-                }
+                | (FilterResolved::Stw(a1, v1, _), FilterResolved::Stw(a2, v2, _))
+                | (FilterResolved::Enw(a1, v1, _), FilterResolved::Enw(a2, v2, _))
+                | (FilterResolved::LessThan(a1, v1, _), FilterResolved::LessThan(a2, v2, _))
+                | (
+                    FilterResolved::GreaterThan(a1, v1, _),
+                    FilterResolved::GreaterThan(a2, v2, _),
+                ) => match a1.cmp(a2) {
+                    Ordering::Equal => v1.cmp(v2),
+                    o => o,
+                },
                 (FilterResolved::Pres(a1, _), FilterResolved::Pres(a2, _)) => a1.cmp(a2),
                 // Now sort these into the generally "best" order.
                 (FilterResolved::Eq(_, _, _), _) => Ordering::Less,
@@ -1211,8 +1298,14 @@ impl Ord for FilterResolved {
                 (_, FilterResolved::Pres(_, _)) => Ordering::Greater,
                 (FilterResolved::LessThan(_, _, _), _) => Ordering::Less,
                 (_, FilterResolved::LessThan(_, _, _)) => Ordering::Greater,
+                (FilterResolved::GreaterThan(_, _, _), _) => Ordering::Less,
+                (_, FilterResolved::GreaterThan(_, _, _)) => Ordering::Greater,
                 (FilterResolved::Cnt(_, _, _), _) => Ordering::Less,
                 (_, FilterResolved::Cnt(_, _, _)) => Ordering::Greater,
+                (FilterResolved::Stw(_, _, _), _) => Ordering::Less,
+                (_, FilterResolved::Stw(_, _, _)) => Ordering::Greater,
+                (FilterResolved::Enw(_, _, _), _) => Ordering::Less,
+                (_, FilterResolved::Enw(_, _, _)) => Ordering::Greater,
                 // They can't be re-arranged, they don't move!
                 (_, _) => Ordering::Equal,
             }
@@ -1235,21 +1328,33 @@ impl FilterResolved {
             }
             FilterComp::SelfUuid => panic!("Not possible to resolve SelfUuid in from_invalid!"),
             FilterComp::Cnt(a, v) => {
-                // TODO: For now, don't emit substring indexes.
-                // let idx = idxmeta.contains(&(&a, &IndexType::SubString));
-                // let idx = NonZeroU8::new(idx as u8);
-                FilterResolved::Cnt(a, v, None)
+                let idx = idxmeta.contains(&(&a, &IndexType::SubString));
+                let idx = NonZeroU8::new(idx as u8);
+                FilterResolved::Cnt(a, v, idx)
+            }
+            FilterComp::Stw(a, v) => {
+                let idx = idxmeta.contains(&(&a, &IndexType::SubString));
+                let idx = NonZeroU8::new(idx as u8);
+                FilterResolved::Stw(a, v, idx)
+            }
+            FilterComp::Enw(a, v) => {
+                let idx = idxmeta.contains(&(&a, &IndexType::SubString));
+                let idx = NonZeroU8::new(idx as u8);
+                FilterResolved::Enw(a, v, idx)
             }
-            FilterComp::Stw(a, v) => FilterResolved::Stw(a, v, None),
-            FilterComp::Enw(a, v) => FilterResolved::Enw(a, v, None),
             FilterComp::Pres(a) => {
                 let idx = idxmeta.contains(&(&a, &IndexType::Presence));
                 FilterResolved::Pres(a, NonZeroU8::new(idx as u8))
             }
             FilterComp::LessThan(a, v) => {
-                // let idx = idxmeta.contains(&(&a, &IndexType::ORDERING));
-                // TODO: For now, don't emit ordering indexes.
-                FilterResolved::LessThan(a, v, None)
+                let idx = idxmeta.contains(&(&a, &IndexType::Ordering));
+                let idx = NonZeroU8::new(idx as u8);
+                FilterResolved::LessThan(a, v, idx)
+            }
+            FilterComp::GreaterThan(a, v) => {
+                let idx = idxmeta.contains(&(&a, &IndexType::Ordering));
+                let idx = NonZeroU8::new(idx as u8);
+                FilterResolved::GreaterThan(a, v, idx)
             }
             FilterComp::Or(vs) => FilterResolved::Or(
                 vs.into_iter()
@@ -1300,7 +1405,8 @@ impl FilterResolved {
             | FilterComp::Stw(..)
             | FilterComp::Enw(..)
             | FilterComp::Pres(_)
-            | FilterComp::LessThan(..) => true,
+            | FilterComp::LessThan(..)
+            | FilterComp::GreaterThan(..) => true,
         }
     }
 
@@ -1335,26 +1441,20 @@ impl FilterResolved {
                 Some(FilterResolved::Cnt(a, v, idx))
             }
             FilterComp::Stw(a, v) => {
-                /*
                 let idxkref = IdxKeyRef::new(&a, &IndexType::SubString);
                 let idx = idxmeta
                     .get(&idxkref as &dyn IdxKeyToRef)
                     .copied()
                     .and_then(NonZeroU8::new);
                 Some(FilterResolved::Stw(a, v, idx))
-                */
-                Some(FilterResolved::Stw(a, v, None))
             }
             FilterComp::Enw(a, v) => {
-                /*
                 let idxkref = IdxKeyRef::new(&a, &IndexType::SubString);
                 let idx = idxmeta
                     .get(&idxkref as &dyn IdxKeyToRef)
                     .copied()
                     .and_then(NonZeroU8::new);
                 Some(FilterResolved::Enw(a, v, idx))
-                */
-                Some(FilterResolved::Enw(a, v, None))
             }
             FilterComp::Pres(a) => {
                 let idxkref = IdxKeyRef::new(&a, &IndexType::Presence);
@@ -1365,8 +1465,20 @@ impl FilterResolved {
                 Some(FilterResolved::Pres(a, idx))
             }
             FilterComp::LessThan(a, v) => {
-                // let idx = idxmeta.contains(&(&a, &IndexType::SubString));
-                Some(FilterResolved::LessThan(a, v, None))
+                let idxkref = IdxKeyRef::new(&a, &IndexType::Ordering);
+                let idx = idxmeta
+                    .get(&idxkref as &dyn IdxKeyToRef)
+                    .copied()
+                    .and_then(NonZeroU8::new);
+                Some(FilterResolved::LessThan(a, v, idx))
+            }
+            FilterComp::GreaterThan(a, v) => {
+                let idxkref = IdxKeyRef::new(&a, &IndexType::Ordering);
+                let idx = idxmeta
+                    .get(&idxkref as &dyn IdxKeyToRef)
+                    .copied()
+                    .and_then(NonZeroU8::new);
+                Some(FilterResolved::GreaterThan(a, v, idx))
             }
             // We set the compound filters slope factor to "None" here, because when we do
             // optimise we'll actually fill in the correct slope factors after we sort those
@@ -1429,6 +1541,7 @@ impl FilterResolved {
             FilterComp::Enw(a, v) => Some(FilterResolved::Enw(a, v, None)),
             FilterComp::Pres(a) => Some(FilterResolved::Pres(a, None)),
             FilterComp::LessThan(a, v) => Some(FilterResolved::LessThan(a, v, None)),
+            FilterComp::GreaterThan(a, v) => Some(FilterResolved::GreaterThan(a, v, None)),
             FilterComp::Or(vs) => {
                 let fi: Option<Vec<_>> = vs
                     .into_iter()
@@ -1594,6 +1707,7 @@ impl FilterResolved {
             | FilterResolved::Enw(_, _, sf)
             | FilterResolved::Pres(_, sf)
             | FilterResolved::LessThan(_, _, sf)
+            | FilterResolved::GreaterThan(_, _, sf)
             | FilterResolved::Or(_, sf)
             | FilterResolved::And(_, sf)
             | FilterResolved::Inclusion(_, sf)
@@ -1852,6 +1966,59 @@ mod tests {
         assert!(e.entry_match_no_index(&f_t1c));
     }
 
+    #[test]
+    fn test_greaterthan_entry_filter() {
+        let e = entry_init!(
+            (Attribute::UserId, Value::new_iutf8("william")),
+            (
+                Attribute::Uuid,
+                Value::Uuid(uuid::uuid!("db237e8a-0079-4b8c-8a56-593b22aa44d1"))
+            ),
+            (Attribute::GidNumber, Value::Uint32(1000)),
+            (
+                Attribute::AccountExpire,
+                Value::new_datetime_epoch(Duration::from_secs(1000))
+            )
+        )
+        .into_sealed_new();
+
+        let f_t1a = filter_resolved!(f_gt(Attribute::GidNumber, PartialValue::new_uint32(500)));
+        assert!(e.entry_match_no_index(&f_t1a));
+
+        let f_t1b = filter_resolved!(f_gt(Attribute::GidNumber, PartialValue::new_uint32(1000)));
+        assert!(!e.entry_match_no_index(&f_t1b));
+
+        // Date times are ordered too.
+        let f_t2a = filter_resolved!(f_gt(
+            Attribute::AccountExpire,
+            PartialValue::new_datetime_epoch(Duration::from_secs(500))
+        ));
+        assert!(e.entry_match_no_index(&f_t2a));
+
+        let f_t2b = filter_resolved!(f_lt(
+            Attribute::AccountExpire,
+            PartialValue::new_datetime_epoch(Duration::from_secs(500))
+        ));
+        assert!(!e.entry_match_no_index(&f_t2b));
+
+        let f_t2c = filter_resolved!(f_lt(
+            Attribute::AccountExpire,
+            PartialValue::new_datetime_epoch(Duration::from_secs(1500))
+        ));
+        assert!(e.entry_match_no_index(&f_t2c));
+    }
+
+    #[test]
+    fn test_startswith_entry_filter() {
+        let e = entry_init!((Attribute::UserId, Value::new_iutf8("william"))).into_sealed_new();
+
+        let f_t1a = filter_resolved!(f_stw(Attribute::UserId, PartialValue::new_iutf8("wil")));
+        assert!(e.entry_match_no_index(&f_t1a));
+
+        let f_t1b = filter_resolved!(f_stw(Attribute::UserId, PartialValue::new_iutf8("iam")));
+        assert!(!e.entry_match_no_index(&f_t1b));
+    }
+
     #[test]
     fn test_or_entry_filter() {
         let e = entry_init!(
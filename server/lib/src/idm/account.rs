@@ -225,6 +225,14 @@ impl Account {
         self.unix_extn.as_ref()
     }
 
+    /// Capture the current operation for debugging, if this account is being captured.
+    pub(crate) fn capture_for_debug(&self) {
+        if sketching::capture::is_capturing() {
+            let uuid = self.uuid.as_hyphenated().to_string();
+            sketching::capture::capture_account(&[&self.name, &self.spn, &uuid]);
+        }
+    }
+
     #[instrument(level = "trace", skip_all)]
     pub(crate) fn try_from_entry_ro(
         value: &Entry<EntrySealed, EntryCommitted>,
@@ -1,7 +1,12 @@
 use crate::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
 use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use time::OffsetDateTime;
+use tokio::sync::mpsc::{self, error::TrySendError};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AuditSource {
@@ -20,6 +25,101 @@ impl From<Source> for AuditSource {
     }
 }
 
+/// How significant an audit event is. These are ordered, so that a sink can drop
+/// events below a minimum severity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AuditSeverity {
+    #[serde(rename = "trace")]
+    Trace,
+    #[default]
+    #[serde(rename = "info")]
+    Info,
+    #[serde(rename = "warn")]
+    Warn,
+    #[serde(rename = "security")]
+    Security,
+}
+
+impl FromStr for AuditSeverity {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trace" => Ok(AuditSeverity::Trace),
+            "info" => Ok(AuditSeverity::Info),
+            "warn" => Ok(AuditSeverity::Warn),
+            "security" => Ok(AuditSeverity::Security),
+            _ => Err("Must be one of trace, info, warn, security"),
+        }
+    }
+}
+
+impl Display for AuditSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditSeverity::Trace => "trace",
+            AuditSeverity::Info => "info",
+            AuditSeverity::Warn => "warn",
+            AuditSeverity::Security => "security",
+        })
+    }
+}
+
+/// A client write operation that is recorded in audit.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOperation {
+    Create,
+    Modify,
+    Delete,
+}
+
+/// How an audited operation ended.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOutcome {
+    #[serde(rename = "success")]
+    Success,
+    /// The identity was not permitted to perform the operation.
+    #[serde(rename = "denied")]
+    Denied,
+    #[serde(rename = "error")]
+    Error,
+}
+
+impl<T> From<&Result<T, OperationError>> for AuditOutcome {
+    fn from(res: &Result<T, OperationError>) -> Self {
+        match res {
+            Ok(_) => AuditOutcome::Success,
+            Err(
+                OperationError::AccessDenied
+                | OperationError::NotAuthenticated
+                | OperationError::NotAuthorised
+                | OperationError::SessionExpired
+                | OperationError::SystemProtectedObject,
+            ) => AuditOutcome::Denied,
+            Err(_) => AuditOutcome::Error,
+        }
+    }
+}
+
+impl Display for AuditOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuditOutcome::Success => "success",
+            AuditOutcome::Denied => "denied",
+            AuditOutcome::Error => "error",
+        })
+    }
+}
+
+/// Where in the server an audit event was raised, so that an audit record points directly
+/// at the code that produced it. Build this with [audit_location!](crate::audit_location).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AuditLocation {
+    pub file: String,
+    pub line: u32,
+    pub module: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum AuditEvent {
     AuthenticationDenied {
@@ -28,5 +128,217 @@ pub enum AuditEvent {
         spn: String,
         #[serde(with = "time::serde::timestamp")]
         time: OffsetDateTime,
+        /// The operation id of the request that caused this event. This matches the
+        /// `X-KANIDM-OPID` header returned to the client.
+        eventid: Uuid,
+        location: AuditLocation,
+    },
+    /// A client write operation completed, whether it succeeded or not.
+    Operation {
+        source: AuditSource,
+        /// The identity that requested the operation.
+        uuid: Uuid,
+        spn: String,
+        operation: AuditOperation,
+        outcome: AuditOutcome,
+        #[serde(with = "time::serde::timestamp")]
+        time: OffsetDateTime,
+        eventid: Uuid,
+        location: AuditLocation,
+    },
+    /// Events older than the audit store retention window were removed.
+    AuditPurged {
+        #[serde(with = "time::serde::timestamp")]
+        time: OffsetDateTime,
+        /// Events recorded before this time were removed.
+        #[serde(with = "time::serde::timestamp")]
+        before: OffsetDateTime,
+        removed: u64,
+        location: AuditLocation,
+    },
+    /// The audit queue was full, so audit events were dropped rather than delaying requests.
+    AuditDropped {
+        #[serde(with = "time::serde::timestamp")]
+        time: OffsetDateTime,
+        /// The number of events dropped since the last time this was reported.
+        dropped: u64,
+        /// The number of events dropped since the server started.
+        total: u64,
+        location: AuditLocation,
     },
+    /// Scheduled database maintenance completed. Sizes are in bytes.
+    DbMaintenance {
+        #[serde(with = "time::serde::timestamp")]
+        time: OffsetDateTime,
+        vacuum: bool,
+        size_before: u64,
+        size_after: u64,
+        wal_checkpointed: bool,
+        location: AuditLocation,
+    },
+    /// Scheduled database maintenance failed.
+    DbMaintenanceFailed {
+        #[serde(with = "time::serde::timestamp")]
+        time: OffsetDateTime,
+        vacuum: bool,
+        error: String,
+        location: AuditLocation,
+    },
+}
+
+impl AuditEvent {
+    /// The audit event for a client write operation, or `None` for internal operations which
+    /// are not audited.
+    pub fn operation<T>(
+        ident: &Identity,
+        operation: AuditOperation,
+        eventid: Uuid,
+        res: &Result<T, OperationError>,
+        location: AuditLocation,
+    ) -> Option<Self> {
+        let (uuid, spn) = match &ident.origin {
+            IdentType::Internal => return None,
+            IdentType::User(u) => (
+                u.entry.get_uuid(),
+                u.entry.get_uuid2spn().to_proto_string_clone(),
+            ),
+            IdentType::Synch(u) => (*u, u.as_hyphenated().to_string()),
+        };
+
+        Some(AuditEvent::Operation {
+            source: ident.source().clone().into(),
+            uuid,
+            spn,
+            operation,
+            outcome: res.into(),
+            time: OffsetDateTime::now_utc(),
+            eventid,
+            location,
+        })
+    }
+
+    pub fn severity(&self) -> AuditSeverity {
+        match self {
+            AuditEvent::AuthenticationDenied { .. } => AuditSeverity::Security,
+            AuditEvent::Operation { outcome, .. } => match outcome {
+                AuditOutcome::Success => AuditSeverity::Info,
+                AuditOutcome::Denied => AuditSeverity::Security,
+                AuditOutcome::Error => AuditSeverity::Warn,
+            },
+            AuditEvent::AuditPurged { .. } => AuditSeverity::Warn,
+            AuditEvent::AuditDropped { .. } => AuditSeverity::Warn,
+            AuditEvent::DbMaintenance { .. } => AuditSeverity::Info,
+            AuditEvent::DbMaintenanceFailed { .. } => AuditSeverity::Warn,
+        }
+    }
+
+    /// How the operation that raised this event ended, for events about an operation.
+    pub fn outcome(&self) -> Option<AuditOutcome> {
+        match self {
+            AuditEvent::Operation { outcome, .. } => Some(*outcome),
+            _ => None,
+        }
+    }
+
+    /// Events about the audit history itself are recorded whatever the minimum severity, so
+    /// that the severity filter can not hide the loss of audit events.
+    pub fn always_recorded(&self) -> bool {
+        matches!(
+            self,
+            AuditEvent::AuditPurged { .. } | AuditEvent::AuditDropped { .. }
+        )
+    }
+}
+
+/// The number of audit events that may be waiting for the audit daemon before new events are
+/// dropped.
+pub const AUDIT_QUEUE_SIZE: usize = 4096;
+
+/// Submits audit events to the audit daemon. The queue is bounded and submitting never waits, so
+/// that a slow audit sink can not delay request processing. When the queue is full the event is
+/// dropped and counted instead.
+#[derive(Clone)]
+pub struct AuditSender {
+    tx: mpsc::Sender<AuditEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditSender {
+    pub fn send(&self, audit_event: AuditEvent) {
+        match self.tx.try_send(audit_event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(audit_event)) => {
+                let total = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                warn!(
+                    ?audit_event,
+                    total, "Audit event queue is full, dropping audit event"
+                );
+            }
+            Err(TrySendError::Closed(_)) => {
+                error!("Unable to submit audit event to queue");
+            }
+        }
+    }
+}
+
+/// The receiving half of the audit queue.
+pub struct AuditReceiver {
+    rx: mpsc::Receiver<AuditEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl AuditReceiver {
+    pub async fn recv(&mut self) -> Option<AuditEvent> {
+        self.rx.recv().await
+    }
+
+    pub fn try_recv(&mut self) -> Result<AuditEvent, mpsc::error::TryRecvError> {
+        self.rx.try_recv()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn blocking_recv(&mut self) -> Option<AuditEvent> {
+        self.rx.blocking_recv()
+    }
+
+    /// The number of audit events dropped because the queue was full, since the server started.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+pub fn audit_channel(size: usize) -> (AuditSender, AuditReceiver) {
+    let (tx, rx) = mpsc::channel(size);
+    let dropped = Arc::new(AtomicU64::new(0));
+    (
+        AuditSender {
+            tx,
+            dropped: dropped.clone(),
+        },
+        AuditReceiver { rx, dropped },
+    )
+}
+
+#[test]
+fn test_audit_channel_drops_when_full() {
+    let (audit_tx, mut audit_rx) = audit_channel(1);
+    let event = || AuditEvent::AuditPurged {
+        time: OffsetDateTime::UNIX_EPOCH,
+        before: OffsetDateTime::UNIX_EPOCH,
+        removed: 0,
+        location: audit_location!(),
+    };
+
+    audit_tx.send(event());
+    audit_tx.send(event());
+    audit_tx.send(event());
+    assert_eq!(audit_rx.dropped(), 2);
+
+    assert!(audit_rx.try_recv().is_ok());
+    assert!(audit_rx.try_recv().is_err());
+
+    // Once there is room again, events are queued.
+    audit_tx.send(event());
+    assert!(audit_rx.try_recv().is_ok());
+    assert_eq!(audit_rx.dropped(), 2);
 }
@@ -24,7 +24,7 @@ use webauthn_rs::prelude::{
 use crate::credential::totp::Totp;
 use crate::credential::{BackupCodes, Credential, CredentialType, Password};
 use crate::idm::account::Account;
-use crate::idm::audit::AuditEvent;
+use crate::idm::audit::{AuditEvent, AuditSender};
 use crate::idm::delayed::{
     AuthSessionRecord, BackupCodeRemoval, DelayedAction, PasswordUpgrade, WebauthnCounterIncrement,
 };
@@ -1189,6 +1189,12 @@ impl AuthSession {
         }
     }
 
+    /// Capture the current operation for debugging, if the account of this session is being
+    /// captured.
+    pub(crate) fn capture_for_debug(&self) {
+        self.account.capture_for_debug();
+    }
+
     // This is used for softlock identification only.
     pub fn get_credential_uuid(&self) -> Result<Option<Uuid>, OperationError> {
         match &self.state {
@@ -1275,8 +1281,9 @@ impl AuthSession {
         &mut self,
         cred: &AuthCredential,
         time: Duration,
+        eventid: Uuid,
         async_tx: &Sender<DelayedAction>,
-        audit_tx: &Sender<AuditEvent>,
+        audit_tx: &AuditSender,
         webauthn: &Webauthn,
         pw_badlist: &HashSet<String>,
     ) -> Result<AuthState, OperationError> {
@@ -1320,17 +1327,14 @@ impl AuthSession {
                         (None, Ok(AuthState::Continue(allowed.into_iter().collect())))
                     }
                     CredState::Denied(reason) => {
-                        if audit_tx
-                            .send(AuditEvent::AuthenticationDenied {
-                                source: self.source.clone().into(),
-                                spn: self.account.spn.clone(),
-                                uuid: self.account.uuid,
-                                time: OffsetDateTime::UNIX_EPOCH + time,
-                            })
-                            .is_err()
-                        {
-                            error!("Unable to submit audit event to queue");
-                        }
+                        audit_tx.send(AuditEvent::AuthenticationDenied {
+                            source: self.source.clone().into(),
+                            spn: self.account.spn.clone(),
+                            uuid: self.account.uuid,
+                            time: OffsetDateTime::UNIX_EPOCH + time,
+                            eventid,
+                            location: audit_location!(),
+                        });
                         security_info!(%reason, "Credentials denied");
                         (
                             Some(AuthSessionState::Denied(reason)),
@@ -1507,7 +1511,7 @@ mod tests {
     use crate::credential::{BackupCodes, Credential};
     use crate::idm::account::Account;
     use crate::idm::accountpolicy::ResolvedAccountPolicy;
-    use crate::idm::audit::AuditEvent;
+    use crate::idm::audit::{audit_channel, AuditEvent, AUDIT_QUEUE_SIZE};
     use crate::idm::authsession::{
         AuthSession, AuthSessionData, BAD_AUTH_TYPE_MSG, BAD_BACKUPCODE_MSG, BAD_PASSWORD_MSG,
         BAD_TOTP_MSG, BAD_WEBAUTHN_MSG, PW_BADLIST_MSG,
@@ -1624,16 +1628,18 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // now check
         let (mut session, pw_badlist_cache) =
             start_password_session!(&mut audit, account, &webauthn, false);
 
         let attempt = AuthCredential::Password("bad_password".to_string());
+        let denied_eventid = Uuid::new_v4();
         match session.validate_creds(
             &attempt,
             Duration::from_secs(0),
+            denied_eventid,
             &async_tx,
             &audit_tx,
             &webauthn,
@@ -1644,7 +1650,13 @@ mod tests {
         };
 
         match audit_rx.try_recv() {
-            Ok(AuditEvent::AuthenticationDenied { .. }) => {}
+            Ok(AuditEvent::AuthenticationDenied {
+                eventid, location, ..
+            }) => {
+                assert_eq!(eventid, denied_eventid);
+                assert_eq!(location.file, file!());
+                assert_eq!(location.module, "kanidmd_lib::idm::authsession");
+            }
             _ => assert!(false),
         }
 
@@ -1657,6 +1669,7 @@ mod tests {
         let uat: UserAuthToken = match session.validate_creds(
             &attempt,
             Duration::from_secs(0),
+            Uuid::new_v4(),
             &async_tx,
             &audit_tx,
             &webauthn,
@@ -1725,7 +1738,7 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // now check, even though the password is correct, Auth should be denied since it is in badlist
         let (mut session, pw_badlist_cache) =
@@ -1735,6 +1748,7 @@ mod tests {
         match session.validate_creds(
             &attempt,
             Duration::from_secs(0),
+            Uuid::new_v4(),
             &async_tx,
             &audit_tx,
             &webauthn,
@@ -1848,7 +1862,7 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // now check
 
@@ -1860,6 +1874,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Anonymous,
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1885,6 +1900,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1907,6 +1923,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_bad),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1931,6 +1948,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1942,6 +1960,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1966,6 +1985,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -1977,6 +1997,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2025,7 +2046,7 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // now check
 
@@ -2040,6 +2061,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2051,6 +2073,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_badlist.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2177,7 +2200,7 @@ mod tests {
     fn test_idm_authsession_webauthn_only_mech() {
         sketching::test_init();
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
         let ts = duration_from_epoch_now();
         // create the ent
         let mut account: Account = BUILTIN_ACCOUNT_ADMIN.clone().into();
@@ -2197,6 +2220,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Anonymous,
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2224,6 +2248,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Passkey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2258,6 +2283,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Passkey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2306,6 +2332,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Passkey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2331,7 +2358,7 @@ mod tests {
     fn test_idm_authsession_webauthn_password_mech() {
         sketching::test_init();
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
         let ts = duration_from_epoch_now();
         // create the ent
         let mut account: Account = BUILTIN_ACCOUNT_ADMIN.clone().into();
@@ -2357,6 +2384,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2380,6 +2408,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(0),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2414,6 +2443,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2443,6 +2473,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2454,6 +2485,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2489,6 +2521,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2500,6 +2533,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2530,7 +2564,7 @@ mod tests {
     fn test_idm_authsession_webauthn_password_totp_mech() {
         sketching::test_init();
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
         let ts = duration_from_epoch_now();
         // create the ent
         let mut account: Account = BUILTIN_ACCOUNT_ADMIN.clone().into();
@@ -2567,6 +2601,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2590,6 +2625,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_bad),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2622,6 +2658,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2651,6 +2688,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2662,6 +2700,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2691,6 +2730,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2702,6 +2742,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2725,6 +2766,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2736,6 +2778,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2765,6 +2808,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::SecurityKey(resp),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2776,6 +2820,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2841,7 +2886,7 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // now check
         // == two step checks
@@ -2854,6 +2899,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2876,6 +2922,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::BackupCode(backup_code_bad),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2899,6 +2946,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::BackupCode(backup_code_good.clone()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2910,6 +2958,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_bad.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2939,6 +2988,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::BackupCode(backup_code_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2950,6 +3000,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2981,6 +3032,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -2992,6 +3044,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -3051,7 +3104,7 @@ mod tests {
         account.primary = Some(cred);
 
         let (async_tx, mut async_rx) = unbounded();
-        let (audit_tx, mut audit_rx) = unbounded();
+        let (audit_tx, mut audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // Test totp_a
         {
@@ -3061,6 +3114,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good_a),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -3072,6 +3126,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -3095,6 +3150,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Totp(totp_good_b),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
@@ -3106,6 +3162,7 @@ mod tests {
             match session.validate_creds(
                 &AuthCredential::Password(pw_good.to_string()),
                 ts,
+                Uuid::new_v4(),
                 &async_tx,
                 &audit_tx,
                 &webauthn,
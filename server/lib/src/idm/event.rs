@@ -415,14 +415,20 @@ impl AuthEventStep {
 pub struct AuthEvent {
     pub ident: Option<Identity>,
     pub step: AuthEventStep,
+    pub eventid: Uuid,
     // pub sessionid: Option<Uuid>,
 }
 
 impl AuthEvent {
-    pub fn from_message(sessionid: Option<Uuid>, req: AuthRequest) -> Result<Self, OperationError> {
+    pub fn from_message(
+        eventid: Uuid,
+        sessionid: Option<Uuid>,
+        req: AuthRequest,
+    ) -> Result<Self, OperationError> {
         Ok(AuthEvent {
             ident: None,
             step: AuthEventStep::from_authstep(req.step, sessionid)?,
+            eventid,
         })
     }
 
@@ -431,6 +437,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::anonymous_init(),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -439,6 +446,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::named_init(name),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -447,6 +455,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::begin_mech(sessionid, mech),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -455,6 +464,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::cred_step_anonymous(sid),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -463,6 +473,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::cred_step_password(sid, pw),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -471,6 +482,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::cred_step_totp(sid, totp),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -479,6 +491,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::cred_step_backup_code(sid, code),
+            eventid: Uuid::new_v4(),
         }
     }
 
@@ -487,6 +500,7 @@ impl AuthEvent {
         AuthEvent {
             ident: None,
             step: AuthEventStep::cred_step_passkey(sid, passkey_response),
+            eventid: Uuid::new_v4(),
         }
     }
 }
@@ -823,7 +823,7 @@ impl<'a> IdmServerProxyWriteTransaction<'a> {
                 SyntaxType::Utf8StringIname,
                 false,
                 ScimAttr::SingleSimple(ScimSimpleAttr::String(value)),
-            ) => Ok(vec![Value::new_iname(value)]),
+            ) => Ok(vec![Value::new_iname(attr_schema.normalise_value_str(value))]),
             (
                 SyntaxType::Utf8String,
                 false,
@@ -833,7 +833,7 @@ impl<'a> IdmServerProxyWriteTransaction<'a> {
                 SyntaxType::Utf8StringInsensitive,
                 false,
                 ScimAttr::SingleSimple(ScimSimpleAttr::String(value)),
-            ) => Ok(vec![Value::new_iutf8(value)]),
+            ) => Ok(vec![Value::new_iutf8(attr_schema.normalise_value_str(value))]),
             (
                 SyntaxType::Uint32,
                 false,
@@ -874,7 +874,7 @@ impl<'a> IdmServerProxyWriteTransaction<'a> {
                     })?;
 
                     let value = match external_id {
-                        ScimSimpleAttr::String(value) => Ok(value.as_str()),
+                        ScimSimpleAttr::String(value) => Ok(attr_schema.normalise_value_str(value)),
                         _ => {
                             error!("Invalid external_id attribute - must be scim simple string");
                             Err(OperationError::InvalidAttribute(format!(
@@ -1611,6 +1611,9 @@ mod tests {
             .expect("Failed to validate sync token");
 
         assert!(Some(sync_uuid) == ident.get_uuid());
+        // Sync agents are unbounded in results, but not in candidates or unindexed scans.
+        assert!(!ident.limits().unindexed_allow);
+        assert!(!ident.limits().search_max_candidates_exempt);
 
         let sync_state = idms_prox_read
             .scim_sync_get_state(&ident)
@@ -2106,6 +2109,50 @@ mod tests {
         assert!(idms_prox_write.commit().is_ok());
     }
 
+    #[idm_test]
+    async fn test_idm_scim_sync_phase_3_normalise_values(
+        idms: &IdmServer,
+        _idms_delayed: &mut IdmServerDelayed,
+    ) {
+        let user_sync_uuid = Uuid::new_v4();
+
+        assert!(apply_phase_3_test(
+            idms,
+            vec![ScimEntry {
+                schemas: vec![SCIM_SCHEMA_SYNC_GROUP.to_string()],
+                id: user_sync_uuid,
+                external_id: Some("cn=testgroup,ou=people,dc=test".to_string()),
+                meta: None,
+                attrs: btreemap!(
+                    (
+                        Attribute::Name.to_string(),
+                        ScimAttr::SingleSimple(ScimSimpleAttr::String(" TestGroup\n".to_string()))
+                    ),
+                    (
+                        Attribute::Description.to_string(),
+                        ScimAttr::SingleSimple(ScimSimpleAttr::String(" A group ".to_string()))
+                    )
+                ),
+            }]
+        )
+        .await
+        .is_ok());
+
+        let ct = Duration::from_secs(TEST_CURRENT_TIME);
+        let mut idms_prox_write = idms.proxy_write(ct).await;
+
+        let ent = idms_prox_write
+            .qs_write
+            .internal_search_uuid(user_sync_uuid)
+            .expect("Unable to access entry");
+
+        // Names are normalised, but free text is kept as it was written.
+        assert!(ent.get_ava_single_iname(Attribute::Name) == Some("testgroup"));
+        assert!(ent.get_ava_single_utf8(Attribute::Description) == Some(" A group "));
+
+        assert!(idms_prox_write.commit().is_ok());
+    }
+
     // -- try to set uuid
     #[idm_test]
     async fn test_idm_scim_sync_phase_3_uuid_manipulation(
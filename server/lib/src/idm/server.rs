@@ -23,9 +23,13 @@ use webauthn_rs::prelude::{Webauthn, WebauthnBuilder};
 
 use super::event::ReadBackupCodeEvent;
 use super::ldap::{LdapBoundToken, LdapSession};
+use crate::be::DbMaintenanceResult;
 use crate::credential::{softlock::CredSoftLock, Credential};
 use crate::idm::account::Account;
-use crate::idm::audit::AuditEvent;
+use crate::idm::audit::{
+    audit_channel, AuditEvent, AuditLocation, AuditOperation, AuditReceiver, AuditSender,
+    AUDIT_QUEUE_SIZE,
+};
 use crate::idm::authsession::{AuthSession, AuthSessionData};
 use crate::idm::credupdatesession::CredentialUpdateSessionMutex;
 use crate::idm::delayed::{
@@ -73,7 +77,7 @@ pub struct IdmServer {
     /// The configured crypto policy for the IDM server. Later this could be transactional and loaded from the db similar to access. But today it's just to allow dynamic pbkdf2rounds
     crypto_policy: CryptoPolicy,
     async_tx: Sender<DelayedAction>,
-    audit_tx: Sender<AuditEvent>,
+    audit_tx: AuditSender,
     /// [Webauthn] verifier/config
     webauthn: Webauthn,
     oauth2rs: Arc<Oauth2ResourceServers>,
@@ -90,7 +94,7 @@ pub struct IdmServerAuthTransaction<'a> {
     pub(crate) sid: Sid,
     // For flagging eventual actions.
     pub(crate) async_tx: Sender<DelayedAction>,
-    pub(crate) audit_tx: Sender<AuditEvent>,
+    pub(crate) audit_tx: AuditSender,
     pub(crate) webauthn: &'a Webauthn,
 }
 
@@ -125,7 +129,7 @@ pub struct IdmServerDelayed {
 }
 
 pub struct IdmServerAudit {
-    pub(crate) audit_rx: Receiver<AuditEvent>,
+    pub(crate) audit_rx: AuditReceiver,
 }
 
 impl IdmServer {
@@ -142,7 +146,7 @@ impl IdmServer {
         };
 
         let (async_tx, async_rx) = unbounded();
-        let (audit_tx, audit_rx) = unbounded();
+        let (audit_tx, audit_rx) = audit_channel(AUDIT_QUEUE_SIZE);
 
         // Get the domain name, as the relying party id.
         let (rp_id, rp_name, oauth2rs_set) = {
@@ -268,6 +272,47 @@ impl IdmServer {
         }
     }
 
+    /// Record the outcome of a client write operation in audit.
+    pub fn audit_operation<T>(
+        &self,
+        ident: &Identity,
+        operation: AuditOperation,
+        eventid: Uuid,
+        res: &Result<T, OperationError>,
+        location: AuditLocation,
+    ) {
+        if let Some(audit_event) = AuditEvent::operation(ident, operation, eventid, res, location) {
+            self.audit_tx.send(audit_event);
+        }
+    }
+
+    /// Run maintenance on the database, and record the outcome in audit.
+    pub async fn db_maintenance(
+        &self,
+        vacuum: bool,
+    ) -> Result<DbMaintenanceResult, OperationError> {
+        let res = self.qs.db_maintenance(vacuum).await;
+        let time = time::OffsetDateTime::now_utc();
+        let audit_event = match &res {
+            Ok(r) => AuditEvent::DbMaintenance {
+                time,
+                vacuum: r.vacuum,
+                size_before: r.size_before,
+                size_after: r.size_after,
+                wal_checkpointed: r.wal_checkpointed,
+                location: audit_location!(),
+            },
+            Err(error) => AuditEvent::DbMaintenanceFailed {
+                time,
+                vacuum,
+                error: error.to_string(),
+                location: audit_location!(),
+            },
+        };
+        self.audit_tx.send(audit_event);
+        res
+    }
+
     #[cfg(test)]
     pub(crate) async fn delayed_action(
         &self,
@@ -298,7 +343,7 @@ impl IdmServerAudit {
         }
     }
 
-    pub fn audit_rx(&mut self) -> &mut Receiver<AuditEvent> {
+    pub fn audit_rx(&mut self) -> &mut AuditReceiver {
         &mut self.audit_rx
     }
 }
@@ -373,7 +418,7 @@ pub trait IdmServerTransaction<'a> {
             basic_authz: _,
         } = client_auth_info;
 
-        match (client_cert, bearer_token) {
+        let ident = match (client_cert, bearer_token) {
             (Some(client_cert_info), _) => {
                 self.client_certificate_to_identity(&client_cert_info, ct, source)
             }
@@ -387,7 +432,9 @@ pub trait IdmServerTransaction<'a> {
                 debug!("No client certificate or bearer tokens were supplied");
                 Err(OperationError::NotAuthenticated)
             }
-        }
+        }?;
+        ident.capture_for_debug();
+        Ok(ident)
     }
 
     /// This function is not using in authentication flows - it is a reflector of the
@@ -819,7 +866,7 @@ pub trait IdmServerTransaction<'a> {
         source: Source,
         ct: Duration,
     ) -> Result<Identity, OperationError> {
-        match session {
+        let ident = match session {
             LdapSession::UnixBind(uuid) => {
                 let anon_entry = self
                     .get_qs_txn()
@@ -875,7 +922,9 @@ pub trait IdmServerTransaction<'a> {
 
                 self.process_apit_to_identity(apit, source, entry, ct)
             }
-        }
+        }?;
+        ident.capture_for_debug();
+        Ok(ident)
     }
 
     #[instrument(level = "info", skip_all)]
@@ -932,7 +981,7 @@ pub trait IdmServerTransaction<'a> {
         // If scope is not Synchronise, then fail.
         let scope = (&sync_token.purpose).into();
 
-        let limits = Limits::unlimited();
+        let limits = Limits::sync();
         Ok(Identity {
             origin: IdentType::Synch(entry.get_uuid()),
             source: client_auth_info.source,
@@ -1023,6 +1072,7 @@ impl<'a> IdmServerAuthTransaction<'a> {
                 // out of the session tree.
                 let (account, account_policy) =
                     Account::try_from_entry_with_policy(entry.as_ref(), &mut self.qs_read)?;
+                account.capture_for_debug();
 
                 trace!(?account.primary);
 
@@ -1104,6 +1154,7 @@ impl<'a> IdmServerAuthTransaction<'a> {
                     })?;
 
                 let mut auth_session = auth_session_ref.lock().await;
+                auth_session.capture_for_debug();
 
                 // Indicate to the session which auth mech we now want to proceed with.
                 let auth_result = auth_session.start_session(&mech.mech);
@@ -1153,6 +1204,7 @@ impl<'a> IdmServerAuthTransaction<'a> {
                     })?;
 
                 let mut auth_session = auth_session_ref.lock().await;
+                auth_session.capture_for_debug();
 
                 let maybe_slock_ref = match auth_session.get_credential_uuid()? {
                     Some(cred_uuid) => {
@@ -1188,6 +1240,7 @@ impl<'a> IdmServerAuthTransaction<'a> {
                         .validate_creds(
                             &creds.cred,
                             ct,
+                            ae.eventid,
                             &self.async_tx,
                             &self.audit_tx,
                             self.webauthn,
@@ -84,14 +84,14 @@ pub mod prelude {
     pub use crate::constants::*;
     pub use crate::entry::{
         Entry, EntryCommitted, EntryIncrementalCommitted, EntryIncrementalNew, EntryInit,
-        EntryInitNew, EntryInvalid, EntryInvalidCommitted, EntryInvalidNew, EntryNew, EntryReduced,
-        EntryReducedCommitted, EntryRefresh, EntryRefreshNew, EntrySealed, EntrySealedCommitted,
-        EntrySealedNew, EntryTuple, EntryValid,
+        EntryInitNew, EntryInvalid, EntryInvalidCommitted, EntryInvalidNew, EntryLimits, EntryNew,
+        EntryReduced, EntryReducedCommitted, EntryRefresh, EntryRefreshNew, EntrySealed,
+        EntrySealedCommitted, EntrySealedNew, EntryTuple, EntryValid,
     };
     pub use crate::event::{CreateEvent, DeleteEvent, ExistsEvent, ModifyEvent, SearchEvent};
     pub use crate::filter::{
-        f_and, f_andnot, f_eq, f_id, f_inc, f_lt, f_or, f_pres, f_self, f_spn_name, f_sub, Filter,
-        FilterInvalid, FilterValid, FC,
+        f_and, f_andnot, f_eq, f_gt, f_id, f_inc, f_lt, f_or, f_pres, f_self, f_spn_name, f_stw,
+        f_sub, Filter, FilterInvalid, FilterValid, FC,
     };
     pub use crate::idm::server::{IdmServer, IdmServerAudit, IdmServerDelayed};
     pub use crate::idm::{ClientAuthInfo, ClientCertInfo};
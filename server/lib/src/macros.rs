@@ -647,6 +647,18 @@ macro_rules! vs_cid {
     });
 }
 
+/// The [AuditLocation](crate::idm::audit::AuditLocation) of the code invoking this macro.
+#[macro_export]
+macro_rules! audit_location {
+    () => {
+        $crate::idm::audit::AuditLocation {
+            file: file!().to_string(),
+            line: line!(),
+            module: module_path!().to_string(),
+        }
+    };
+}
+
 macro_rules! limmediate_warning {
     ($($arg:tt)*) => ({
         eprint!($($arg)*)
@@ -2,6 +2,7 @@
 //! express the series of Modifications that should be applied. These are expressed
 //! as "states" on what attribute-values should appear as within the `Entry`
 
+use std::collections::BTreeSet;
 use std::slice;
 
 use kanidm_proto::internal::{
@@ -244,6 +245,11 @@ impl ModifyList<ModifyValid> {
     pub fn iter(&self) -> slice::Iter<Modify> {
         self.mods.iter()
     }
+
+    /// If this modlist can add values, and so grow the entries it applies to.
+    pub(crate) fn adds_values(&self) -> bool {
+        self.mods.iter().any(|m| matches!(m, Modify::Present(..)))
+    }
 }
 
 impl<VALID> ModifyList<VALID> {
@@ -254,4 +260,26 @@ impl<VALID> ModifyList<VALID> {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// The names of the attributes this modlist changes. Assertions change nothing, so the
+    /// attributes they check are not included.
+    pub(crate) fn changed_attrs(&self) -> BTreeSet<AttrString> {
+        self.mods
+            .iter()
+            .filter_map(|m| match m {
+                Modify::Present(a, _) | Modify::Removed(a, _) | Modify::Purged(a) => {
+                    Some(a.clone())
+                }
+                Modify::Assert(..) => None,
+            })
+            .collect()
+    }
+
+    /// The values this modlist adds, with the attribute each is added to.
+    pub(crate) fn present_values(&self) -> impl Iterator<Item = (&AttrString, &Value)> {
+        self.mods.iter().filter_map(|m| match m {
+            Modify::Present(a, v) => Some((a, v)),
+            _ => None,
+        })
+    }
 }
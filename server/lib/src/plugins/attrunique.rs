@@ -64,6 +64,15 @@ where
         .map(|()| cand_attr)
 }
 
+// and[ attr eq k, andnot [ uuid eq v ]]
+// Basically this says where name but also not self.
+fn cand_filter(((attr, v), uuid): &((AttrString, PartialValue), Uuid)) -> FC<'_> {
+    f_and(vec![
+        FC::Eq(attr, v.clone()),
+        f_andnot(FC::Eq(Attribute::Uuid.as_ref(), PartialValue::Uuid(*uuid))),
+    ])
+}
+
 fn enforce_unique<VALID, STATE>(
     qs: &mut QueryServerWriteTransaction,
     cand: &[Entry<VALID, STATE>],
@@ -115,18 +124,7 @@ fn enforce_unique<VALID, STATE>(
     }
 
     // Now do an internal search on name and !uuid for each
-    let mut cand_filters = Vec::with_capacity(0);
-    for ((attr, v), uuid) in cand_attr.iter() {
-        // and[ attr eq k, andnot [ uuid eq v ]]
-        // Basically this says where name but also not self.
-        cand_filters.push(f_and(vec![
-            FC::Eq(attr, v.clone()),
-            f_andnot(FC::Eq(Attribute::Uuid.as_ref(), PartialValue::Uuid(*uuid))),
-        ]));
-    }
-
-    // Or
-    let filt_in = filter!(f_or(cand_filters.clone()));
+    let filt_in = filter!(f_or(cand_attr.iter().map(cand_filter).collect()));
 
     trace!(?filt_in);
 
@@ -136,9 +134,6 @@ fn enforce_unique<VALID, STATE>(
         e
     })?;
 
-    // TODO! Need to make this show what conflicted!
-    // We can probably bisect over the filter to work this out?
-
     if conflict_cand {
         // Some kind of confilct exists. We need to isolate which parts of the filter were suspect.
         // To do this, we bisect over the filter and it's suspect elements.
@@ -148,54 +143,47 @@ fn enforce_unique<VALID, STATE>(
         //
         // We do a bisect rather than a linear one-at-a-time search because we want to try to somewhat minimise calls
         // through internal exists since that has a filter resolve and validate step.
+        //
+        // The attribute of the first element isolated is reported to the caller. The entry
+        // that already holds the value is only logged.
+        let mut conflict = None;
 
-        // Fast-ish path. There is 0 or 1 element, so we just fast return.
-        if cand_filters.len() < 2 {
-            error!(
-                ?cand_filters,
-                "The following filter conditions failed to assert uniqueness"
-            );
-        } else {
-            // First iteration, we already failed and we know that, so we just prime and setup two
-            // chunks here.
-
-            let mid = cand_filters.len() / 2;
-            let (left, right) = cand_filters.split_at(mid);
-
-            let mut queue = VecDeque::new();
-            queue.push_back(left);
-            queue.push_back(right);
-
-            // Ok! We are setup to go
+        let mut queue = VecDeque::new();
+        queue.push_back(cand_attr.as_slice());
 
-            while let Some(cand_query) = queue.pop_front() {
-                let filt_in = filter!(f_or(cand_query.to_vec()));
-                let conflict_cand = qs.internal_search(filt_in).map_err(|e| {
-                    admin_error!("internal exists error {:?}", e);
-                    e
-                })?;
+        while let Some(cand_query) = queue.pop_front() {
+            let filt_in = filter!(f_or(cand_query.iter().map(cand_filter).collect()));
+            let conflict_cand = qs.internal_search(filt_in).map_err(|e| {
+                admin_error!("internal exists error {:?}", e);
+                e
+            })?;
 
-                // A conflict was found!
-                if let Some(conflict_cand_zero) = conflict_cand.first() {
-                    if cand_query.len() >= 2 {
-                        // Continue to split to isolate.
-                        let mid = cand_query.len() / 2;
-                        let (left, right) = cand_query.split_at(mid);
-                        queue.push_back(left);
-                        queue.push_back(right);
-                        // Continue!
-                    } else {
-                        // Report this as a failing query.
-                        error!(cand_filters = ?cand_query, conflicting_with = %conflict_cand_zero.get_display_id(), "The following filter conditions failed to assert uniqueness");
+            // A conflict was found!
+            if let Some(conflict_cand_zero) = conflict_cand.first() {
+                if cand_query.len() >= 2 {
+                    // Continue to split to isolate.
+                    let mid = cand_query.len() / 2;
+                    let (left, right) = cand_query.split_at(mid);
+                    queue.push_back(left);
+                    queue.push_back(right);
+                    // Continue!
+                } else {
+                    // Report this as a failing query.
+                    error!(cand_filters = ?cand_query, conflicting_with = %conflict_cand_zero.get_display_id(), conflicting_uuid = %conflict_cand_zero.get_uuid(), "The following filter conditions failed to assert uniqueness");
+                    if conflict.is_none() {
+                        conflict = cand_query.first().map(|((attr, _), _)| attr.to_string());
                     }
                 }
             }
-            // End logging / warning iterator
         }
 
-        Err(OperationError::Plugin(PluginError::AttrUnique(
-            "duplicate value detected".to_string(),
-        )))
+        Err(match conflict {
+            Some(attr) => OperationError::Plugin(PluginError::AttrUniqueConflict(attr)),
+            // The search should always isolate the conflict that exists found.
+            None => OperationError::Plugin(PluginError::AttrUnique(
+                "duplicate value detected".to_string(),
+            )),
+        })
     } else {
         // If all okay, okay!
         Ok(())
@@ -507,8 +495,8 @@ mod tests {
         let preload = vec![e];
 
         run_create_test!(
-            Err(OperationError::Plugin(PluginError::AttrUnique(
-                "duplicate value detected".to_string()
+            Err(OperationError::Plugin(PluginError::AttrUniqueConflict(
+                Attribute::Name.to_string()
             ))),
             preload,
             create,
@@ -562,8 +550,8 @@ mod tests {
         let preload = vec![ea, eb];
 
         run_modify_test!(
-            Err(OperationError::Plugin(PluginError::AttrUnique(
-                "duplicate value detected".to_string()
+            Err(OperationError::Plugin(PluginError::AttrUniqueConflict(
+                Attribute::Name.to_string()
             ))),
             preload,
             filter!(f_or!([f_eq(
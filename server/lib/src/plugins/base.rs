@@ -21,6 +21,15 @@ use crate::prelude::*;
 
 pub struct Base {}
 
+// The uuid is the identity of an entry, and the change cids are maintained by the
+// server on every write. None of these may be altered by a modification, else the
+// server would silently overwrite the change.
+fn is_protected_attr(attr: &AttrString) -> bool {
+    attr == Attribute::Uuid.as_ref()
+        || attr == Attribute::LastModifiedCid.as_ref()
+        || attr == Attribute::CreatedAtCid.as_ref()
+}
+
 impl Plugin for Base {
     fn id() -> &'static str {
         "plugin_base"
@@ -169,9 +178,9 @@ impl Plugin for Base {
                 Modify::Purged(a) => Some(a),
                 Modify::Assert(_, _) => None,
             };
-            if attr == Some(&AttrString::from(Attribute::Uuid)) {
+            if attr.is_some_and(is_protected_attr) {
                 debug!(?modify, "Modify in violation");
-                request_error!("Modifications to UUID's are NOT ALLOWED");
+                request_error!("Modifications to UUID's or change metadata are NOT ALLOWED");
                 Err(OperationError::SystemProtectedAttribute)
             } else {
                 Ok(())
@@ -196,9 +205,9 @@ impl Plugin for Base {
                     Modify::Purged(a) => Some(a),
                     Modify::Assert(_, _) => None,
                 };
-                if attr == Some(&AttrString::from(Attribute::Uuid)) {
+                if attr.is_some_and(is_protected_attr) {
                     debug!(?modify, "Modify in violation");
-                    request_error!("Modifications to UUID's are NOT ALLOWED");
+                    request_error!("Modifications to UUID's or change metadata are NOT ALLOWED");
                     Err(OperationError::SystemProtectedAttribute)
                 } else {
                     Ok(())
@@ -670,6 +679,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_modify_change_cids_purged() {
+        // Test attempting to purge the server maintained change cids
+        let ea: Entry<EntryInit, EntryNew> = Entry::unsafe_from_entry_str(
+            r#"{
+            "attrs": {
+                "class": ["group"],
+                "name": ["testgroup_a"],
+                "description": ["testgroup"],
+                "uuid": ["d2b496bd-8493-47b7-8142-f568b5cf47ee"]
+            }
+        }"#,
+        );
+
+        let preload = vec![ea];
+
+        run_modify_test!(
+            Err(OperationError::SystemProtectedAttribute),
+            preload,
+            filter!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("testgroup_a")
+            )),
+            ModifyList::new_list(vec![
+                Modify::Purged(Attribute::LastModifiedCid.into()),
+                Modify::Purged(Attribute::CreatedAtCid.into())
+            ]),
+            None,
+            |_| {},
+            |_| {}
+        );
+    }
+
     #[test]
     fn test_protected_uuid_range() {
         // Test an external create, it should fail.
@@ -209,6 +209,14 @@ impl EntryChangeState {
         }
     }
 
+    /// The most recent change to the entry.
+    pub(crate) fn latest_cid(&self) -> &Cid {
+        match &self.st {
+            State::Live { at, changes } => changes.values().max().unwrap_or(at),
+            State::Tombstone { at } => at,
+        }
+    }
+
     #[cfg(test)]
     pub(crate) fn get_tail_cid(&self) -> Cid {
         #![allow(clippy::expect_used)]
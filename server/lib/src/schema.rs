@@ -88,11 +88,27 @@ pub struct SchemaAttribute {
     pub phantom: bool,
     pub sync_allowed: bool,
     pub replicated: bool,
+    /// Writes that use a deprecated attribute still succeed, but are warned about.
+    pub deprecated: bool,
     pub index: Vec<IndexType>,
     pub syntax: SyntaxType,
 }
 
 impl SchemaAttribute {
+    /// Normalise a value supplied by a client as a string before it is parsed. Surrounding
+    /// whitespace is removed from names, case insensitive strings and references, where it is
+    /// never significant and would stop the value matching its index. Free text and every
+    /// other syntax are kept as they were written.
+    pub fn normalise_value_str<'a>(&self, value: &'a str) -> &'a str {
+        match self.syntax {
+            SyntaxType::Utf8StringIname
+            | SyntaxType::Utf8StringInsensitive
+            | SyntaxType::Uuid
+            | SyntaxType::ReferenceUuid => value.trim(),
+            _ => value,
+        }
+    }
+
     pub fn try_from(value: &Entry<EntrySealed, EntryCommitted>) -> Result<Self, OperationError> {
         // Convert entry to a schema attribute.
 
@@ -157,6 +173,10 @@ impl SchemaAttribute {
             .get_ava_single_bool(Attribute::Replicated)
             .unwrap_or(true);
 
+        let deprecated = value
+            .get_ava_single_bool(Attribute::Deprecated)
+            .unwrap_or(false);
+
         // index vec
         // even if empty, it SHOULD be present ... (is that valid to put an empty set?)
         // The get_ava_opt_index handles the optional case for us :)
@@ -181,6 +201,7 @@ impl SchemaAttribute {
             phantom,
             sync_allowed,
             replicated,
+            deprecated,
             index,
             syntax,
         })
@@ -379,6 +400,11 @@ impl From<SchemaAttribute> for EntryInitNew {
             vec![Value::Bool(value.sync_allowed)],
         );
 
+        // deprecated
+        if value.deprecated {
+            entry.set_ava(Attribute::Deprecated, vec![Value::Bool(true)]);
+        }
+
         // uid
         entry.set_ava(Attribute::Uuid, vec![Value::Uuid(value.uuid)]);
 
@@ -411,6 +437,8 @@ pub struct SchemaClass {
     pub uuid: Uuid,
     pub description: String,
     pub sync_allowed: bool,
+    /// Writes that add a deprecated class still succeed, but are warned about.
+    pub deprecated: bool,
     /// This allows modification of system types to be extended in custom ways
     pub systemmay: Vec<AttrString>,
     pub may: Vec<AttrString>,
@@ -461,6 +489,10 @@ impl SchemaClass {
             .get_ava_single_bool(Attribute::SyncAllowed)
             .unwrap_or(false);
 
+        let deprecated = value
+            .get_ava_single_bool(Attribute::Deprecated)
+            .unwrap_or(false);
+
         // These are all "optional" lists of strings.
         let systemmay = value
             .get_ava_iter_iutf8(Attribute::SystemMay)
@@ -501,6 +533,7 @@ impl SchemaClass {
             uuid,
             description,
             sync_allowed,
+            deprecated,
             systemmay,
             may,
             systemmust,
@@ -551,6 +584,11 @@ impl From<SchemaClass> for EntryInitNew {
             vec![Value::Bool(value.sync_allowed)],
         );
 
+        // deprecated
+        if value.deprecated {
+            entry.set_ava(Attribute::Deprecated, vec![Value::Bool(true)]);
+        }
+
         // uid
         entry.set_ava(Attribute::Uuid, vec![Value::Uuid(value.uuid)]);
 
@@ -823,6 +861,19 @@ impl<'a> SchemaWriteTransaction<'a> {
         r
     }
 
+    /// The indexes declared by schema, with the syntax of each indexed attribute. The backend
+    /// indexes only need to be rebuilt when this changes.
+    pub(crate) fn index_declarations(&self) -> BTreeSet<(AttrString, SyntaxType, IndexType)> {
+        self.get_attributes()
+            .values()
+            .flat_map(|a| {
+                a.index
+                    .iter()
+                    .map(move |itype| (a.name.clone(), a.syntax, *itype))
+            })
+            .collect()
+    }
+
     pub fn reload_idxmeta(&self) -> Vec<IdxKey> {
         self.get_attributes()
             .values()
@@ -853,6 +904,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality, IndexType::Presence],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -870,6 +922,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality, IndexType::Presence],
                 syntax: SyntaxType::Uuid,
             },
@@ -889,6 +942,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality, IndexType::Presence],
                 syntax: SyntaxType::Uuid,
             },
@@ -906,6 +960,23 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
+                index: vec![],
+                syntax: SyntaxType::Cid,
+            },
+        );
+        self.attributes.insert(
+            Attribute::CreatedAtCid.into(),
+            SchemaAttribute {
+                name: Attribute::CreatedAtCid.into(),
+                uuid: UUID_SCHEMA_ATTR_CREATED_AT_CID,
+                description: String::from("The cid of the change that created this object"),
+                multivalue: false,
+                unique: false,
+                phantom: false,
+                sync_allowed: false,
+                replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Cid,
             },
@@ -921,7 +992,12 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: true,
                 replicated: true,
-                index: vec![IndexType::Equality, IndexType::Presence],
+                deprecated: false,
+                index: vec![
+                    IndexType::Equality,
+                    IndexType::Presence,
+                    IndexType::SubString,
+                ],
                 syntax: SyntaxType::Utf8StringIname,
             },
         );
@@ -938,6 +1014,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::SecurityPrincipalName,
             },
@@ -953,6 +1030,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -968,6 +1046,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -983,6 +1062,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: true,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -996,6 +1076,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Boolean,
             });
@@ -1008,6 +1089,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Boolean,
             });
@@ -1020,6 +1102,20 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
+                index: vec![],
+                syntax: SyntaxType::Boolean,
+            });
+        self.attributes.insert(Attribute::Deprecated.into(), SchemaAttribute {
+                name: Attribute::Deprecated.into(),
+                uuid: UUID_SCHEMA_ATTR_DEPRECATED,
+                description: String::from("If true, this attribute or class is deprecated. Writes that use it succeed but are warned about."),
+                multivalue: false,
+                unique: false,
+                phantom: false,
+                sync_allowed: false,
+                replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Boolean,
             });
@@ -1032,6 +1128,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Boolean,
             });
@@ -1048,6 +1145,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Boolean,
             },
@@ -1065,6 +1163,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::IndexId,
             },
@@ -1082,6 +1181,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::SyntaxId,
             },
@@ -1099,6 +1199,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1116,6 +1217,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1133,6 +1235,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1150,6 +1253,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1167,6 +1271,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     phantom: false,
                     sync_allowed: false,
                     replicated: true,
+                    deprecated: false,
                     index: vec![],
                     syntax: SyntaxType::Utf8StringInsensitive,
                 },
@@ -1184,6 +1289,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     phantom: false,
                     sync_allowed: false,
                     replicated: true,
+                    deprecated: false,
                     index: vec![],
                     syntax: SyntaxType::Utf8StringInsensitive,
                 },
@@ -1201,6 +1307,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1218,6 +1325,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     phantom: false,
                     sync_allowed: false,
                     replicated: true,
+                    deprecated: false,
                     index: vec![],
                     syntax: SyntaxType::Utf8StringInsensitive,
                 },
@@ -1236,6 +1344,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     phantom: false,
                     sync_allowed: false,
                     replicated: true,
+                    deprecated: false,
                     index: vec![IndexType::Equality],
                     syntax: SyntaxType::Boolean,
                 },
@@ -1254,6 +1363,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality, IndexType::SubString],
                 syntax: SyntaxType::JsonFilter,
             },
@@ -1271,6 +1381,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1289,6 +1400,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality, IndexType::SubString],
                 syntax: SyntaxType::JsonFilter,
             },
@@ -1306,6 +1418,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1321,6 +1434,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1338,6 +1452,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1356,6 +1471,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1373,6 +1489,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1388,6 +1505,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     phantom: false,
                     sync_allowed: false,
                     replicated: true,
+                    deprecated: false,
                     index: vec![IndexType::Equality],
                     syntax: SyntaxType::Utf8StringInsensitive,
                 },
@@ -1405,6 +1523,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1421,6 +1540,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1436,6 +1556,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1455,6 +1576,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 // DMO which is very costly, while still retaining our ability to revive entries
                 // and their group memberships as a best effort.
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1470,6 +1592,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: true,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1485,6 +1608,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: true,
                 replicated: false,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1503,6 +1627,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Uint32,
             },
@@ -1519,6 +1644,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringIname,
             },
@@ -1536,6 +1662,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1553,6 +1680,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1572,6 +1700,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1589,6 +1718,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![IndexType::Equality],
                 syntax: SyntaxType::ReferenceUuid,
             },
@@ -1604,6 +1734,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: false,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1620,6 +1751,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: true,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -1638,6 +1770,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: true,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -1654,6 +1787,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: true,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::TotpSecret,
             },
@@ -1671,6 +1805,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1686,6 +1821,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1701,6 +1837,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Uuid,
             },
@@ -1716,6 +1853,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringInsensitive,
             },
@@ -1731,6 +1869,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8StringIname,
             },
@@ -1746,6 +1885,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::SshKey,
             },
@@ -1761,6 +1901,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::SshKey,
             },
@@ -1776,6 +1917,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::EmailAddress,
             },
@@ -1791,6 +1933,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::EmailAddress,
             },
@@ -1806,6 +1949,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::EmailAddress,
             },
@@ -1821,6 +1965,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::EmailAddress,
             },
@@ -1836,6 +1981,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -1851,6 +1997,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -1866,6 +2013,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Uint32,
             },
@@ -1881,6 +2029,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: true,
                 sync_allowed: false,
                 replicated: false,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Utf8String,
             },
@@ -1897,6 +2046,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 phantom: false,
                 sync_allowed: true,
                 replicated: true,
+                deprecated: false,
                 index: vec![],
                 syntax: SyntaxType::Image,
             },
@@ -1912,6 +2062,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                     Attribute::Replicated.into(),
                     Attribute::Phantom.into(),
                     Attribute::SyncAllowed.into(),
+                    Attribute::Deprecated.into(),
                     Attribute::Index.into(),
                 ],
                 systemmust: vec![
@@ -1934,6 +2085,7 @@ impl<'a> SchemaWriteTransaction<'a> {
                 description: String::from("Definition of a schema classtype"),
                 systemmay: vec![
                     Attribute::SyncAllowed.into(),
+                    Attribute::Deprecated.into(),
                     Attribute::SystemMay.into(),
                     Attribute::May.into(),
                     Attribute::SystemMust.into(),
@@ -1961,6 +2113,8 @@ impl<'a> SchemaWriteTransaction<'a> {
                 systemmay: vec![
                     Attribute::Description.into(),
                     Attribute::EntryManagedBy.into(),
+                    // May, as entries created before this attribute existed don't have it.
+                    Attribute::CreatedAtCid.into(),
                 ],
                 systemmust: vec![
                     Attribute::Class.into(),
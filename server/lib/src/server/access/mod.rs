@@ -234,6 +234,21 @@ pub trait AccessControlsTransaction<'a> {
         filter_orig: &Filter<FilterValid>,
         entries: Vec<Arc<EntrySealedCommitted>>,
     ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError> {
+        self.filter_entries_iter(ident, filter_orig, entries.into_iter().map(Ok))
+    }
+
+    /// As [filter_entries](AccessControlsTransaction::filter_entries), but the entries are
+    /// consumed as they are produced, such as from a backend search cursor, so that entries
+    /// which are denied are released immediately. The first error from the iterator is returned.
+    fn filter_entries_iter<I>(
+        &self,
+        ident: &Identity,
+        filter_orig: &Filter<FilterValid>,
+        entries: I,
+    ) -> Result<Vec<Arc<EntrySealedCommitted>>, OperationError>
+    where
+        I: IntoIterator<Item = Result<Arc<EntrySealedCommitted>, OperationError>>,
+    {
         // Prepare some shared resources.
 
         // Get the set of attributes requested by this se filter. This is what we are
@@ -244,27 +259,30 @@ pub trait AccessControlsTransaction<'a> {
         let related_acp = self.search_related_acp(ident);
 
         // For each entry.
-        let entries_is_empty = entries.is_empty();
-        let allowed_entries: Vec<_> = entries
-            .into_iter()
-            .filter(|e| {
-                match apply_search_access(ident, related_acp.as_slice(), e) {
-                    SearchResult::Denied => false,
-                    SearchResult::Grant => true,
-                    SearchResult::Allow(allowed_attrs) => {
-                        // The allow set constrained.
-                        let decision = requested_attrs.is_subset(&allowed_attrs);
-                        security_debug!(
-                            ?decision,
-                            allowed = ?allowed_attrs,
-                            requested = ?requested_attrs,
-                            "search attribute decision",
-                        );
-                        decision
-                    }
+        let mut entries_is_empty = true;
+        let mut allowed_entries = Vec::new();
+        for entry in entries {
+            let e = entry?;
+            entries_is_empty = false;
+            let allowed = match apply_search_access(ident, related_acp.as_slice(), &e) {
+                SearchResult::Denied => false,
+                SearchResult::Grant => true,
+                SearchResult::Allow(allowed_attrs) => {
+                    // The allow set constrained.
+                    let decision = requested_attrs.is_subset(&allowed_attrs);
+                    security_debug!(
+                        ?decision,
+                        allowed = ?allowed_attrs,
+                        requested = ?requested_attrs,
+                        "search attribute decision",
+                    );
+                    decision
                 }
-            })
-            .collect();
+            };
+            if allowed {
+                allowed_entries.push(e);
+            }
+        }
 
         if allowed_entries.is_empty() {
             if !entries_is_empty {
@@ -46,6 +46,10 @@ impl<'a> QueryServerWriteTransaction<'a> {
             return Err(OperationError::EmptyRequest);
         }
 
+        if me.modset.values().any(|ml| ml.adds_values()) {
+            self.check_quota(0)?;
+        }
+
         let filter_or = me
             .modset
             .keys()
@@ -164,6 +168,20 @@ impl<'a> QueryServerWriteTransaction<'a> {
             })
             .collect::<Result<Vec<EntrySealedCommitted>, _>>()?;
 
+        self.check_entry_limits(
+            &me.ident,
+            norm_cand
+                .iter()
+                .filter_map(|e| me.modset.get(&e.get_uuid()).map(|modlist| (e, modlist))),
+        )?;
+        self.warn_deprecated(
+            &me.ident,
+            pre_candidates
+                .iter()
+                .map(|e| Some(e.as_ref()))
+                .zip(norm_cand.iter()),
+        );
+
         // Backend Modify
         self.be_txn
             .modify(&self.cid, &pre_candidates, &norm_cand)
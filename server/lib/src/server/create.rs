@@ -2,12 +2,43 @@ use crate::prelude::*;
 use crate::server::CreateEvent;
 use crate::server::{ChangeFlag, Plugins};
 
+/// How strictly a create treats the values that are normally maintained by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CreateMode {
+    /// The default - server managed values given by the creator are replaced.
+    Strict,
+    /// As strict, but the indexes are rebuilt once after all entries are created.
+    Bulk,
+    /// As bulk, but server managed values such as the creation cid are kept as given. This
+    /// is only used when restoring or importing entries from another source, and is only
+    /// chosen by `internal_import_create`, which always creates as the internal identity.
+    Relaxed,
+}
+
 impl<'a> QueryServerWriteTransaction<'a> {
     #[instrument(level = "debug", skip_all)]
     /// The create event is a raw, read only representation of the request
     /// that was made to us, including information about the identity
     /// performing the request.
     pub fn create(&mut self, ce: &CreateEvent) -> Result<(), OperationError> {
+        self.create_inner(ce, CreateMode::Strict).map(|_| ())
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    /// As [`create`](Self::create), returning the uuids of the created entries in the order
+    /// they were given. Entries created without a uuid have one assigned by the base plugin.
+    pub fn create_returning_uuids(
+        &mut self,
+        ce: &CreateEvent,
+    ) -> Result<Vec<Uuid>, OperationError> {
+        self.create_inner(ce, CreateMode::Strict)
+    }
+
+    fn create_inner(
+        &mut self,
+        ce: &CreateEvent,
+        mode: CreateMode,
+    ) -> Result<Vec<Uuid>, OperationError> {
         if !ce.ident.is_internal() {
             security_info!(name = %ce.ident, "create initiator");
         }
@@ -17,6 +48,8 @@ impl<'a> QueryServerWriteTransaction<'a> {
             return Err(OperationError::EmptyRequest);
         }
 
+        self.check_quota(ce.entries.len())?;
+
         // TODO #67: Do we need limits on number of creates, or do we constraint
         // based on request size in the frontend?
 
@@ -46,9 +79,18 @@ impl<'a> QueryServerWriteTransaction<'a> {
         }
 
         // Assign our replication metadata now, since we can proceed with this operation.
+        // The cid an entry was created at is recorded from domain level 8.
+        let record_created = self.get_domain_version() >= DOMAIN_LEVEL_8;
         let mut candidates: Vec<Entry<EntryInvalid, EntryNew>> = candidates
             .into_iter()
-            .map(|e| e.assign_cid(self.cid.clone(), &self.schema))
+            .map(|e| {
+                e.assign_cid(
+                    self.cid.clone(),
+                    &self.schema,
+                    record_created,
+                    mode == CreateMode::Relaxed,
+                )
+            })
             .collect();
 
         // run any pre plugins, giving them the list of mutable candidates.
@@ -83,8 +125,33 @@ impl<'a> QueryServerWriteTransaction<'a> {
             e
         })?;
 
+        // The client supplied every value of the entries it creates.
+        self.check_entry_limits(
+            &ce.ident,
+            norm_cand.iter().zip(
+                ce.entries
+                    .iter()
+                    .map(|e| EntryInitNew::new().gen_modlist_diff(e)),
+            ),
+        )?;
+        self.warn_deprecated(&ce.ident, norm_cand.iter().map(|e| (None, e)));
+
         // We may change from ce.entries later to something else?
-        let commit_cand = self.be_txn.create(&self.cid, norm_cand).map_err(|e| {
+        let commit_cand = if mode != CreateMode::Strict {
+            // The new entries are indexed once they are all written, rather than as each one is
+            // written. The post plugins need to search the new entries, so this must happen
+            // before they run.
+            self.be_txn
+                .create_unindexed(&self.cid, norm_cand)
+                .and_then(|commit_cand| {
+                    self.be_txn
+                        .index_created(&commit_cand)
+                        .map(|()| commit_cand)
+                })
+        } else {
+            self.be_txn.create(&self.cid, norm_cand)
+        }
+        .map_err(|e| {
             admin_error!("betxn create failure {:?}", e);
             e
         })?;
@@ -151,8 +218,8 @@ impl<'a> QueryServerWriteTransaction<'a> {
             self.changed_flags.insert(ChangeFlag::KEY_MATERIAL)
         }
 
-        self.changed_uuid
-            .extend(commit_cand.iter().map(|e| e.get_uuid()));
+        let created_uuids: Vec<Uuid> = commit_cand.iter().map(|e| e.get_uuid()).collect();
+        self.changed_uuid.extend(created_uuids.iter().copied());
 
         trace!(
             changed = ?self.changed_flags.iter_names().collect::<Vec<_>>(),
@@ -165,7 +232,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
         } else {
             admin_info!("Create operation success");
         }
-        Ok(())
+        Ok(created_uuids)
     }
 
     pub fn internal_create(
@@ -175,11 +242,34 @@ impl<'a> QueryServerWriteTransaction<'a> {
         let ce = CreateEvent::new_internal(entries);
         self.create(&ce)
     }
+
+    /// Create a large number of entries, as when importing a directory. The entries are checked
+    /// by schema and each plugin runs once over all of them, and the new entries are indexed
+    /// after they are all created rather than as each one is written.
+    pub fn internal_bulk_create(
+        &mut self,
+        entries: Vec<Entry<EntryInit, EntryNew>>,
+    ) -> Result<(), OperationError> {
+        let ce = CreateEvent::new_internal(entries);
+        self.create_inner(&ce, CreateMode::Bulk).map(|_| ())
+    }
+
+    /// As [`internal_bulk_create`](Self::internal_bulk_create), but values that are normally
+    /// maintained by the server, such as the creation cid, are kept as given rather than
+    /// replaced. This is for restoring or importing entries that already have this history.
+    pub fn internal_import_create(
+        &mut self,
+        entries: Vec<Entry<EntryInit, EntryNew>>,
+    ) -> Result<(), OperationError> {
+        let ce = CreateEvent::new_internal(entries);
+        self.create_inner(&ce, CreateMode::Relaxed).map(|_| ())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
+    use crate::server::ServerPhase;
     use std::sync::Arc;
 
     #[qs_test]
@@ -260,6 +350,69 @@ mod tests {
         assert!(server_txn.commit().is_ok());
     }
 
+    #[qs_test]
+    async fn test_create_quota(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        let idm_admin = server_txn
+            .internal_search_uuid(UUID_IDM_ADMIN)
+            .expect("failed");
+
+        let person = |name: &str| {
+            entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Name, Value::new_iname(name)),
+                (Attribute::DisplayName, Value::new_utf8s(name)),
+                (Attribute::Uuid, Value::Uuid(Uuid::new_v4()))
+            )
+        };
+        let client_create = |name: &str| {
+            CreateEvent::new_impersonate_identity(
+                Identity::from_impersonate_entry_readwrite(idm_admin.clone()),
+                vec![person(name)],
+            )
+        };
+
+        // Allow one more entry than the database holds now.
+        let live = server_txn
+            .get_be_txn()
+            .count_live_entries()
+            .expect("failed");
+        server_txn.get_be_txn().set_quota(Some(live + 1), None);
+
+        // Creates made while the server migrates may go past the quota.
+        server_txn.set_phase(ServerPhase::DomainInfoReady);
+        let ce = CreateEvent::new_internal(vec![person("testperson1"), person("testperson2")]);
+        assert!(server_txn.create(&ce).is_ok());
+        server_txn.set_phase(ServerPhase::Running);
+
+        assert_eq!(
+            server_txn.create(&client_create("testperson3")),
+            Err(OperationError::DB0006EntryQuotaExceeded)
+        );
+        // Once running, internal creates such as those of a sync are refused as well.
+        assert_eq!(
+            server_txn.internal_create(vec![person("testperson3")]),
+            Err(OperationError::DB0006EntryQuotaExceeded)
+        );
+
+        // Deleted entries in the recycle bin are not counted.
+        let de = DeleteEvent::new_internal_invalid(filter!(f_or!([
+            f_eq(Attribute::Name, PartialValue::new_iname("testperson1")),
+            f_eq(Attribute::Name, PartialValue::new_iname("testperson2"))
+        ])));
+        assert!(server_txn.delete(&de).is_ok());
+
+        assert!(server_txn.create(&client_create("testperson3")).is_ok());
+        assert_eq!(
+            server_txn.create(&client_create("testperson4")),
+            Err(OperationError::DB0006EntryQuotaExceeded)
+        );
+
+        assert!(server_txn.commit().is_ok());
+    }
+
     #[qs_pair_test]
     async fn test_pair_create_user(server_a: &QueryServer, server_b: &QueryServer) {
         let mut server_a_txn = server_a.write(duration_from_epoch_now()).await;
@@ -306,4 +459,193 @@ mod tests {
         assert!(server_a_txn.commit().is_ok());
         assert!(server_b_txn.commit().is_ok());
     }
+
+    #[qs_test]
+    async fn test_create_returning_uuids(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+
+        let group_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup_a"))
+        );
+        let e2 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup_b")),
+            (Attribute::Uuid, Value::Uuid(group_uuid))
+        );
+
+        let ce = CreateEvent::new_internal(vec![e1, e2]);
+        let uuids = server_txn
+            .create_returning_uuids(&ce)
+            .expect("create failure");
+        assert_eq!(uuids.len(), 2);
+        assert_eq!(uuids[1], group_uuid);
+
+        // The first entry was assigned a uuid, which is the one returned.
+        let found = server_txn
+            .internal_search_uuid(uuids[0])
+            .expect("failed to find created entry");
+        assert!(found.attribute_equality(Attribute::Name, &PartialValue::new_iname("testgroup_a")));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test(domain_level=DOMAIN_LEVEL_8)]
+    async fn test_create_created_at_cid(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        let create_cid = server_txn.get_txn_cid().clone();
+
+        let group_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            // Replaced by the server.
+            (Attribute::CreatedAtCid, Value::new_cid(Cid::new_zero()))
+        );
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        assert!(server_txn
+            .internal_modify_uuid(
+                group_uuid,
+                &ModifyList::new_purge_and_set(Attribute::Description, Value::new_utf8s("test"))
+            )
+            .is_ok());
+
+        // Only the last modified cid moves when the entry is changed.
+        let group = server_txn
+            .internal_search_uuid(group_uuid)
+            .expect("failed to find created entry");
+        assert!(group.attribute_equality(
+            Attribute::CreatedAtCid,
+            &PartialValue::new_cid(create_cid.clone())
+        ));
+        assert!(group.get_last_changed() > create_cid);
+
+        let next_cid = server_txn.get_txn_cid().clone();
+        let found = server_txn
+            .internal_search(filter!(f_and!([
+                f_eq(Attribute::Uuid, PartialValue::Uuid(group_uuid)),
+                f_lt(Attribute::CreatedAtCid, PartialValue::new_cid(next_cid))
+            ])))
+            .expect("search failure");
+        assert_eq!(found.len(), 1);
+
+        // Clients may not change when an entry was created.
+        let admin = server_txn.internal_search_uuid(UUID_ADMIN).expect("failed");
+        let me = ModifyEvent::new_impersonate_entry(
+            admin,
+            filter!(f_eq(Attribute::Uuid, PartialValue::Uuid(group_uuid))),
+            ModifyList::new_purge(Attribute::CreatedAtCid),
+        );
+        assert_eq!(server_txn.modify(&me), Err(OperationError::AccessDenied));
+
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[qs_test]
+    async fn test_bulk_create(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+
+        let group_uuid = Uuid::new_v4();
+        let mut entries: Vec<_> = (0..50)
+            .map(|i| {
+                entry_init!(
+                    (Attribute::Class, EntryClass::Object.to_value()),
+                    (Attribute::Class, EntryClass::Person.to_value()),
+                    (Attribute::Class, EntryClass::Account.to_value()),
+                    (Attribute::Name, Value::new_iname(&format!("bulkperson{i}"))),
+                    (Attribute::DisplayName, Value::new_utf8s("Bulk Person"))
+                )
+            })
+            .collect();
+        let members: Vec<_> = (0..50).map(|_| Uuid::new_v4()).collect();
+        for (e, u) in entries.iter_mut().zip(members.iter()) {
+            e.add_ava(Attribute::Uuid, Value::Uuid(*u));
+        }
+        let mut group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("bulkgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid))
+        );
+        for u in members.iter() {
+            group.add_ava(Attribute::Member, Value::Refer(*u));
+        }
+        entries.push(group);
+
+        assert!(server_txn.internal_bulk_create(entries).is_ok());
+
+        // The new entries are indexed, and the post plugins have seen them.
+        let person = server_txn
+            .internal_search_uuid(members[7])
+            .expect("failed to find bulk created entry");
+        assert!(person.attribute_equality(Attribute::MemberOf, &PartialValue::Refer(group_uuid)));
+        let found = server_txn
+            .internal_search(filter!(f_eq(
+                Attribute::Name,
+                PartialValue::new_iname("bulkperson49")
+            )))
+            .expect("search failure");
+        assert_eq!(found.len(), 1);
+
+        // Schema is still enforced.
+        let invalid = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Person.to_value()),
+            (Attribute::Class, EntryClass::Account.to_value()),
+            (Attribute::Name, Value::new_iname("bulkinvalid"))
+        );
+        assert!(matches!(
+            server_txn.internal_bulk_create(vec![invalid]),
+            Err(OperationError::SchemaViolation(_))
+        ));
+    }
+
+    #[qs_test(domain_level=DOMAIN_LEVEL_8)]
+    async fn test_import_create_relaxed(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+
+        let group_uuid = Uuid::new_v4();
+        let group = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("importgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::CreatedAtCid, Value::new_cid(Cid::new_zero()))
+        );
+        assert!(server_txn.internal_import_create(vec![group]).is_ok());
+
+        // The given uuid and creation cid are kept.
+        let group = server_txn
+            .internal_search_uuid(group_uuid)
+            .expect("failed to find imported entry");
+        assert!(group.attribute_equality(
+            Attribute::CreatedAtCid,
+            &PartialValue::new_cid(Cid::new_zero())
+        ));
+
+        // Without a creation cid, the server assigns one as usual.
+        let create_cid = server_txn.get_txn_cid().clone();
+        let other_uuid = Uuid::new_v4();
+        let other = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("importother")),
+            (Attribute::Uuid, Value::Uuid(other_uuid))
+        );
+        assert!(server_txn.internal_import_create(vec![other]).is_ok());
+        let other = server_txn
+            .internal_search_uuid(other_uuid)
+            .expect("failed to find imported entry");
+        assert!(
+            other.attribute_equality(Attribute::CreatedAtCid, &PartialValue::new_cid(create_cid))
+        );
+    }
 }
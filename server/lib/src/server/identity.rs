@@ -145,7 +145,7 @@ impl Identity {
             source: Source::Internal,
             session_id: uuid!("00000000-0000-0000-0000-000000000000"),
             scope: AccessScope::ReadWrite,
-            limits: Limits::unlimited(),
+            limits: Limits::internal(),
         }
     }
 
@@ -197,6 +197,27 @@ impl Identity {
         }
     }
 
+    /// Capture the current operation for debugging, if this identity or the address it is from
+    /// is being captured.
+    pub(crate) fn capture_for_debug(&self) {
+        if !sketching::capture::is_capturing() {
+            return;
+        }
+        if let IdentType::User(u) = &self.origin {
+            let uuid = u.entry.get_uuid().as_hyphenated().to_string();
+            let spn = u.entry.get_uuid2spn().to_proto_string_clone();
+            let name = u
+                .entry
+                .get_ava_single_iname(Attribute::Name)
+                .unwrap_or_default();
+            sketching::capture::capture_account(&[name, &spn, &uuid]);
+        }
+        match &self.source {
+            Source::Https(ip) | Source::Ldaps(ip) => sketching::capture::capture_client_ip(*ip),
+            Source::Internal => {}
+        }
+    }
+
     pub fn get_user_entry(&self) -> Option<Arc<EntrySealedCommitted>> {
         match &self.origin {
             IdentType::Internal | IdentType::Synch(_) => None,
@@ -1,4 +1,5 @@
 use crate::value::CredentialType;
+use std::iter::once;
 use std::time::Duration;
 
 use crate::prelude::*;
@@ -760,7 +761,21 @@ impl<'a> QueryServerWriteTransaction<'a> {
             return Err(OperationError::MG0004DomainLevelInDevelopment);
         }
 
-        Ok(())
+        // Entries created before this level have no record of when they were created. The
+        // closest is the cid their change state began at.
+        let filter = filter!(f_and!([
+            f_pres(Attribute::Class),
+            f_andnot(f_pres(Attribute::CreatedAtCid))
+        ]));
+        let mut work_set = self.internal_search_writeable(&filter)?;
+        for (pre, entry) in work_set.iter_mut() {
+            let created = pre.get_changestate().at().clone();
+            entry.set_ava(Attribute::CreatedAtCid, once(Value::new_cid(created)));
+        }
+
+        self.internal_apply_writable(work_set).map(|()| {
+            info!("recorded the created at cid of existing entries");
+        })
     }
 
     #[instrument(level = "info", skip_all)]
@@ -1269,4 +1284,46 @@ mod tests {
 
         write_txn.commit().expect("Unable to commit");
     }
+
+    #[qs_test(domain_level=DOMAIN_LEVEL_7)]
+    async fn test_migrations_dl7_dl8(server: &QueryServer) {
+        let mut write_txn = server.write(duration_from_epoch_now()).await;
+
+        // Before level 8, the cid an entry was created at is not recorded.
+        let group_uuid = Uuid::new_v4();
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::Group.to_value()),
+            (Attribute::Name, Value::new_iname("testgroup")),
+            (Attribute::Uuid, Value::Uuid(group_uuid)),
+            (Attribute::CreatedAtCid, Value::new_cid(Cid::new_zero()))
+        );
+        write_txn
+            .internal_create(vec![e1])
+            .expect("Unable to create group");
+
+        let group = write_txn
+            .internal_search_uuid(group_uuid)
+            .expect("Unable to access group");
+        assert!(!group.attribute_pres(Attribute::CreatedAtCid));
+        let created = group.get_changestate().at().clone();
+
+        // Set the version to 8.
+        write_txn
+            .internal_apply_domain_migration(DOMAIN_LEVEL_8)
+            .expect("Unable to set domain level to version 8");
+
+        // Existing entries are given the cid their change state began at.
+        let group = write_txn
+            .internal_search_uuid(group_uuid)
+            .expect("Unable to access group");
+        assert!(group.attribute_equality(Attribute::CreatedAtCid, &PartialValue::new_cid(created)));
+
+        let domain_entry = write_txn
+            .internal_search_uuid(UUID_DOMAIN_INFO)
+            .expect("Unable to access domain entry");
+        assert!(domain_entry.attribute_pres(Attribute::CreatedAtCid));
+
+        write_txn.commit().expect("Unable to commit");
+    }
 }
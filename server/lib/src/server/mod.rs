@@ -1,6 +1,7 @@
 //! `server` contains the query server, which is the main high level construction
 //! to coordinate queries and operations in the server.
 
+use std::borrow::Borrow;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -11,9 +12,12 @@ use std::collections::BTreeSet;
 use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::trace;
 
-use kanidm_proto::internal::{DomainInfo as ProtoDomainInfo, UiHint};
+use kanidm_proto::internal::{DomainInfo as ProtoDomainInfo, FilterExplain, UiHint};
 
-use crate::be::{Backend, BackendReadTransaction, BackendTransaction, BackendWriteTransaction};
+use crate::be::{
+    Backend, BackendReadTransaction, BackendTransaction, BackendWriteTransaction,
+    DbMaintenanceResult,
+};
 // We use so many, we just import them all ...
 use crate::filter::{
     Filter, FilterInvalid, FilterValid, FilterValidResolved, ResolveFilterCache,
@@ -98,6 +102,9 @@ pub struct QueryServer {
     dyngroup_cache: Arc<CowCell<DynGroupCache>>,
     cid_max: Arc<CowCell<Cid>>,
     key_providers: Arc<KeyProviders>,
+    tombstone_max_age: u64,
+    recyclebin_max_age: u64,
+    entry_limits: EntryLimits,
 }
 
 pub struct QueryServerReadTransaction<'a> {
@@ -142,6 +149,8 @@ pub struct QueryServerWriteTransaction<'a> {
     curtime: Duration,
     cid: CowCellWriteTxn<'a, Cid>,
     trim_cid: Cid,
+    recyclebin_max_age: u64,
+    entry_limits: EntryLimits,
     pub(crate) be_txn: BackendWriteTransaction<'a>,
     pub(crate) schema: SchemaWriteTransaction<'a>,
     accesscontrols: AccessControlsWriteTransaction<'a>,
@@ -153,6 +162,8 @@ pub struct QueryServerWriteTransaction<'a> {
 
     // Store the list of changed uuids for other invalidation needs?
     pub(super) changed_uuid: HashSet<Uuid>,
+    // Warnings about the writes of this transaction, to be returned to the client.
+    warnings: BTreeSet<String>,
     _db_ticket: SemaphorePermit<'a>,
     _write_ticket: SemaphorePermit<'a>,
     resolve_filter_cache: ARCacheReadTxn<
@@ -168,6 +179,97 @@ impl<'a> QueryServerWriteTransaction<'a> {
     pub(crate) fn trim_cid(&self) -> &Cid {
         &self.trim_cid
     }
+
+    /// Check that entries written on behalf of a client are within the configured size limits.
+    /// Each entry is paired with the changes the client supplied to it. This is checked after
+    /// all pre plugins and schema validation, immediately before the entries are written.
+    /// Internal writes are not limited, so that the server can always maintain its own entries.
+    pub(crate) fn check_entry_limits<'e, STATE, VALID, M, I>(
+        &self,
+        ident: &Identity,
+        writes: I,
+    ) -> Result<(), OperationError>
+    where
+        STATE: Clone + 'e,
+        M: Borrow<ModifyList<VALID>>,
+        I: IntoIterator<Item = (&'e Entry<EntrySealed, STATE>, M)>,
+    {
+        if ident.is_internal() {
+            return Ok(());
+        }
+
+        writes.into_iter().try_for_each(|(e, supplied)| {
+            e.check_limits(&self.entry_limits, supplied.borrow())
+                .map_err(|err| {
+                    request_error!(?err, uuid = ?e.get_uuid(), "Entry exceeds the configured size limits");
+                    OperationError::SchemaViolation(err)
+                })
+        })
+    }
+
+    /// Warn about deprecated attributes or classes that a client has added or changed on these
+    /// entries. The write still proceeds, but the warnings are logged and kept so that they can
+    /// be returned with the response. Deprecated values that are only being removed, or that
+    /// were already present, are not warned about so that they can be cleaned up quietly.
+    /// Schema is only deprecated from domain level 8.
+    pub(crate) fn warn_deprecated<'e, STATE, I>(&mut self, ident: &Identity, changes: I)
+    where
+        STATE: Clone + 'e,
+        I: Iterator<
+            Item = (
+                Option<&'e EntrySealedCommitted>,
+                &'e Entry<EntrySealed, STATE>,
+            ),
+        >,
+    {
+        if ident.is_internal() || self.get_domain_version() < DOMAIN_LEVEL_8 {
+            return;
+        }
+
+        let attributes = self.schema.get_attributes();
+        let classes = self.schema.get_classes();
+
+        for (pre, post) in changes {
+            for (attr, vs) in post.get_ava_iter() {
+                let deprecated = attributes
+                    .get(attr)
+                    .map(|sa| sa.deprecated)
+                    .unwrap_or(false);
+                if deprecated && pre.and_then(|e| e.get_ava().get(attr)) != Some(vs) {
+                    admin_warn!(
+                        attr = %attr,
+                        uuid = ?post.get_uuid(),
+                        "Write uses a deprecated attribute"
+                    );
+                    self.warnings
+                        .insert(format!("attribute {attr} is deprecated"));
+                }
+            }
+
+            let pre_classes = pre.and_then(|e| e.get_ava_as_iutf8(Attribute::Class));
+            for class in post
+                .get_ava_iter_iutf8(Attribute::Class)
+                .into_iter()
+                .flatten()
+            {
+                let deprecated = classes.get(class).map(|sc| sc.deprecated).unwrap_or(false);
+                if deprecated && !pre_classes.is_some_and(|pc| pc.contains(class)) {
+                    admin_warn!(
+                        class = %class,
+                        uuid = ?post.get_uuid(),
+                        "Write uses a deprecated class"
+                    );
+                    self.warnings.insert(format!("class {class} is deprecated"));
+                }
+            }
+        }
+    }
+
+    /// Take the warnings about the writes made so far in this transaction, such as the use
+    /// of deprecated schema, so that they can be returned to the client.
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.warnings).into_iter().collect()
+    }
 }
 
 /// The `QueryServerTransaction` trait provides a set of common read only operations to be
@@ -220,6 +322,13 @@ pub trait QueryServerTransaction<'a> {
         &mut ResolveFilterCacheReadTxn<'a>,
     );
 
+    fn get_be_txn_and_accesscontrols(
+        &mut self,
+    ) -> (
+        &mut Self::BackendTransactionType,
+        &Self::AccessControlsTransactionType,
+    );
+
     /// Conduct a search and apply access controls to yield a set of entries that
     /// have been reduced to the set of user visible avas. Note that if you provide
     /// a `SearchEvent` for the internal user, this query will fail. It is invalid for
@@ -290,20 +399,55 @@ pub trait QueryServerTransaction<'a> {
         // the QS wr/ro to the plugin trait. However, there shouldn't be a need for search
         // plugins, because all data transforms should be in the write path.
 
-        let res = self.get_be_txn().search(lims, &vfr).map_err(|e| {
+        let (be_txn, access) = self.get_be_txn_and_accesscontrols();
+        let backend_failure = |e| {
             admin_error!(?e, "backend failure");
             OperationError::Backend
-        })?;
+        };
+        let res = be_txn.search_cursor(lims, &vfr).map_err(backend_failure)?;
 
-        // Apply ACP before we let the plugins "have at it".
+        // Apply ACP before we let the plugins "have at it". Entries are checked as the
+        // backend yields them, so denied entries are never accumulated.
         // WARNING; for external searches this is NOT the only
         // ACP application. There is a second application to reduce the
         // attribute set on the entries!
         //
-        let access = self.get_accesscontrols();
-        access.search_filter_entries(se, res).map_err(|e| {
-            admin_error!(?e, "Unable to access filter entries");
-            e
+        access
+            .filter_entries_iter(
+                &se.ident,
+                &se.filter_orig,
+                res.map(|r| r.map_err(backend_failure)),
+            )
+            .map_err(|e| {
+                admin_error!(?e, "Unable to access filter entries");
+                e
+            })
+    }
+
+    /// Report how the backend would execute the filter of a search, without loading any
+    /// entries. The candidate count is taken before access controls are applied, so this is
+    /// only available to internal identities.
+    #[instrument(level = "debug", skip_all)]
+    fn explain(&mut self, se: &SearchEvent) -> Result<FilterExplain, OperationError> {
+        if !se.ident.is_internal() {
+            security_access!(initiator = %se.ident, "explain denied to non-internal identity");
+            return Err(OperationError::AccessDenied);
+        }
+
+        let (be_txn, resolve_filter_cache) = self.get_resolve_filter_cache_and_be_txn();
+        let idxmeta = be_txn.get_idxmeta_ref();
+
+        let vfr = se
+            .filter
+            .resolve(&se.ident, Some(idxmeta), Some(resolve_filter_cache))
+            .map_err(|e| {
+                admin_error!(?e, "explain filter resolve failure");
+                e
+            })?;
+
+        self.get_be_txn().explain(&vfr).map_err(|e| {
+            admin_error!(?e, "backend failure");
+            OperationError::Backend
         })
     }
 
@@ -592,6 +736,7 @@ pub trait QueryServerTransaction<'a> {
         // Lookup the attr
         match schema.get_attributes().get(attr) {
             Some(schema_a) => {
+                let value = schema_a.normalise_value_str(value);
                 match schema_a.syntax {
                     SyntaxType::Utf8String => Ok(Value::new_utf8(value.to_string())),
                     SyntaxType::Utf8StringInsensitive => Ok(Value::new_iutf8(value)),
@@ -684,6 +829,9 @@ pub trait QueryServerTransaction<'a> {
         // Lookup the attr
         match schema.get_attributes().get(attr) {
             Some(schema_a) => {
+                // Normalised in the same way as clone_value, so that filters match what was
+                // stored.
+                let value = schema_a.normalise_value_str(value);
                 match schema_a.syntax {
                     SyntaxType::Utf8String | SyntaxType::TotpSecret => {
                         Ok(PartialValue::new_utf8(value.to_string()))
@@ -1064,6 +1212,15 @@ impl<'a> QueryServerTransaction<'a> for QueryServerReadTransaction<'a> {
         (&mut self.be_txn, &mut self.resolve_filter_cache)
     }
 
+    fn get_be_txn_and_accesscontrols(
+        &mut self,
+    ) -> (
+        &mut BackendReadTransaction<'a>,
+        &AccessControlsReadTransaction<'a>,
+    ) {
+        (&mut self.be_txn, &self.accesscontrols)
+    }
+
     fn pw_badlist(&self) -> &HashSet<String> {
         &self.system_config.pw_badlist
     }
@@ -1117,7 +1274,7 @@ impl<'a> QueryServerReadTransaction<'a> {
     /// Verify the data content of the server is as expected. This will probably
     /// call various functions for validation, including possibly plugin
     /// verifications.
-    pub(crate) fn verify(&mut self) -> Vec<Result<(), ConsistencyError>> {
+    pub fn verify(&mut self) -> Vec<Result<(), ConsistencyError>> {
         // If we fail after backend, we need to return NOW because we can't
         // assert any other faith in the DB states.
         //  * backend
@@ -1215,6 +1372,15 @@ impl<'a> QueryServerTransaction<'a> for QueryServerWriteTransaction<'a> {
         (&mut self.be_txn, &mut self.resolve_filter_cache)
     }
 
+    fn get_be_txn_and_accesscontrols(
+        &mut self,
+    ) -> (
+        &mut BackendWriteTransaction<'a>,
+        &AccessControlsWriteTransaction<'a>,
+    ) {
+        (&mut self.be_txn, &self.accesscontrols)
+    }
+
     fn pw_badlist(&self) -> &HashSet<String> {
         &self.system_config.pw_badlist
     }
@@ -1268,6 +1434,9 @@ impl QueryServer {
         };
 
         let pool_size = be.get_pool_size();
+        let tombstone_max_age = be.get_tombstone_max_age();
+        let recyclebin_max_age = be.get_recyclebin_max_age();
+        let entry_limits = be.get_entry_limits();
 
         debug!("Server UUID -> {:?}", s_uuid);
         debug!("Domain UUID -> {:?}", d_uuid);
@@ -1328,6 +1497,19 @@ impl QueryServer {
             dyngroup_cache,
             cid_max,
             key_providers,
+            tombstone_max_age,
+            recyclebin_max_age,
+            entry_limits,
+        })
+    }
+
+    /// The point before which tombstones and change states can be trimmed. The age is checked
+    /// when the server starts, so this can only fail if the clock has moved back a long way, in
+    /// which case nothing is trimmed.
+    fn trim_cid(cid: &Cid, tombstone_max_age: u64) -> Cid {
+        cid.sub_secs(tombstone_max_age).unwrap_or_else(|err| {
+            error!(?err, %tombstone_max_age, "Unable to generate trim cid, nothing will be trimmed");
+            Cid::new_zero()
         })
     }
 
@@ -1374,10 +1556,7 @@ impl QueryServer {
         let schema = self.schema.read();
 
         let cid_max = self.cid_max.read();
-        #[allow(clippy::expect_used)]
-        let trim_cid = cid_max
-            .sub_secs(CHANGELOG_MAX_AGE)
-            .expect("unable to generate trim cid");
+        let trim_cid = Self::trim_cid(&cid_max, self.tombstone_max_age);
 
         #[allow(clippy::expect_used)]
         QueryServerReadTransaction {
@@ -1397,6 +1576,29 @@ impl QueryServer {
         }
     }
 
+    /// Run maintenance on the database, see [Backend::maintenance]. This waits for, and then
+    /// blocks, writers until it completes. Readers are not blocked.
+    pub async fn db_maintenance(
+        &self,
+        vacuum: bool,
+    ) -> Result<DbMaintenanceResult, OperationError> {
+        #[allow(clippy::expect_used)]
+        let _write_ticket = self
+            .write_ticket
+            .acquire()
+            .await
+            .expect("unable to acquire writer_ticket for maintenance");
+
+        #[allow(clippy::expect_used)]
+        let _db_ticket = self
+            .db_tickets
+            .acquire()
+            .await
+            .expect("unable to acquire db_ticket for maintenance");
+
+        self.be.maintenance(vacuum)
+    }
+
     pub async fn write(&self, curtime: Duration) -> QueryServerWriteTransaction<'_> {
         // Guarantee we are the only writer on the thread pool
         #[allow(clippy::expect_used)]
@@ -1446,10 +1648,7 @@ impl QueryServer {
         // Update the cid now.
         *cid = Cid::new_lamport(cid.s_uuid, curtime, &cid.ts);
 
-        #[allow(clippy::expect_used)]
-        let trim_cid = cid
-            .sub_secs(CHANGELOG_MAX_AGE)
-            .expect("unable to generate trim cid");
+        let trim_cid = Self::trim_cid(&cid, self.tombstone_max_age);
 
         QueryServerWriteTransaction {
             // I think this is *not* needed, because commit is mut self which should
@@ -1465,11 +1664,14 @@ impl QueryServer {
             curtime,
             cid,
             trim_cid,
+            recyclebin_max_age: self.recyclebin_max_age,
+            entry_limits: self.entry_limits,
             be_txn,
             schema: schema_write,
             accesscontrols: self.accesscontrols.write(),
             changed_flags: ChangeFlag::empty(),
             changed_uuid: HashSet::new(),
+            warnings: BTreeSet::new(),
             _db_ticket: db_ticket,
             _write_ticket: write_ticket,
             resolve_filter_cache: self.resolve_filter_cache.read(),
@@ -1922,8 +2124,11 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
         // This is here to catch when we increase domain levels but didn't create the migration
         // hooks. If this fails it probably means you need to add another migration hook
-        // in the above.
-        debug_assert!(domain_info_version <= DOMAIN_MAX_LEVEL);
+        // in the above. Tests may also raise to the level in development.
+        debug_assert!(
+            domain_info_version <= DOMAIN_MAX_LEVEL
+                || (cfg!(test) && domain_info_version <= DOMAIN_NEXT_LEVEL)
+        );
 
         Ok(())
     }
@@ -2036,6 +2241,17 @@ impl<'a> QueryServerWriteTransaction<'a> {
         *self.phase
     }
 
+    /// Refuse a write that would grow the database past its quota, where `count` entries are
+    /// created or revived. Migrations must always be able to complete, so the quota only applies
+    /// once the server is running. It then applies to internal writes as well, such as the
+    /// entries applied by a sync.
+    pub(crate) fn check_quota(&mut self, count: usize) -> Result<(), OperationError> {
+        if self.get_phase() < ServerPhase::Running {
+            return Ok(());
+        }
+        self.be_txn.check_quota(count)
+    }
+
     pub(crate) fn reload(&mut self) -> Result<(), OperationError> {
         // First, check if the domain version has changed. This can trigger
         // changes to schema, access controls and more.
@@ -2048,12 +2264,15 @@ impl<'a> QueryServerWriteTransaction<'a> {
         //
         // Reload the schema from qs.
         if self.changed_flags.contains(ChangeFlag::SCHEMA) {
+            let index_pre = self.schema.index_declarations();
             self.reload_schema()?;
 
             // If the server is in a late phase of start up or is
-            // operational, then a reindex may be required. After the reindex, the schema
-            // must also be reloaded so that slope optimisation indexes are loaded correctly.
-            if *self.phase >= ServerPhase::Running {
+            // operational, then a reindex may be required if the indexes declared by schema
+            // changed. After the reindex, the schema must also be reloaded so that slope
+            // optimisation indexes are loaded correctly.
+            if *self.phase >= ServerPhase::Running && index_pre != self.schema.index_declarations()
+            {
                 self.reindex()?;
                 self.reload_schema()?;
             }
@@ -2174,6 +2393,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
+    use kanidm_proto::internal::FilterExplainIndex;
 
     #[qs_test]
     async fn test_name_to_uuid(server: &QueryServer) {
@@ -2212,6 +2432,75 @@ mod tests {
         assert!(r6 == Ok(t_uuid));
     }
 
+    #[qs_test]
+    async fn test_explain_filter(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+
+        assert!(server_txn
+            .internal_create(vec![entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Class, EntryClass::Account.to_value()),
+                (Attribute::Class, EntryClass::Person.to_value()),
+                (Attribute::Name, Value::new_iname("testperson1")),
+                (Attribute::Description, Value::new_utf8s("testperson1")),
+                (Attribute::DisplayName, Value::new_utf8s("testperson1"))
+            ),])
+            .is_ok());
+
+        // Name is indexed, so only the matching entry is a candidate.
+        let se = SearchEvent::new_internal_invalid(filter!(f_eq(
+            Attribute::Name,
+            PartialValue::new_iname("testperson1")
+        )));
+        let explain = server_txn.explain(&se).expect("Failed to explain filter");
+        assert_eq!(explain.candidates, 1);
+        assert!(!explain.full_scan);
+        assert!(!explain.filter_test);
+
+        // GidNumber has no ordering index, so every entry must be loaded and tested.
+        let se = SearchEvent::new_internal_invalid(filter!(f_lt(
+            Attribute::GidNumber,
+            PartialValue::new_uint32(1000)
+        )));
+        let explain = server_txn.explain(&se).expect("Failed to explain filter");
+        assert!(explain.full_scan);
+        assert!(explain.filter_test);
+        assert!(explain.candidates > 1);
+        assert_eq!(explain.plan.term, "lt");
+        assert_eq!(explain.plan.index, FilterExplainIndex::Unindexed);
+
+        // Each term is reported with its own candidates, showing which is unindexed.
+        let se = SearchEvent::new_internal_invalid(filter!(f_and!([
+            f_eq(Attribute::Name, PartialValue::new_iname("testperson1")),
+            f_lt(Attribute::GidNumber, PartialValue::new_uint32(1000))
+        ])));
+        let explain = server_txn.explain(&se).expect("Failed to explain filter");
+        assert!(!explain.full_scan);
+        assert!(explain.filter_test);
+        assert_eq!(explain.candidates, 1);
+        assert_eq!(explain.plan.term, "and");
+        assert_eq!(explain.plan.index, FilterExplainIndex::Partial);
+
+        let name_term = explain
+            .plan
+            .terms
+            .iter()
+            .find(|t| t.term == "eq")
+            .expect("no eq term");
+        assert_eq!(name_term.attr.as_deref(), Some(Attribute::Name.as_ref()));
+        assert_eq!(name_term.index, FilterExplainIndex::Indexed);
+        assert_eq!(name_term.candidates, 1);
+
+        let gid_term = explain
+            .plan
+            .terms
+            .iter()
+            .find(|t| t.term == "lt")
+            .expect("no lt term");
+        assert_eq!(gid_term.index, FilterExplainIndex::Unindexed);
+        assert!(gid_term.candidates > 1);
+    }
+
     #[qs_test]
     async fn test_external_id_to_uuid(server: &QueryServer) {
         let mut server_txn = server.write(duration_from_epoch_now()).await;
@@ -2349,6 +2638,37 @@ mod tests {
 
         debug!("{:?}", r4);
         assert!(r4 == Ok(Value::Refer(uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))));
+
+        // test names, insensitive strings and references are normalised, regardless of how
+        // the client wrote them.
+        let r5 = server_txn.clone_value("name", " TestPerson1\n");
+        assert!(r5 == Ok(Value::new_iname("testperson1")));
+
+        let r6 = server_txn.clone_value("member", " CC8E95B4-C24F-4D68-BA54-8BED76F63930 ");
+        assert!(r6 == Ok(Value::Refer(uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))));
+
+        let r7 = server_txn.clone_partialvalue("name", "TESTPERSON1 ");
+        assert!(r7 == Ok(PartialValue::new_iname("testperson1")));
+
+        let r8 = server_txn.clone_value("loginshell", " /bin/Zsh ");
+        assert!(r8 == Ok(Value::new_iutf8("/bin/zsh")));
+
+        let r9 = server_txn.clone_value("uuid", "\tcc8e95b4-c24f-4d68-ba54-8bed76f63930 ");
+        assert!(r9 == Ok(Value::Uuid(uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))));
+
+        let r10 = server_txn.clone_partialvalue("member", " testperson1 ");
+        assert!(
+            r10 == Ok(PartialValue::Refer(uuid!(
+                "cc8e95b4-c24f-4d68-ba54-8bed76f63930"
+            )))
+        );
+
+        // test free text is kept as it was written.
+        let r11 = server_txn.clone_value("displayname", " Test Person 1 ");
+        assert!(r11 == Ok(Value::new_utf8s(" Test Person 1 ")));
+
+        let r12 = server_txn.clone_partialvalue("description", "testperson1 ");
+        assert!(r12 == Ok(PartialValue::new_utf8s("testperson1 ")));
     }
 
     #[qs_test]
@@ -2504,4 +2824,125 @@ mod tests {
         server_txn.commit().expect("should not fail");
         // Commit.
     }
+
+    #[qs_test]
+    async fn test_dynamic_schema_attr_index(server: &QueryServer) {
+        let e1 = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ExtensibleObject.to_value()),
+            (Attribute::Name, Value::new_iname("testobj1")),
+            (
+                Attribute::Uuid,
+                Value::Uuid(uuid!("cc8e95b4-c24f-4d68-ba54-8bed76f63930"))
+            ),
+            (Attribute::TestAttr, Value::new_utf8s("test"))
+        );
+
+        // Attribute definition, without any index.
+        let attr_uuid = uuid!("cfcae205-31c3-484b-8ced-667d1709c5e3");
+        let e_ad = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::AttributeType.to_value()),
+            (Attribute::Uuid, Value::Uuid(attr_uuid)),
+            (Attribute::AttributeName, Attribute::TestAttr.to_value()),
+            (Attribute::Description, Value::new_utf8s("Test Attribute")),
+            (Attribute::MultiValue, Value::new_bool(false)),
+            (Attribute::Unique, Value::new_bool(false)),
+            (
+                Attribute::Syntax,
+                Value::new_syntaxs("UTF8STRING").expect("syntax")
+            )
+        );
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        assert!(server_txn.internal_create(vec![e_ad]).is_ok());
+        server_txn.commit().expect("should not fail");
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        assert!(server_txn.internal_create(vec![e1]).is_ok());
+        server_txn.commit().expect("should not fail");
+
+        // Declaring an index rebuilds the indexes, so the existing entry is found through it.
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        assert!(server_txn
+            .internal_modify_uuid(
+                attr_uuid,
+                &ModifyList::new_append(Attribute::Index, Value::Index(IndexType::Equality))
+            )
+            .is_ok());
+        server_txn.commit().expect("should not fail");
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        let filt = filter!(f_eq(Attribute::TestAttr, PartialValue::new_utf8s("test")));
+        let found = server_txn.internal_search(filt).expect("search failure");
+        assert_eq!(found.len(), 1);
+        server_txn.commit().expect("should not fail");
+
+        let verify = server.verify().await;
+        assert!(verify.is_empty());
+    }
+
+    #[qs_test]
+    async fn test_deprecated_schema_warning(server: &QueryServer) {
+        let e_ad = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::AttributeType.to_value()),
+            (
+                Attribute::Uuid,
+                Value::Uuid(uuid!("cfcae205-31c3-484b-8ced-667d1709c5e3"))
+            ),
+            (Attribute::AttributeName, Attribute::TestAttr.to_value()),
+            (Attribute::Description, Value::new_utf8s("Test Attribute")),
+            (Attribute::MultiValue, Value::new_bool(false)),
+            (Attribute::Unique, Value::new_bool(false)),
+            (Attribute::Deprecated, Value::new_bool(true)),
+            (
+                Attribute::Syntax,
+                Value::new_syntaxs("UTF8STRING").expect("syntax")
+            )
+        );
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        assert!(server_txn.internal_create(vec![e_ad]).is_ok());
+        server_txn.commit().expect("should not fail");
+
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+        let admin = server_txn.internal_search_uuid(UUID_ADMIN).expect("failed");
+        let ident = Identity::from_impersonate_entry_readwrite(admin);
+
+        let pre = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ExtensibleObject.to_value()),
+            (Attribute::Name, Value::new_iname("testobj1"))
+        )
+        .into_sealed_committed();
+        let post = entry_init!(
+            (Attribute::Class, EntryClass::Object.to_value()),
+            (Attribute::Class, EntryClass::ExtensibleObject.to_value()),
+            (Attribute::Name, Value::new_iname("testobj1")),
+            (Attribute::TestAttr, Value::new_utf8s("test"))
+        )
+        .into_sealed_committed();
+
+        // Nothing is deprecated until domain level 8.
+        server_txn.warn_deprecated(&ident, std::iter::once((Some(&pre), &post)));
+        assert!(server_txn.take_warnings().is_empty());
+
+        server_txn
+            .internal_apply_domain_migration(DOMAIN_LEVEL_8)
+            .expect("Unable to set domain level to version 8");
+
+        // Adding a value to a deprecated attribute is warned about.
+        server_txn.warn_deprecated(&ident, std::iter::once((Some(&pre), &post)));
+        assert_eq!(
+            server_txn.take_warnings(),
+            vec!["attribute testattr is deprecated".to_string()]
+        );
+        assert!(server_txn.take_warnings().is_empty());
+
+        // But an existing value that is left unchanged is not, nor are internal writes.
+        server_txn.warn_deprecated(&ident, std::iter::once((Some(&post), &post)));
+        server_txn.warn_deprecated(&Identity::from_internal(), std::iter::once((None, &post)));
+        assert!(server_txn.take_warnings().is_empty());
+    }
 }
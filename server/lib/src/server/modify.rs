@@ -47,6 +47,12 @@ impl<'a> QueryServerWriteTransaction<'a> {
             return Err(OperationError::EmptyRequest);
         }
 
+        // Only changes that add values are refused by the quota, so that values can still be
+        // removed to recover space.
+        if me.modlist.adds_values() {
+            self.check_quota(0)?;
+        }
+
         // Is the modlist valid?
         // This is now done in the event transform
 
@@ -155,6 +161,15 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
         let norm_cand: Vec<Entry<_, _>> = res?;
 
+        self.check_entry_limits(&me.ident, norm_cand.iter().map(|e| (e, &me.modlist)))?;
+        self.warn_deprecated(
+            &me.ident,
+            pre_candidates
+                .iter()
+                .map(|e| Some(e.as_ref()))
+                .zip(norm_cand.iter()),
+        );
+
         Ok(Some(ModifyPartial {
             norm_cand,
             pre_candidates,
@@ -712,6 +727,35 @@ mod tests {
             .is_ok());
     }
 
+    #[qs_test]
+    async fn test_modify_quota(server: &QueryServer) {
+        let mut server_txn = server.write(duration_from_epoch_now()).await;
+
+        let t_uuid = Uuid::new_v4();
+        assert!(server_txn
+            .internal_create(vec![entry_init!(
+                (Attribute::Class, EntryClass::Object.to_value()),
+                (Attribute::Uuid, Value::Uuid(t_uuid)),
+                (Attribute::Description, Value::Utf8("test".into()))
+            ),])
+            .is_ok());
+
+        // The database is already larger than this.
+        server_txn.get_be_txn().set_quota(None, Some(1));
+
+        // Changes that add values are refused, even when internal, but values can be removed.
+        assert_eq!(
+            server_txn.internal_modify_uuid(
+                t_uuid,
+                &ModifyList::new_purge_and_set(Attribute::Description, Value::Utf8("grown".into()))
+            ),
+            Err(OperationError::DB0007SizeQuotaExceeded)
+        );
+        assert!(server_txn
+            .internal_modify_uuid(t_uuid, &ModifyList::new_purge(Attribute::Description))
+            .is_ok());
+    }
+
     #[qs_test]
     async fn test_modify_invalid_class(server: &QueryServer) {
         // Test modifying an entry and adding an extra class, that would cause the entry
@@ -28,7 +28,7 @@ impl<'a> QueryServerWriteTransaction<'a> {
     pub fn purge_recycled(&mut self) -> Result<usize, OperationError> {
         // Send everything that is recycled to tombstone
         // Search all recycled
-        let cid = self.cid.sub_secs(RECYCLEBIN_MAX_AGE).map_err(|e| {
+        let cid = self.cid.sub_secs(self.recyclebin_max_age).map_err(|e| {
             admin_error!(err = ?e, "Unable to generate search cid for purge_recycled");
             e
         })?;
@@ -109,6 +109,9 @@ impl<'a> QueryServerWriteTransaction<'a> {
 
         trace!("revive: pre_candidates -> {:?}", pre_candidates);
 
+        // The revived entries are live again, so they count towards the quota.
+        self.check_quota(pre_candidates.len())?;
+
         // Check access against a "fake" modify.
         let modlist = ModifyList::new_list(vec![Modify::Removed(
             Attribute::Class.into(),
@@ -248,6 +251,9 @@ mod tests {
     use crate::server::SearchEvent;
 
     use super::ReviveRecycledEvent;
+    use crate::be::{Backend, BackendConfig};
+    use crate::schema::Schema;
+    use kanidm_proto::internal::FsType;
 
     #[qs_test]
     async fn test_recycle_simple(server: &QueryServer) {
@@ -611,6 +617,179 @@ mod tests {
         assert!(server_txn.commit().is_ok());
     }
 
+    #[tokio::test]
+    async fn test_search_max_candidates_internal() {
+        sketching::test_init();
+        // A server that refuses any client search with more than one candidate.
+        let schema = Schema::new().expect("Failed to init schema");
+        let idxmeta = schema.write().reload_idxmeta();
+        let cfg =
+            BackendConfig::new("", 1, FsType::Generic, Some(1024)).search_max_candidates(Some(1));
+        let be = Backend::new(cfg, idxmeta, false).expect("Failed to init BE");
+        let server = QueryServer::new(be, schema, "example.com".to_string(), Duration::ZERO)
+            .expect("Failed to setup Query Server");
+        // Migrations search the whole database, and must not be limited.
+        server
+            .initialise_helper(duration_from_epoch_now(), DOMAIN_TGT_LEVEL)
+            .await
+            .expect("init failed!");
+
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + Duration::from_secs(RECYCLEBIN_MAX_AGE * 2);
+        let time_p3 = time_p2 + Duration::from_secs(CHANGELOG_MAX_AGE * 2);
+
+        let mut server_txn = server.write(time_p1).await;
+        let ce = CreateEvent::new_internal(vec![
+            create_user("testperson1", "5d3ee8cf-43b6-4ab9-8f6d-7b0b0b8ff1b5"),
+            create_user("testperson2", "0b7ad1b1-a39f-4ef1-a8e6-8e8c7b1cd6f4"),
+        ]);
+        assert!(server_txn.create(&ce).is_ok());
+        let de_sin = DeleteEvent::new_internal_invalid(filter!(f_or!([
+            f_eq(Attribute::Name, PartialValue::new_iname("testperson1")),
+            f_eq(Attribute::Name, PartialValue::new_iname("testperson2"))
+        ])));
+        assert!(server_txn.delete(&de_sin).is_ok());
+
+        // A client search is still refused.
+        let admin = server_txn.internal_search_uuid(UUID_ADMIN).expect("failed");
+        let se = SearchEvent::new_impersonate_entry(admin, filter!(f_pres(Attribute::Class)));
+        assert_eq!(
+            server_txn.search(&se).map(|r| r.len()),
+            Err(OperationError::DB0003SearchCandidateLimitExceeded)
+        );
+        assert!(server_txn.commit().is_ok());
+
+        // Purges find both entries, which is more than the limit.
+        let filt_i_ts = filter_all!(f_eq(Attribute::Class, EntryClass::Tombstone.into()));
+
+        let mut server_txn = server.write(time_p2).await;
+        assert!(server_txn.purge_recycled().is_ok());
+        let r1 = server_txn
+            .internal_search(filt_i_ts.clone())
+            .expect("internal search failed");
+        assert!(r1.len() == 2);
+        assert!(server_txn.commit().is_ok());
+
+        let mut server_txn = server.write(time_p3).await;
+        assert!(server_txn.purge_tombstones().is_ok());
+        let r2 = server_txn
+            .internal_search(filt_i_ts)
+            .expect("internal search failed");
+        assert!(r2.is_empty());
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_tombstone_max_age() {
+        sketching::test_init();
+        // A server that keeps tombstones for a minute, rather than the changelog max age.
+        let schema = Schema::new().expect("Failed to init schema");
+        let idxmeta = schema.write().reload_idxmeta();
+        let cfg =
+            BackendConfig::new("", 1, FsType::Generic, Some(1024)).tombstone_max_age(Some(60));
+        let be = Backend::new(cfg, idxmeta, false).expect("Failed to init BE");
+        let server = QueryServer::new(be, schema, "example.com".to_string(), Duration::ZERO)
+            .expect("Failed to setup Query Server");
+        server
+            .initialise_helper(duration_from_epoch_now(), DOMAIN_TGT_LEVEL)
+            .await
+            .expect("init failed!");
+
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + Duration::from_secs(RECYCLEBIN_MAX_AGE * 2);
+        let time_p3 = time_p2 + Duration::from_secs(120);
+        assert!(time_p3 < time_p2 + Duration::from_secs(CHANGELOG_MAX_AGE));
+
+        let filt_i_ts = filter_all!(f_eq(Attribute::Class, EntryClass::Tombstone.into()));
+
+        let mut server_txn = server.write(time_p1).await;
+        let ce = CreateEvent::new_internal(vec![create_user(
+            "testperson1",
+            "9557f49c-97a5-4277-a9a5-097d17eb8317",
+        )]);
+        assert!(server_txn.create(&ce).is_ok());
+        let de_sin = DeleteEvent::new_internal_invalid(filter!(f_eq(
+            Attribute::Name,
+            PartialValue::new_iname("testperson1")
+        )));
+        assert!(server_txn.delete(&de_sin).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        // The entry becomes a tombstone, which is within the window.
+        let mut server_txn = server.write(time_p2).await;
+        assert!(server_txn.purge_recycled().is_ok());
+        assert!(server_txn.purge_tombstones().is_ok());
+        let r1 = server_txn
+            .internal_search(filt_i_ts.clone())
+            .expect("internal search failed");
+        assert!(r1.len() == 1);
+        assert!(server_txn.commit().is_ok());
+
+        // Past the configured window, but not the default, it is purged.
+        let mut server_txn = server.write(time_p3).await;
+        assert!(server_txn.purge_tombstones().is_ok());
+        let r2 = server_txn
+            .internal_search(filt_i_ts)
+            .expect("internal search failed");
+        assert!(r2.is_empty());
+        assert!(server_txn.commit().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_recyclebin_max_age() {
+        sketching::test_init();
+        // A server that keeps deleted entries in the recycle bin for a minute.
+        let schema = Schema::new().expect("Failed to init schema");
+        let idxmeta = schema.write().reload_idxmeta();
+        let cfg =
+            BackendConfig::new("", 1, FsType::Generic, Some(1024)).recyclebin_max_age(Some(60));
+        let be = Backend::new(cfg, idxmeta, false).expect("Failed to init BE");
+        let server = QueryServer::new(be, schema, "example.com".to_string(), Duration::ZERO)
+            .expect("Failed to setup Query Server");
+        server
+            .initialise_helper(duration_from_epoch_now(), DOMAIN_TGT_LEVEL)
+            .await
+            .expect("init failed!");
+
+        let time_p1 = duration_from_epoch_now();
+        let time_p2 = time_p1 + Duration::from_secs(30);
+        let time_p3 = time_p1 + Duration::from_secs(120);
+        assert!(time_p3 < time_p1 + Duration::from_secs(RECYCLEBIN_MAX_AGE));
+
+        let filt_i_rc = filter_all!(f_eq(Attribute::Class, EntryClass::Recycled.into()));
+
+        let mut server_txn = server.write(time_p1).await;
+        let ce = CreateEvent::new_internal(vec![create_user(
+            "testperson1",
+            "7e0f4ff2-6a5f-4bd8-9bda-4c58a5ae3b42",
+        )]);
+        assert!(server_txn.create(&ce).is_ok());
+        let de_sin = DeleteEvent::new_internal_invalid(filter!(f_eq(
+            Attribute::Name,
+            PartialValue::new_iname("testperson1")
+        )));
+        assert!(server_txn.delete(&de_sin).is_ok());
+        assert!(server_txn.commit().is_ok());
+
+        // Within the window, the entry stays in the recycle bin.
+        let mut server_txn = server.write(time_p2).await;
+        assert!(server_txn.purge_recycled().is_ok());
+        let r1 = server_txn
+            .internal_search(filt_i_rc.clone())
+            .expect("internal search failed");
+        assert!(r1.len() == 1);
+        assert!(server_txn.commit().is_ok());
+
+        // Past the configured window, but not the default, it is purged.
+        let mut server_txn = server.write(time_p3).await;
+        assert!(server_txn.purge_recycled().is_ok());
+        let r2 = server_txn
+            .internal_search(filt_i_rc)
+            .expect("internal search failed");
+        assert!(r2.is_empty());
+        assert!(server_txn.commit().is_ok());
+    }
+
     fn create_user(name: &str, uuid: &str) -> Entry<EntryInit, EntryNew> {
         entry_init!(
             (Attribute::Class, EntryClass::Object.to_value()),
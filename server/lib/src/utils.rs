@@ -2,12 +2,30 @@ use crate::prelude::*;
 use hashbrown::HashSet;
 use rand::distributions::{Distribution, Uniform};
 use rand::{thread_rng, Rng};
+use std::collections::BTreeSet;
 
 #[derive(Debug)]
 pub struct DistinctAlpha;
 
 pub type Sid = [u8; 4];
 
+/// The overlapping sequences of three characters in a value, that substring indexes are keyed
+/// by. A value shorter than three characters has none.
+pub(crate) fn trigram_iter(value: &str) -> impl Iterator<Item = &str> {
+    let bounds: Vec<usize> = value
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .chain(std::iter::once(value.len()))
+        .collect();
+    (0..bounds.len().saturating_sub(3)).map(move |i| &value[bounds[i]..bounds[i + 3]])
+}
+
+/// The distinct trigrams of a set of values, as substring index keys.
+pub(crate) fn trigram_idx_keys<'a>(values: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let keys: BTreeSet<&str> = values.flat_map(trigram_iter).collect();
+    keys.into_iter().map(str::to_string).collect()
+}
+
 pub fn uuid_to_gid_u32(u: Uuid) -> u32 {
     let b_ref = u.as_bytes();
     let mut x: [u8; 4] = [0; 4];
@@ -90,7 +108,7 @@ mod tests {
     use crate::prelude::*;
     use std::time::Duration;
 
-    use crate::utils::{uuid_from_duration, uuid_to_gid_u32};
+    use crate::utils::{trigram_idx_keys, trigram_iter, uuid_from_duration, uuid_to_gid_u32};
 
     #[test]
     fn test_utils_uuid_from_duration() {
@@ -121,4 +139,22 @@ mod tests {
         let r3 = uuid_to_gid_u32(u3);
         assert!(r3 == 0x12345678);
     }
+
+    #[test]
+    fn test_utils_trigram_iter() {
+        assert_eq!(
+            trigram_iter("william").collect::<Vec<_>>(),
+            vec!["wil", "ill", "lli", "lia", "iam"]
+        );
+        assert_eq!(trigram_iter("abc").collect::<Vec<_>>(), vec!["abc"]);
+        assert!(trigram_iter("ab").next().is_none());
+        assert!(trigram_iter("").next().is_none());
+        // Trigrams are of characters, not bytes.
+        assert_eq!(trigram_iter("jöns").collect::<Vec<_>>(), vec!["jön", "öns"]);
+
+        assert_eq!(
+            trigram_idx_keys(["aaaa", "aaab"].into_iter()),
+            vec!["aaa", "aab"]
+        );
+    }
 }
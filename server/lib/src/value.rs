@@ -38,6 +38,7 @@ use crate::credential::{totp::Totp, Credential};
 use crate::prelude::*;
 use crate::repl::cid::Cid;
 use crate::server::identity::IdentityId;
+use crate::utils::trigram_idx_keys;
 use crate::valueset::image::ImageValueThings;
 use crate::valueset::uuid_to_proto_string;
 
@@ -176,6 +177,7 @@ pub enum IndexType {
     Equality,
     Presence,
     SubString,
+    Ordering,
 }
 
 impl TryFrom<&str> for IndexType {
@@ -187,6 +189,7 @@ impl TryFrom<&str> for IndexType {
             "EQUALITY" => Ok(IndexType::Equality),
             "PRESENCE" => Ok(IndexType::Presence),
             "SUBSTRING" => Ok(IndexType::SubString),
+            "ORDERING" => Ok(IndexType::Ordering),
             // UUID map?
             // UUID rev map?
             _ => Err(()),
@@ -200,6 +203,7 @@ impl IndexType {
             IndexType::Equality => "eq",
             IndexType::Presence => "pres",
             IndexType::SubString => "sub",
+            IndexType::Ordering => "ord",
         }
     }
 }
@@ -213,6 +217,7 @@ impl fmt::Display for IndexType {
                 IndexType::Equality => "EQUALITY",
                 IndexType::Presence => "PRESENCE",
                 IndexType::SubString => "SUBSTRING",
+                IndexType::Ordering => "ORDERING",
             }
         )
     }
@@ -891,9 +896,37 @@ impl PartialValue {
         }
     }
 
-    #[allow(clippy::unimplemented)]
-    pub fn get_idx_sub_key(&self) -> String {
-        unimplemented!();
+    /// The substring index keys that an entry must have to contain this value. This is empty
+    /// if the value is too short, or of a syntax that is not substring indexed.
+    pub fn get_idx_sub_keys(&self) -> Vec<String> {
+        match self {
+            PartialValue::Utf8(s) => trigram_idx_keys(std::iter::once(s.to_lowercase().as_str())),
+            PartialValue::Iutf8(s) | PartialValue::Iname(s) => {
+                trigram_idx_keys(std::iter::once(s.as_str()))
+            }
+            _ => Vec::with_capacity(0),
+        }
+    }
+
+    /// The ordering index key of this value. The keys of an attribute sort in the same order
+    /// as its values, so that a less than or greater than term is resolved as a range of keys.
+    /// This is `None` for syntaxes that are not ordered.
+    pub fn get_idx_ord_key(&self) -> Option<String> {
+        match self {
+            PartialValue::Uint32(u) => Some(format!("{u:010}")),
+            PartialValue::Uuid(u) | PartialValue::Refer(u) => Some(u.as_hyphenated().to_string()),
+            PartialValue::DateTime(odt) => {
+                // Flipping the sign bit orders negative timestamps before positive ones.
+                let nanos = odt.unix_timestamp_nanos() as u128 ^ (1 << 127);
+                Some(format!("{nanos:032x}"))
+            }
+            PartialValue::Cid(cid) => Some(format!(
+                "{:032x}-{}",
+                cid.ts.as_nanos(),
+                cid.s_uuid.as_hyphenated()
+            )),
+            _ => None,
+        }
     }
 }
 
@@ -1122,7 +1155,7 @@ impl fmt::Display for KeyStatus {
 ///
 /// This type is used when you need the "full data" of an attribute. Typically this is in a create
 /// or modification operation where you are applying a set of complete values into an entry.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub enum Value {
     Utf8(String),
     /// Case insensitive string
@@ -1189,6 +1222,99 @@ pub enum Value {
     Certificate(Box<Certificate>),
 }
 
+/// Shown in place of sensitive values such as secrets and private keys in debug output, so that
+/// they can never reach the logs regardless of what the caller chose to format.
+#[derive(Clone, Copy)]
+pub(crate) struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Value::Utf8(a) => f.debug_tuple("Utf8").field(a).finish(),
+            Value::Iutf8(a) => f.debug_tuple("Iutf8").field(a).finish(),
+            Value::Iname(a) => f.debug_tuple("Iname").field(a).finish(),
+            Value::Uuid(a) => f.debug_tuple("Uuid").field(a).finish(),
+            Value::Bool(a) => f.debug_tuple("Bool").field(a).finish(),
+            Value::Syntax(a) => f.debug_tuple("Syntax").field(a).finish(),
+            Value::Index(a) => f.debug_tuple("Index").field(a).finish(),
+            Value::Refer(a) => f.debug_tuple("Refer").field(a).finish(),
+            Value::JsonFilt(a) => f.debug_tuple("JsonFilt").field(a).finish(),
+            Value::Cred(a, _) => f.debug_tuple("Cred").field(a).field(&Redacted).finish(),
+            Value::SshKey(a, b) => f.debug_tuple("SshKey").field(a).field(b).finish(),
+            Value::SecretValue(_) => f.debug_tuple("SecretValue").field(&Redacted).finish(),
+            Value::Spn(a, b) => f.debug_tuple("Spn").field(a).field(b).finish(),
+            Value::Uint32(a) => f.debug_tuple("Uint32").field(a).finish(),
+            Value::Cid(a) => f.debug_tuple("Cid").field(a).finish(),
+            Value::Nsuniqueid(a) => f.debug_tuple("Nsuniqueid").field(a).finish(),
+            Value::DateTime(a) => f.debug_tuple("DateTime").field(a).finish(),
+            Value::EmailAddress(a, b) => f.debug_tuple("EmailAddress").field(a).field(b).finish(),
+            Value::PhoneNumber(a, b) => f.debug_tuple("PhoneNumber").field(a).field(b).finish(),
+            Value::Address(a) => f.debug_tuple("Address").field(a).finish(),
+            Value::Url(a) => f.debug_tuple("Url").field(a).finish(),
+            Value::OauthScope(a) => f.debug_tuple("OauthScope").field(a).finish(),
+            Value::OauthScopeMap(a, b) => f.debug_tuple("OauthScopeMap").field(a).field(b).finish(),
+            Value::PrivateBinary(_) => f.debug_tuple("PrivateBinary").field(&Redacted).finish(),
+            Value::PublicBinary(a, b) => f.debug_tuple("PublicBinary").field(a).field(b).finish(),
+            Value::RestrictedString(a) => f.debug_tuple("RestrictedString").field(a).finish(),
+            Value::IntentToken(a, b) => f.debug_tuple("IntentToken").field(a).field(b).finish(),
+            Value::Passkey(a, b, c) => f.debug_tuple("Passkey").field(a).field(b).field(c).finish(),
+            Value::AttestedPasskey(a, b, c) => f
+                .debug_tuple("AttestedPasskey")
+                .field(a)
+                .field(b)
+                .field(c)
+                .finish(),
+            Value::Session(a, b) => f.debug_tuple("Session").field(a).field(b).finish(),
+            Value::ApiToken(a, b) => f.debug_tuple("ApiToken").field(a).field(b).finish(),
+            Value::Oauth2Session(a, b) => f.debug_tuple("Oauth2Session").field(a).field(b).finish(),
+            Value::JwsKeyEs256(_) => f.debug_tuple("JwsKeyEs256").field(&Redacted).finish(),
+            Value::JwsKeyRs256(_) => f.debug_tuple("JwsKeyRs256").field(&Redacted).finish(),
+            Value::UiHint(a) => f.debug_tuple("UiHint").field(a).finish(),
+            Value::TotpSecret(a, b) => f.debug_tuple("TotpSecret").field(a).field(b).finish(),
+            Value::AuditLogString(a, b) => {
+                f.debug_tuple("AuditLogString").field(a).field(b).finish()
+            }
+            Value::EcKeyPrivate(_) => f.debug_tuple("EcKeyPrivate").field(&Redacted).finish(),
+            Value::Image(a) => f.debug_tuple("Image").field(a).finish(),
+            Value::CredentialType(a) => f.debug_tuple("CredentialType").field(a).finish(),
+            Value::WebauthnAttestationCaList(a) => {
+                f.debug_tuple("WebauthnAttestationCaList").field(a).finish()
+            }
+            Value::OauthClaimValue(a, b, c) => f
+                .debug_tuple("OauthClaimValue")
+                .field(a)
+                .field(b)
+                .field(c)
+                .finish(),
+            Value::OauthClaimMap(a, b) => f.debug_tuple("OauthClaimMap").field(a).field(b).finish(),
+            Value::KeyInternal {
+                id,
+                usage,
+                valid_from,
+                status,
+                status_cid,
+                der: _,
+            } => f
+                .debug_struct("KeyInternal")
+                .field("id", id)
+                .field("usage", usage)
+                .field("valid_from", valid_from)
+                .field("status", status)
+                .field("status_cid", status_cid)
+                .field("der", &Redacted)
+                .finish(),
+            Value::HexString(a) => f.debug_tuple("HexString").field(a).finish(),
+            Value::Certificate(a) => f.debug_tuple("Certificate").field(a).finish(),
+        }
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -2119,6 +2245,9 @@ mod tests {
         let r3 = IndexType::try_from("SUBSTRING");
         assert_eq!(r3, Ok(IndexType::SubString));
 
+        let r5 = IndexType::try_from("ORDERING");
+        assert_eq!(r5, Ok(IndexType::Ordering));
+
         let r4 = IndexType::try_from("thaoeusaneuh");
         assert_eq!(r4, Err(()));
     }
@@ -2361,4 +2490,63 @@ mod tests {
         assert!(KeyStatus::Valid < KeyStatus::Retained);
         assert!(KeyStatus::Retained < KeyStatus::Revoked);
     }
+
+    #[test]
+    fn test_value_idx_ord_key_order() {
+        let ord_key = |pv: PartialValue| pv.get_idx_ord_key().expect("no ord key");
+
+        // The keys sort as the values they were made from.
+        assert!(ord_key(PartialValue::new_uint32(9)) < ord_key(PartialValue::new_uint32(10)));
+        assert!(
+            ord_key(PartialValue::new_uint32(10)) < ord_key(PartialValue::new_uint32(u32::MAX))
+        );
+
+        let before = PartialValue::new_datetime_s("1969-12-31T23:59:59Z").expect("invalid");
+        let epoch = PartialValue::new_datetime_s("1970-01-01T00:00:00Z").expect("invalid");
+        let after = PartialValue::new_datetime_s("2023-09-02T01:00:00+10:00").expect("invalid");
+        assert!(ord_key(before.clone()) < ord_key(epoch.clone()));
+        assert!(ord_key(epoch) < ord_key(after));
+
+        let cid_a = Cid::new(
+            uuid!("00000000-0000-0000-0000-000000000002"),
+            Duration::from_secs(9),
+        );
+        let cid_b = Cid::new(
+            uuid!("00000000-0000-0000-0000-000000000001"),
+            Duration::from_secs(10),
+        );
+        assert!(cid_a < cid_b);
+        assert!(ord_key(PartialValue::new_cid(cid_a)) < ord_key(PartialValue::new_cid(cid_b)));
+
+        // Syntaxes without an order have no key.
+        assert!(PartialValue::new_utf8s("value").get_idx_ord_key().is_none());
+    }
+
+    #[test]
+    fn test_value_debug_redacts_secrets() {
+        use crate::credential::totp::TOTP_DEFAULT_STEP;
+        use kanidm_lib_crypto::CryptoPolicy;
+
+        let secret = Value::SecretValue("very secret".to_string());
+        assert_eq!(format!("{:?}", secret), "SecretValue(<redacted>)");
+
+        let binary = Value::PrivateBinary(b"very secret".to_vec());
+        assert_eq!(format!("{:?}", binary), "PrivateBinary(<redacted>)");
+
+        let totp = Value::TotpSecret("totp".to_string(), Totp::generate_secure(TOTP_DEFAULT_STEP));
+        assert!(format!("{:?}", totp).contains("secret: <redacted>"));
+
+        let vs = ValueSetSecret::new("very secret".to_string());
+        assert!(!format!("{:?}", vs).contains("very secret"));
+
+        // Credentials are shown by their tag alone, without any password hash or key material.
+        let cred = Credential::new_password_only(&CryptoPolicy::minimum(), "very secret")
+            .expect("failed to create credential");
+        let cred = Value::Cred("primary".to_string(), cred);
+        assert_eq!(format!("{:?}", cred), r#"Cred("primary", <redacted>)"#);
+
+        // Values that are not sensitive are shown in full.
+        let utf8 = Value::new_utf8s("not secret");
+        assert_eq!(format!("{:?}", utf8), r#"Utf8("not secret")"#);
+    }
 }
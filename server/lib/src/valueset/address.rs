@@ -141,6 +141,10 @@ impl ValueSetT for ValueSetAddress {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -372,6 +376,10 @@ impl ValueSetT for ValueSetEmailAddress {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -96,6 +96,10 @@ impl ValueSetT for ValueSetAuditLogString {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -7,13 +7,22 @@ use smolset::SmolSet;
 use crate::prelude::*;
 use crate::repl::proto::ReplAttrV1;
 use crate::schema::SchemaAttribute;
+use crate::value::Redacted;
 use crate::valueset::{DbValueSetV2, ValueSet};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ValueSetPrivateBinary {
     set: SmolSet<[Vec<u8>; 1]>,
 }
 
+impl std::fmt::Debug for ValueSetPrivateBinary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ValueSetPrivateBinary")
+            .field("set", &vec![Redacted; self.set.len()])
+            .finish()
+    }
+}
+
 impl ValueSetPrivateBinary {
     pub fn new(b: Vec<u8>) -> Box<Self> {
         let mut set = SmolSet::new();
@@ -86,6 +95,10 @@ impl ValueSetT for ValueSetPrivateBinary {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -247,6 +260,10 @@ impl ValueSetT for ValueSetPublicBinary {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
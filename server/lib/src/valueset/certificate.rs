@@ -171,6 +171,10 @@ impl ValueSetT for ValueSetCertificate {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
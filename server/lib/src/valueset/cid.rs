@@ -101,6 +101,13 @@ impl ValueSetT for ValueSetCid {
         }
     }
 
+    fn greaterthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Cid(c2) => self.set.iter().any(|c1| c1 > c2),
+            _ => false,
+        }
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -109,6 +116,13 @@ impl ValueSetT for ValueSetCid {
         Vec::with_capacity(0)
     }
 
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|c| PartialValue::Cid(c.clone()).get_idx_ord_key())
+            .collect()
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Cid
     }
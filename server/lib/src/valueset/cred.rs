@@ -116,6 +116,10 @@ impl ValueSetT for ValueSetCredential {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -408,6 +412,10 @@ impl ValueSetT for ValueSetIntentToken {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -689,6 +697,10 @@ impl ValueSetT for ValueSetPasskey {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -881,6 +893,10 @@ impl ValueSetT for ValueSetAttestedPasskey {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -1067,6 +1083,10 @@ impl ValueSetT for ValueSetCredentialType {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -1233,6 +1253,10 @@ impl ValueSetT for ValueSetWebauthnAttestationCaList {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.ca_list.len()
     }
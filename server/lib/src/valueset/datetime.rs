@@ -99,8 +99,18 @@ impl ValueSetT for ValueSetDateTime {
         false
     }
 
-    fn lessthan(&self, _pv: &PartialValue) -> bool {
-        false
+    fn lessthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::DateTime(odt) => self.set.iter().any(|v| v < odt),
+            _ => false,
+        }
+    }
+
+    fn greaterthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::DateTime(odt) => self.set.iter().any(|v| v > odt),
+            _ => false,
+        }
     }
 
     fn len(&self) -> usize {
@@ -119,6 +129,13 @@ impl ValueSetT for ValueSetDateTime {
             .collect()
     }
 
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|odt| PartialValue::DateTime(*odt).get_idx_ord_key())
+            .collect()
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::DateTime
     }
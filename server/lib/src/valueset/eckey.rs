@@ -110,6 +110,10 @@ impl ValueSetT for ValueSetEcKeyPrivate {
         false
     }
 
+    fn greaterthan(&self, _pv: &crate::value::PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         1
     }
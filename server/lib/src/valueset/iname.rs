@@ -3,6 +3,7 @@ use std::collections::BTreeSet;
 use crate::prelude::*;
 use crate::repl::proto::ReplAttrV1;
 use crate::schema::SchemaAttribute;
+use crate::utils::trigram_idx_keys;
 use crate::valueset::{DbValueSetV2, ValueSet};
 
 #[derive(Debug, Clone)]
@@ -109,6 +110,10 @@ impl ValueSetT for ValueSetIname {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -117,6 +122,10 @@ impl ValueSetT for ValueSetIname {
         self.set.iter().cloned().collect()
     }
 
+    fn generate_idx_sub_keys(&self) -> Vec<String> {
+        trigram_idx_keys(self.set.iter().map(String::as_str))
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Utf8StringIname
     }
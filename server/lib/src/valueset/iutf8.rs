@@ -4,6 +4,7 @@ use super::iname::ValueSetIname;
 use crate::prelude::*;
 use crate::repl::proto::ReplAttrV1;
 use crate::schema::SchemaAttribute;
+use crate::utils::trigram_idx_keys;
 use crate::valueset::{DbValueSetV2, ValueSet};
 
 #[derive(Debug, Clone)]
@@ -110,6 +111,10 @@ impl ValueSetT for ValueSetIutf8 {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -118,6 +123,10 @@ impl ValueSetT for ValueSetIutf8 {
         self.set.iter().cloned().collect()
     }
 
+    fn generate_idx_sub_keys(&self) -> Vec<String> {
+        trigram_idx_keys(self.set.iter().map(String::as_str))
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Utf8StringInsensitive
     }
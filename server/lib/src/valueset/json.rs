@@ -98,6 +98,10 @@ impl ValueSetT for ValueSetJsonFilter {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
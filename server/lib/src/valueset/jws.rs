@@ -110,6 +110,10 @@ impl ValueSetT for ValueSetJwsKeyEs256 {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -296,6 +300,10 @@ impl ValueSetT for ValueSetJwsKeyRs256 {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
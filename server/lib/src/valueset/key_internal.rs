@@ -253,6 +253,10 @@ impl ValueSetT for ValueSetKeyInternal {
         false
     }
 
+    fn greaterthan(&self, _pv: &crate::value::PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
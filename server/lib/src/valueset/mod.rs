@@ -128,10 +128,22 @@ pub trait ValueSetT: std::fmt::Debug + DynClone {
 
     fn lessthan(&self, pv: &PartialValue) -> bool;
 
+    fn greaterthan(&self, pv: &PartialValue) -> bool;
+
     fn len(&self) -> usize;
 
     fn generate_idx_eq_keys(&self) -> Vec<String>;
 
+    /// The substring index keys of this valueset. Only string syntaxes can be substring indexed.
+    fn generate_idx_sub_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
+    /// The ordering index keys of this valueset. Only ordered syntaxes can be ordering indexed.
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        Vec::with_capacity(0)
+    }
+
     fn syntax(&self) -> SyntaxType;
 
     fn validate(&self, schema_attr: &SchemaAttribute) -> bool;
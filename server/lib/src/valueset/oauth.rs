@@ -94,6 +94,10 @@ impl ValueSetT for ValueSetOauthScope {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -271,6 +275,10 @@ impl ValueSetT for ValueSetOauthScopeMap {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -604,6 +612,10 @@ impl ValueSetT for ValueSetOauthClaimMap {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
@@ -109,6 +109,10 @@ impl ValueSetT for ValueSetRestricted {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
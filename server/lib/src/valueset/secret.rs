@@ -3,13 +3,22 @@ use smolset::SmolSet;
 use crate::prelude::*;
 use crate::repl::proto::ReplAttrV1;
 use crate::schema::SchemaAttribute;
+use crate::value::Redacted;
 use crate::valueset::{DbValueSetV2, ValueSet};
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ValueSetSecret {
     set: SmolSet<[String; 1]>,
 }
 
+impl std::fmt::Debug for ValueSetSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ValueSetSecret")
+            .field("set", &vec![Redacted; self.set.len()])
+            .finish()
+    }
+}
+
 impl ValueSetSecret {
     pub fn new(b: String) -> Box<Self> {
         let mut set = SmolSet::new();
@@ -82,6 +91,10 @@ impl ValueSetT for ValueSetSecret {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -117,6 +117,10 @@ impl ValueSetT for ValueSetSshKey {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.map.len()
     }
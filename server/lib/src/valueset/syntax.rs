@@ -92,6 +92,10 @@ impl ValueSetT for ValueSetSyntax {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -81,6 +81,10 @@ impl ValueSetT for ValueSetUiHint {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
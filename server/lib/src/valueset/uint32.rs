@@ -94,6 +94,13 @@ impl ValueSetT for ValueSetUint32 {
         }
     }
 
+    fn greaterthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Uint32(u) => self.set.iter().any(|i| i > u),
+            _ => false,
+        }
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -102,6 +109,13 @@ impl ValueSetT for ValueSetUint32 {
         self.set.iter().map(|b| b.to_string()).collect()
     }
 
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|u| PartialValue::Uint32(*u).get_idx_ord_key())
+            .collect()
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Uint32
     }
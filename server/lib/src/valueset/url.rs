@@ -88,6 +88,10 @@ impl ValueSetT for ValueSetUrl {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -3,6 +3,7 @@ use std::collections::BTreeSet;
 use crate::prelude::*;
 use crate::repl::proto::ReplAttrV1;
 use crate::schema::SchemaAttribute;
+use crate::utils::trigram_idx_keys;
 use crate::valueset::{DbValueSetV2, ValueSet};
 
 #[derive(Debug, Clone)]
@@ -113,6 +114,10 @@ impl ValueSetT for ValueSetUtf8 {
         false
     }
 
+    fn greaterthan(&self, _pv: &PartialValue) -> bool {
+        false
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -121,6 +126,12 @@ impl ValueSetT for ValueSetUtf8 {
         self.set.iter().cloned().collect()
     }
 
+    fn generate_idx_sub_keys(&self) -> Vec<String> {
+        // Substring matching is case insensitive, so the index is too.
+        let lower: Vec<String> = self.set.iter().map(|s| s.to_lowercase()).collect();
+        trigram_idx_keys(lower.iter().map(String::as_str))
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Utf8String
     }
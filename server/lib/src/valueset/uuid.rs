@@ -96,6 +96,13 @@ impl ValueSetT for ValueSetUuid {
         }
     }
 
+    fn greaterthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Uuid(u) => self.set.iter().any(|v| v > u),
+            _ => false,
+        }
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -107,6 +114,13 @@ impl ValueSetT for ValueSetUuid {
             .collect()
     }
 
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|u| PartialValue::Uuid(*u).get_idx_ord_key())
+            .collect()
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::Uuid
     }
@@ -267,6 +281,13 @@ impl ValueSetT for ValueSetRefer {
         }
     }
 
+    fn greaterthan(&self, pv: &PartialValue) -> bool {
+        match pv {
+            PartialValue::Refer(u) => self.set.iter().any(|v| v > u),
+            _ => false,
+        }
+    }
+
     fn len(&self) -> usize {
         self.set.len()
     }
@@ -278,6 +299,13 @@ impl ValueSetT for ValueSetRefer {
             .collect()
     }
 
+    fn generate_idx_ord_keys(&self) -> Vec<String> {
+        self.set
+            .iter()
+            .filter_map(|u| PartialValue::Refer(*u).get_idx_ord_key())
+            .collect()
+    }
+
     fn syntax(&self) -> SyntaxType {
         SyntaxType::ReferenceUuid
     }
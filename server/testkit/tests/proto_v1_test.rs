@@ -49,8 +49,9 @@ async fn test_server_create(rsclient: KanidmClient) {
         .await;
     assert!(a_res.is_ok());
 
+    // The server assigns the uuid of the new entry.
     let res = rsclient.create(vec![e]).await;
-    assert!(res.is_ok());
+    assert!(res.is_ok_and(|uuids| uuids.len() == 1));
 }
 
 #[kanidmd_testkit::test]
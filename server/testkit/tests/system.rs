@@ -1,4 +1,5 @@
 use kanidm_client::KanidmClient;
+use kanidmd_testkit::{create_user, ADMIN_TEST_PASSWORD};
 
 /// This literally tests that the thing exists and responds in a way we expect, probably worth testing it better...
 #[kanidmd_testkit::test]
@@ -29,3 +30,21 @@ async fn test_v1_system_post_attr(rsclient: KanidmClient) {
     let body = response.text().await.unwrap();
     eprintln!("{}", body);
 }
+
+/// An in memory database must be read through the same connection that wrote it, whatever the
+/// number of threads.
+#[kanidmd_testkit::test(db_path = String::new(), threads = 4)]
+async fn test_in_memory_db_read_after_write(rsclient: KanidmClient) {
+    let res = rsclient
+        .auth_simple_password("admin", ADMIN_TEST_PASSWORD)
+        .await;
+    assert!(res.is_ok());
+
+    create_user(&rsclient, "memory_account", "memory_group").await;
+
+    let account = rsclient
+        .idm_person_account_get("memory_account")
+        .await
+        .expect("Failed to get the account");
+    assert!(account.is_some());
+}
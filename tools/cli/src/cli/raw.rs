@@ -18,6 +18,15 @@ fn read_file<T: DeserializeOwned, P: AsRef<Path>>(path: P) -> Result<T, Box<dyn
     Ok(serde_json::from_reader(r)?)
 }
 
+/// A filter may be given as JSON, or in the LDAP style such as `(&(class=account)(name=william))`.
+fn parse_filter(filter: &str) -> Result<Filter, Box<dyn Error>> {
+    if filter.trim_start().starts_with('(') {
+        Ok(filter.parse()?)
+    } else {
+        Ok(serde_json::from_str(filter)?)
+    }
+}
+
 impl RawOpt {
     pub fn debug(&self) -> bool {
         match self {
@@ -33,7 +42,7 @@ impl RawOpt {
             RawOpt::Search(sopt) => {
                 let client = sopt.commonopts.to_client(OpType::Read).await;
 
-                let filter: Filter = match serde_json::from_str(sopt.filter.as_str()) {
+                let filter = match parse_filter(sopt.filter.as_str()) {
                     Ok(f) => f,
                     Err(e) => {
                         error!("Error parsing filter -> {:?}", e);
@@ -70,14 +79,15 @@ impl RawOpt {
 
                 let entries = r_entries.into_iter().map(|b| Entry { attrs: b }).collect();
 
-                if let Err(e) = client.create(entries).await {
-                    error!("Error -> {:?}", e);
+                match client.create(entries).await {
+                    Ok(uuids) => uuids.iter().for_each(|u| println!("{}", u)),
+                    Err(e) => error!("Error -> {:?}", e),
                 }
             }
             RawOpt::Modify(mopt) => {
                 let client = mopt.commonopts.to_client(OpType::Write).await;
                 // Read the file?
-                let filter: Filter = match serde_json::from_str(mopt.filter.as_str()) {
+                let filter = match parse_filter(mopt.filter.as_str()) {
                     Ok(f) => f,
                     Err(e) => {
                         error!("Error -> {:?}", e);
@@ -100,7 +110,7 @@ impl RawOpt {
             }
             RawOpt::Delete(dopt) => {
                 let client = dopt.commonopts.to_client(OpType::Write).await;
-                let filter: Filter = match serde_json::from_str(dopt.filter.as_str()) {
+                let filter = match parse_filter(dopt.filter.as_str()) {
                     Ok(f) => f,
                     Err(e) => {
                         error!("Error -> {:?}", e);